@@ -1,3 +1,7 @@
 //! gearclaw_agent
 //! Compatibility extraction crate for agent orchestration.
-pub use gearclaw_core::agent::{Agent, AgentConfig, LLMLoop, ToolRouter};
+pub use gearclaw_core::agent::{
+    cap_tool_output, describe_attachments, describe_tool_call, parse_tool_arguments, Agent,
+    AgentConfig, AgentObserver, AgentProgress, DeltaSink, IncomingAttachment, LLMLoop,
+    MessageAddressing, ProgressSender, RunReport, ToolCallRecord, ToolRouter,
+};
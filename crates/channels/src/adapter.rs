@@ -3,8 +3,14 @@
 // Defines unified interface for all messaging platforms.
 
 use async_trait::async_trait;
+pub use gearclaw_core::agent::{IncomingAttachment, MessageAddressing};
 use serde_json::Value as JsonValue;
+use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
+
+/// How often a typing indicator is re-sent; Discord's expire after ~10 seconds.
+pub const TYPING_REFRESH_INTERVAL: Duration = Duration::from_secs(8);
 
 /// Message target (where to send)
 #[derive(Debug, Clone)]
@@ -19,6 +25,28 @@ pub enum MessageTarget {
 pub struct MessageContent {
     pub text: Option<String>,
     pub embeds: Vec<Embed>,
+    /// Files uploaded alongside the text
+    pub attachments: Vec<Attachment>,
+}
+
+/// File sent with a message (report, screenshot, ...)
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub bytes: Vec<u8>,
+    /// MIME type, for platforms that need it declared (Discord infers it
+    /// from the filename)
+    pub mime: String,
+}
+
+impl Attachment {
+    pub fn new(filename: impl Into<String>, bytes: Vec<u8>, mime: impl Into<String>) -> Self {
+        Self {
+            filename: filename.into(),
+            bytes,
+            mime: mime.into(),
+        }
+    }
 }
 
 /// Rich embed (image, file, etc.)
@@ -36,6 +64,10 @@ pub struct IncomingMessage {
     pub platform: String,
     pub source: MessageSource,
     pub content: String,
+    /// Files the sender attached
+    pub attachments: Vec<IncomingAttachment>,
+    /// Sender, mentions and reply target from platform metadata
+    pub addressing: MessageAddressing,
     pub metadata: JsonValue,
 }
 
@@ -60,6 +92,12 @@ pub trait ChannelAdapter: Send + Sync {
     /// Platform name (e.g., "discord", "telegram", "whatsapp")
     fn platform_name(&self) -> &str;
 
+    /// Maximum characters per message, if the platform enforces one.
+    /// Adapters split longer text with [`split_message`].
+    fn message_limit(&self) -> Option<usize> {
+        None
+    }
+
     /// Start of adapter (connect to platform)
     async fn start(&mut self) -> Result<(), ChannelError>;
 
@@ -70,6 +108,11 @@ pub trait ChannelAdapter: Send + Sync {
         content: MessageContent,
     ) -> Result<(), ChannelError>;
 
+    /// Show a typing indicator on `target` (no-op unless the platform has one)
+    async fn send_typing(&self, _target: &MessageTarget) -> Result<(), ChannelError> {
+        Ok(())
+    }
+
     /// Subscribe to incoming messages
     fn on_message(
         &self,
@@ -82,6 +125,148 @@ pub trait ChannelAdapter: Send + Sync {
     async fn health_check(&self) -> Result<bool, ChannelError>;
 }
 
+/// Drive `work` to completion while keeping a typing indicator visible on
+/// `target`. Typing failures are logged and never affect `work`.
+pub async fn with_typing<A, F>(adapter: &A, target: &MessageTarget, work: F) -> F::Output
+where
+    A: ChannelAdapter + ?Sized,
+    F: Future,
+{
+    tokio::pin!(work);
+    let mut refresh = tokio::time::interval(TYPING_REFRESH_INTERVAL);
+    loop {
+        tokio::select! {
+            output = &mut work => return output,
+            _ = refresh.tick() => {
+                if let Err(e) = adapter.send_typing(target).await {
+                    tracing::debug!("Typing indicator failed on {:?}: {}", target, e);
+                }
+            }
+        }
+    }
+}
+
+/// Split `text` into chunks of at most `limit` characters.
+///
+/// Prefers line boundaries, then word boundaries, and only cuts mid-word when
+/// a single word is longer than the limit. A code block that spans chunks is
+/// closed at the end of one chunk and reopened (with its language tag) at the
+/// start of the next, so every chunk renders on its own.
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    if text.chars().count() <= limit {
+        return vec![text.to_string()];
+    }
+
+    let mut splitter = Splitter {
+        limit,
+        chunks: Vec::new(),
+        current: String::new(),
+        current_len: 0,
+        fence: None,
+    };
+    for line in text.split_inclusive('\n') {
+        splitter.push_line(line);
+    }
+    splitter.flush();
+    splitter.chunks
+}
+
+const FENCE: &str = "```";
+
+struct Splitter {
+    limit: usize,
+    chunks: Vec<String>,
+    current: String,
+    current_len: usize,
+    /// Opening line of the code block we are inside, if any
+    fence: Option<String>,
+}
+
+impl Splitter {
+    fn push_line(&mut self, line: &str) {
+        let is_fence = line.trim_start().starts_with(FENCE);
+        let closes_fence = is_fence && self.fence.is_some();
+        let mut rest = line;
+
+        loop {
+            // Room left once the closing fence we may need is accounted for.
+            let reserve = if self.fence.is_some() && !closes_fence {
+                FENCE.len() + 1
+            } else {
+                0
+            };
+            let budget = self.limit.saturating_sub(self.current_len + reserve).max(1);
+            let len = rest.chars().count();
+            if len <= budget {
+                self.append(rest, len);
+                break;
+            }
+            if !self.is_fresh() {
+                self.flush();
+                continue;
+            }
+            // The line alone is too long: cut at the last whitespace in budget.
+            let cut = rest
+                .char_indices()
+                .nth(budget)
+                .map(|(i, _)| i)
+                .unwrap_or(rest.len());
+            let cut = rest[..cut]
+                .rfind(char::is_whitespace)
+                .filter(|&i| i > 0)
+                .map(|i| i + rest[i..].chars().next().map_or(1, char::len_utf8))
+                .unwrap_or(cut);
+            let (head, tail) = rest.split_at(cut);
+            self.append(head, head.chars().count());
+            self.flush();
+            rest = tail;
+        }
+
+        if is_fence {
+            self.fence = if closes_fence {
+                None
+            } else {
+                Some(line.trim_end().to_string())
+            };
+        }
+    }
+
+    fn append(&mut self, text: &str, len: usize) {
+        self.current.push_str(text);
+        self.current_len += len;
+    }
+
+    /// Whether the current chunk holds nothing but a reopened fence
+    fn is_fresh(&self) -> bool {
+        match &self.fence {
+            Some(opening) => self.current.trim_end() == opening.as_str(),
+            None => self.current.is_empty(),
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut chunk = std::mem::take(&mut self.current).trim_end().to_string();
+        if self.fence.is_some() {
+            chunk.push('\n');
+            chunk.push_str(FENCE);
+        }
+        if !chunk.trim().is_empty() && !self.is_only_fence(&chunk) {
+            self.chunks.push(chunk);
+        }
+        self.current_len = 0;
+        if let Some(opening) = self.fence.clone() {
+            self.append(&opening, opening.chars().count());
+            self.append("\n", 1);
+        }
+    }
+
+    fn is_only_fence(&self, chunk: &str) -> bool {
+        self.fence
+            .as_ref()
+            .is_some_and(|opening| chunk == format!("{}\n{}", opening, FENCE))
+    }
+}
+
 /// Channel error type
 #[derive(Debug)]
 pub enum ChannelError {
@@ -171,3 +356,137 @@ impl Default for ChannelManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_balanced(chunks: &[String], limit: usize) {
+        for chunk in chunks {
+            assert!(
+                chunk.chars().count() <= limit,
+                "chunk over limit: {:?}",
+                chunk
+            );
+            let fences = chunk
+                .lines()
+                .filter(|l| l.trim_start().starts_with(FENCE))
+                .count();
+            assert_eq!(fences % 2, 0, "unbalanced fences in {:?}", chunk);
+        }
+    }
+
+    #[test]
+    fn test_short_text_is_not_split() {
+        let text = "hello\n```rust\nfn main() {}\n```";
+        assert_eq!(split_message(text, 2000), vec![text.to_string()]);
+        assert_eq!(split_message("", 10), vec![String::new()]);
+    }
+
+    #[test]
+    fn test_splits_on_line_boundaries() {
+        let text = "first line\nsecond line\nthird line";
+        let chunks = split_message(text, 24);
+        assert_eq!(chunks, vec!["first line\nsecond line", "third line"]);
+    }
+
+    #[test]
+    fn test_long_line_splits_on_word_boundary() {
+        let text = "alpha beta gamma delta epsilon";
+        let chunks = split_message(text, 12);
+        assert_balanced(&chunks, 12);
+        assert_eq!(chunks, vec!["alpha beta", "gamma delta", "epsilon"]);
+
+        let word = "x".repeat(25);
+        let chunks = split_message(&word, 10);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks.concat(), word);
+    }
+
+    #[test]
+    fn test_code_block_is_closed_and_reopened_across_chunks() {
+        let code: Vec<String> = (0..20).map(|i| format!("let v{} = {};", i, i)).collect();
+        let text = format!(
+            "Here is the code:\n```rust\n{}\n```\nDone.",
+            code.join("\n")
+        );
+        let chunks = split_message(&text, 80);
+        assert!(chunks.len() > 2);
+        assert_balanced(&chunks, 80);
+
+        assert!(chunks[0].starts_with("Here is the code:"));
+        for chunk in &chunks[1..chunks.len() - 1] {
+            assert!(chunk.starts_with("```rust\n"), "not reopened: {:?}", chunk);
+        }
+        assert!(chunks.last().unwrap().ends_with("Done."));
+
+        // No code line is lost or duplicated.
+        let joined = chunks.join("\n");
+        for line in &code {
+            assert_eq!(joined.matches(line.as_str()).count(), 1);
+        }
+    }
+
+    #[derive(Default)]
+    struct TypingRecorder {
+        typing: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ChannelAdapter for TypingRecorder {
+        fn platform_name(&self) -> &str {
+            "test"
+        }
+        async fn start(&mut self) -> Result<(), ChannelError> {
+            Ok(())
+        }
+        async fn send_message(
+            &self,
+            _target: MessageTarget,
+            _content: MessageContent,
+        ) -> Result<(), ChannelError> {
+            Ok(())
+        }
+        async fn send_typing(&self, target: &MessageTarget) -> Result<(), ChannelError> {
+            self.typing.lock().unwrap().push(format!("{:?}", target));
+            Ok(())
+        }
+        fn on_message(
+            &self,
+        ) -> Pin<Box<dyn futures_util::stream::Stream<Item = IncomingMessage> + Send>> {
+            Box::pin(futures_util::stream::empty())
+        }
+        async fn resolve_target(&self, identifier: &str) -> Result<MessageTarget, ChannelError> {
+            Ok(MessageTarget::Channel(identifier.to_string()))
+        }
+        async fn health_check(&self) -> Result<bool, ChannelError> {
+            Ok(true)
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_typing_is_refreshed_until_work_completes() {
+        let adapter = TypingRecorder::default();
+        let target = MessageTarget::Channel("42".to_string());
+
+        let output = with_typing(&adapter, &target, async {
+            tokio::time::sleep(Duration::from_secs(20)).await;
+            "done"
+        })
+        .await;
+
+        assert_eq!(output, "done");
+        // Immediately, then at 8s and 16s.
+        assert_eq!(adapter.typing.lock().unwrap().len(), 3);
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert_eq!(adapter.typing.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_counts_characters_not_bytes() {
+        let text = "你好".repeat(10);
+        let chunks = split_message(&text, 20);
+        assert_eq!(chunks, vec![text]);
+    }
+}
@@ -6,7 +6,8 @@ pub mod adapter;
 pub mod platforms;
 
 pub use adapter::{
-    ChannelAdapter, ChannelError, ChannelManager, IncomingMessage, MessageContent, MessageSource,
+    split_message, with_typing, Attachment, ChannelAdapter, ChannelError, ChannelManager,
+    IncomingAttachment, IncomingMessage, MessageAddressing, MessageContent, MessageSource,
     MessageTarget,
 };
 pub use platforms::discord::DiscordAdapter;
@@ -3,17 +3,23 @@
 // Implements Discord bot integration using twilight-rs library.
 
 use crate::adapter::{
-    ChannelAdapter, ChannelError, IncomingMessage, MessageContent, MessageSource, MessageTarget,
+    split_message, ChannelAdapter, ChannelError, IncomingAttachment, IncomingMessage,
+    MessageAddressing, MessageContent, MessageSource, MessageTarget,
 };
 use async_trait::async_trait;
 use serde_json::json;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
 use twilight_gateway::{
     Event, EventTypeFlags, Intents, Shard, ShardId, StreamExt as TwilightStreamExt,
 };
+use twilight_http::api_error::ApiError;
+use twilight_http::error::ErrorType;
 use twilight_http::Client as HttpClient;
+use twilight_model::channel::Message;
+use twilight_model::http::attachment::Attachment as DiscordAttachment;
 use twilight_model::id::{marker::ChannelMarker, Id};
 
 /// Discord adapter configuration
@@ -35,22 +41,100 @@ impl Default for DiscordConfig {
     }
 }
 
+/// Attempts per message chunk before a rate-limited send is given up
+const MAX_SEND_ATTEMPTS: u32 = 3;
+
+/// Longest `retry_after` we are willing to sleep through
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(60);
+
+/// How long to wait if Discord returns 429 without a usable `retry_after`
+const DEFAULT_RETRY_WAIT: Duration = Duration::from_secs(1);
+
+/// Wait requested by a 429 response, if `error` is one.
+///
+/// twilight's ratelimiter already paces requests from the `X-RateLimit-*`
+/// headers; this covers the 429s that still slip through (shared or global
+/// limits), whose body carries `retry_after` in seconds.
+fn rate_limit_wait(error: &twilight_http::Error) -> Option<Duration> {
+    match error.kind() {
+        ErrorType::Response {
+            error: ApiError::Ratelimited(limited),
+            ..
+        } => Some(
+            Duration::try_from_secs_f64(limited.retry_after)
+                .unwrap_or(DEFAULT_RETRY_WAIT)
+                .min(MAX_RETRY_WAIT),
+        ),
+        ErrorType::Response { status, .. } if status.get() == 429 => Some(DEFAULT_RETRY_WAIT),
+        _ => None,
+    }
+}
+
+/// Convert a Discord message into the platform-neutral form. `bot_user_id`
+/// is the bot's own id from the gateway `Ready` event, if received yet.
+fn to_incoming(msg: &Message, bot_user_id: Option<String>) -> IncomingMessage {
+    IncomingMessage {
+        platform: "discord".to_string(),
+        source: MessageSource::User {
+            id: msg.author.id.get().to_string(),
+            name: msg.author.name.clone(),
+        },
+        content: msg.content.clone(),
+        attachments: msg
+            .attachments
+            .iter()
+            .map(|a| IncomingAttachment {
+                url: a.url.clone(),
+                filename: a.filename.clone(),
+                content_type: a.content_type.clone(),
+                size: a.size,
+            })
+            .collect(),
+        addressing: MessageAddressing {
+            sender_id: Some(msg.author.id.get().to_string()),
+            mentioned_user_ids: msg
+                .mentions
+                .iter()
+                .map(|m| m.id.get().to_string())
+                .collect(),
+            reply_to_user_id: msg
+                .referenced_message
+                .as_ref()
+                .map(|replied| replied.author.id.get().to_string()),
+            bot_user_id,
+        },
+        metadata: json!({
+            "channel_id": msg.channel_id.get().to_string(),
+            "guild_id": msg.guild_id.map(|id| id.get().to_string()),
+            "message_id": msg.id.get().to_string(),
+        }),
+    }
+}
+
 /// Discord adapter
 pub struct DiscordAdapter {
     config: DiscordConfig,
     http: Arc<HttpClient>,
     message_tx: Arc<tokio::sync::Mutex<Option<broadcast::Sender<IncomingMessage>>>>,
+    /// The bot's own user id, learned from the gateway `Ready` event
+    bot_user_id: Arc<std::sync::RwLock<Option<String>>>,
 }
 
 impl DiscordAdapter {
     /// Create new Discord adapter
     pub fn new(config: DiscordConfig) -> Self {
         let http = HttpClient::new(config.bot_token.clone());
+        Self::with_http(config, http)
+    }
+
+    /// Create with a preconfigured HTTP client (e.g. pointed at a proxy)
+    pub fn with_http(config: DiscordConfig, http: HttpClient) -> Self {
         let (tx, _) = broadcast::channel(100);
         Self {
             config,
             http: Arc::new(http),
             message_tx: Arc::new(tokio::sync::Mutex::new(Some(tx))),
+            bot_user_id: Arc::default(),
         }
     }
 
@@ -70,32 +154,7 @@ impl DiscordAdapter {
 
     /// Split message into chunks if too long
     pub fn chunk_message(message: &str, limit: usize) -> Vec<String> {
-        if message.len() <= limit {
-            return vec![message.to_string()];
-        }
-
-        let mut chunks = Vec::new();
-        let mut current = String::new();
-
-        for line in message.lines() {
-            if current.len() + line.len() + 1 > limit {
-                if !current.is_empty() {
-                    chunks.push(std::mem::take(&mut current));
-                }
-                current = line.to_string();
-            } else {
-                if !current.is_empty() {
-                    current.push('\n');
-                }
-                current.push_str(line);
-            }
-        }
-
-        if !current.is_empty() {
-            chunks.push(current);
-        }
-
-        chunks
+        split_message(message, limit)
     }
 
     /// Parse channel ID from various formats
@@ -132,11 +191,16 @@ impl ChannelAdapter for DiscordAdapter {
         "discord"
     }
 
+    fn message_limit(&self) -> Option<usize> {
+        Some(self.config.message_limit)
+    }
+
     async fn start(&mut self) -> Result<(), ChannelError> {
         tracing::info!("Discord adapter starting...");
 
         // Clone Arcs for the spawned task
         let message_tx = self.message_tx.clone();
+        let bot_user_id = self.bot_user_id.clone();
         let token = self.config.bot_token.clone();
 
         // Spawn gateway task in background
@@ -204,6 +268,13 @@ impl ChannelAdapter for DiscordAdapter {
                     }
                 };
 
+                if let Event::Ready(ready) = &event {
+                    *bot_user_id
+                        .write()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner) =
+                        Some(ready.user.id.get().to_string());
+                }
+
                 // Process only message create events
                 if let Event::MessageCreate(msg) = event {
                     // Skip messages from bots (including self)
@@ -219,19 +290,11 @@ impl ChannelAdapter for DiscordAdapter {
                     );
 
                     // Convert Discord message to IncomingMessage
-                    let incoming = IncomingMessage {
-                        platform: "discord".to_string(),
-                        source: MessageSource::User {
-                            id: msg.author.id.get().to_string(),
-                            name: msg.author.name.clone(),
-                        },
-                        content: msg.content.clone(),
-                        metadata: json!({
-                            "channel_id": msg.channel_id.get().to_string(),
-                            "guild_id": msg.guild_id.map(|id| id.get().to_string()),
-                            "message_id": msg.id.get().to_string(),
-                        }),
-                    };
+                    let own_id = bot_user_id
+                        .read()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .clone();
+                    let incoming = to_incoming(&msg, own_id);
 
                     // Send to broadcast channel (ignore if no receivers)
                     let tx = message_tx.lock().await;
@@ -280,31 +343,73 @@ impl ChannelAdapter for DiscordAdapter {
         // Parse channel ID
         let parsed_id = Self::parse_channel_id(channel_id)?;
 
-        // Get message text
-        let text = content
-            .text
-            .as_ref()
-            .ok_or_else(|| ChannelError::SendFailed {
-                target: target.clone(),
-                source: "Message content is empty".to_string(),
-            })?;
+        // Get message text; attachment-only messages are allowed
+        let text = match (&content.text, content.attachments.is_empty()) {
+            (Some(text), _) => text.as_str(),
+            (None, false) => "",
+            (None, true) => {
+                return Err(ChannelError::SendFailed {
+                    target: target.clone(),
+                    source: "Message content is empty".to_string(),
+                });
+            }
+        };
 
         // Chunk message if needed
         let chunks = Self::chunk_message(text, self.config.message_limit);
         let chunk_count = chunks.len();
 
+        // Files go out with the last chunk, after the text that introduces them
+        let attachments: Vec<DiscordAttachment> = content
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(index, a)| {
+                DiscordAttachment::from_bytes(a.filename.clone(), a.bytes.clone(), index as u64)
+            })
+            .collect();
+
         // Send each chunk
-        for chunk in &chunks {
+        for (index, chunk) in chunks.iter().enumerate() {
             let id = Id::<ChannelMarker>::new(parsed_id);
-
-            self.http
-                .create_message(id)
-                .content(chunk)
-                .await
-                .map_err(|e| ChannelError::SendFailed {
-                    target: target.clone(),
-                    source: format!("HTTP error: {}", e),
-                })?;
+            let files: &[DiscordAttachment] = if index + 1 == chunk_count {
+                &attachments
+            } else {
+                &[]
+            };
+
+            let mut attempt = 1;
+            loop {
+                let mut request = self.http.create_message(id);
+                if !chunk.is_empty() {
+                    request = request.content(chunk);
+                }
+                if !files.is_empty() {
+                    request = request.attachments(files);
+                }
+                match request.await {
+                    Ok(_) => break,
+                    Err(e) => match rate_limit_wait(&e) {
+                        Some(wait) if attempt < MAX_SEND_ATTEMPTS => {
+                            tracing::warn!(
+                                "Discord rate limited channel {} (attempt {}/{}), retrying in {:?}",
+                                parsed_id,
+                                attempt,
+                                MAX_SEND_ATTEMPTS,
+                                wait
+                            );
+                            tokio::time::sleep(wait).await;
+                            attempt += 1;
+                        }
+                        _ => {
+                            return Err(ChannelError::SendFailed {
+                                target: target.clone(),
+                                source: format!("HTTP error: {}", e),
+                            });
+                        }
+                    },
+                }
+            }
 
             tracing::debug!("Sent message chunk to Discord channel {}", parsed_id);
         }
@@ -318,6 +423,25 @@ impl ChannelAdapter for DiscordAdapter {
         Ok(())
     }
 
+    async fn send_typing(&self, target: &MessageTarget) -> Result<(), ChannelError> {
+        let MessageTarget::Channel(channel_id) = target else {
+            return Err(ChannelError::SendFailed {
+                target: target.clone(),
+                source: "Typing indicators are only supported in channels".to_string(),
+            });
+        };
+        let id = Id::<ChannelMarker>::new(Self::parse_channel_id(channel_id)?);
+
+        self.http
+            .create_typing_trigger(id)
+            .await
+            .map_err(|e| ChannelError::SendFailed {
+                target: target.clone(),
+                source: format!("HTTP error: {}", e),
+            })?;
+        Ok(())
+    }
+
     fn on_message(
         &self,
     ) -> Pin<Box<dyn futures_util::stream::Stream<Item = IncomingMessage> + Send>> {
@@ -363,3 +487,281 @@ impl ChannelAdapter for DiscordAdapter {
         Ok(!self.config.bot_token.is_empty())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::Attachment;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Plain-HTTP stand-in for the Discord API. Replies with `responses` in
+    /// order (status, body) and records each raw request.
+    async fn spawn_discord_mock(
+        responses: Vec<(u16, &'static str)>,
+    ) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for (status, body) in responses {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let request = loop {
+                    let n = stream.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                    let text = String::from_utf8_lossy(&buf).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text[..end]
+                            .lines()
+                            .find_map(|l| {
+                                l.to_ascii_lowercase()
+                                    .strip_prefix("content-length:")
+                                    .map(|v| v.trim().parse::<usize>().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if buf.len() >= end + 4 + length || n == 0 {
+                            break text;
+                        }
+                    }
+                };
+                recorded.lock().unwrap().push(request);
+                let reason = if status == 429 {
+                    "Too Many Requests"
+                } else {
+                    "OK"
+                };
+                let response = format!(
+                    "HTTP/1.1 {} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    reason,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.ok();
+            }
+        });
+        (addr, requests)
+    }
+
+    fn adapter_for(addr: String) -> DiscordAdapter {
+        let http = HttpClient::builder()
+            .token("test-token".to_string())
+            .proxy(addr, true)
+            .ratelimiter(None)
+            .build();
+        DiscordAdapter::with_http(
+            DiscordConfig {
+                bot_token: "test-token".to_string(),
+                message_limit: 2000,
+            },
+            http,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_send_typing_posts_to_channel_typing_endpoint() {
+        let (addr, requests) = spawn_discord_mock(vec![(204, "")]).await;
+        let adapter = adapter_for(addr);
+
+        adapter
+            .send_typing(&MessageTarget::Channel("123456789".to_string()))
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(
+            requests[0].starts_with("POST /api/v10/channels/123456789/typing "),
+            "unexpected request: {}",
+            requests[0]
+        );
+    }
+
+    const RATE_LIMITED: &str =
+        r#"{"message":"You are being rate limited.","retry_after":0.05,"global":false}"#;
+
+    fn text(content: &str) -> MessageContent {
+        MessageContent {
+            text: Some(content.to_string()),
+            embeds: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_message_uploads_attachments_as_multipart() {
+        let (addr, requests) = spawn_discord_mock(vec![(200, "{}")]).await;
+        let adapter = adapter_for(addr);
+
+        let content = MessageContent {
+            text: Some("Here is the report".to_string()),
+            embeds: Vec::new(),
+            attachments: vec![Attachment::new(
+                "report.txt",
+                b"line one\nline two".to_vec(),
+                "text/plain",
+            )],
+        };
+        adapter
+            .send_message(MessageTarget::Channel("42".to_string()), content)
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let request = &requests[0];
+        assert!(request.starts_with("POST /api/v10/channels/42/messages "));
+        assert!(request
+            .to_ascii_lowercase()
+            .contains("content-type: multipart/form-data; boundary="));
+        assert!(request.contains(r#"name="files[0]"; filename="report.txt""#));
+        assert!(request.contains("line one\nline two"));
+        assert!(request.contains(r#"name="payload_json""#));
+        assert!(request.contains("Here is the report"));
+    }
+
+    #[tokio::test]
+    async fn test_text_only_send_is_plain_json() {
+        let (addr, requests) = spawn_discord_mock(vec![(200, "{}")]).await;
+        let adapter = adapter_for(addr);
+
+        adapter
+            .send_message(MessageTarget::Channel("42".to_string()), text("hi"))
+            .await
+            .unwrap();
+
+        let request = requests.lock().unwrap()[0].to_ascii_lowercase();
+        assert!(request.contains("content-type: application/json"));
+        assert!(!request.contains("multipart"));
+    }
+
+    #[tokio::test]
+    async fn test_send_message_retries_after_429() {
+        let (addr, requests) = spawn_discord_mock(vec![(429, RATE_LIMITED), (200, "{}")]).await;
+        let adapter = adapter_for(addr);
+
+        adapter
+            .send_message(MessageTarget::Channel("42".to_string()), text("hello"))
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests
+            .iter()
+            .all(|r| r.starts_with("POST /api/v10/channels/42/messages ")));
+    }
+
+    #[tokio::test]
+    async fn test_send_message_gives_up_after_max_attempts() {
+        let (addr, requests) = spawn_discord_mock(vec![(429, RATE_LIMITED); 5]).await;
+        let adapter = adapter_for(addr);
+
+        let result = adapter
+            .send_message(MessageTarget::Channel("42".to_string()), text("hello"))
+            .await;
+
+        assert!(matches!(result, Err(ChannelError::SendFailed { .. })));
+        assert_eq!(requests.lock().unwrap().len(), MAX_SEND_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn test_discord_message_attachments_are_mapped() {
+        let msg: Message = serde_json::from_value(json!({
+            "id": "1000",
+            "channel_id": "42",
+            "guild_id": "7",
+            "type": 0,
+            "content": "what is in this file?",
+            "author": {
+                "id": "9",
+                "username": "alice",
+                "discriminator": "0",
+                "avatar": null
+            },
+            "attachments": [{
+                "id": "55",
+                "filename": "diagram.png",
+                "content_type": "image/png",
+                "size": 2048,
+                "url": "https://cdn.discordapp.com/attachments/42/55/diagram.png",
+                "proxy_url": "https://media.discordapp.net/attachments/42/55/diagram.png"
+            }],
+            "embeds": [],
+            "mentions": [],
+            "mention_roles": [],
+            "mention_everyone": false,
+            "pinned": false,
+            "tts": false,
+            "timestamp": "2024-01-01T00:00:00.000000+00:00",
+            "edited_timestamp": null
+        }))
+        .unwrap();
+
+        let incoming = to_incoming(&msg, None);
+
+        assert_eq!(incoming.content, "what is in this file?");
+        assert_eq!(
+            incoming.attachments,
+            vec![IncomingAttachment {
+                url: "https://cdn.discordapp.com/attachments/42/55/diagram.png".to_string(),
+                filename: "diagram.png".to_string(),
+                content_type: Some("image/png".to_string()),
+                size: 2048,
+            }]
+        );
+        assert_eq!(incoming.metadata["channel_id"], "42");
+    }
+
+    #[test]
+    fn test_discord_mentions_and_reply_are_mapped() {
+        let author = |id: &str, name: &str| json!({ "id": id, "username": name, "discriminator": "0", "avatar": null });
+        let message = |id: &str, author: serde_json::Value| {
+            json!({
+                "id": id,
+                "channel_id": "42",
+                "type": 0,
+                "content": "",
+                "author": author,
+                "attachments": [],
+                "embeds": [],
+                "mentions": [],
+                "mention_roles": [],
+                "mention_everyone": false,
+                "pinned": false,
+                "tts": false,
+                "timestamp": "2024-01-01T00:00:00.000000+00:00",
+                "edited_timestamp": null
+            })
+        };
+        let mut reply = message("1001", author("9", "alice"));
+        reply["type"] = json!(19);
+        reply["content"] = json!("<@999> and what about tomorrow?");
+        let mut mention = author("999", "gearclaw");
+        mention["bot"] = json!(true);
+        mention["public_flags"] = json!(0);
+        reply["mentions"] = json!([mention]);
+        reply["referenced_message"] = message("1000", author("999", "gearclaw"));
+        let msg: Message = serde_json::from_value(reply).unwrap();
+
+        let incoming = to_incoming(&msg, Some("999".to_string()));
+
+        assert_eq!(incoming.addressing.sender_id.as_deref(), Some("9"));
+        assert_eq!(incoming.addressing.mentioned_user_ids, vec!["999"]);
+        assert_eq!(incoming.addressing.reply_to_user_id.as_deref(), Some("999"));
+        assert!(incoming.addressing.addresses_bot());
+    }
+
+    #[tokio::test]
+    async fn test_send_typing_rejects_direct_messages() {
+        let adapter = adapter_for("127.0.0.1:9".to_string());
+        let result = adapter
+            .send_typing(&MessageTarget::DirectMessage("1".to_string()))
+            .await;
+        assert!(matches!(result, Err(ChannelError::SendFailed { .. })));
+    }
+}
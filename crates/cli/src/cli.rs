@@ -10,6 +10,17 @@ pub struct Cli {
     /// Config file path
     #[arg(short, long, value_name = "FILE")]
     pub config_path: Option<String>,
+
+    /// Suppress the banner and info logs (warnings and errors still show)
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Print the response once it is complete instead of streaming tokens
+    #[arg(long, global = true)]
+    pub no_stream: bool,
+    /// Log format: text | json (defaults to GEARCLAW_LOG_FORMAT, then text)
+    #[arg(long, global = true, value_parser = ["text", "json"])]
+    pub log_format: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -24,6 +35,12 @@ pub enum Commands {
         output: Option<PathBuf>,
     },
 
+    /// Configuration management
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
     /// List all sessions
     ListSessions,
 
@@ -41,17 +58,31 @@ pub enum Commands {
         /// Session ID to use (optional)
         #[arg(short, long)]
         session: Option<String>,
+
+        /// Output format: text streams the response; json prints one object
+        /// with the response, tool calls, token usage and session id
+        #[arg(long, default_value = "text", value_parser = ["text", "json"])]
+        output: String,
     },
 
     /// Initialize configuration
     Init,
 
+    /// Check API key, endpoint, git, memory DB and skills path
+    Doctor,
+
     /// Memory management
     Memory {
         #[command(subcommand)]
         command: MemoryCommands,
     },
 
+    /// Session management
+    Session {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+
     /// Search skills from configured sources
     SearchSkill {
         /// Query text (matches name/description)
@@ -62,6 +93,22 @@ pub enum Commands {
         /// Force refresh git sources before searching
         #[arg(long)]
         update: bool,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Output format: text | json | jsonl
+        #[arg(long, default_value = "text", value_parser = ["text", "json", "jsonl"])]
+        output: String,
+    },
+
+    /// List installed and available skills, flagging name conflicts
+    ListSkills {
+        /// Optional source name filter
+        #[arg(long)]
+        source: Option<String>,
+        /// Force refresh git sources before listing
+        #[arg(long)]
+        update: bool,
     },
 
     /// Install a skill by name from configured sources
@@ -82,8 +129,24 @@ pub enum Commands {
         update: bool,
     },
 
+    /// Remove an installed skill from skills_path
+    UninstallSkill {
+        /// Skill name to uninstall
+        name: String,
+        /// Show removal plan without deleting files
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// List configured skill sources
-    ListSources,
+    ListSources {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Output format: text | json | jsonl
+        #[arg(long, default_value = "text", value_parser = ["text", "json", "jsonl"])]
+        output: String,
+    },
     /// Show recent skill installation audit records
     ListAudit {
         /// Number of recent audit records to show
@@ -135,6 +198,68 @@ pub enum Commands {
         /// Allow unauthenticated requests (DANGEROUS, dev-only)
         #[arg(long)]
         allow_unauthenticated: bool,
+
+        /// Allow port 0 (listen on a random free port)
+        #[arg(long)]
+        ephemeral: bool,
+    },
+
+    /// Gateway token management
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands,
+    },
+
+    /// Skill source maintenance
+    Skill {
+        #[command(subcommand)]
+        command: SkillCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Validate the configuration file and report errors and warnings
+    Check,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TokenCommands {
+    /// Create a new token (printed once, only its hash is stored)
+    Create {
+        /// Device ID the token belongs to
+        device_id: String,
+        /// Comma-separated methods the token may call (default: all)
+        #[arg(long, value_delimiter = ',')]
+        scopes: Option<Vec<String>>,
+    },
+    /// List registered tokens
+    List,
+    /// Revoke a token by ID
+    Revoke {
+        /// Token ID (from `token list`)
+        id: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SkillCommands {
+    /// Manage cached clones of git skill sources
+    Cache {
+        #[command(subcommand)]
+        command: SkillCacheCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SkillCacheCommands {
+    /// List cached sources with size and last sync time
+    Info,
+    /// Remove cached clones (all, or one source's)
+    Clear {
+        /// Only clear this source's cache
+        #[arg(long)]
+        source: Option<String>,
     },
 }
 
@@ -143,5 +268,46 @@ pub enum MemoryCommands {
     /// Sync memory index
     Sync,
     /// Search memory
-    Search { query: String },
+    Search {
+        query: String,
+        /// Print each matching chunk in full instead of a one-line preview
+        #[arg(long)]
+        full: bool,
+        /// Also print N lines around each chunk from its source file
+        #[arg(long, value_name = "N")]
+        context: Option<usize>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionCommands {
+    /// List sessions with message count, last update and size
+    List,
+    /// Fork a session into a new, independent session
+    Fork {
+        /// Source session ID
+        source_id: String,
+        /// New session ID
+        new_id: String,
+        /// Overwrite the target session if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Delete sessions not updated within the given age
+    Prune {
+        /// Maximum age, e.g. 30d, 12h, 45m, 2w
+        #[arg(long, value_name = "AGE")]
+        older_than: String,
+    },
+    /// Export a session transcript
+    Export {
+        /// Session ID
+        session_id: String,
+        /// Output format: md | json
+        #[arg(long, default_value = "md", value_parser = ["md", "json"])]
+        format: String,
+        /// Write to file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
 }
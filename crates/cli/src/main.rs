@@ -1,36 +1,58 @@
 mod cli;
 
 use clap::Parser;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use tracing::info;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
-use crate::cli::{Cli, Commands};
-use gearclaw_agent::Agent;
+use crate::cli::{
+    Cli, Commands, ConfigCommands, SessionCommands, SkillCacheCommands, SkillCommands,
+    TokenCommands,
+};
+use gearclaw_agent::{Agent, DeltaSink};
 use gearclaw_core::config::{Config, SkillSourceKind, SkillTrustPolicy};
 use gearclaw_core::error::GearClawError;
+use gearclaw_core::logging::{self, LogFormat};
+use gearclaw_core::mcp::ServerHealth;
+use gearclaw_core::session::SessionManager;
 
 #[tokio::main]
 async fn main() -> Result<(), GearClawError> {
-    // Initialize tracing
-    let env_filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("gearclaw=info,warn"));
+    // Parse CLI arguments
+    let cli = Cli::parse();
+    // Machine-readable output owns stdout: no banner, logs go to stderr
+    let json_output =
+        matches!(&cli.command, Some(Commands::Run { output, .. }) if output == "json");
+
+    // Initialize tracing; --quiet drops info logs even if RUST_LOG asks for them
+    let env_filter = if cli.quiet {
+        EnvFilter::new("warn")
+    } else {
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("gearclaw=info,warn"))
+    };
+    let log_writer = if json_output {
+        fmt::writer::BoxMakeWriter::new(std::io::stderr)
+    } else {
+        fmt::writer::BoxMakeWriter::new(std::io::stdout)
+    };
+
+    let log_format = LogFormat::resolve(cli.log_format.as_deref())?;
 
     tracing_subscriber::registry()
         .with(env_filter)
-        .with(fmt::layer())
+        .with(logging::fmt_layer(log_format, log_writer))
         .init();
 
-    print_banner();
+    if !json_output && !cli.quiet {
+        print_banner();
+    }
     info!("🦾⚙️ GearClaw - OpenClaw Rust 原型");
     info!("版本: 0.1.0");
     info!("");
 
-    // Parse CLI arguments
-    let cli = Cli::parse();
-
     // Handle Init command immediately
     if let Some(Commands::Init) = &cli.command {
         handle_init()?;
@@ -48,12 +70,21 @@ async fn main() -> Result<(), GearClawError> {
         return Ok(());
     }
 
+    // Handle config commands before loading, so parse errors are reported too
+    if let Some(Commands::Config { command }) = &cli.command {
+        return handle_config_command(&cli.config_path, command);
+    }
+
+    if let Some(Commands::Doctor) = &cli.command {
+        return handle_doctor(&cli.config_path).await;
+    }
+
     // Load configuration
     let config = Config::load(&cli.config_path)?;
     // Handle commands that do not require LLM/Agent initialization
     match &cli.command {
-        Some(Commands::ListSources) => {
-            handle_list_sources(&config);
+        Some(Commands::ListSources { json, output }) => {
+            handle_list_sources(&config, resolve_output_format(output, *json)?)?;
             return Ok(());
         }
         Some(Commands::TrustPolicy) => {
@@ -87,8 +118,20 @@ async fn main() -> Result<(), GearClawError> {
             query,
             source,
             update,
+            json,
+            output,
         }) => {
-            handle_search_skill(&config, query, source.as_deref(), *update)?;
+            handle_search_skill(
+                &config,
+                query,
+                source.as_deref(),
+                *update,
+                resolve_output_format(output, *json)?,
+            )?;
+            return Ok(());
+        }
+        Some(Commands::ListSkills { source, update }) => {
+            handle_list_skills(&config, source.as_deref(), *update)?;
             return Ok(());
         }
         Some(Commands::InstallSkill {
@@ -101,6 +144,22 @@ async fn main() -> Result<(), GearClawError> {
             handle_install_skill(&config, name, source.as_deref(), *force, *dry_run, *update)?;
             return Ok(());
         }
+        Some(Commands::UninstallSkill { name, dry_run }) => {
+            handle_uninstall_skill(&config, name, *dry_run)?;
+            return Ok(());
+        }
+        Some(Commands::Session { command }) => {
+            handle_session_command(&config, command).await?;
+            return Ok(());
+        }
+        Some(Commands::Token { command }) => {
+            handle_token_command(&config, command).await?;
+            return Ok(());
+        }
+        Some(Commands::Skill { command }) => {
+            handle_skill_command(&config, command)?;
+            return Ok(());
+        }
         _ => {}
     }
 
@@ -115,11 +174,18 @@ async fn main() -> Result<(), GearClawError> {
         }
         Some(Commands::ConfigSample { .. })
         | Some(Commands::Init)
-        | Some(Commands::ListSources)
+        | Some(Commands::Doctor)
+        | Some(Commands::Config { .. })
+        | Some(Commands::ListSources { .. })
         | Some(Commands::ListAudit { .. })
         | Some(Commands::TrustPolicy)
         | Some(Commands::SearchSkill { .. })
-        | Some(Commands::InstallSkill { .. }) => {
+        | Some(Commands::ListSkills { .. })
+        | Some(Commands::InstallSkill { .. })
+        | Some(Commands::UninstallSkill { .. })
+        | Some(Commands::Session { .. })
+        | Some(Commands::Token { .. })
+        | Some(Commands::Skill { .. }) => {
             // Already handled
         }
         Some(Commands::ListSessions) => {
@@ -139,43 +205,99 @@ async fn main() -> Result<(), GearClawError> {
             agent.session_manager.delete_session(&session_id)?;
             println!("✅ 会话已删除: {}", session_id);
         }
-        Some(Commands::Run { prompt, session }) => {
+        Some(Commands::Run {
+            prompt,
+            session,
+            output,
+        }) => {
             // Run single command
             let mut sess = agent
                 .session_manager
                 .get_or_create_session(session.as_deref().unwrap_or("default"))?;
-            let _ = agent.process_message(&mut sess, &prompt).await?;
-            println!(); // Ensure newline
-            agent.session_manager.save_session(&sess).await?;
+            if output == "json" {
+                let report = agent.process_message_report(&mut sess, &prompt).await?;
+                agent.session_manager.save_session(&sess).await?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else if cli.no_stream {
+                let response = agent
+                    .process_message_with_sink(
+                        &mut sess,
+                        &prompt,
+                        DeltaSink::Writer(&mut std::io::sink()),
+                    )
+                    .await?;
+                agent.session_manager.save_session(&sess).await?;
+                println!("{}", response);
+            } else {
+                let _ = agent.process_message(&mut sess, &prompt).await?;
+                println!(); // Ensure newline
+                agent.session_manager.save_session(&sess).await?;
+            }
         }
         Some(Commands::Memory { command }) => match command {
             crate::cli::MemoryCommands::Sync => {
-                agent.memory_manager.sync().await?;
+                use std::io::{IsTerminal, Write};
+                // Redraw one line on a terminal; piped output gets plain lines
+                let terminal = std::io::stdout().is_terminal();
+                agent
+                    .memory_manager
+                    .sync_with_progress(|p| {
+                        if terminal {
+                            print!(
+                                "\r\x1b[2K  [{}/{}] {}",
+                                p.files_done, p.files_total, p.current_path
+                            );
+                            std::io::stdout().flush().ok();
+                        } else {
+                            println!("  [{}/{}] {}", p.files_done, p.files_total, p.current_path);
+                        }
+                    })
+                    .await?;
+                if terminal {
+                    println!();
+                }
                 println!("✅ 记忆同步完成");
             }
-            crate::cli::MemoryCommands::Search { query } => {
+            crate::cli::MemoryCommands::Search {
+                query,
+                full,
+                context,
+            } => {
                 let results = agent.memory_manager.search(&query, 5).await?;
                 if results.is_empty() {
                     println!("没有找到相关记忆");
                 } else {
+                    let workspace = &config.agent.workspace;
                     println!("🔍 搜索结果:");
                     for (i, res) in results.iter().enumerate() {
                         println!(
-                            "{}. [{:.2}] {} (Line {})",
+                            "{}. [{:.2}] {}",
                             i + 1,
                             res.score,
-                            res.path,
-                            res.start_line.unwrap_or(0)
+                            memory_result_location(workspace, res)
                         );
-                        let preview: String = res
-                            .text
-                            .lines()
-                            .take(1)
-                            .collect::<String>()
-                            .chars()
-                            .take(80)
-                            .collect();
-                        println!("   {}...", preview);
+                        let surrounding =
+                            context.and_then(|n| memory_result_context(workspace, res, n));
+                        if let Some(lines) = surrounding {
+                            for (number, line, in_chunk) in lines {
+                                let marker = if in_chunk { '>' } else { ' ' };
+                                println!("  {}{:>5} | {}", marker, number, line);
+                            }
+                        } else if full {
+                            for line in res.text.trim().lines() {
+                                println!("   {}", line);
+                            }
+                        } else {
+                            let preview: String = res
+                                .text
+                                .lines()
+                                .take(1)
+                                .collect::<String>()
+                                .chars()
+                                .take(80)
+                                .collect();
+                            println!("   {}...", preview);
+                        }
                     }
                 }
             }
@@ -203,6 +325,28 @@ async fn main() -> Result<(), GearClawError> {
                 println!("⚠️  Skipping MCP connectivity and Agent MCP execution checks.");
             } else {
                 let tools = agent.mcp_manager.list_tools().await;
+                let mut statuses: Vec<_> = agent
+                    .mcp_manager
+                    .server_status()
+                    .await
+                    .into_iter()
+                    .collect();
+                statuses.sort_by(|a, b| a.0.cmp(&b.0));
+                for (server, status) in &statuses {
+                    let icon = match status.health {
+                        ServerHealth::Healthy => "✅",
+                        ServerHealth::Unhealthy => "⚠️ ",
+                        ServerHealth::Failed => "❌",
+                    };
+                    print!(
+                        "{} {} ({:?}, restarts: {})",
+                        icon, server, status.health, status.restarts
+                    );
+                    match &status.last_error {
+                        Some(error) => println!(" - {}", error),
+                        None => println!(),
+                    }
+                }
                 if tools.is_empty() {
                     println!("⚠️  No MCP tools found. Is the server running?");
                 } else {
@@ -266,9 +410,10 @@ async fn main() -> Result<(), GearClawError> {
             port,
             dev,
             allow_unauthenticated,
+            ephemeral,
         }) => {
             // Start Gateway server
-            handle_gateway(&config, host, port, dev, allow_unauthenticated).await?;
+            handle_gateway(&config, host, port, dev, allow_unauthenticated, ephemeral).await?;
         }
         None => {
             // Default to interactive mode
@@ -279,6 +424,491 @@ async fn main() -> Result<(), GearClawError> {
     Ok(())
 }
 
+fn handle_config_command(
+    config_path: &Option<String>,
+    command: &ConfigCommands,
+) -> Result<(), GearClawError> {
+    match command {
+        ConfigCommands::Check => {
+            let config = match Config::load(config_path) {
+                Ok(config) => config,
+                Err(e) => {
+                    println!("❌ 配置加载失败: {}", e);
+                    return Err(e);
+                }
+            };
+            match config.validate() {
+                Ok(warnings) => {
+                    for warning in &warnings {
+                        println!("⚠️  {}", warning);
+                    }
+                    if warnings.is_empty() {
+                        println!("✅ 配置检查通过");
+                    } else {
+                        println!("✅ 配置有效，但有 {} 条警告", warnings.len());
+                    }
+                    Ok(())
+                }
+                Err(e) => {
+                    println!("❌ {}", e);
+                    Err(e)
+                }
+            }
+        }
+    }
+}
+
+/// Outcome of one `gearclaw doctor` check
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DoctorCheck {
+    name: &'static str,
+    ok: bool,
+    /// A failed fatal check makes `doctor` exit non-zero
+    fatal: bool,
+    detail: String,
+    /// How to fix a failed check
+    hint: Option<String>,
+}
+
+impl DoctorCheck {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            ok: true,
+            fatal: false,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn fail(
+        name: &'static str,
+        fatal: bool,
+        detail: impl Into<String>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            name,
+            ok: false,
+            fatal,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+}
+
+const DOCTOR_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+async fn handle_doctor(config_path: &Option<String>) -> Result<(), GearClawError> {
+    let config = match Config::load(config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("❌ 配置: {}", e);
+            println!("   → 运行 `gearclaw init` 生成配置，或用 -c 指定配置文件");
+            return Err(e);
+        }
+    };
+
+    let endpoint = doctor_endpoint(&config, std::env::var("OPENAI_BASE_URL").ok());
+    let needs_git = config.agent.skill_sources.iter().any(|source| {
+        source.enabled && source.kind == gearclaw_core::config::SkillSourceKind::GitRepo
+    });
+    let checks = [
+        check_api_key(&config, std::env::var("OPENAI_API_KEY").ok()),
+        check_endpoint(&endpoint, DOCTOR_CONNECT_TIMEOUT).await,
+        check_git("git", needs_git),
+        check_memory_db(&config.memory.db_path, config.memory.enabled),
+        check_skills_path(&config.agent.skills_path),
+    ];
+
+    for check in &checks {
+        let mark = if check.ok { "✅" } else { "❌" };
+        println!("{} {}: {}", mark, check.name, check.detail);
+        if let Some(hint) = check.hint.as_deref().filter(|_| !check.ok) {
+            println!("   → {}", hint);
+        }
+    }
+    let fatal = checks.iter().filter(|c| !c.ok && c.fatal).count();
+    if fatal > 0 {
+        return Err(GearClawError::Other(format!(
+            "doctor: {} fatal check(s) failed",
+            fatal
+        )));
+    }
+    println!("✅ 环境检查完成");
+    Ok(())
+}
+
+/// Endpoint the agent will use: `OPENAI_BASE_URL` replaces the default one
+fn doctor_endpoint(config: &Config, env_base_url: Option<String>) -> String {
+    match env_base_url {
+        Some(url) if config.llm.endpoint == gearclaw_core::config::default_endpoint() => url,
+        _ => config.llm.endpoint.clone(),
+    }
+}
+
+fn check_api_key(config: &Config, env_key: Option<String>) -> DoctorCheck {
+    let has = |key: &Option<String>| key.as_deref().is_some_and(|k| !k.trim().is_empty());
+    if has(&config.llm.api_key) {
+        DoctorCheck::pass("API key", "已在配置文件中设置")
+    } else if has(&env_key) {
+        DoctorCheck::pass("API key", "使用环境变量 OPENAI_API_KEY")
+    } else {
+        DoctorCheck::fail(
+            "API key",
+            true,
+            "未设置",
+            "在配置的 llm.api_key 中填写，或设置环境变量 OPENAI_API_KEY",
+        )
+    }
+}
+
+/// Host and port of an `http(s)://host[:port]/...` URL
+fn endpoint_address(endpoint: &str) -> Option<(String, u16)> {
+    let (default_port, rest) = if let Some(rest) = endpoint.strip_prefix("https://") {
+        (443, rest)
+    } else if let Some(rest) = endpoint.strip_prefix("http://") {
+        (80, rest)
+    } else {
+        return None;
+    };
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority.rsplit('@').next()?;
+    if authority.is_empty() {
+        return None;
+    }
+    let (host, port) = match authority.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, after) = bracketed.split_once(']')?;
+            (host, after.strip_prefix(':'))
+        }
+        None => match authority.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().ok()?,
+        None => default_port,
+    };
+    Some((host.to_string(), port))
+}
+
+async fn check_endpoint(endpoint: &str, timeout: std::time::Duration) -> DoctorCheck {
+    let Some((host, port)) = endpoint_address(endpoint) else {
+        return DoctorCheck::fail(
+            "Endpoint",
+            true,
+            format!("无法解析地址 {}", endpoint),
+            "llm.endpoint 应形如 https://api.openai.com/v1",
+        );
+    };
+    let connect = tokio::net::TcpStream::connect((host.as_str(), port));
+    match tokio::time::timeout(timeout, connect).await {
+        Ok(Ok(_)) => DoctorCheck::pass("Endpoint", format!("{} 可连接", endpoint)),
+        Ok(Err(e)) => DoctorCheck::fail(
+            "Endpoint",
+            true,
+            format!("无法连接 {}: {}", endpoint, e),
+            "检查 llm.endpoint / OPENAI_BASE_URL、网络与代理设置",
+        ),
+        Err(_) => DoctorCheck::fail(
+            "Endpoint",
+            true,
+            format!("连接 {} 超时（{} 秒）", endpoint, timeout.as_secs()),
+            "检查 llm.endpoint / OPENAI_BASE_URL、网络与代理设置",
+        ),
+    }
+}
+
+/// `required` when a git skill source is configured
+fn check_git(program: &str, required: bool) -> DoctorCheck {
+    match Command::new(program).arg("--version").output() {
+        Ok(output) if output.status.success() => DoctorCheck::pass(
+            "git",
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        _ => DoctorCheck::fail(
+            "git",
+            required,
+            if required {
+                "未找到，git 技能源无法同步"
+            } else {
+                "未找到（仅 git 技能源需要）"
+            },
+            "安装 git 并确保其在 PATH 中",
+        ),
+    }
+}
+
+/// The database file, or the directory it will be created in, must be
+/// writable. Fatal only when memory is enabled.
+fn check_memory_db(db_path: &Path, enabled: bool) -> DoctorCheck {
+    let result = if db_path.exists() {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(db_path)
+            .map(|_| ())
+    } else {
+        // Probe the nearest existing ancestor: the agent creates the rest
+        // on first use, and doctor should not
+        let parent = db_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let existing = parent
+            .ancestors()
+            .find(|p| p.as_os_str().is_empty() || p.exists())
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        if existing.is_dir() {
+            let probe = existing.join(format!(".gearclaw-doctor-{}", std::process::id()));
+            std::fs::write(&probe, b"").and_then(|_| std::fs::remove_file(&probe))
+        } else {
+            Err(std::io::Error::other(format!(
+                "{} 不是目录",
+                existing.display()
+            )))
+        }
+    };
+    match result {
+        Ok(()) => DoctorCheck::pass("Memory DB", format!("{} 可写", db_path.display())),
+        Err(e) => DoctorCheck::fail(
+            "Memory DB",
+            enabled,
+            format!("{} 不可写: {}", db_path.display(), e),
+            "修改 memory.db_path 或目录权限",
+        ),
+    }
+}
+
+/// A missing skills directory only means no skills are loaded
+fn check_skills_path(skills_path: &Path) -> DoctorCheck {
+    if !skills_path.exists() {
+        return DoctorCheck::fail(
+            "Skills",
+            false,
+            format!("{} 不存在，不会加载任何技能", skills_path.display()),
+            format!(
+                "创建该目录（mkdir -p {}）或修改 agent.skills_path",
+                skills_path.display()
+            ),
+        );
+    }
+    match std::fs::read_dir(skills_path) {
+        Ok(_) => DoctorCheck::pass("Skills", format!("{} 可读", skills_path.display())),
+        Err(e) => DoctorCheck::fail(
+            "Skills",
+            true,
+            format!("{} 不可读: {}", skills_path.display(), e),
+            "修改 agent.skills_path 或目录权限",
+        ),
+    }
+}
+
+async fn handle_token_command(
+    config: &Config,
+    command: &TokenCommands,
+) -> Result<(), GearClawError> {
+    let auth = gearclaw_gateway::TokenAuth::load(&config.gateway.tokens_path)
+        .map_err(|e| GearClawError::Other(e.to_string()))?;
+    match command {
+        TokenCommands::Create { device_id, scopes } => {
+            let token = format!(
+                "{}{}",
+                uuid::Uuid::new_v4().simple(),
+                uuid::Uuid::new_v4().simple()
+            );
+            let scopes = scopes.as_ref().map(|s| s.iter().cloned().collect());
+            let id = auth
+                .register_with_scopes(
+                    token.clone(),
+                    device_id.clone(),
+                    "gateway".to_string(),
+                    scopes,
+                )
+                .await
+                .map_err(|e| GearClawError::Other(e.to_string()))?;
+            println!("✅ 已创建令牌 {} (设备: {})", id, device_id);
+            println!("   {}", token);
+            println!("   令牌仅显示这一次，请妥善保存");
+        }
+        TokenCommands::List => {
+            let tokens = auth.list().await;
+            if tokens.is_empty() {
+                println!("没有已注册的令牌");
+                return Ok(());
+            }
+            println!(
+                "{:<12}  {:<20}  {:<20}  {:<20}  权限",
+                "ID", "设备", "创建时间", "最后使用"
+            );
+            for info in tokens {
+                let scopes = match &info.scopes {
+                    Some(scopes) => {
+                        let mut scopes: Vec<&str> = scopes.iter().map(String::as_str).collect();
+                        scopes.sort();
+                        scopes.join(",")
+                    }
+                    None => "*".to_string(),
+                };
+                println!(
+                    "{:<12}  {:<20}  {:<20}  {:<20}  {}",
+                    info.id,
+                    info.device_id,
+                    info.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    info.last_used
+                        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    scopes
+                );
+            }
+        }
+        TokenCommands::Revoke { id } => {
+            let removed = auth
+                .revoke_id(id)
+                .await
+                .map_err(|e| GearClawError::Other(e.to_string()))?;
+            if removed {
+                println!("✅ 已撤销令牌 {}", id);
+            } else {
+                println!("⚠️  未找到令牌 {}", id);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_session_command(
+    config: &Config,
+    command: &SessionCommands,
+) -> Result<(), GearClawError> {
+    let manager = SessionManager::new(config.session.clone())?;
+    match command {
+        SessionCommands::List => {
+            let sessions = manager.list_sessions_detailed()?;
+            if sessions.is_empty() {
+                println!("没有会话记录");
+                return Ok(());
+            }
+            let id_width = sessions
+                .iter()
+                .map(|s| s.id.len())
+                .max()
+                .unwrap_or(0)
+                .max("ID".len());
+            println!(
+                "{:<id_width$}  {:>8}  {:<19}  {:>10}",
+                "ID", "MESSAGES", "UPDATED", "SIZE"
+            );
+            for session in sessions {
+                println!(
+                    "{:<id_width$}  {:>8}  {:<19}  {:>10}",
+                    session.id,
+                    session.message_count,
+                    session.updated_at.format("%Y-%m-%d %H:%M:%S"),
+                    format_size(session.size_bytes)
+                );
+            }
+        }
+        SessionCommands::Fork {
+            source_id,
+            new_id,
+            force,
+        } => {
+            if *force && source_id != new_id {
+                // Make sure the source exists before discarding the target.
+                manager.load_session(source_id)?;
+                manager.delete_session(new_id)?;
+            }
+            let forked = manager.fork(source_id, new_id).await?;
+            println!(
+                "✅ 已从 {} 分叉出新会话 {} ({} 条消息)",
+                source_id,
+                forked.id,
+                forked.messages.len()
+            );
+        }
+        SessionCommands::Prune { older_than } => {
+            let max_age = parse_age(older_than).ok_or_else(|| {
+                GearClawError::config_parse_error(format!(
+                    "无效的时长: {}（示例: 30d, 12h, 45m, 2w）",
+                    older_than
+                ))
+            })?;
+            let report = manager.prune(max_age)?;
+            if report.pruned.is_empty() && report.failed.is_empty() {
+                println!("没有需要清理的会话");
+            }
+            if !report.pruned.is_empty() {
+                println!("🧹 已清理 {} 个会话:", report.pruned.len());
+                for id in &report.pruned {
+                    println!("  • {}", id);
+                }
+            }
+            if !report.failed.is_empty() {
+                println!("⚠️  {} 个会话清理失败:", report.failed.len());
+                for id in &report.failed {
+                    println!("  • {}", id);
+                }
+            }
+        }
+        SessionCommands::Export {
+            session_id,
+            format,
+            output,
+        } => {
+            let rendered = match format.as_str() {
+                "json" => {
+                    let session = manager.load_session(session_id)?;
+                    serde_json::to_string_pretty(&session)?
+                }
+                _ => manager.export_markdown(session_id)?,
+            };
+            match output {
+                Some(path) => {
+                    std::fs::write(path, rendered)?;
+                    println!("✅ 会话已导出: {:?}", path);
+                }
+                None => println!("{}", rendered),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse ages like `30d`, `12h`, `45m`, `90s` or `2w`.
+fn parse_age(input: &str) -> Option<std::time::Duration> {
+    let input = input.trim();
+    let split = input.find(|ch: char| !ch.is_ascii_digit())?;
+    let (value, unit) = input.split_at(split);
+    let value: u64 = value.parse().ok()?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    Some(std::time::Duration::from_secs(value.checked_mul(seconds)?))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
 fn print_banner() {
     println!(
         r#"
@@ -363,22 +993,11 @@ fn handle_init() -> Result<(), GearClawError> {
         // Create a sample skill
         let sample_skill_dir = skills_dir.join("hello");
         std::fs::create_dir_all(&sample_skill_dir).map_err(GearClawError::IoError)?;
-        let skill_md = r#"---
-name: hello_world
-description: A simple hello world skill
-metadata: {}
----
-
-# Hello World Skill
-
-This skill allows you to say hello.
-
-```bash
-echo "Hello from GearClaw Skill!"
-```
-"#;
-        std::fs::write(sample_skill_dir.join("SKILL.md"), skill_md)
-            .map_err(GearClawError::IoError)?;
+        std::fs::write(
+            sample_skill_dir.join("SKILL.md"),
+            gearclaw_core::skills::SAMPLE_SKILL_MD,
+        )
+        .map_err(GearClawError::IoError)?;
         println!("✅ 创建示例技能: hello_world");
     }
 
@@ -397,7 +1016,7 @@ echo "Hello from GearClaw Skill!"
     Ok(())
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 struct EffectiveSkillSource {
     name: String,
     kind: SkillSourceKind,
@@ -406,9 +1025,11 @@ struct EffectiveSkillSource {
     enabled: bool,
     trusted: bool,
     verify_head_commit_signature: bool,
+    allowed_signers: Vec<String>,
+    subdir: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 struct SkillCatalogEntry {
     name: String,
     description: String,
@@ -422,6 +1043,27 @@ struct SkillCatalogEntry {
     skill_dir: PathBuf,
 }
 
+/// All skills sharing a name (case-insensitive): the installed copy, if
+/// any, and every source that provides one
+#[derive(Debug, Clone)]
+struct SkillListing {
+    name: String,
+    installed: Option<(String, PathBuf)>,
+    sources: Vec<SkillCatalogEntry>,
+}
+
+impl SkillListing {
+    /// More than one source provides this name
+    fn is_conflict(&self) -> bool {
+        self.sources.len() > 1
+    }
+
+    /// The installed copy hides a source skill of the same name
+    fn is_shadowing(&self) -> bool {
+        self.installed.is_some() && !self.sources.is_empty()
+    }
+}
+
 #[derive(Debug, Clone)]
 struct GitSyncResult {
     cache_dir: PathBuf,
@@ -429,41 +1071,88 @@ struct GitSyncResult {
     signature_verified: bool,
 }
 
+/// Integrity record for an installed skill, kept in `skill.lock`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SkillLockEntry {
+    source: String,
+    #[serde(default)]
+    commit: Option<String>,
+    /// SHA-256 over the skill's files, see [`skill_content_hash`]
+    content_hash: String,
+    installed_at: u64,
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 struct DiscoveryOptions {
     force_update: bool,
 }
 
+/// Rotate the skill install audit log once it reaches this size
+const SKILL_AUDIT_LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// Rotated audit logs kept (`.log.1` is the newest)
+const SKILL_AUDIT_LOG_KEEP: usize = 5;
+
 #[derive(Debug, Clone)]
 struct AuditRecord {
     timestamp: u64,
     fields: BTreeMap<String, String>,
 }
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum AuditOutputFormat {
+enum OutputFormat {
     Text,
     Json,
     Jsonl,
 }
 
-fn resolve_audit_output_format(
-    output: &str,
-    json_flag: bool,
-) -> Result<AuditOutputFormat, GearClawError> {
+fn resolve_output_format(output: &str, json_flag: bool) -> Result<OutputFormat, GearClawError> {
     if json_flag {
-        return Ok(AuditOutputFormat::Json);
+        return Ok(OutputFormat::Json);
     }
     match output {
-        "text" => Ok(AuditOutputFormat::Text),
-        "json" => Ok(AuditOutputFormat::Json),
-        "jsonl" => Ok(AuditOutputFormat::Jsonl),
+        "text" => Ok(OutputFormat::Text),
+        "json" => Ok(OutputFormat::Json),
+        "jsonl" => Ok(OutputFormat::Jsonl),
         other => Err(GearClawError::Other(format!(
-            "Unsupported audit output format: {}",
+            "Unsupported output format: {}",
             other
         ))),
     }
 }
 
+/// Render `items` as a JSON array or one JSON object per line. Text output
+/// is command-specific, so it renders nothing here.
+fn render_structured<T: Serialize>(
+    items: &[T],
+    format: OutputFormat,
+) -> Result<String, GearClawError> {
+    let to_err =
+        |e: serde_json::Error| GearClawError::Other(format!("serialize json failed: {}", e));
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(items).map_err(to_err),
+        OutputFormat::Jsonl => items
+            .iter()
+            .map(|item| serde_json::to_string(item).map_err(to_err))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|lines| lines.join("\n")),
+        OutputFormat::Text => Ok(String::new()),
+    }
+}
+
+/// Print `items` as JSON/JSONL; returns false for text so the caller prints
+fn print_structured<T: Serialize>(
+    items: &[T],
+    format: OutputFormat,
+) -> Result<bool, GearClawError> {
+    if format == OutputFormat::Text {
+        return Ok(false);
+    }
+    let out = render_structured(items, format)?;
+    if !out.is_empty() {
+        println!("{}", out);
+    }
+    Ok(true)
+}
+
 impl AuditRecord {
     fn field(&self, key: &str) -> Option<&str> {
         self.fields.get(key).map(String::as_str)
@@ -531,13 +1220,15 @@ fn split_escaped_audit_fields(line: &str) -> Vec<String> {
 fn effective_skill_sources(config: &Config) -> Vec<EffectiveSkillSource> {
     if config.agent.skill_sources.is_empty() {
         return vec![EffectiveSkillSource {
-            name: "local-default".to_string(),
+            name: gearclaw_core::skills::DEFAULT_SKILL_SOURCE.to_string(),
             kind: SkillSourceKind::LocalDir,
             location: config.agent.skills_path.clone(),
             revision: None,
             enabled: true,
             trusted: true,
             verify_head_commit_signature: false,
+            allowed_signers: Vec::new(),
+            subdir: None,
         }];
     }
 
@@ -553,6 +1244,8 @@ fn effective_skill_sources(config: &Config) -> Vec<EffectiveSkillSource> {
             enabled: s.enabled,
             trusted: s.trusted,
             verify_head_commit_signature: s.verify_head_commit_signature,
+            allowed_signers: s.allowed_signers.clone(),
+            subdir: s.subdir.clone(),
         })
         .collect()
 }
@@ -574,6 +1267,7 @@ fn git_ref_exists(repo: &Path, revision: &str) -> Result<bool, GearClawError> {
     Ok(output.status == 0)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_list_audit(
     config: &Config,
     limit: usize,
@@ -585,45 +1279,30 @@ fn handle_list_audit(
     json_output: bool,
     output: &str,
 ) -> Result<(), GearClawError> {
-    let output_format = resolve_audit_output_format(output, json_output)?;
+    let output_format = resolve_output_format(output, json_output)?;
     let log_path = skill_install_audit_log_path(config);
-    if !log_path.exists() {
+    if !log_path.exists() && !rotated_audit_log_path(&log_path, 1).exists() {
         match output_format {
-            AuditOutputFormat::Json => println!("[]"),
-            AuditOutputFormat::Jsonl => {}
-            AuditOutputFormat::Text => println!("暂无安装审计日志: {}", log_path.display()),
+            OutputFormat::Json => println!("[]"),
+            OutputFormat::Jsonl => {}
+            OutputFormat::Text => println!("暂无安装审计日志: {}", log_path.display()),
         }
         return Ok(());
     }
 
-    let content = std::fs::read_to_string(&log_path).map_err(GearClawError::IoError)?;
-    let mut records: Vec<AuditRecord> = content
-        .lines()
-        .filter(|l| !l.trim().is_empty())
-        .filter_map(parse_audit_record_line)
-        .collect();
-
-    if let Some(source) = source_filter {
-        records.retain(|r| r.field("source") == Some(source));
-    }
-    if let Some(skill) = skill_filter {
-        records.retain(|r| r.field("skill") == Some(skill));
-    }
-    if let Some(status) = status_filter {
-        records.retain(|r| r.field("status") == Some(status));
-    }
-    if let Some(min_ts) = since {
-        records.retain(|r| r.timestamp >= min_ts);
-    }
-    if let Some(max_ts) = until {
-        records.retain(|r| r.timestamp <= max_ts);
-    }
+    let records = read_audit_records(&log_path, |r| {
+        source_filter.is_none_or(|source| r.field("source") == Some(source))
+            && skill_filter.is_none_or(|skill| r.field("skill") == Some(skill))
+            && status_filter.is_none_or(|status| r.field("status") == Some(status))
+            && since.is_none_or(|min_ts| r.timestamp >= min_ts)
+            && until.is_none_or(|max_ts| r.timestamp <= max_ts)
+    })?;
 
     if records.is_empty() {
         match output_format {
-            AuditOutputFormat::Json => println!("[]"),
-            AuditOutputFormat::Jsonl => {}
-            AuditOutputFormat::Text => println!("安装审计日志为空: {}", log_path.display()),
+            OutputFormat::Json => println!("[]"),
+            OutputFormat::Jsonl => {}
+            OutputFormat::Text => println!("安装审计日志为空: {}", log_path.display()),
         }
         return Ok(());
     }
@@ -631,7 +1310,7 @@ fn handle_list_audit(
     let max_lines = limit.max(1);
     let start = records.len().saturating_sub(max_lines);
     let selected = &records[start..];
-    if output_format == AuditOutputFormat::Text {
+    if output_format == OutputFormat::Text {
         println!(
             "🧾 Skill install audit (latest {} / filtered total {})",
             selected.len(),
@@ -650,58 +1329,48 @@ fn handle_list_audit(
         }
         json_records.push(serde_json::Value::Object(object));
     }
-    match output_format {
-        AuditOutputFormat::Json => {
-            let out = serde_json::to_string_pretty(&json_records)
-                .map_err(|e| GearClawError::Other(format!("serialize audit json failed: {}", e)))?;
-            println!("{}", out);
-        }
-        AuditOutputFormat::Jsonl => {
-            for record in json_records {
-                let line = serde_json::to_string(&record).map_err(|e| {
-                    GearClawError::Other(format!("serialize audit jsonl failed: {}", e))
-                })?;
-                println!("{}", line);
-            }
-        }
-        AuditOutputFormat::Text => {
-            for record in selected {
-                println!(
-                    "  {} | skill={} | source={} | status={} | commit={} | target={}",
-                    record.timestamp,
-                    record.field("skill").unwrap_or("unknown"),
-                    record.field("source").unwrap_or("unknown"),
-                    record.field("status").unwrap_or("unknown"),
-                    record.field("commit").unwrap_or("unknown"),
-                    record.field("target").unwrap_or("unknown"),
-                );
-            }
+    if !print_structured(&json_records, output_format)? {
+        for record in selected {
+            println!(
+                "  {} | skill={} | source={} | status={} | commit={} | target={}",
+                record.timestamp,
+                record.field("skill").unwrap_or("unknown"),
+                record.field("source").unwrap_or("unknown"),
+                record.field("status").unwrap_or("unknown"),
+                record.field("commit").unwrap_or("unknown"),
+                record.field("target").unwrap_or("unknown"),
+            );
         }
     }
 
     Ok(())
 }
 
-fn handle_list_sources(config: &Config) {
+fn handle_list_sources(config: &Config, format: OutputFormat) -> Result<(), GearClawError> {
     let sources = effective_skill_sources(config);
+    if print_structured(&sources, format)? {
+        return Ok(());
+    }
     println!("📦 Skill Sources:");
     if sources.is_empty() {
         println!("  (none)");
-        return;
+        return Ok(());
     }
     for source in sources {
         let revision = source.revision.as_deref().unwrap_or("default");
         println!(
-            "  • {} | kind={} | enabled={} | trusted={} | verify_sig={} | revision={} | location={}",
+            "  • {} | kind={} | enabled={} | trusted={} | verify_sig={} | revision={} | location={} | subdir={}",
             source.name,
             source_kind_label(&source.kind),
             source.enabled,
             source.trusted,
             source.verify_head_commit_signature,
             revision,
-            source.location.display()
+            source.location.display(),
+            source.subdir.as_deref().unwrap_or("-")
         );
     }
+    Ok(())
 }
 
 fn handle_trust_policy(config: &Config) {
@@ -727,6 +1396,7 @@ fn handle_search_skill(
     query: &str,
     source_filter: Option<&str>,
     force_update: bool,
+    format: OutputFormat,
 ) -> Result<(), GearClawError> {
     let all_entries = discover_skills(config, source_filter, DiscoveryOptions { force_update })?;
     let q = query.to_lowercase();
@@ -737,6 +1407,9 @@ fn handle_search_skill(
         })
         .collect();
 
+    if print_structured(&matched, format)? {
+        return Ok(());
+    }
     if matched.is_empty() {
         println!("没有匹配的 skill: {}", query);
         return Ok(());
@@ -760,6 +1433,235 @@ fn handle_search_skill(
     Ok(())
 }
 
+fn handle_skill_command(config: &Config, command: &SkillCommands) -> Result<(), GearClawError> {
+    match command {
+        SkillCommands::Cache { command } => match command {
+            SkillCacheCommands::Info => handle_skill_cache_info(config),
+            SkillCacheCommands::Clear { source } => {
+                let removed = clear_skill_source_cache(config, source.as_deref())?;
+                if removed.is_empty() {
+                    println!("没有需要清理的缓存");
+                }
+                for dir in removed {
+                    println!("🗑️ 已删除缓存: {}", dir.display());
+                }
+                Ok(())
+            }
+        },
+    }
+}
+
+fn handle_skill_cache_info(config: &Config) -> Result<(), GearClawError> {
+    let cache_root = skill_source_cache_root(config);
+    let mut dirs = cached_source_dirs(&cache_root)?;
+    if dirs.is_empty() {
+        println!("暂无技能来源缓存: {}", cache_root.display());
+        return Ok(());
+    }
+    dirs.sort();
+
+    let sources = effective_skill_sources(config);
+    let now = now_epoch_secs();
+    println!("🗃️ Skill source cache ({}):", cache_root.display());
+    for dir in dirs {
+        let owner = sources
+            .iter()
+            .filter(|s| s.kind == SkillSourceKind::GitRepo)
+            .find(|s| source_cache_dir(config, s) == dir)
+            .map(|s| s.name.as_str())
+            .unwrap_or("(unconfigured)");
+        let last_sync = match source_last_sync_epoch(&dir) {
+            Some(epoch) => format!("{} ({}s ago)", epoch, now.saturating_sub(epoch)),
+            None => "never".to_string(),
+        };
+        println!(
+            "  • {} | size={} | last_sync={} | {}",
+            owner,
+            format_size(dir_size(&dir)),
+            last_sync,
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// Remove cached clones: every entry under the cache root, or only the named
+/// source's. Returns the removed directories.
+fn clear_skill_source_cache(
+    config: &Config,
+    source_name: Option<&str>,
+) -> Result<Vec<PathBuf>, GearClawError> {
+    let cache_root = skill_source_cache_root(config);
+    if !cache_root.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let targets = match source_name {
+        Some(name) => {
+            let source = effective_skill_sources(config)
+                .into_iter()
+                .find(|s| s.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| GearClawError::Other(format!("Unknown skill source '{}'", name)))?;
+            let dir = source_cache_dir(config, &source);
+            if dir.exists() {
+                vec![dir]
+            } else {
+                Vec::new()
+            }
+        }
+        None => cached_source_dirs(&cache_root)?,
+    };
+
+    let root = std::fs::canonicalize(&cache_root).map_err(GearClawError::IoError)?;
+    for dir in &targets {
+        // A symlinked entry is removed as a link; never follow it out of the root
+        let is_link = std::fs::symlink_metadata(dir)
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false);
+        if is_link {
+            std::fs::remove_file(dir).map_err(GearClawError::IoError)?;
+            continue;
+        }
+        let resolved = std::fs::canonicalize(dir).map_err(GearClawError::IoError)?;
+        if resolved == root || !resolved.starts_with(&root) {
+            return Err(GearClawError::Other(format!(
+                "Refusing to remove {}: it is outside the skill source cache {}",
+                resolved.display(),
+                root.display()
+            )));
+        }
+        std::fs::remove_dir_all(dir).map_err(GearClawError::IoError)?;
+    }
+    Ok(targets)
+}
+
+fn cached_source_dirs(cache_root: &Path) -> Result<Vec<PathBuf>, GearClawError> {
+    if !cache_root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut dirs = Vec::new();
+    for entry in std::fs::read_dir(cache_root).map_err(GearClawError::IoError)? {
+        dirs.push(entry.map_err(GearClawError::IoError)?.path());
+    }
+    Ok(dirs)
+}
+
+/// Total size of regular files under `path`, not following symlinks
+fn dir_size(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| dir_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn handle_list_skills(
+    config: &Config,
+    source_filter: Option<&str>,
+    force_update: bool,
+) -> Result<(), GearClawError> {
+    let entries = discover_skills(config, source_filter, DiscoveryOptions { force_update })?;
+    let listings = list_skills(config, entries)?;
+    if listings.is_empty() {
+        println!("没有已安装或可用的 skill");
+        return Ok(());
+    }
+
+    println!("📚 Skills ({}):", listings.len());
+    for listing in &listings {
+        match &listing.installed {
+            Some((description, dir)) => println!(
+                "  • {}  [installed: {}]  {}",
+                listing.name,
+                dir.display(),
+                description
+            ),
+            None => println!("  • {}", listing.name),
+        }
+        for source in &listing.sources {
+            println!(
+                "      ↳ [{}] trusted={} rev={}  {}",
+                source.source_name,
+                source.source_trusted,
+                source.source_revision.as_deref().unwrap_or("default"),
+                source.description
+            );
+        }
+        if listing.is_conflict() {
+            let names: Vec<&str> = listing
+                .sources
+                .iter()
+                .map(|s| s.source_name.as_str())
+                .collect();
+            println!(
+                "      ⚠️ 冲突: {} 个来源提供同名 skill（{}），安装时需用 --source 指定",
+                names.len(),
+                names.join(", ")
+            );
+        }
+        if listing.is_shadowing() {
+            println!("      ⚠️ 已安装版本会覆盖来源中的同名 skill");
+        }
+    }
+    Ok(())
+}
+
+/// Group discovered source skills with the skills installed under
+/// `skills_path`, by case-insensitive name
+fn list_skills(
+    config: &Config,
+    entries: Vec<SkillCatalogEntry>,
+) -> Result<Vec<SkillListing>, GearClawError> {
+    let mut listings: BTreeMap<String, SkillListing> = BTreeMap::new();
+    let skills_root = std::fs::canonicalize(&config.agent.skills_path).ok();
+
+    for skill_file in collect_skill_files(&config.agent.skills_path)? {
+        let Ok((name, description)) = parse_skill_metadata(&skill_file) else {
+            continue;
+        };
+        let dir = skill_file.parent().unwrap_or(&skill_file).to_path_buf();
+        listings
+            .entry(name.to_lowercase())
+            .or_insert_with(|| SkillListing {
+                name: name.clone(),
+                installed: None,
+                sources: Vec::new(),
+            })
+            .installed = Some((description, dir));
+    }
+
+    for entry in entries {
+        // A source rooted at skills_path (the default) lists the installed
+        // copies themselves; they aren't a separate provider.
+        let installed_copy = skills_root.as_ref().is_some_and(|root| {
+            std::fs::canonicalize(&entry.skill_dir).is_ok_and(|dir| dir.starts_with(root))
+        });
+        if installed_copy {
+            continue;
+        }
+        listings
+            .entry(entry.name.to_lowercase())
+            .or_insert_with(|| SkillListing {
+                name: entry.name.clone(),
+                installed: None,
+                sources: Vec::new(),
+            })
+            .sources
+            .push(entry);
+    }
+
+    Ok(listings.into_values().collect())
+}
+
 fn handle_install_skill(
     config: &Config,
     name: &str,
@@ -831,6 +1733,12 @@ fn handle_install_skill(
         }
     }
 
+    let content_hash = skill_content_hash(&selected.skill_dir)?;
+    let mut lock = read_skill_lock(config)?;
+    if let Some(warning) = skill_lock_drift(&lock, &selected, &content_hash) {
+        println!("{}", warning);
+    }
+
     if dry_run {
         println!("🧪 Dry-run: install plan");
         println!("  skill: {}", selected.name);
@@ -851,6 +1759,16 @@ fn handle_install_skill(
     }
 
     copy_dir_recursive(&selected.skill_dir, &target_dir)?;
+    lock.insert(
+        selected.name.clone(),
+        SkillLockEntry {
+            source: selected.source_name.clone(),
+            commit: selected.source_head_commit.clone(),
+            content_hash,
+            installed_at: now_epoch_secs(),
+        },
+    );
+    write_skill_lock(config, &lock)?;
     append_install_audit_log(
         config,
         &selected,
@@ -871,6 +1789,106 @@ fn handle_install_skill(
     Ok(())
 }
 
+fn handle_uninstall_skill(config: &Config, name: &str, dry_run: bool) -> Result<(), GearClawError> {
+    let target_root = &config.agent.skills_path;
+    let install_dir_name = sanitize_skill_dir_name(name).ok_or_else(|| {
+        GearClawError::Other(format!(
+            "Skill name '{}' cannot be converted to a valid directory name",
+            name
+        ))
+    })?;
+    let target_dir = target_root.join(install_dir_name);
+
+    if !target_dir.is_dir() {
+        println!(
+            "❌ 未安装 skill: {}（{} 不存在）",
+            name,
+            target_dir.display()
+        );
+        return Ok(());
+    }
+
+    // Resolve symlinks so the removal can't escape skills_path
+    let root = std::fs::canonicalize(target_root).map_err(GearClawError::IoError)?;
+    let resolved = std::fs::canonicalize(&target_dir).map_err(GearClawError::IoError)?;
+    if resolved == root || !resolved.starts_with(&root) {
+        return Err(GearClawError::Other(format!(
+            "Refusing to remove {}: it is outside skills_path {}",
+            resolved.display(),
+            root.display()
+        )));
+    }
+
+    let entry = installed_skill_entry(config, name, &target_dir);
+
+    if dry_run {
+        println!("🧪 Dry-run: uninstall plan");
+        println!("  skill: {}", entry.name);
+        println!("  source: {}", entry.source_name);
+        println!("  target: {}", target_dir.display());
+        println!("  no files were changed.");
+        return Ok(());
+    }
+
+    std::fs::remove_dir_all(&target_dir).map_err(GearClawError::IoError)?;
+    let mut lock = read_skill_lock(config)?;
+    if lock.remove(&entry.name).is_some() {
+        write_skill_lock(config, &lock)?;
+    }
+    append_install_audit_log(
+        config,
+        &entry,
+        &target_dir,
+        trust_policy_label(&config.agent.skill_trust_policy),
+        "uninstalled",
+    )?;
+    println!(
+        "✅ 已卸载 skill '{}'（{}）",
+        entry.name,
+        target_dir.display()
+    );
+    println!("提示: 变更在新建 Agent 会话后生效。");
+
+    Ok(())
+}
+
+/// Catalog entry for an installed skill, with source details taken from its
+/// latest `installed` audit record when there is one
+fn installed_skill_entry(config: &Config, name: &str, skill_dir: &Path) -> SkillCatalogEntry {
+    let (skill_name, description) = parse_skill_metadata(&skill_dir.join("SKILL.md"))
+        .unwrap_or_else(|_| (name.to_string(), "No description".to_string()));
+
+    let record = read_audit_records(&skill_install_audit_log_path(config), |r| {
+        r.field("status") == Some("installed")
+            && r.field("skill")
+                .is_some_and(|s| s.eq_ignore_ascii_case(&skill_name))
+    })
+    .ok()
+    .and_then(|mut records| records.pop());
+    let field = |key: &str| record.as_ref().and_then(|r| r.field(key));
+
+    SkillCatalogEntry {
+        name: skill_name.clone(),
+        description,
+        source_name: field("source").unwrap_or("unknown").to_string(),
+        source_kind: if field("kind") == Some("git_repo") {
+            SkillSourceKind::GitRepo
+        } else {
+            SkillSourceKind::LocalDir
+        },
+        source_location: PathBuf::from(field("location").unwrap_or("")),
+        source_revision: field("revision")
+            .filter(|r| *r != "default")
+            .map(str::to_string),
+        source_head_commit: field("commit")
+            .filter(|c| *c != "unknown")
+            .map(str::to_string),
+        source_signature_verified: field("sig_verified") == Some("true"),
+        source_trusted: field("trusted") == Some("true"),
+        skill_dir: skill_dir.to_path_buf(),
+    }
+}
+
 fn discover_skills(
     config: &Config,
     source_filter: Option<&str>,
@@ -904,7 +1922,21 @@ fn discover_skills(
             }
         };
 
-        for skill_file in collect_skill_files(&source_root)? {
+        let scan_root = match source.subdir.as_deref() {
+            Some(subdir) => source_root.join(subdir),
+            None => source_root,
+        };
+        if !scan_root.is_dir() {
+            tracing::warn!(
+                "Skill source '{}' has no directory at {}; skipping it",
+                source.name,
+                std::path::absolute(&scan_root)
+                    .unwrap_or_else(|_| scan_root.clone())
+                    .display()
+            );
+            continue;
+        }
+        for skill_file in collect_skill_files(&scan_root)? {
             match parse_skill_metadata(&skill_file) {
                 Ok((name, description)) => {
                     if let Some(skill_dir) = skill_file.parent() {
@@ -954,11 +1986,8 @@ fn sync_git_source(
         )));
     }
 
-    let cache_root = skill_source_cache_root(config);
-    std::fs::create_dir_all(&cache_root).map_err(GearClawError::IoError)?;
-
-    let source_slug = sanitize_skill_dir_name(&source.name).unwrap_or_else(|| "source".to_string());
-    let cache_dir = cache_root.join(format!("{}-{:016x}", source_slug, stable_hash(&location)));
+    std::fs::create_dir_all(skill_source_cache_root(config)).map_err(GearClawError::IoError)?;
+    let cache_dir = source_cache_dir(config, source);
     let git_dir = cache_dir.join(".git");
     let mut needs_fresh_clone = !cache_dir.exists() || !git_dir.exists();
     let mut did_network_update = false;
@@ -976,18 +2005,14 @@ fn sync_git_source(
             std::fs::remove_dir_all(&cache_dir).map_err(GearClawError::IoError)?;
         }
 
-        let clone_result = run_git_command(
-            None,
-            [
-                "clone",
-                "--depth",
-                "1",
-                "--quiet",
-                location.as_str(),
-                cache_dir.to_string_lossy().as_ref(),
-            ]
-            .as_slice(),
-        )?;
+        let cache_dir_arg = cache_dir.to_string_lossy().to_string();
+        let mut clone_args = vec!["clone", "--depth", "1", "--quiet"];
+        if source.subdir.is_some() {
+            // Fetch blobs lazily; the sparse checkout below pulls only the subdir
+            clone_args.extend(["--filter=blob:none", "--no-checkout"]);
+        }
+        clone_args.extend([location.as_str(), cache_dir_arg.as_str()]);
+        let clone_result = run_git_command(None, &clone_args)?;
         if clone_result.status != 0 {
             return Err(GearClawError::Other(format!(
                 "Failed to clone source '{}' from '{}': {}",
@@ -1042,6 +2067,7 @@ fn sync_git_source(
         checkout_ref = "FETCH_HEAD".to_string();
     }
 
+    apply_sparse_checkout(&cache_dir, source)?;
     let checkout_result = run_git_command(
         Some(&cache_dir),
         ["checkout", "--force", "--detach", checkout_ref.as_str()].as_slice(),
@@ -1063,12 +2089,23 @@ fn sync_git_source(
                 source.name, verify_result.stderr
             )));
         }
+        if !source.allowed_signers.is_empty() {
+            let signer = run_git_command(
+                Some(&cache_dir),
+                ["log", "-1", "--format=%GF%n%GK", "HEAD"].as_slice(),
+            )?;
+            if signer.status != 0 {
+                return Err(GearClawError::Other(format!(
+                    "Failed to read signing key for source '{}': {}",
+                    source.name, signer.stderr
+                )));
+            }
+            check_commit_signer(&source.name, &source.allowed_signers, &signer.stdout)?;
+        }
         signature_verified = true;
     }
 
-    if did_network_update {
-        write_source_last_sync_epoch(&cache_dir, now_epoch_secs())?;
-    } else if source_last_sync_epoch(&cache_dir).is_none() {
+    if did_network_update || source_last_sync_epoch(&cache_dir).is_none() {
         write_source_last_sync_epoch(&cache_dir, now_epoch_secs())?;
     }
     let head_commit = git_head_commit(&cache_dir)?;
@@ -1079,6 +2116,88 @@ fn sync_git_source(
     })
 }
 
+/// Restrict the cache's working tree to `source.subdir`, or restore the full
+/// tree if a subdir was configured before and has since been removed
+fn apply_sparse_checkout(
+    cache_dir: &Path,
+    source: &EffectiveSkillSource,
+) -> Result<(), GearClawError> {
+    let result = match source.subdir.as_deref() {
+        Some(subdir) => run_git_command(
+            Some(cache_dir),
+            ["sparse-checkout", "set", "--cone", "--", subdir].as_slice(),
+        )?,
+        None => {
+            let sparse = run_git_command(
+                Some(cache_dir),
+                ["config", "--get", "core.sparseCheckout"].as_slice(),
+            )?;
+            if sparse.stdout != "true" {
+                return Ok(());
+            }
+            run_git_command(Some(cache_dir), ["sparse-checkout", "disable"].as_slice())?
+        }
+    };
+    if result.status != 0 {
+        return Err(GearClawError::Other(format!(
+            "Failed to configure sparse checkout for source '{}': {}",
+            source.name, result.stderr
+        )));
+    }
+    Ok(())
+}
+
+/// Check the signing key reported by `git log --format=%GF%n%GK` against a
+/// source's `allowed_signers`. An entry may be a full fingerprint or a long
+/// (16 hex digit) key ID; case and spaces are ignored.
+fn check_commit_signer(
+    source_name: &str,
+    allowed_signers: &[String],
+    git_log_output: &str,
+) -> Result<(), GearClawError> {
+    fn normalize(key: &str) -> String {
+        key.chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_uppercase()
+    }
+
+    let signer_keys: Vec<String> = git_log_output
+        .lines()
+        .map(normalize)
+        .filter(|key| !key.is_empty())
+        .collect();
+    if signer_keys.is_empty() {
+        return Err(GearClawError::Other(format!(
+            "Signature verification failed for source '{}': no signing key found on HEAD",
+            source_name
+        )));
+    }
+
+    let allowed = allowed_signers.iter().map(|s| normalize(s)).any(|allowed| {
+        // Short IDs are trivially forged, so need at least a long key ID
+        allowed.len() >= 16
+            && signer_keys
+                .iter()
+                .any(|key| key.ends_with(&allowed) || allowed.ends_with(key.as_str()))
+    });
+    if !allowed {
+        return Err(GearClawError::Other(format!(
+            "Signature verification failed for source '{}': signing key {} is not in allowed_signers",
+            source_name,
+            signer_keys.join("/")
+        )));
+    }
+    Ok(())
+}
+
+/// Where a git source's clone lives under [`skill_source_cache_root`]
+fn source_cache_dir(config: &Config, source: &EffectiveSkillSource) -> PathBuf {
+    let location = source.location.to_string_lossy().trim().to_string();
+    let source_slug = sanitize_skill_dir_name(&source.name).unwrap_or_else(|| "source".to_string());
+    skill_source_cache_root(config).join(format!("{}-{:016x}", source_slug, stable_hash(&location)))
+}
+
 fn skill_source_cache_root(config: &Config) -> PathBuf {
     config
         .agent
@@ -1198,43 +2317,14 @@ fn collect_skill_files_recursive(root: &Path, out: &mut Vec<PathBuf>) -> Result<
 
 fn parse_skill_metadata(path: &Path) -> Result<(String, String), GearClawError> {
     let content = std::fs::read_to_string(path).map_err(GearClawError::IoError)?;
-    let parts: Vec<&str> = content.splitn(3, "---").collect();
-    if parts.len() < 3 || !parts[0].trim().is_empty() {
-        return Err(GearClawError::Other(format!(
-            "invalid frontmatter format in {}",
-            path.display()
-        )));
-    }
-
-    let frontmatter = parts[1];
-    let mut name: Option<String> = None;
-    let mut description: Option<String> = None;
-
-    for line in frontmatter.lines() {
-        let line = line.trim();
-        if let Some(raw) = line.strip_prefix("name:") {
-            name = Some(parse_frontmatter_scalar(raw));
-        } else if let Some(raw) = line.strip_prefix("description:") {
-            description = Some(parse_frontmatter_scalar(raw));
-        }
-    }
-
-    let name =
-        name.ok_or_else(|| GearClawError::Other(format!("missing `name` in {}", path.display())))?;
-    let description = description.unwrap_or_else(|| "No description".to_string());
-    Ok((name, description))
-}
-
-fn parse_frontmatter_scalar(raw: &str) -> String {
-    let raw = raw.trim();
-    if raw.len() >= 2 {
-        if (raw.starts_with('"') && raw.ends_with('"'))
-            || (raw.starts_with('\'') && raw.ends_with('\''))
-        {
-            return raw[1..raw.len() - 1].trim().to_string();
-        }
-    }
-    raw.to_string()
+    let (meta, _) = gearclaw_core::skills::parse_skill_file(&content)
+        .map_err(|e| GearClawError::Other(format!("{} in {}", e, path.display())))?;
+    let description = if meta.description.trim().is_empty() {
+        "No description".to_string()
+    } else {
+        meta.description
+    };
+    Ok((meta.name, description))
 }
 
 fn sanitize_skill_dir_name(name: &str) -> Option<String> {
@@ -1293,19 +2383,7 @@ fn append_install_audit_log(
     policy_label: &str,
     status: &str,
 ) -> Result<(), GearClawError> {
-    use std::io::Write;
-
     let log_path = skill_install_audit_log_path(config);
-    if let Some(parent) = log_path.parent() {
-        std::fs::create_dir_all(parent).map_err(GearClawError::IoError)?;
-    }
-
-    let mut file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)
-        .map_err(GearClawError::IoError)?;
-
     let ts = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .map(|d| d.as_secs())
@@ -1325,11 +2403,208 @@ fn append_install_audit_log(
         sanitize_log_field(&target_dir.to_string_lossy()),
         sanitize_log_field(status)
     );
+    append_audit_line(&log_path, &line, SKILL_AUDIT_LOG_MAX_BYTES)
+}
+
+/// Append `line`, first rotating the log if it has reached `max_bytes`
+fn append_audit_line(log_path: &Path, line: &str, max_bytes: u64) -> Result<(), GearClawError> {
+    use std::io::Write;
+
+    if let Some(parent) = log_path.parent() {
+        std::fs::create_dir_all(parent).map_err(GearClawError::IoError)?;
+    }
+    let size = std::fs::metadata(log_path).map(|m| m.len()).unwrap_or(0);
+    if size >= max_bytes {
+        rotate_audit_log(log_path)?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .map_err(GearClawError::IoError)?;
     file.write_all(line.as_bytes())
         .map_err(GearClawError::IoError)?;
     Ok(())
 }
 
+/// Shift `log.N` to `log.N+1` (dropping the oldest) and move `log` to `log.1`
+fn rotate_audit_log(log_path: &Path) -> Result<(), GearClawError> {
+    let oldest = rotated_audit_log_path(log_path, SKILL_AUDIT_LOG_KEEP);
+    if oldest.exists() {
+        std::fs::remove_file(&oldest).map_err(GearClawError::IoError)?;
+    }
+    for n in (1..SKILL_AUDIT_LOG_KEEP).rev() {
+        let from = rotated_audit_log_path(log_path, n);
+        if from.exists() {
+            std::fs::rename(&from, rotated_audit_log_path(log_path, n + 1))
+                .map_err(GearClawError::IoError)?;
+        }
+    }
+    std::fs::rename(log_path, rotated_audit_log_path(log_path, 1)).map_err(GearClawError::IoError)
+}
+
+fn rotated_audit_log_path(log_path: &Path, n: usize) -> PathBuf {
+    let mut name = log_path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Records matching `keep` across rotated files and the live log, oldest
+/// first, read line by line
+fn read_audit_records(
+    log_path: &Path,
+    keep: impl Fn(&AuditRecord) -> bool,
+) -> Result<Vec<AuditRecord>, GearClawError> {
+    use std::io::BufRead;
+
+    let mut files: Vec<PathBuf> = (1..=SKILL_AUDIT_LOG_KEEP)
+        .rev()
+        .map(|n| rotated_audit_log_path(log_path, n))
+        .collect();
+    files.push(log_path.to_path_buf());
+
+    let mut records = Vec::new();
+    for path in files {
+        let file = match std::fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(GearClawError::IoError(e)),
+        };
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line.map_err(GearClawError::IoError)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(record) = parse_audit_record_line(&line) {
+                if keep(&record) {
+                    records.push(record);
+                }
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// `path:line` of a memory search result, with workspace files made
+/// absolute so terminals can open them; notes have no file and keep their
+/// `note:<id>` path
+fn memory_result_location(
+    workspace: &Path,
+    result: &gearclaw_core::memory::SearchResult,
+) -> String {
+    match result.start_line {
+        Some(line) => format!("{}:{}", workspace.join(&result.path).display(), line),
+        None => result.path.clone(),
+    }
+}
+
+/// The result's chunk plus `lines` lines either side, read from its source
+/// file as `(line number, text, inside chunk)`. `None` for notes and files
+/// that can no longer be read.
+fn memory_result_context(
+    workspace: &Path,
+    result: &gearclaw_core::memory::SearchResult,
+    lines: usize,
+) -> Option<Vec<(usize, String, bool)>> {
+    let start = result.start_line?;
+    let end = result.end_line.unwrap_or(start).max(start);
+    let content = std::fs::read_to_string(workspace.join(&result.path)).ok()?;
+    let first = start.saturating_sub(lines).max(1);
+    let last = end + lines;
+    Some(
+        content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .filter(|(number, _)| (first..=last).contains(number))
+            .map(|(number, line)| (number, line.to_string(), (start..=end).contains(&number)))
+            .collect(),
+    )
+}
+
+fn skill_lock_path(config: &Config) -> PathBuf {
+    gearclaw_core::skills::skill_lock_path(&config.agent.skills_path)
+}
+
+fn read_skill_lock(config: &Config) -> Result<BTreeMap<String, SkillLockEntry>, GearClawError> {
+    let path = skill_lock_path(config);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = std::fs::read_to_string(&path).map_err(GearClawError::IoError)?;
+    serde_json::from_str(&content)
+        .map_err(|e| GearClawError::Other(format!("Invalid {}: {}", path.display(), e)))
+}
+
+fn write_skill_lock(
+    config: &Config,
+    lock: &BTreeMap<String, SkillLockEntry>,
+) -> Result<(), GearClawError> {
+    let path = skill_lock_path(config);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(GearClawError::IoError)?;
+    }
+    let content = serde_json::to_string_pretty(lock)
+        .map_err(|e| GearClawError::Other(format!("Failed to serialize skill.lock: {}", e)))?;
+    std::fs::write(&path, content + "\n").map_err(GearClawError::IoError)
+}
+
+/// Hex SHA-256 over every file under `dir`, in path order, covering each
+/// relative path and its bytes
+fn skill_content_hash(dir: &Path) -> Result<String, GearClawError> {
+    use sha2::{Digest, Sha256};
+
+    fn collect(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), GearClawError> {
+        for entry in std::fs::read_dir(dir).map_err(GearClawError::IoError)? {
+            let path = entry.map_err(GearClawError::IoError)?.path();
+            if path.is_dir() {
+                collect(&path, out)?;
+            } else if path.is_file() {
+                out.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    collect(dir, &mut files)?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        let relative = file.strip_prefix(dir).unwrap_or(&file);
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(std::fs::read(&file).map_err(GearClawError::IoError)?);
+        hasher.update([0]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// A warning when the source content differs from what `skill.lock` recorded
+/// at the last install
+fn skill_lock_drift(
+    lock: &BTreeMap<String, SkillLockEntry>,
+    selected: &SkillCatalogEntry,
+    content_hash: &str,
+) -> Option<String> {
+    let locked = lock.get(&selected.name)?;
+    if locked.content_hash == content_hash {
+        return None;
+    }
+    Some(format!(
+        "⚠️ skill '{}' 的来源内容与 skill.lock 记录不一致:\n   source: {} -> {}\n   commit: {} -> {}\n   hash: {} -> {}",
+        selected.name,
+        locked.source,
+        selected.source_name,
+        locked.commit.as_deref().unwrap_or("unknown"),
+        selected.source_head_commit.as_deref().unwrap_or("unknown"),
+        locked.content_hash,
+        content_hash
+    ))
+}
+
 fn skill_install_audit_log_path(config: &Config) -> PathBuf {
     config
         .agent
@@ -1359,6 +2634,7 @@ async fn handle_gateway(
     port: Option<u16>,
     dev: bool,
     allow_unauthenticated: bool,
+    ephemeral: bool,
 ) -> Result<(), GearClawError> {
     use gearclaw_channels::platforms::discord::DiscordConfig;
     use gearclaw_channels::{ChannelAdapter, DiscordAdapter};
@@ -1371,6 +2647,23 @@ async fn handle_gateway(
     let allow_unauthenticated_requests =
         allow_unauthenticated || config.gateway.allow_unauthenticated_requests;
 
+    let gw_config = gearclaw_gateway::GatewayConfig {
+        host: gw_host.clone(),
+        port: gw_port,
+        ws_path: config.gateway.ws_path.clone(),
+        allow_unauthenticated_requests,
+        rate_limit_per_minute: config.gateway.rate_limit_per_minute,
+        metrics_enabled: config.gateway.metrics_enabled,
+        allow_ephemeral_port: ephemeral,
+        ..Default::default()
+    };
+
+    // Reject a bad host or port before any adapter is started
+    gw_config
+        .resolve_bind_addr()
+        .await
+        .map_err(|e| GearClawError::Other(format!("Gateway 地址无效: {:#}", e)))?;
+
     // Configure logging
     if dev {
         let env_filter =
@@ -1408,7 +2701,7 @@ async fn handle_gateway(
         None
     };
 
-    if let Some(token) = discord_token.ok() {
+    if let Ok(token) = discord_token {
         println!("📱 Discord Bot Token 已设置");
         println!("   正在初始化 Discord 适配器...");
         println!();
@@ -1466,17 +2759,29 @@ async fn handle_gateway(
                     .unwrap_or(&sender_id)
                     .to_string();
 
-                // Process message with agent
+                // Process message with agent, showing "typing..." until it replies
                 tracing::info!("🤖 Calling Agent.process_channel_message()...");
 
-                match agent_clone
-                    .process_channel_message(
-                        &incoming_msg.platform,
-                        &trigger_source_id,
-                        &incoming_msg.content,
-                    )
-                    .await
+                let processing = agent_clone.process_channel_message_with_addressing(
+                    &incoming_msg.platform,
+                    &trigger_source_id,
+                    &incoming_msg.content,
+                    &incoming_msg.attachments,
+                    &incoming_msg.addressing,
+                );
+                let result = match incoming_msg
+                    .metadata
+                    .get("channel_id")
+                    .and_then(|v| v.as_str())
                 {
+                    Some(id) => {
+                        let target = gearclaw_channels::MessageTarget::Channel(id.to_string());
+                        gearclaw_channels::with_typing(&discord, &target, processing).await
+                    }
+                    None => processing.await,
+                };
+
+                match result {
                     Ok(response) => {
                         tracing::info!(
                             "✅ Agent.process_channel_message() returned, response length: {}",
@@ -1507,6 +2812,7 @@ async fn handle_gateway(
                             let content = MessageContent {
                                 text: Some(response.clone()),
                                 embeds: Vec::new(),
+                                attachments: Vec::new(),
                             };
 
                             if let Err(e) = discord.send_message(target, content).await {
@@ -1546,14 +2852,6 @@ async fn handle_gateway(
         Arc::new(Agent::new(config.clone()).await?)
     };
 
-    // Create gateway config
-    let gw_config = gearclaw_gateway::GatewayConfig {
-        host: gw_host,
-        port: gw_port,
-        ws_path: config.gateway.ws_path.clone(),
-        allow_unauthenticated_requests,
-    };
-
     // Create server with agent integration
     let handlers = MethodHandlers::new();
     handlers.set_agent(agent.clone()).await;
@@ -1561,7 +2859,12 @@ async fn handle_gateway(
         .set_trigger_config(config.agent.triggers.clone())
         .await;
 
-    let server = GatewayServer::new(gw_config).with_handlers(Arc::new(handlers));
+    let auth = gearclaw_gateway::TokenAuth::load(&config.gateway.tokens_path)
+        .map_err(|e| GearClawError::Other(format!("Failed to load gateway tokens: {}", e)))?;
+
+    let server = GatewayServer::new(gw_config)
+        .with_handlers(Arc::new(handlers))
+        .with_auth(Arc::new(auth));
 
     println!("🌐 Gateway 服务器启动中...");
     println!();
@@ -1569,7 +2872,613 @@ async fn handle_gateway(
     server
         .start()
         .await
-        .map_err(|e| GearClawError::Other(format!("Gateway error: {}", e)))?;
+        .map_err(|e| GearClawError::Other(format!("Gateway error: {:#}", e)))?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_doctor_api_key_from_config_or_env() {
+        let mut config = Config::sample();
+        config.llm.api_key = None;
+        let missing = check_api_key(&config, None);
+        assert!(!missing.ok && missing.fatal);
+        assert!(missing.hint.unwrap().contains("OPENAI_API_KEY"));
+        assert!(check_api_key(&config, Some("sk-env".to_string())).ok);
+        assert!(!check_api_key(&config, Some("  ".to_string())).ok);
+
+        config.llm.api_key = Some("sk-config".to_string());
+        assert!(check_api_key(&config, None).ok);
+    }
+
+    #[test]
+    fn test_doctor_endpoint_address() {
+        assert_eq!(
+            endpoint_address("https://api.openai.com/v1"),
+            Some(("api.openai.com".to_string(), 443))
+        );
+        assert_eq!(
+            endpoint_address("http://localhost:11434/v1"),
+            Some(("localhost".to_string(), 11434))
+        );
+        assert_eq!(
+            endpoint_address("http://[::1]:8080"),
+            Some(("::1".to_string(), 8080))
+        );
+        assert_eq!(
+            endpoint_address("https://[::1]/v1"),
+            Some(("::1".to_string(), 443))
+        );
+        assert_eq!(endpoint_address("api.openai.com/v1"), None);
+
+        let mut config = Config::sample();
+        config.llm.endpoint = gearclaw_core::config::default_endpoint();
+        let env = Some("http://proxy:8080/v1".to_string());
+        assert_eq!(
+            doctor_endpoint(&config, env.clone()),
+            "http://proxy:8080/v1"
+        );
+        config.llm.endpoint = "http://custom/v1".to_string();
+        assert_eq!(doctor_endpoint(&config, env), "http://custom/v1");
+    }
+
+    #[tokio::test]
+    async fn test_doctor_endpoint_reachability() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = format!("http://{}/v1", listener.local_addr().unwrap());
+        let timeout = std::time::Duration::from_secs(2);
+        assert!(check_endpoint(&open, timeout).await.ok);
+
+        drop(listener);
+        let closed = check_endpoint(&open, timeout).await;
+        assert!(!closed.ok && closed.fatal);
+        assert!(!check_endpoint("not a url", timeout).await.ok);
+    }
+
+    #[test]
+    fn test_doctor_git_is_fatal_only_when_required() {
+        let missing = check_git("gearclaw-no-such-git", false);
+        assert!(!missing.ok && !missing.fatal);
+        assert!(check_git("gearclaw-no-such-git", true).fatal);
+    }
+
+    #[test]
+    fn test_doctor_memory_db_writability() {
+        let dir = TempDir::new().unwrap();
+        let db_path = dir.path().join("memory").join("index.sqlite");
+        assert!(check_memory_db(&db_path, true).ok);
+        // Nothing is created along the way
+        assert!(!db_path.parent().unwrap().exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        // A file where the parent directory should be
+        let blocked = dir.path().join("file");
+        std::fs::write(&blocked, "x").unwrap();
+        let check = check_memory_db(&blocked.join("index.sqlite"), true);
+        assert!(!check.ok && check.fatal);
+        assert!(!check_memory_db(&blocked.join("index.sqlite"), false).fatal);
+    }
+
+    #[test]
+    fn test_doctor_skills_path() {
+        let dir = TempDir::new().unwrap();
+        assert!(check_skills_path(dir.path()).ok);
+        let missing = check_skills_path(&dir.path().join("skills"));
+        assert!(!missing.ok && !missing.fatal);
+        let file = dir.path().join("file");
+        std::fs::write(&file, "x").unwrap();
+        let not_dir = check_skills_path(&file);
+        assert!(!not_dir.ok && not_dir.fatal);
+    }
+
+    #[test]
+    fn test_memory_context_includes_neighboring_lines() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("notes.md"),
+            "# Title\n\nbefore\nchunk one\nchunk two\nafter\n\nfar away\n",
+        )
+        .unwrap();
+        let result = gearclaw_core::memory::SearchResult {
+            path: "notes.md".to_string(),
+            text: "chunk one\nchunk two".to_string(),
+            score: 1.0,
+            start_line: Some(4),
+            end_line: Some(5),
+        };
+
+        let lines = memory_result_context(dir.path(), &result, 1).unwrap();
+        let numbered: Vec<(usize, &str, bool)> = lines
+            .iter()
+            .map(|(n, line, in_chunk)| (*n, line.as_str(), *in_chunk))
+            .collect();
+        assert_eq!(
+            numbered,
+            [
+                (3, "before", false),
+                (4, "chunk one", true),
+                (5, "chunk two", true),
+                (6, "after", false),
+            ]
+        );
+        assert_eq!(
+            memory_result_location(dir.path(), &result),
+            format!("{}:4", dir.path().join("notes.md").display())
+        );
+
+        let note = gearclaw_core::memory::SearchResult {
+            path: "note:abc".to_string(),
+            start_line: None,
+            end_line: None,
+            ..result
+        };
+        assert!(memory_result_context(dir.path(), &note, 3).is_none());
+        assert_eq!(memory_result_location(dir.path(), &note), "note:abc");
+    }
+
+    fn config_in(dir: &Path) -> Config {
+        let mut config = Config::sample();
+        config.agent.skills_path = dir.join("skills");
+        config.agent.skill_sources.clear();
+        config
+    }
+
+    fn install(config: &Config, dir_name: &str) -> PathBuf {
+        let skill_dir = config.agent.skills_path.join(dir_name);
+        std::fs::create_dir_all(&skill_dir).unwrap();
+        std::fs::write(
+            skill_dir.join("SKILL.md"),
+            gearclaw_core::skills::SAMPLE_SKILL_MD,
+        )
+        .unwrap();
+        skill_dir
+    }
+
+    fn local_source(name: &str, location: &Path) -> gearclaw_core::config::SkillSourceConfig {
+        gearclaw_core::config::SkillSourceConfig {
+            name: name.to_string(),
+            kind: SkillSourceKind::LocalDir,
+            location: location.to_string_lossy().to_string(),
+            revision: None,
+            enabled: true,
+            trusted: true,
+            verify_head_commit_signature: false,
+            allowed_signers: Vec::new(),
+            subdir: None,
+        }
+    }
+
+    #[test]
+    fn test_list_skills_flags_same_name_from_two_sources() {
+        let dir = TempDir::new().unwrap();
+        let mut config = config_in(dir.path());
+        for source in ["team", "community"] {
+            let skill_dir = dir.path().join(source).join("hello");
+            std::fs::create_dir_all(&skill_dir).unwrap();
+            std::fs::write(
+                skill_dir.join("SKILL.md"),
+                gearclaw_core::skills::SAMPLE_SKILL_MD,
+            )
+            .unwrap();
+            config
+                .agent
+                .skill_sources
+                .push(local_source(source, &dir.path().join(source)));
+        }
+        let unique_dir = dir.path().join("team").join("other");
+        std::fs::create_dir_all(&unique_dir).unwrap();
+        std::fs::write(
+            unique_dir.join("SKILL.md"),
+            "---\nname: other\ndescription: Only in team\n---\n",
+        )
+        .unwrap();
+
+        let entries = discover_skills(&config, None, DiscoveryOptions::default()).unwrap();
+        let listings = list_skills(&config, entries).unwrap();
+
+        assert_eq!(listings.len(), 2);
+        let hello = &listings[0];
+        assert_eq!(hello.name, "hello_world");
+        assert!(hello.is_conflict());
+        assert!(!hello.is_shadowing());
+        let mut sources: Vec<&str> = hello
+            .sources
+            .iter()
+            .map(|s| s.source_name.as_str())
+            .collect();
+        sources.sort();
+        assert_eq!(sources, ["community", "team"]);
+        assert!(!listings[1].is_conflict());
+    }
+
+    #[test]
+    fn test_discover_skills_skips_missing_source_roots() {
+        let dir = TempDir::new().unwrap();
+        let mut config = config_in(dir.path());
+        config
+            .agent
+            .skill_sources
+            .push(local_source("gone", &dir.path().join("gone")));
+
+        let entries = discover_skills(&config, None, DiscoveryOptions::default()).unwrap();
+        assert!(entries.is_empty());
+
+        let skill_dir = dir.path().join("team").join("hello");
+        std::fs::create_dir_all(&skill_dir).unwrap();
+        std::fs::write(
+            skill_dir.join("SKILL.md"),
+            gearclaw_core::skills::SAMPLE_SKILL_MD,
+        )
+        .unwrap();
+        config
+            .agent
+            .skill_sources
+            .push(local_source("team", &dir.path().join("team")));
+        let entries = discover_skills(&config, None, DiscoveryOptions::default()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].source_name, "team");
+    }
+
+    #[test]
+    fn test_list_skills_detects_installed_shadowing() {
+        let dir = TempDir::new().unwrap();
+        let mut config = config_in(dir.path());
+        install(&config, "hello_world");
+        let skill_dir = dir.path().join("team").join("hello");
+        std::fs::create_dir_all(&skill_dir).unwrap();
+        std::fs::write(
+            skill_dir.join("SKILL.md"),
+            gearclaw_core::skills::SAMPLE_SKILL_MD,
+        )
+        .unwrap();
+        config
+            .agent
+            .skill_sources
+            .push(local_source("team", &dir.path().join("team")));
+
+        let entries = discover_skills(&config, None, DiscoveryOptions::default()).unwrap();
+        let listings = list_skills(&config, entries).unwrap();
+
+        assert_eq!(listings.len(), 1);
+        assert!(listings[0].installed.is_some());
+        assert!(listings[0].is_shadowing());
+        assert!(!listings[0].is_conflict());
+    }
+
+    #[test]
+    fn test_list_skills_default_source_is_not_shadowing() {
+        let dir = TempDir::new().unwrap();
+        let config = config_in(dir.path());
+        install(&config, "hello_world");
+
+        let entries = discover_skills(&config, None, DiscoveryOptions::default()).unwrap();
+        let listings = list_skills(&config, entries).unwrap();
+
+        assert_eq!(listings.len(), 1);
+        assert!(listings[0].sources.is_empty());
+        assert!(!listings[0].is_shadowing());
+    }
+
+    fn write_skill_md(dir: &Path, name: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(
+            dir.join("SKILL.md"),
+            format!("---\nname: {}\ndescription: test\n---\n", name),
+        )
+        .unwrap();
+    }
+
+    fn discovered_names(config: &Config) -> Vec<String> {
+        let mut names: Vec<String> = discover_skills(config, None, DiscoveryOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_subdir_restricts_local_source() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        write_skill_md(&repo.join("skills").join("wanted"), "wanted");
+        write_skill_md(&repo.join("examples").join("unwanted"), "unwanted");
+        let mut config = config_in(dir.path());
+        config.agent.skill_sources.push(local_source("repo", &repo));
+        assert_eq!(discovered_names(&config), ["unwanted", "wanted"]);
+
+        config.agent.skill_sources[0].subdir = Some("skills".to_string());
+        assert_eq!(discovered_names(&config), ["wanted"]);
+    }
+
+    #[test]
+    fn test_subdir_sparse_checkout_for_git_source() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        write_skill_md(&repo.join("skills").join("wanted"), "wanted");
+        write_skill_md(&repo.join("examples").join("unwanted"), "unwanted");
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .current_dir(&repo)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "--quiet"]);
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "skills"]);
+
+        let mut config = config_in(dir.path());
+        let mut source = local_source("mono", &repo);
+        source.kind = SkillSourceKind::GitRepo;
+        source.subdir = Some("skills".to_string());
+        config.agent.skill_sources.push(source);
+        assert_eq!(discovered_names(&config), ["wanted"]);
+
+        let cache_dir = std::fs::read_dir(skill_source_cache_root(&config))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        assert!(cache_dir.join("skills").join("wanted").exists());
+        assert!(!cache_dir.join("examples").exists());
+
+        // Dropping the subdir restores the full tree
+        config.agent.skill_sources[0].subdir = None;
+        assert_eq!(discovered_names(&config), ["unwanted", "wanted"]);
+    }
+
+    #[test]
+    fn test_install_writes_lock_and_detects_changed_source() {
+        let dir = TempDir::new().unwrap();
+        let mut config = config_in(dir.path());
+        let source_dir = dir.path().join("team");
+        write_skill_md(&source_dir.join("hello"), "hello");
+        config
+            .agent
+            .skill_sources
+            .push(local_source("team", &source_dir));
+
+        handle_install_skill(&config, "hello", None, false, false, false).unwrap();
+        let lock = read_skill_lock(&config).unwrap();
+        let entry = lock.get("hello").unwrap();
+        assert_eq!(entry.source, "team");
+        let installed_hash = skill_content_hash(&config.agent.skills_path.join("hello")).unwrap();
+        assert_eq!(entry.content_hash, installed_hash);
+
+        let selected = discover_skills(&config, None, DiscoveryOptions::default())
+            .unwrap()
+            .remove(0);
+        assert!(skill_lock_drift(&lock, &selected, &installed_hash).is_none());
+
+        std::fs::write(source_dir.join("hello").join("run.sh"), "echo changed").unwrap();
+        let changed_hash = skill_content_hash(&selected.skill_dir).unwrap();
+        assert_ne!(changed_hash, installed_hash);
+        let warning = skill_lock_drift(&lock, &selected, &changed_hash).unwrap();
+        assert!(warning.contains(&installed_hash));
+        assert!(warning.contains(&changed_hash));
+
+        handle_install_skill(&config, "hello", None, true, false, false).unwrap();
+        assert_eq!(
+            read_skill_lock(&config).unwrap()["hello"].content_hash,
+            changed_hash
+        );
+
+        handle_uninstall_skill(&config, "hello", false).unwrap();
+        assert!(read_skill_lock(&config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_commit_signer_allowlist() {
+        let allowed = vec![
+            "3AA5 C343 7146 7D70 1C2A  8A5C 9D49 0CA4 2A6B A0B5".to_string(),
+            "ffeeddccbbaa9988".to_string(),
+        ];
+        // git log --format=%GF%n%GK: fingerprint, then key ID
+        let by_fingerprint = "3AA5C34371467D701C2A8A5C9D490CA42A6BA0B5\n9D490CA42A6BA0B5";
+        assert!(check_commit_signer("team", &allowed, by_fingerprint).is_ok());
+        let by_key_id = "\nFFEEDDCCBBAA9988";
+        assert!(check_commit_signer("team", &allowed, by_key_id).is_ok());
+
+        let other = "0123456789ABCDEF0123456789ABCDEF01234567\n89ABCDEF01234567";
+        let err = check_commit_signer("team", &allowed, other).unwrap_err();
+        assert!(err.to_string().contains("not in allowed_signers"));
+        assert!(check_commit_signer("team", &allowed, "\n").is_err());
+        assert!(check_commit_signer("team", &["2A6BA0B5".to_string()], by_fingerprint).is_err());
+    }
+
+    #[test]
+    fn test_clear_single_source_cache() {
+        let dir = TempDir::new().unwrap();
+        let mut config = config_in(dir.path());
+        for name in ["alpha", "beta"] {
+            let mut source = local_source(name, &dir.path().join(name));
+            source.kind = SkillSourceKind::GitRepo;
+            config.agent.skill_sources.push(source);
+        }
+        let sources = effective_skill_sources(&config);
+        let alpha_cache = source_cache_dir(&config, &sources[0]);
+        let beta_cache = source_cache_dir(&config, &sources[1]);
+        for cache in [&alpha_cache, &beta_cache] {
+            std::fs::create_dir_all(cache.join(".git")).unwrap();
+            write_source_last_sync_epoch(cache, 1).unwrap();
+        }
+
+        let removed = clear_skill_source_cache(&config, Some("alpha")).unwrap();
+        assert_eq!(removed, vec![alpha_cache.clone()]);
+        assert!(!alpha_cache.exists());
+        assert!(beta_cache.exists());
+
+        // Clearing again is a no-op; unknown sources are an error
+        assert!(clear_skill_source_cache(&config, Some("alpha"))
+            .unwrap()
+            .is_empty());
+        assert!(clear_skill_source_cache(&config, Some("missing")).is_err());
+
+        clear_skill_source_cache(&config, None).unwrap();
+        assert!(!beta_cache.exists());
+        assert!(skill_source_cache_root(&config).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_clear_cache_does_not_follow_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let config = config_in(dir.path());
+        let cache_root = skill_source_cache_root(&config);
+        std::fs::create_dir_all(&cache_root).unwrap();
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("keep.txt"), "x").unwrap();
+        std::os::unix::fs::symlink(&outside, cache_root.join("link")).unwrap();
+
+        clear_skill_source_cache(&config, None).unwrap();
+        assert!(!cache_root.join("link").exists());
+        assert!(outside.join("keep.txt").exists());
+    }
+
+    #[test]
+    fn test_catalog_and_sources_render_as_json() {
+        let dir = TempDir::new().unwrap();
+        let mut config = config_in(dir.path());
+        let source_dir = dir.path().join("team");
+        write_skill_md(&source_dir.join("alpha"), "alpha");
+        write_skill_md(&source_dir.join("beta"), "beta");
+        config
+            .agent
+            .skill_sources
+            .push(local_source("team", &source_dir));
+
+        let entries = discover_skills(&config, None, DiscoveryOptions::default()).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&render_structured(&entries, OutputFormat::Json).unwrap())
+                .unwrap();
+        let names: Vec<&str> = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["alpha", "beta"]);
+        assert_eq!(json[0]["source_name"], "team");
+        assert_eq!(json[0]["source_kind"], "local_dir");
+
+        let jsonl = render_structured(&entries, OutputFormat::Jsonl).unwrap();
+        let lines: Vec<serde_json::Value> = jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["name"], "beta");
+
+        let sources = effective_skill_sources(&config);
+        let json: serde_json::Value =
+            serde_json::from_str(&render_structured(&sources, OutputFormat::Json).unwrap())
+                .unwrap();
+        assert_eq!(json[0]["name"], "team");
+        assert_eq!(json[0]["kind"], "local_dir");
+        assert_eq!(json[0]["trusted"], true);
+
+        let empty: Vec<SkillCatalogEntry> = Vec::new();
+        assert_eq!(render_structured(&empty, OutputFormat::Json).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_audit_log_rotates_and_reads_across_files() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("skill_install_audit.log");
+        let line = |ts: u64| format!("{}|skill=s{}|source=a\\|b|status=installed\n", ts, ts);
+        let line_len = line(10).len() as u64;
+
+        // Two lines per file: the third append rotates
+        for ts in 10..13 {
+            append_audit_line(&log_path, &line(ts), 2 * line_len).unwrap();
+        }
+        assert!(rotated_audit_log_path(&log_path, 1).exists());
+        assert_eq!(std::fs::read_to_string(&log_path).unwrap(), line(12));
+
+        for ts in 13..10 + 2 * (SKILL_AUDIT_LOG_KEEP as u64 + 2) {
+            append_audit_line(&log_path, &line(ts), 2 * line_len).unwrap();
+        }
+        assert!(rotated_audit_log_path(&log_path, SKILL_AUDIT_LOG_KEEP).exists());
+        assert!(!rotated_audit_log_path(&log_path, SKILL_AUDIT_LOG_KEEP + 1).exists());
+
+        let records = read_audit_records(&log_path, |_| true).unwrap();
+        let timestamps: Vec<u64> = records.iter().map(|r| r.timestamp).collect();
+        let expected_len = 2 * (SKILL_AUDIT_LOG_KEEP + 1);
+        assert_eq!(timestamps.len(), expected_len);
+        assert!(timestamps.windows(2).all(|w| w[0] + 1 == w[1]));
+        assert_eq!(records[0].field("source"), Some("a|b"));
+
+        let oldest_kept = *timestamps.first().unwrap();
+        let old = read_audit_records(&log_path, |r| r.timestamp == oldest_kept).unwrap();
+        assert_eq!(old.len(), 1);
+        assert_eq!(
+            old[0].field("skill"),
+            Some(format!("s{}", oldest_kept).as_str())
+        );
+    }
+
+    #[test]
+    fn test_uninstall_writes_audit_line() {
+        let dir = TempDir::new().unwrap();
+        let config = config_in(dir.path());
+        let skill_dir = install(&config, "hello_world");
+        std::fs::write(
+            skill_install_audit_log_path(&config),
+            "100|skill=hello_world|source=team|kind=git_repo|location=https://example.com/skills.git|revision=main|commit=abc123|sig_verified=true|trusted=true|policy=trusted_only|target=x|status=installed\n",
+        )
+        .unwrap();
+
+        handle_uninstall_skill(&config, "hello_world", false).unwrap();
+
+        assert!(!skill_dir.exists());
+        let log = std::fs::read_to_string(skill_install_audit_log_path(&config)).unwrap();
+        let record = parse_audit_record_line(log.lines().last().unwrap()).unwrap();
+        assert_eq!(record.field("skill"), Some("hello_world"));
+        assert_eq!(record.field("status"), Some("uninstalled"));
+        assert_eq!(record.field("source"), Some("team"));
+        assert_eq!(record.field("kind"), Some("git_repo"));
+        assert_eq!(record.field("commit"), Some("abc123"));
+        assert_eq!(
+            record.field("target"),
+            Some(skill_dir.to_string_lossy().as_ref())
+        );
+    }
+
+    #[test]
+    fn test_uninstall_dry_run_changes_nothing() {
+        let dir = TempDir::new().unwrap();
+        let config = config_in(dir.path());
+        let skill_dir = install(&config, "hello_world");
+
+        handle_uninstall_skill(&config, "hello_world", true).unwrap();
+
+        assert!(skill_dir.join("SKILL.md").exists());
+        assert!(!skill_install_audit_log_path(&config).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_uninstall_refuses_symlink_outside_skills_path() {
+        let dir = TempDir::new().unwrap();
+        let config = config_in(dir.path());
+        std::fs::create_dir_all(&config.agent.skills_path).unwrap();
+        let outside = dir.path().join("outside");
+        std::fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, config.agent.skills_path.join("escape")).unwrap();
+
+        assert!(handle_uninstall_skill(&config, "escape", false).is_err());
+        assert!(outside.exists());
+    }
+}
@@ -0,0 +1,36 @@
+// Global --quiet flag
+
+use std::process::Command;
+
+/// A line of the ASCII-art banner
+const BANNER_LINE: &str = r"/ /_/ /  ___/ /_/ / /";
+
+fn config_sample(quiet: bool) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_gearclaw_cli"));
+    if quiet {
+        command.arg("--quiet");
+    }
+    let output = command
+        .args(["config-sample", "--output"])
+        .arg(dir.path().join("sample.toml"))
+        .env_remove("RUST_LOG")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_quiet_suppresses_banner_and_info_logs() {
+    let stdout = config_sample(true);
+    assert!(!stdout.contains(BANNER_LINE), "{}", stdout);
+    assert!(!stdout.contains("INFO"), "{}", stdout);
+    // Command output itself is kept
+    assert!(stdout.contains("示例配置已生成"));
+}
+
+#[test]
+fn test_banner_is_shown_by_default() {
+    assert!(config_sample(false).contains(BANNER_LINE));
+}
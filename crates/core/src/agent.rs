@@ -1,28 +1,479 @@
-use crate::config::{default_endpoint, Config};
+use crate::config::{default_endpoint, Config, LLMAuthScheme};
 use crate::error::GearClawError;
-use crate::llm::{FunctionCall, LLMClient, Message, ToolCall};
+use crate::llm::{
+    AuthScheme, FunctionCall, LLMClient, LlmBackend, LlmError, Message, RetryPolicy,
+    SamplingParams, ToolCall, Usage,
+};
 use crate::mcp::McpManager;
 use crate::memory::MemoryManager;
-use crate::session::{Session, SessionManager};
-use crate::skills::SkillManager;
+use crate::session::{
+    estimate_text_tokens, estimate_tokens, sanitize_session_id, Session, SessionManager,
+    SessionOverrides,
+};
+use crate::skills::{SkillManager, UNTRUSTED_SKILL_TOOLS};
 use crate::tools::{ToolExecutor, ToolResult};
+use crate::triggers::{is_own_message, TriggerMatcher};
 use futures::StreamExt;
 use rustyline::history::DefaultHistory;
 use rustyline::Editor;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::io::Write;
+use std::path::Path;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+/// Progress notifications emitted while the agent handles a message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentProgress {
+    /// A chunk of assistant text as it streams in.
+    Delta(String),
+    ToolStarted {
+        /// The model's tool call id, pairing a start with its finish
+        id: String,
+        name: String,
+        arguments: String,
+    },
+    ToolFinished {
+        id: String,
+        name: String,
+        success: bool,
+        output: String,
+    },
+}
+
+pub type ProgressSender = tokio::sync::mpsc::UnboundedSender<AgentProgress>;
+
+/// Where the agent writes streamed assistant text while handling a message.
+pub enum DeltaSink<'a> {
+    /// Print to stdout (the interactive CLI)
+    Stdout,
+    /// Send [`AgentProgress`] events, including tool calls
+    Progress(&'a ProgressSender),
+    /// Write the text to a buffer, file, or other writer
+    Writer(&'a mut (dyn Write + Send)),
+}
+
+impl DeltaSink<'_> {
+    /// A chunk of assistant text
+    fn delta(&mut self, text: &str) {
+        match self {
+            DeltaSink::Stdout => {
+                print!("{}", text);
+                std::io::stdout().flush().ok();
+            }
+            DeltaSink::Progress(progress) => {
+                let _ = progress.send(AgentProgress::Delta(text.to_string()));
+            }
+            DeltaSink::Writer(writer) => {
+                let _ = writer.write_all(text.as_bytes());
+                let _ = writer.flush();
+            }
+        }
+    }
+
+    /// Formatting and notices for text streams; progress consumers get the
+    /// final response instead
+    fn note(&mut self, text: &str) {
+        if !matches!(self, DeltaSink::Progress(_)) {
+            self.delta(text);
+        }
+    }
+
+    fn progress(&self) -> Option<&ProgressSender> {
+        match self {
+            DeltaSink::Progress(progress) => Some(progress),
+            _ => None,
+        }
+    }
+}
+
+/// A tool call made while handling a message, as listed in a [`RunReport`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ToolCallRecord {
+    pub name: String,
+    /// Parsed JSON arguments, or the raw string if they were not valid JSON
+    pub arguments: Value,
+    pub success: bool,
+    /// Output, capped at `agent.max_tool_result_bytes`
+    pub output: String,
+}
+
+/// Everything a non-interactive run produced, for structured output such as
+/// `gearclaw run --output json`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RunReport {
+    pub response: String,
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// Tokens used by this run only, not the whole session
+    pub usage: Usage,
+    pub session_id: String,
+}
+
+/// Observer of an agent's turn, e.g. for a live tool-execution timeline.
+/// Every method defaults to a no-op; callbacks run inline in the agent loop,
+/// so they should return quickly.
+pub trait AgentObserver: Send + Sync {
+    /// A chunk of assistant text as it streams in.
+    fn on_assistant_delta(&self, _delta: &str) {}
+    /// A tool call is about to be dispatched.
+    fn on_tool_call_start(&self, _name: &str, _arguments: &str) {}
+    /// A tool call finished; dispatch errors arrive as a failed result.
+    fn on_tool_call_end(&self, _name: &str, _result: &ToolResult) {}
+}
+
+/// File attached to an inbound channel message. The agent only sees its
+/// metadata and URL; fetching the content is left to tools.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct IncomingAttachment {
+    pub url: String,
+    pub filename: String,
+    pub content_type: Option<String>,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Who sent a channel message and whom it addresses, as reported by the
+/// channel adapter from platform metadata rather than message text.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MessageAddressing {
+    /// Platform user id of the sender
+    pub sender_id: Option<String>,
+    /// User ids @-mentioned in the message
+    pub mentioned_user_ids: Vec<String>,
+    /// Author of the message this one replies to
+    pub reply_to_user_id: Option<String>,
+    /// The bot's own user id on the platform, once the adapter knows it
+    pub bot_user_id: Option<String>,
+}
+
+impl MessageAddressing {
+    /// Whether the message mentions the bot by id or replies to it. Always
+    /// false while the bot's own id is unknown.
+    pub fn addresses_bot(&self) -> bool {
+        let Some(bot) = self.bot_user_id.as_deref() else {
+            return false;
+        };
+        self.mentioned_user_ids.iter().any(|id| id == bot)
+            || self.reply_to_user_id.as_deref() == Some(bot)
+    }
+}
+
+/// Attachment listing appended to a user message (empty when there are none).
+pub fn describe_attachments(attachments: &[IncomingAttachment]) -> String {
+    if attachments.is_empty() {
+        return String::new();
+    }
+    let mut text = String::from("\n\n[Attachments]");
+    for a in attachments {
+        text.push_str(&format!(
+            "\n- {} ({}, {} bytes): {}",
+            a.filename,
+            a.content_type.as_deref().unwrap_or("unknown type"),
+            a.size,
+            a.url
+        ));
+    }
+    text
+}
+
+/// `command arg...` of an `exec` call, if it names a command
+fn exec_command_line(args: &Value) -> Option<String> {
+    let command = args.get("command")?.as_str()?;
+    let mut line = command.to_string();
+    for arg in args
+        .get("args")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str())
+    {
+        line.push(' ');
+        line.push_str(arg);
+    }
+    Some(line)
+}
+
+/// Tool message for a failed call: a compact JSON object the model can
+/// recognize as a failure, e.g.
+/// `{"error":true,"message":"...","kind":"tool_execution"}`
+pub fn tool_error_output(kind: &str, message: &str) -> String {
+    json!({ "error": true, "message": message, "kind": kind }).to_string()
+}
+
+/// Cut tool output to at most `max_bytes` (on a char boundary), noting how
+/// much was dropped so the model knows the result is partial
+pub fn cap_tool_output(mut output: String, max_bytes: usize) -> String {
+    if output.len() <= max_bytes {
+        return output;
+    }
+    let total = output.len();
+    let mut end = max_bytes;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    output.truncate(end);
+    output.push_str(&format!(
+        "\n...[输出已截断：共 {} 字节，仅保留前 {} 字节]",
+        total, end
+    ));
+    output
+}
+
+/// Parse tool-call arguments from the model. Empty arguments mean `{}`;
+/// JSON wrapped in a markdown fence or with trailing commas is repaired.
+/// Otherwise the parse error is returned for feedback to the model.
+pub fn parse_tool_arguments(arguments: &str) -> Result<Value, String> {
+    if arguments.trim().is_empty() {
+        return Ok(json!({}));
+    }
+    let original = match serde_json::from_str(arguments) {
+        Ok(args) => return Ok(args),
+        Err(e) => e,
+    };
+    let repaired = strip_trailing_commas(strip_code_fence(arguments));
+    match serde_json::from_str(&repaired) {
+        Ok(args) => {
+            tracing::debug!("Repaired malformed tool arguments: {}", arguments);
+            Ok(args)
+        }
+        Err(_) => Err(original.to_string()),
+    }
+}
+
+/// Remove a surrounding ```/```json fence
+fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// Drop commas that directly precede `}` or `]`, outside of strings
+fn strip_trailing_commas(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &c) in chars.iter().enumerate() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+            if matches!(next, Some('}') | Some(']')) {
+                continue;
+            }
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Tools that only read, so several calls in one turn can run concurrently
+/// without one seeing another's half-done changes
+const PARALLEL_SAFE_TOOLS: &[&str] = &[
+    "read_file",
+    "list_files",
+    "file_info",
+    "web_search",
+    "fetch_url",
+];
+
+/// Tool list for the system prompt, used when the endpoint can't take
+/// `tools` in the request. The model can't call them, so it is told to say
+/// which tool and arguments it would use instead.
+fn describe_tools_in_prompt(tools: &[crate::llm::ToolSpec]) -> String {
+    let mut text = String::from("\n\n## Tools\n\n");
+    text.push_str("This endpoint does not support function calling. These tools exist, but you cannot invoke them; when one would help, tell the user which tool and arguments to use.\n\n");
+    for tool in tools {
+        text.push_str(&format!(
+            "- `{}`: {} Parameters: {}\n",
+            tool.function.name, tool.function.description, tool.function.parameters
+        ));
+    }
+    text
+}
+
+/// Fill the `{agent_name}` and `{tools}` placeholders of a system prompt
+/// template. Prompts without them are returned unchanged.
+pub fn render_system_prompt(template: &str, agent_name: &str, tool_names: &[String]) -> String {
+    let tools = if tool_names.is_empty() {
+        "（无）".to_string()
+    } else {
+        tool_names.join(", ")
+    };
+    template
+        .replace("{agent_name}", agent_name)
+        .replace("{tools}", &tools)
+}
+
+/// Whether `path` stays inside `root` once `..` and symlinks are resolved.
+/// Only the existing part of `path` can be resolved; a `..` below it is
+/// rejected rather than guessed at.
+fn is_within(root: &Path, path: &Path) -> bool {
+    let Ok(root) = root.canonicalize() else {
+        return false;
+    };
+    let mut existing = path;
+    let mut missing = Vec::new();
+    while !existing.exists() {
+        let (Some(name), Some(parent)) = (existing.file_name(), existing.parent()) else {
+            return false;
+        };
+        missing.push(name);
+        existing = parent;
+    }
+    let Ok(mut resolved) = existing.canonicalize() else {
+        return false;
+    };
+    resolved.extend(missing.iter().rev());
+    resolved.starts_with(&root)
+}
+
+/// Tools the agent keeps while an untrusted skill's instructions are in the
+/// system prompt. `run_skill` stays: it checks each skill's own trust.
+fn available_with_untrusted_skills(tool_name: &str) -> bool {
+    tool_name == "run_skill" || UNTRUSTED_SKILL_TOOLS.contains(&tool_name)
+}
+
+/// macOS tools that run arbitrary scripts; AppleScript's `do shell script`
+/// would get round the exec allowlist
+const MACOS_SCRIPT_TOOLS: [&str; 2] = ["macos_applescript", "macos_jxa"];
+
+/// Tools `tools.security = "deny"` still runs: they only read files or
+/// write memory notes
+const ALLOWED_UNDER_DENY: [&str; 4] = ["read_file", "list_files", "file_info", "memory_remember"];
+
+/// `tools.security` for MCP and macOS tools: `deny` refuses them all,
+/// `allowlist` refuses the scripting tools
+fn check_tool_security(security: &str, tool_name: &str) -> Result<(), GearClawError> {
+    match security {
+        "deny" => Err(GearClawError::ToolExecutionError(
+            "工具执行被禁止 (security=deny)".to_string(),
+        )),
+        "allowlist" if MACOS_SCRIPT_TOOLS.contains(&tool_name) => {
+            Err(GearClawError::ToolExecutionError(format!(
+                "security=allowlist 不允许执行脚本工具: {}",
+                tool_name
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Short human-readable form of a tool call, for confirmation prompts
+pub fn describe_tool_call(tool_name: &str, arguments: &str) -> String {
+    let args = parse_tool_arguments(arguments).unwrap_or_else(|_| json!({}));
+    match (tool_name, exec_command_line(&args)) {
+        ("exec", Some(line)) => line,
+        _ => format!("{} {}", tool_name, arguments),
+    }
+}
+
+/// A command typed at the interactive prompt, as opposed to a message for
+/// the agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InteractiveCommand {
+    Exit,
+    Clear,
+    Help,
+    /// `/model` shows the current model; `/model <name>` switches to it
+    Model(Option<String>),
+    /// Print the effective system prompt
+    System,
+    /// Print accumulated token usage
+    Tokens,
+    /// Print the session's working directory
+    Pwd,
+    /// `/cd <path>` moves the session's working directory; `/cd` goes home
+    Cd(Option<String>),
+    /// A `/word` that is not a known command
+    Unknown(String),
+}
+
+/// Parse a line from the interactive prompt. Returns `None` for ordinary
+/// messages. `exit`, `quit`, `clear` and `help` work with or without a slash.
+pub fn parse_interactive_command(input: &str) -> Option<InteractiveCommand> {
+    let input = input.trim();
+    let (word, rest) = match input.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (input, ""),
+    };
+    let bare = word.strip_prefix('/');
+    let command = match (bare.unwrap_or(word), rest.is_empty()) {
+        ("exit" | "quit", true) => InteractiveCommand::Exit,
+        ("clear", true) => InteractiveCommand::Clear,
+        ("help", true) => InteractiveCommand::Help,
+        _ if bare.is_none() => return None,
+        ("model", true) => InteractiveCommand::Model(None),
+        ("model", false) => InteractiveCommand::Model(Some(rest.to_string())),
+        ("system", true) => InteractiveCommand::System,
+        ("tokens", true) => InteractiveCommand::Tokens,
+        ("pwd", true) => InteractiveCommand::Pwd,
+        ("cd", true) => InteractiveCommand::Cd(None),
+        ("cd", false) => InteractiveCommand::Cd(Some(rest.to_string())),
+        _ => InteractiveCommand::Unknown(input.to_string()),
+    };
+    Some(command)
+}
+
+/// Line editor for the interactive prompt, keeping at most `max_entries`
+/// history lines
+pub fn interactive_editor(max_entries: usize) -> Result<Editor<(), DefaultHistory>, GearClawError> {
+    let config = rustyline::Config::builder()
+        .max_history_size(max_entries.max(1))
+        .and_then(|builder| builder.history_ignore_dups(true))
+        .map_err(|e| GearClawError::IoError(std::io::Error::other(e)))?
+        .build();
+    Editor::with_config(config).map_err(|e| GearClawError::IoError(std::io::Error::other(e)))
+}
+
+/// Load saved prompt history. A missing file is a first run; an unreadable
+/// or corrupt one is logged and skipped so the prompt still starts.
+pub fn load_interactive_history(rl: &mut Editor<(), DefaultHistory>, path: &Path) {
+    match rl.load_history(path) {
+        Ok(()) => {}
+        Err(rustyline::error::ReadlineError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+        }
+        Err(e) => {
+            tracing::warn!("Ignoring unreadable history file {}: {}", path.display(), e);
+            let _ = rl.clear_history();
+        }
+    }
+}
+
+/// Save prompt history, creating the parent directory if needed.
+pub fn save_interactive_history(
+    rl: &mut Editor<(), DefaultHistory>,
+    path: &Path,
+) -> Result<(), GearClawError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    rl.save_history(path)
+        .map_err(|e| GearClawError::IoError(std::io::Error::other(e)))
+}
 
 pub struct Agent {
     config: Config,
-    llm_client: Arc<LLMClient>,
+    llm_client: Arc<dyn LlmBackend>,
     tool_executor: ToolExecutor,
     pub session_manager: SessionManager,
     pub skill_manager: SkillManager,
     pub memory_manager: MemoryManager,
     pub mcp_manager: Arc<McpManager>,
+    observers: std::sync::RwLock<Vec<Arc<dyn AgentObserver>>>,
+    trigger_matcher: TriggerMatcher,
 }
 /// Tool routing abstraction for Agent tool-call dispatch.
 pub struct ToolRouter<'a> {
@@ -62,7 +513,32 @@ impl<'a> LLMLoop<'a> {
         user_message: &str,
     ) -> Result<String, GearClawError> {
         self.agent
-            .process_message_inner(session, user_message)
+            .process_message_inner(session, user_message, DeltaSink::Stdout)
+            .await
+    }
+
+    /// Like [`LLMLoop::run`], but reports deltas and tool calls to `progress`
+    /// instead of printing them.
+    pub async fn run_with_progress(
+        &self,
+        session: &mut Session,
+        user_message: &str,
+        progress: &ProgressSender,
+    ) -> Result<String, GearClawError> {
+        self.agent
+            .process_message_inner(session, user_message, DeltaSink::Progress(progress))
+            .await
+    }
+
+    /// Like [`LLMLoop::run`], but streams assistant text to `sink`.
+    pub async fn run_with_sink(
+        &self,
+        session: &mut Session,
+        user_message: &str,
+        sink: DeltaSink<'_>,
+    ) -> Result<String, GearClawError> {
+        self.agent
+            .process_message_inner(session, user_message, sink)
             .await
     }
 }
@@ -95,20 +571,72 @@ impl Agent {
         info!("Using endpoint from: {} - {}", endpoint_source, endpoint);
         info!("Using model: {}", config.llm.primary);
 
-        let llm_client = Arc::new(LLMClient::new(
-            api_key,
-            endpoint,
-            config.llm.primary.clone(),
-            config.llm.embedding_model.clone(),
-            config.llm.temperature,
-        ));
+        let mut extra_headers = config.llm.extra_headers.clone();
+        if let Some(organization) = &config.llm.organization {
+            extra_headers.insert("OpenAI-Organization".to_string(), organization.clone());
+        }
 
+        // 0 disables a timeout
+        let seconds = |secs: u64| (secs > 0).then(|| std::time::Duration::from_secs(secs));
+        let llm_client = Arc::new(
+            LLMClient::new(
+                api_key,
+                endpoint,
+                config.llm.primary.clone(),
+                config.llm.embedding_model.clone(),
+                config.llm.temperature,
+            )
+            .with_force_http1(config.llm.force_http1)
+            .with_auth_scheme(match config.llm.auth_scheme {
+                LLMAuthScheme::Bearer => AuthScheme::Bearer,
+                LLMAuthScheme::ApiKey => AuthScheme::ApiKeyHeader,
+            })
+            .with_extra_headers(extra_headers)
+            .with_fallbacks(config.llm.fallbacks.clone())
+            .with_http_tracing(config.llm.debug_tracing)
+            .with_capabilities(config.llm.capabilities)
+            .with_embedding_endpoint(
+                config.llm.embedding_endpoint.clone(),
+                config.llm.embedding_api_key.clone(),
+            )
+            .with_sampling(SamplingParams {
+                temperature: config.llm.temperature,
+                top_p: config.llm.top_p,
+                frequency_penalty: config.llm.frequency_penalty,
+                presence_penalty: config.llm.presence_penalty,
+            })
+            .with_retry_policy(RetryPolicy {
+                max_attempts: config.llm.retry.max_attempts,
+                base_delay_ms: config.llm.retry.base_delay_ms,
+                max_delay_ms: config.llm.retry.max_delay_ms,
+                jitter: config.llm.retry.jitter,
+            })
+            .with_timeouts(
+                seconds(config.llm.request_timeout_secs),
+                seconds(config.llm.stream_idle_timeout_secs),
+            ),
+        );
+
+        Self::new_with_backend(config, llm_client).await
+    }
+
+    /// Build an agent on an existing LLM backend instead of one constructed
+    /// from `config.llm`, e.g. a scripted backend in tests.
+    pub async fn new_with_backend(
+        config: Config,
+        llm_client: Arc<dyn LlmBackend>,
+    ) -> Result<Self, GearClawError> {
         let tool_executor = ToolExecutor::new(&config.tools.security);
 
         let mut skill_manager = SkillManager::new();
         if let Err(e) = skill_manager.load_from_dir(&config.agent.skills_path) {
             tracing::warn!("Failed to load skills: {}", e);
         }
+        skill_manager.apply_source_trust(
+            &crate::skills::skill_lock_path(&config.agent.skills_path),
+            &config.agent.skill_sources,
+            &config.agent.skill_trust_policy,
+        );
 
         let mcp_manager = Arc::new(McpManager::new(config.mcp.clone()));
         if let Err(e) = mcp_manager.init_clients().await {
@@ -123,6 +651,8 @@ impl Agent {
             llm_client.clone(),
         )?;
 
+        let trigger_matcher = TriggerMatcher::new(config.agent.triggers.clone());
+
         let agent = Agent {
             config,
             llm_client,
@@ -131,6 +661,8 @@ impl Agent {
             skill_manager,
             memory_manager,
             mcp_manager,
+            observers: std::sync::RwLock::default(),
+            trigger_matcher,
         };
 
         // Auto-sync memory if enabled
@@ -147,11 +679,33 @@ impl Agent {
         Ok(agent)
     }
 
+    /// Register an observer notified of deltas and tool calls in every turn
+    pub fn add_observer(&self, observer: Arc<dyn AgentObserver>) {
+        self.observers
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .push(observer);
+    }
+
+    fn notify(&self, event: impl Fn(&dyn AgentObserver)) {
+        for observer in self
+            .observers
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .iter()
+        {
+            event(observer.as_ref());
+        }
+    }
+
     pub async fn start_interactive(&self) -> Result<(), GearClawError> {
         let mut session = self.session_manager.get_or_create_session("interactive")?;
-        let mut rl = Editor::<(), DefaultHistory>::new().map_err(|e| {
-            GearClawError::IoError(std::io::Error::new(std::io::ErrorKind::Other, e))
-        })?;
+        let history_path = self.config.agent.history_path.clone();
+        let persist_history = self.config.agent.history_max_entries > 0;
+        let mut rl = interactive_editor(self.config.agent.history_max_entries)?;
+        if persist_history {
+            load_interactive_history(&mut rl, &history_path);
+        }
 
         println!("⚙️ GearClaw 交互模式已启动");
         println!("输入 'exit' 或 'quit' 退出");
@@ -171,32 +725,93 @@ impl Agent {
                         continue;
                     }
 
+                    let command = parse_interactive_command(input);
+
                     // 添加到历史记录（排除特殊命令）
-                    if !matches!(input, "exit" | "quit" | "clear" | "help") {
+                    if !matches!(
+                        command,
+                        Some(
+                            InteractiveCommand::Exit
+                                | InteractiveCommand::Clear
+                                | InteractiveCommand::Help
+                        )
+                    ) {
                         let _ = rl.add_history_entry(input);
                     }
 
-                    match input {
-                        "exit" | "quit" => {
+                    match command {
+                        Some(InteractiveCommand::Exit) => {
                             info!("退出交互模式");
                             break;
                         }
-                        "clear" => {
+                        Some(InteractiveCommand::Clear) => {
                             session.clear_history();
                             println!("✓ 对话历史已清除");
                             let _ = rl.clear_history();
                             continue;
                         }
-                        "help" => {
+                        Some(InteractiveCommand::Help) => {
                             self.print_help();
                             continue;
                         }
-                        _ => {
+                        Some(InteractiveCommand::Model(None)) => {
+                            println!("当前模型: {}", self.session_models(&session)[0]);
+                            continue;
+                        }
+                        Some(InteractiveCommand::Model(Some(model))) => {
+                            println!("✓ 后续对话将使用模型: {}", model);
+                            session.overrides.model = Some(model);
+                            continue;
+                        }
+                        Some(InteractiveCommand::System) => {
+                            let query = session
+                                .messages
+                                .iter()
+                                .rev()
+                                .find(|m| m.role == "user")
+                                .and_then(|m| m.content.clone())
+                                .unwrap_or_default();
+                            println!("{}", self.system_prompt_for(&session, &query).await);
+                            continue;
+                        }
+                        Some(InteractiveCommand::Tokens) => {
+                            let usage = session.usage;
+                            println!(
+                                "Token 用量: prompt={} completion={} total={}",
+                                usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+                            );
+                            continue;
+                        }
+                        Some(InteractiveCommand::Pwd) => {
+                            println!("{}", session.cwd.display());
+                            continue;
+                        }
+                        Some(InteractiveCommand::Cd(target)) => {
+                            let target = match target {
+                                Some(path) => std::path::PathBuf::from(path),
+                                None => dirs::home_dir()
+                                    .unwrap_or_else(|| std::path::PathBuf::from("/")),
+                            };
+                            match session.change_dir(&target) {
+                                Ok(cwd) => {
+                                    println!("✓ 工作目录: {}", cwd.display());
+                                    self.session_manager.save_session(&session).await?;
+                                }
+                                Err(e) => println!("❌ 无法切换目录: {}", e),
+                            }
+                            continue;
+                        }
+                        Some(InteractiveCommand::Unknown(command)) => {
+                            println!("未知命令: {}（输入 help 查看可用命令）", command);
+                            continue;
+                        }
+                        None => {
                             println!("🤖 GearClaw: ");
                             std::io::stdout().flush().ok();
 
                             let _ = self.process_message(&mut session, input).await?;
                             println!();
+                            self.confirm_pending(&mut session, &mut rl).await?;
                         }
                     }
                 }
@@ -208,10 +823,59 @@ impl Agent {
             }
         }
 
+        if persist_history {
+            if let Err(e) = save_interactive_history(&mut rl, &history_path) {
+                tracing::warn!(
+                    "Failed to save history to {}: {}",
+                    history_path.display(),
+                    e
+                );
+            }
+        }
         self.session_manager.save_session(&session).await?;
         Ok(())
     }
 
+    /// Ask y/N for each held tool call, run the accepted ones, and report
+    /// the outcome back to the model until nothing is left pending.
+    async fn confirm_pending(
+        &self,
+        session: &mut Session,
+        rl: &mut Editor<(), DefaultHistory>,
+    ) -> Result<(), GearClawError> {
+        while !session.pending_confirmations.is_empty() {
+            let pending = std::mem::take(&mut session.pending_confirmations);
+            let mut report = String::from("[工具确认结果]");
+            for tc in pending {
+                let action = describe_tool_call(&tc.function.name, &tc.function.arguments);
+                let answer = rl
+                    .readline(&format!("⚠️  执行 `{}`? [y/N] ", action))
+                    .unwrap_or_default();
+                if !answer.trim().eq_ignore_ascii_case("y") {
+                    report.push_str(&format!("\n- `{}`: 用户拒绝执行", action));
+                    continue;
+                }
+                let output = match self
+                    .execute_confirmed_tool_call(session, &tc.function.name, &tc.function.arguments)
+                    .await
+                {
+                    Ok(res) if res.success => res.output,
+                    Ok(res) => format!("失败: {}", res.error.unwrap_or(res.output)),
+                    Err(e) => format!("失败: {}", e),
+                };
+                println!("{}", output);
+                report.push_str(&format!("\n- `{}`: 已执行，结果:\n{}", action, output));
+            }
+
+            println!("🤖 GearClaw: ");
+            let _ = self.process_message(session, &report).await?;
+            println!();
+        }
+        Ok(())
+    }
+
+    /// Process a message, printing assistant text to stdout as it streams.
+    /// Only the interactive CLI should use this; daemons pass a sink.
     pub async fn process_message(
         &self,
         session: &mut Session,
@@ -220,10 +884,100 @@ impl Agent {
         LLMLoop::new(self).run(session, user_message).await
     }
 
+    /// Process a message, streaming progress to `progress` rather than stdout.
+    pub async fn process_message_with_progress(
+        &self,
+        session: &mut Session,
+        user_message: &str,
+        progress: &ProgressSender,
+    ) -> Result<String, GearClawError> {
+        LLMLoop::new(self)
+            .run_with_progress(session, user_message, progress)
+            .await
+    }
+
+    /// Process a message without printing, streaming assistant text to `sink`
+    /// (e.g. [`DeltaSink::Writer`] over a buffer).
+    pub async fn process_message_with_sink(
+        &self,
+        session: &mut Session,
+        user_message: &str,
+        sink: DeltaSink<'_>,
+    ) -> Result<String, GearClawError> {
+        LLMLoop::new(self)
+            .run_with_sink(session, user_message, sink)
+            .await
+    }
+
+    /// Process a message without printing and collect the response, tool
+    /// calls and token usage into a [`RunReport`].
+    pub async fn process_message_report(
+        &self,
+        session: &mut Session,
+        user_message: &str,
+    ) -> Result<RunReport, GearClawError> {
+        let usage_before = session.usage;
+        let (progress, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let response = self
+            .process_message_with_progress(session, user_message, &progress)
+            .await?;
+        drop(progress);
+
+        let mut tool_calls: Vec<ToolCallRecord> = Vec::new();
+        // Record index of each started call awaiting its result, by call id
+        let mut pending: HashMap<String, usize> = HashMap::new();
+        while let Ok(event) = events.try_recv() {
+            match event {
+                AgentProgress::Delta(_) => {}
+                AgentProgress::ToolStarted {
+                    id,
+                    name,
+                    arguments,
+                } => {
+                    pending.insert(id, tool_calls.len());
+                    tool_calls.push(ToolCallRecord {
+                        arguments: parse_tool_arguments(&arguments)
+                            .unwrap_or(Value::String(arguments)),
+                        name,
+                        success: false,
+                        output: String::new(),
+                    });
+                }
+                AgentProgress::ToolFinished {
+                    id,
+                    success,
+                    output,
+                    ..
+                } => {
+                    // Parallel calls may finish out of order
+                    if let Some(index) = pending.remove(&id) {
+                        let record = &mut tool_calls[index];
+                        record.success = success;
+                        record.output =
+                            cap_tool_output(output, self.config.agent.max_tool_result_bytes);
+                    }
+                }
+            }
+        }
+
+        let usage_after = session.usage;
+        Ok(RunReport {
+            response,
+            tool_calls,
+            usage: Usage {
+                prompt_tokens: usage_after.prompt_tokens - usage_before.prompt_tokens,
+                completion_tokens: usage_after.completion_tokens - usage_before.completion_tokens,
+                total_tokens: usage_after.total_tokens - usage_before.total_tokens,
+            },
+            session_id: session.id.clone(),
+        })
+    }
+
     async fn process_message_inner(
         &self,
         session: &mut Session,
         user_message: &str,
+        mut sink: DeltaSink<'_>,
     ) -> Result<String, GearClawError> {
         if !user_message.is_empty() {
             session.add_message(Message {
@@ -234,82 +988,92 @@ impl Agent {
             });
         }
 
+        if let Some(limit) = self.config.agent.compact_after_tokens {
+            if session.estimated_tokens > limit {
+                let before = session.estimated_tokens;
+                match session
+                    .compact(
+                        self.config.agent.compact_keep_recent,
+                        self.llm_client.as_ref(),
+                    )
+                    .await
+                {
+                    Ok(0) => {}
+                    Ok(folded) => info!(
+                        "Compacted {} messages in session {} (~{} -> ~{} tokens)",
+                        folded, session.id, before, session.estimated_tokens
+                    ),
+                    Err(e) => tracing::warn!("Session compaction failed: {}", e),
+                }
+            }
+        }
+
+        session.pending_confirmations.clear();
+
         let mut final_response_content = String::new();
+        let max_turns = self.config.agent.max_tool_turns;
         let mut loop_count = 0;
+        let mut finished = false;
 
-        // Loop for tool calls (max 15 turns to prevent infinite loops)
-        while loop_count < 15 {
+        // Loop for tool calls, capped to stop runaway tool loops
+        while loop_count < max_turns {
             loop_count += 1;
 
-            let mut tool_specs = self.tool_executor.available_tools();
-            if self.mcp_manager.is_enabled() {
-                let mcp_tools = self.mcp_manager.list_tools().await;
-                tool_specs.extend(mcp_tools);
-            }
-
+            let tool_specs = self.tool_specs(session).await;
+            let tool_names: Vec<String> = tool_specs.iter().map(|t| t.name.clone()).collect();
             let llm_tools = self.convert_to_llm_tools(tool_specs);
 
             // Construct messages with system prompt and skills context
             let mut messages = Vec::new();
-
-            // Build system prompt with memory context if enabled
-            let mut system_prompt = self.config.agent.system_prompt.clone();
-            system_prompt.push_str(&self.skill_manager.get_prompt_context());
-
-            // Search memory if enabled and add to system prompt
-            if self.config.agent.memory_enabled && !user_message.is_empty() {
-                match self.memory_manager.search(user_message, 3).await {
-                    Ok(memories) if !memories.is_empty() => {
-                        tracing::debug!("Found {} relevant memories", memories.len());
-                        let memory_context = memories
-                            .iter()
-                            .map(|m| format!("- [{}] {} (score: {:.2})", m.path, m.text, m.score))
-                            .collect::<Vec<_>>()
-                            .join("\n");
-
-                        system_prompt.push_str("\n\n=== Relevant Context ===\n");
-                        system_prompt.push_str("The following information from your memory may be relevant to this conversation:\n\n");
-                        system_prompt.push_str(&memory_context);
-                        system_prompt.push_str("\n========================\n");
-                    }
-                    Ok(_) => {
-                        tracing::debug!("No relevant memories found");
-                    }
-                    Err(e) => {
-                        tracing::warn!("Memory search failed: {}", e);
-                        // Continue without memory context rather than failing
-                    }
-                }
+            let mut system_prompt = self
+                .system_prompt_with_tools(session, user_message, &tool_names)
+                .await;
+            let supports_tools = self.llm_client.capabilities().supports_tools;
+            if !supports_tools {
+                system_prompt.push_str(&describe_tools_in_prompt(&llm_tools));
             }
 
-            messages.push(Message {
+            let system_message = Message {
                 role: "system".to_string(),
                 content: Some(system_prompt),
                 tool_calls: None,
                 tool_call_id: None,
-            });
+            };
+            let history_budget =
+                self.history_budget(&system_message, supports_tools.then_some(&llm_tools[..]));
+            messages.push(system_message);
 
-            messages.extend(session.get_messages());
+            messages.extend(session.messages_within_budget(history_budget));
 
-            let mut stream = self
+            let models = self.session_models(session);
+            let (model, mut stream) = self
                 .llm_client
                 .chat_completion_stream(
+                    &models,
                     messages,
-                    Some(llm_tools.clone()),
-                    Some(self.config.session.max_tokens),
+                    supports_tools.then(|| llm_tools.clone()),
+                    Some(self.config.llm.max_completion_tokens),
+                    SamplingParams::default(),
                 )
                 .await?;
+            if model != models[0] {
+                info!("模型 {} 不可用，已切换到备用模型 {}", models[0], model);
+            }
 
             let mut current_content = String::new();
+            let mut turn_usage = None;
             let mut tool_call_chunks: HashMap<usize, (String, String, String)> = HashMap::new();
 
             while let Some(result) = stream.next().await {
                 match result {
                     Ok(response) => {
+                        if response.usage.is_some() {
+                            turn_usage = response.usage;
+                        }
                         for choice in response.choices {
                             if let Some(content) = choice.delta.content {
-                                print!("{}", content);
-                                std::io::stdout().flush().ok();
+                                self.notify(|o| o.on_assistant_delta(&content));
+                                sink.delta(&content);
                                 current_content.push_str(&content);
                             }
 
@@ -335,16 +1099,27 @@ impl Agent {
                             }
                         }
                     }
+                    Err(LlmError::Json(e)) => {
+                        // Some vendors interleave events that aren't chunks
+                        warn!("Skipping unparseable stream event: {}", e);
+                    }
                     Err(e) => {
-                        if e.to_string().contains("Stream finished") {
-                            // Normal stream termination
-                        } else {
-                            error!("Stream chunk error: {}", e);
-                        }
+                        // A broken or stalled stream must not pass for a
+                        // complete (truncated) answer
+                        error!("Stream chunk error: {}", e);
+                        return Err(e.into());
                     }
                 }
             }
 
+            if let Some(usage) = turn_usage {
+                info!(
+                    "Token usage: prompt={} completion={} total={}",
+                    usage.prompt_tokens, usage.completion_tokens, usage.total_tokens
+                );
+                session.record_usage(usage);
+            }
+
             // Reconstruct tool calls
             let mut tool_calls_vec = Vec::new();
             let mut sorted_calls: Vec<_> = tool_call_chunks.into_iter().collect();
@@ -382,46 +1157,305 @@ impl Agent {
             });
 
             if tool_calls_vec.is_empty() {
+                finished = true;
                 break; // Done
             }
 
             // Execute tools
-            println!();
-            let tool_router = ToolRouter::new(self);
-            for tc in &tool_calls_vec {
-                info!("工具调用: {} - {}", tc.function.name, tc.function.arguments);
-                let result = tool_router
-                    .route(session, &tc.function.name, &tc.function.arguments)
-                    .await;
-
-                let output = match result {
-                    Ok(res) => res.output,
-                    Err(e) => format!("Error: {}", e),
-                };
+            sink.note("\n");
+            let progress = sink.progress();
+            // A batch of read-only calls runs concurrently; anything that
+            // writes, runs commands or changes directory runs in call order.
+            let parallel = tool_calls_vec.len() > 1
+                && tool_calls_vec
+                    .iter()
+                    .all(|tc| PARALLEL_SAFE_TOOLS.contains(&tc.function.name.as_str()));
+            let results: Vec<ToolResult> = if parallel {
+                let shared: &Session = session;
+                futures::future::join_all(tool_calls_vec.iter().map(|tc| async move {
+                    self.tool_started(tc, progress);
+                    let result = self
+                        .execute_tool_call_shared(shared, &tc.function.name, &tc.function.arguments)
+                        .await;
+                    self.tool_finished(tc, result, progress)
+                }))
+                .await
+            } else {
+                let tool_router = ToolRouter::new(self);
+                let mut results = Vec::with_capacity(tool_calls_vec.len());
+                for tc in &tool_calls_vec {
+                    self.tool_started(tc, progress);
+                    let result = tool_router
+                        .route(session, &tc.function.name, &tc.function.arguments)
+                        .await;
+                    results.push(self.tool_finished(tc, result, progress));
+                }
+                results
+            };
+
+            // Results are in call order, so each maps back to its tool_call_id
+            for (tc, result) in tool_calls_vec.iter().zip(results) {
+                if result.requires_confirmation {
+                    session.pending_confirmations.push(tc.clone());
+                }
 
-                // Add Tool Message
+                // Add Tool Message, capped so one huge output can't eat the
+                // context window (observers and progress saw it in full)
                 session.add_message(Message {
                     role: "tool".to_string(),
-                    content: Some(output),
+                    content: Some(cap_tool_output(
+                        result.output,
+                        self.config.agent.max_tool_result_bytes,
+                    )),
                     tool_calls: None,
                     tool_call_id: Some(tc.id.clone()),
                 });
             }
+
+            // Held calls go back to the caller; don't let the model carry on
+            // as if they had run.
+            if !session.pending_confirmations.is_empty() {
+                final_response_content.push_str("\n\n⚠️ 以下操作需要确认后才会执行:");
+                for tc in &session.pending_confirmations {
+                    final_response_content.push_str(&format!(
+                        "\n- {}",
+                        describe_tool_call(&tc.function.name, &tc.function.arguments)
+                    ));
+                }
+                finished = true;
+                break;
+            }
+        }
+
+        if !finished {
+            tracing::warn!(
+                "Session {} hit the tool-call limit ({} turns) before a final answer",
+                session.id,
+                max_turns
+            );
+            let note = format!(
+                "\n\n⚠️ 已达到工具调用轮数上限（{} 轮，agent.max_tool_turns），回答可能不完整。",
+                max_turns
+            );
+            sink.note(&note);
+            sink.note("\n");
+            final_response_content.push_str(&note);
         }
 
         Ok(final_response_content)
     }
 
+    fn tool_started(&self, tc: &ToolCall, progress: Option<&ProgressSender>) {
+        info!("工具调用: {} - {}", tc.function.name, tc.function.arguments);
+        if let Some(progress) = progress {
+            let _ = progress.send(AgentProgress::ToolStarted {
+                id: tc.id.clone(),
+                name: tc.function.name.clone(),
+                arguments: tc.function.arguments.clone(),
+            });
+        }
+        self.notify(|o| o.on_tool_call_start(&tc.function.name, &tc.function.arguments));
+    }
+
+    /// Report a finished call, folding dispatch errors into a failed result
+    fn tool_finished(
+        &self,
+        tc: &ToolCall,
+        result: Result<ToolResult, GearClawError>,
+        progress: Option<&ProgressSender>,
+    ) -> ToolResult {
+        let result = result.unwrap_or_else(|e| ToolResult {
+            success: false,
+            output: tool_error_output(e.kind(), &e.to_string()),
+            error: Some(e.to_string()),
+            requires_confirmation: false,
+        });
+        self.notify(|o| o.on_tool_call_end(&tc.function.name, &result));
+        if let Some(progress) = progress {
+            let _ = progress.send(AgentProgress::ToolFinished {
+                id: tc.id.clone(),
+                name: tc.function.name.clone(),
+                success: result.success,
+                output: result.output.clone(),
+            });
+        }
+        result
+    }
+
+    /// Dispatch a tool call. With `tools.confirm_destructive` on, calls
+    /// matching a destructive pattern are not run but returned with
+    /// `requires_confirmation` set.
     pub async fn execute_tool_call(
         &self,
         session: &mut Session,
         tool_name: &str,
         arguments: &str,
     ) -> Result<ToolResult, GearClawError> {
-        let args: Value = serde_json::from_str(arguments).unwrap_or(json!({}));
+        if let Some(held) = self.held_for_confirmation(tool_name, arguments) {
+            return Ok(held);
+        }
+        self.run_tool_call(session, tool_name, arguments).await
+    }
+
+    /// Like [`Agent::execute_tool_call`] on a shared session, so calls can run
+    /// concurrently. Only for [`PARALLEL_SAFE_TOOLS`]: a `cd` would be
+    /// dropped here.
+    async fn execute_tool_call_shared(
+        &self,
+        session: &Session,
+        tool_name: &str,
+        arguments: &str,
+    ) -> Result<ToolResult, GearClawError> {
+        if let Some(held) = self.held_for_confirmation(tool_name, arguments) {
+            return Ok(held);
+        }
+        let mut cwd = session.cwd.clone();
+        self.invoke_tool(
+            &mut cwd,
+            session.overrides.security.as_deref(),
+            tool_name,
+            arguments,
+        )
+        .await
+    }
+
+    fn held_for_confirmation(&self, tool_name: &str, arguments: &str) -> Option<ToolResult> {
+        self.needs_confirmation(tool_name, arguments)
+            .then(|| ToolResult {
+                success: false,
+                output: format!(
+                    "未执行：`{}` 需要用户确认",
+                    describe_tool_call(tool_name, arguments)
+                ),
+                error: None,
+                requires_confirmation: true,
+            })
+    }
+
+    /// Run a tool call the user has confirmed, skipping the destructive check
+    pub async fn execute_confirmed_tool_call(
+        &self,
+        session: &mut Session,
+        tool_name: &str,
+        arguments: &str,
+    ) -> Result<ToolResult, GearClawError> {
+        self.run_tool_call(session, tool_name, arguments).await
+    }
+
+    fn needs_confirmation(&self, tool_name: &str, arguments: &str) -> bool {
+        if !self.config.tools.confirm_destructive {
+            return false;
+        }
+        let args = parse_tool_arguments(arguments).unwrap_or_else(|_| json!({}));
+        let command_line = if tool_name == "exec" {
+            exec_command_line(&args)
+        } else {
+            None
+        };
+        // Calls read-only mode refuses are refused right away, not held
+        !self
+            .config
+            .tools
+            .read_only_refuses(tool_name, command_line.as_deref())
+            && self
+                .config
+                .tools
+                .is_destructive(tool_name, command_line.as_deref())
+    }
+
+    async fn run_tool_call(
+        &self,
+        session: &mut Session,
+        tool_name: &str,
+        arguments: &str,
+    ) -> Result<ToolResult, GearClawError> {
+        let security = session.overrides.security.clone();
+        self.invoke_tool(&mut session.cwd, security.as_deref(), tool_name, arguments)
+            .await
+    }
+
+    /// Run a tool in working directory `cwd` (which `cd` updates) under the
+    /// session's `security` override, if any
+    async fn invoke_tool(
+        &self,
+        cwd: &mut std::path::PathBuf,
+        security: Option<&str>,
+        tool_name: &str,
+        arguments: &str,
+    ) -> Result<ToolResult, GearClawError> {
+        // Report bad arguments to the model instead of running the tool
+        // without them, so it can correct the call on the next turn.
+        let args = match parse_tool_arguments(arguments) {
+            Ok(args) => args,
+            Err(e) => {
+                let message = format!("arguments were not valid JSON: {}", e);
+                return Ok(ToolResult {
+                    success: false,
+                    output: tool_error_output("invalid_arguments", &message),
+                    error: Some(message),
+                    requires_confirmation: false,
+                });
+            }
+        };
+
+        let command_line = if tool_name == "exec" {
+            exec_command_line(&args)
+        } else {
+            None
+        };
+        if self
+            .config
+            .tools
+            .read_only_refuses(tool_name, command_line.as_deref())
+        {
+            let message = format!(
+                "refused: tools.filesystem = \"read_only\" does not allow `{}`",
+                describe_tool_call(tool_name, arguments)
+            );
+            return Ok(ToolResult {
+                success: false,
+                output: tool_error_output("read_only", &message),
+                error: Some(message),
+                requires_confirmation: false,
+            });
+        }
+
+        let is_builtin = self
+            .builtin_tool_specs()
+            .iter()
+            .any(|tool| tool.name == tool_name);
+        let in_profile_list =
+            is_builtin || tool_name == "memory_remember" || tool_name.contains("__");
+        if in_profile_list && !self.config.tools.profile_allows(tool_name) {
+            let message = format!(
+                "tool `{}` is not available in tools.profile = \"{}\"",
+                tool_name, self.config.tools.profile
+            );
+            return Ok(ToolResult {
+                success: false,
+                output: tool_error_output("profile", &message),
+                error: Some(message),
+                requires_confirmation: false,
+            });
+        }
+
+        if self.skill_manager.has_untrusted() && !available_with_untrusted_skills(tool_name) {
+            let message = format!(
+                "tool `{}` is not available while skills from untrusted sources are loaded (allowed: {})",
+                tool_name,
+                UNTRUSTED_SKILL_TOOLS.join(", ")
+            );
+            return Ok(ToolResult {
+                success: false,
+                output: tool_error_output("untrusted_skill", &message),
+                error: Some(message),
+                requires_confirmation: false,
+            });
+        }
 
         // Check if it's an MCP tool
         if tool_name.contains("__") {
+            check_tool_security(security.unwrap_or(&self.config.tools.security), tool_name)?;
             if !self.mcp_manager.is_enabled() {
                 return Err(GearClawError::from(crate::error::DomainError::Mcp {
                     server: tool_name
@@ -456,37 +1490,47 @@ impl Agent {
                             dirs::home_dir().unwrap_or_else(|| std::path::PathBuf::from("/"))
                         };
 
-                        let new_cwd = if new_dir.is_absolute() {
-                            new_dir
-                        } else {
-                            session.cwd.join(new_dir)
-                        };
-
-                        let resolved_cwd = match std::fs::canonicalize(&new_cwd) {
-                            Ok(p) => p,
-                            Err(_) => new_cwd,
-                        };
-
-                        if resolved_cwd.exists() && resolved_cwd.is_dir() {
-                            session.cwd = resolved_cwd.clone();
-                            Ok(ToolResult {
-                                success: true,
-                                output: format!("Changed directory to {}", resolved_cwd.display()),
-                                error: None,
-                            })
-                        } else {
-                            Ok(ToolResult {
-                                success: false,
-                                output: "".to_string(),
-                                error: Some(format!(
-                                    "Directory not found: {}",
-                                    resolved_cwd.display()
-                                )),
-                            })
+                        match crate::session::resolve_dir(cwd, &new_dir) {
+                            Ok(resolved_cwd) => {
+                                *cwd = resolved_cwd.clone();
+                                Ok(ToolResult {
+                                    success: true,
+                                    output: format!(
+                                        "Changed directory to {}",
+                                        resolved_cwd.display()
+                                    ),
+                                    error: None,
+                                    requires_confirmation: false,
+                                })
+                            }
+                            Err(e) => {
+                                let (kind, message) = match e {
+                                    crate::session::SessionError::DirectoryNotFound(path) => (
+                                        "not_found",
+                                        format!("Directory not found: {}", path.display()),
+                                    ),
+                                    e => ("tool_execution", e.to_string()),
+                                };
+                                Ok(ToolResult {
+                                    success: false,
+                                    output: tool_error_output(kind, &message),
+                                    error: Some(message),
+                                    requires_confirmation: false,
+                                })
+                            }
                         }
+                    } else if let Some(security) = security {
+                        self.tool_executor
+                            .exec_command_with_security(
+                                security,
+                                cmd,
+                                cmd_args,
+                                Some(cwd.as_path()),
+                            )
+                            .await
                     } else {
                         self.tool_executor
-                            .exec_command(cmd, cmd_args, Some(&session.cwd))
+                            .exec_command(cmd, cmd_args, Some(cwd.as_path()))
                             .await
                     }
                 } else {
@@ -513,7 +1557,7 @@ impl Agent {
                 let full_path = if path.is_absolute() {
                     path.to_path_buf()
                 } else {
-                    session.cwd.join(path)
+                    cwd.join(path)
                 };
 
                 let content =
@@ -543,6 +1587,7 @@ impl Agent {
                     success: true,
                     output,
                     error: None,
+                    requires_confirmation: false,
                 })
             }
             "write_file" => {
@@ -560,9 +1605,25 @@ impl Agent {
                 let full_path = if path.is_absolute() {
                     path.to_path_buf()
                 } else {
-                    session.cwd.join(path)
+                    cwd.join(path)
                 };
 
+                match security.unwrap_or(&self.config.tools.security) {
+                    "deny" => {
+                        return Err(GearClawError::ToolExecutionError(
+                            "工具执行被禁止 (security=deny)".to_string(),
+                        ));
+                    }
+                    "allowlist" if !is_within(&self.config.agent.workspace, &full_path) => {
+                        return Err(GearClawError::ToolExecutionError(format!(
+                            "security=allowlist 只允许写入工作区 {} 内的文件: {}",
+                            self.config.agent.workspace.display(),
+                            full_path.display()
+                        )));
+                    }
+                    _ => {}
+                }
+
                 if let Some(parent) = full_path.parent() {
                     std::fs::create_dir_all(parent).map_err(GearClawError::IoError)?;
                 }
@@ -573,6 +1634,7 @@ impl Agent {
                     success: true,
                     output: format!("文件已写入: {}", full_path.display()),
                     error: None,
+                    requires_confirmation: false,
                 })
             }
             "list_files" => {
@@ -591,14 +1653,16 @@ impl Agent {
                 let full_path = if path.is_absolute() {
                     path.to_path_buf()
                 } else {
-                    session.cwd.join(path)
+                    cwd.join(path)
                 };
 
                 if !full_path.exists() {
+                    let message = format!("Directory not found: {}", full_path.display());
                     return Ok(ToolResult {
                         success: false,
-                        output: "".to_string(),
-                        error: Some(format!("Directory not found: {}", full_path.display())),
+                        output: tool_error_output("not_found", &message),
+                        error: Some(message),
+                        requires_confirmation: false,
                     });
                 }
 
@@ -636,6 +1700,7 @@ impl Agent {
                     success: true,
                     output,
                     error: None,
+                    requires_confirmation: false,
                 })
             }
             "file_info" => {
@@ -646,14 +1711,16 @@ impl Agent {
                 let full_path = if path.is_absolute() {
                     path.to_path_buf()
                 } else {
-                    session.cwd.join(path)
+                    cwd.join(path)
                 };
 
                 if !full_path.exists() {
+                    let message = format!("Path not found: {}", full_path.display());
                     return Ok(ToolResult {
                         success: false,
-                        output: "".to_string(),
-                        error: Some(format!("Path not found: {}", full_path.display())),
+                        output: tool_error_output("not_found", &message),
+                        error: Some(message),
+                        requires_confirmation: false,
                     });
                 }
 
@@ -682,6 +1749,7 @@ impl Agent {
                     success: true,
                     output: info,
                     error: None,
+                    requires_confirmation: false,
                 })
             }
             "web_search" => {
@@ -689,35 +1757,104 @@ impl Agent {
                     GearClawError::ToolExecutionError("web_search 需要查询参数".to_string())
                 })?;
 
+                if security.unwrap_or(&self.config.tools.security) == "deny" {
+                    return Err(GearClawError::ToolExecutionError(
+                        "工具执行被禁止 (security=deny)".to_string(),
+                    ));
+                }
                 self.tool_executor
-                    .exec_command(
-                        "curl",
-                        vec![
-                            "-s".to_string(),
-                            "-L".to_string(),
-                            format!(
-                                "https://api.duckduckgo.com/?q={}",
-                                urlencoding::encode(query)
-                            ),
-                        ],
-                        None,
-                    )
+                    .web_search(&self.config.tools.web_search, query)
                     .await
             }
-            "git_status" => {
-                self.tool_executor
-                    .exec_command("git", vec!["status".to_string()], Some(&session.cwd))
+            "fetch_url" => {
+                let url = args.get("url").and_then(|v| v.as_str()).ok_or_else(|| {
+                    GearClawError::ToolExecutionError("fetch_url 需要 'url' 参数".to_string())
+                })?;
+                let max_bytes = args
+                    .get("max_bytes")
+                    .and_then(|v| v.as_u64())
+                    .map_or(gearclaw_tools::fetch::DEFAULT_FETCH_MAX_BYTES, |n| {
+                        n as usize
+                    });
+                if security.unwrap_or(&self.config.tools.security) == "deny" {
+                    return Err(GearClawError::ToolExecutionError(
+                        "工具执行被禁止 (security=deny)".to_string(),
+                    ));
+                }
+                self.tool_executor.fetch_url(url, max_bytes).await
+            }
+            "memory_remember" => {
+                let text = args.get("text").and_then(|v| v.as_str()).ok_or_else(|| {
+                    GearClawError::ToolExecutionError(
+                        "memory_remember 需要 'text' 参数".to_string(),
+                    )
+                })?;
+                let tags: Vec<String> = args
+                    .get("tags")
+                    .and_then(|v| v.as_array())
+                    .map(|arr| {
+                        arr.iter()
+                            .filter_map(|v| v.as_str())
+                            .map(|s| s.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                let path = self.memory_manager.add_note(text, &tags).await?;
+                Ok(ToolResult {
+                    success: true,
+                    output: format!("已记住 ({})", path),
+                    error: None,
+                    requires_confirmation: false,
+                })
+            }
+            "run_skill" => {
+                let name = args.get("name").and_then(|v| v.as_str()).ok_or_else(|| {
+                    GearClawError::ToolExecutionError("run_skill 需要 'name' 参数".to_string())
+                })?;
+                let skill_args: Vec<String> = args
+                    .get("args")
+                    .and_then(|v| v.as_array())
+                    .map(|arr| {
+                        arr.iter()
+                            .filter_map(|v| v.as_str())
+                            .map(|s| s.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                self.skill_manager
+                    .run_skill(
+                        &self.tool_executor,
+                        name,
+                        &skill_args,
+                        Some(cwd.as_path()),
+                        security,
+                    )
                     .await
             }
-            "docker_ps" => {
+            "git_status" | "docker_ps" => {
+                let (cmd, arg) = if tool_name == "git_status" {
+                    ("git", "status")
+                } else {
+                    ("docker", "ps")
+                };
                 self.tool_executor
-                    .exec_command("docker", vec!["ps".to_string()], Some(&session.cwd))
+                    .exec_command_with_security(
+                        security.unwrap_or(&self.config.tools.security),
+                        cmd,
+                        vec![arg.to_string()],
+                        Some(cwd.as_path()),
+                    )
                     .await
             }
             _ => {
                 // Check if it's a macOS-specific tool
-                #[cfg(target_os = "macos")]
                 if tool_name.starts_with("macos_") {
+                    check_tool_security(
+                        security.unwrap_or(&self.config.tools.security),
+                        tool_name,
+                    )?;
                     let output = self
                         .tool_executor
                         .macos
@@ -727,6 +1864,7 @@ impl Agent {
                         success: true,
                         output,
                         error: None,
+                        requires_confirmation: false,
                     });
                 }
 
@@ -735,6 +1873,85 @@ impl Agent {
         }
     }
 
+    /// Tokens left for session history in one request: the context window
+    /// (`llm.capabilities.max_context`, else `session.max_tokens`) less the
+    /// completion reserve, the system prompt and the tool definitions
+    fn history_budget(
+        &self,
+        system_message: &Message,
+        tools: Option<&[crate::llm::ToolSpec]>,
+    ) -> usize {
+        let window = self
+            .llm_client
+            .capabilities()
+            .max_context
+            .unwrap_or(self.config.session.max_tokens);
+        let tool_tokens = tools.map_or(0, |tools| {
+            estimate_text_tokens(&serde_json::to_string(tools).unwrap_or_default())
+        });
+        window
+            .saturating_sub(self.config.llm.max_completion_tokens)
+            .saturating_sub(estimate_tokens(system_message))
+            .saturating_sub(tool_tokens)
+    }
+
+    /// Every tool offered to the model: built-ins and skills allowed by
+    /// `tools.profile`, then memory and MCP tools, less any the session's
+    /// security level or `tools.filesystem` would refuse outright
+    async fn tool_specs(&self, session: &Session) -> Vec<crate::tools::ToolSpec> {
+        let mut tool_specs = self.unfiltered_tool_specs().await;
+        let security = session
+            .overrides
+            .security
+            .as_deref()
+            .unwrap_or(&self.config.tools.security);
+        tool_specs.retain(|tool| !self.refuses_every_call(security, &tool.name));
+        tool_specs
+    }
+
+    /// Whether `invoke_tool` refuses every call of `tool_name` under
+    /// `security` or `tools.filesystem`. `exec` is dropped under `deny`
+    /// although `cd` still works, since nothing else would.
+    fn refuses_every_call(&self, security: &str, tool_name: &str) -> bool {
+        if self.config.tools.read_only_refuses(tool_name, None) {
+            return true;
+        }
+        match security {
+            "deny" => !ALLOWED_UNDER_DENY.contains(&tool_name),
+            "allowlist" => MACOS_SCRIPT_TOOLS.contains(&tool_name),
+            _ => false,
+        }
+    }
+
+    async fn unfiltered_tool_specs(&self) -> Vec<crate::tools::ToolSpec> {
+        let mut tool_specs = self.builtin_tool_specs();
+        tool_specs.retain(|tool| self.config.tools.profile_allows(&tool.name));
+        if self.skill_manager.has_untrusted() {
+            // Untrusted skill instructions are in the system prompt; don't
+            // offer the model anything they shouldn't be able to ask for
+            tool_specs.retain(|tool| available_with_untrusted_skills(&tool.name));
+            return tool_specs;
+        }
+        if self.config.memory.enabled && self.config.tools.profile_allows("memory_remember") {
+            tool_specs.push(MemoryManager::remember_tool_spec());
+        }
+        if self.mcp_manager.is_enabled() {
+            let mcp_tools = self.mcp_manager.list_tools().await;
+            tool_specs.extend(
+                mcp_tools
+                    .into_iter()
+                    .filter(|tool| self.config.tools.profile_allows(&tool.name)),
+            );
+        }
+        tool_specs
+    }
+
+    fn builtin_tool_specs(&self) -> Vec<crate::tools::ToolSpec> {
+        let mut tool_specs = self.tool_executor.available_tools();
+        tool_specs.extend(self.skill_manager.tool_spec());
+        tool_specs
+    }
+
     fn convert_to_llm_tools(
         &self,
         tools: Vec<crate::tools::ToolSpec>,
@@ -762,11 +1979,98 @@ impl Agent {
             .collect()
     }
 
+    /// Models to try for `session`, in order: its override (see `/model`),
+    /// then the configured primary and fallbacks.
+    fn session_models(&self, session: &Session) -> Vec<String> {
+        let mut models = self.llm_client.models();
+        if let Some(model) = &session.overrides.model {
+            models.retain(|m| m != model);
+            models.insert(0, model.clone());
+        }
+        models
+    }
+
+    /// System prompt sent with a turn: the session's (or global) prompt with
+    /// the agent name and available tools filled in, the skills context, and
+    /// memories relevant to `query` when memory is enabled.
+    pub async fn system_prompt_for(&self, session: &Session, query: &str) -> String {
+        let tool_names: Vec<String> = self
+            .tool_specs(session)
+            .await
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        self.system_prompt_with_tools(session, query, &tool_names)
+            .await
+    }
+
+    async fn system_prompt_with_tools(
+        &self,
+        session: &Session,
+        query: &str,
+        tool_names: &[String],
+    ) -> String {
+        let template = session
+            .overrides
+            .system_prompt
+            .as_deref()
+            .unwrap_or(&self.config.agent.system_prompt);
+        let mut system_prompt = render_system_prompt(template, &self.config.agent.name, tool_names);
+        system_prompt.push_str(&self.skill_manager.get_prompt_context());
+
+        // Search memory if enabled and add to system prompt
+        let memory_enabled = session
+            .overrides
+            .memory_enabled
+            .unwrap_or(self.config.agent.memory_enabled);
+        if memory_enabled && !query.is_empty() {
+            match self.memory_manager.search(query, 3).await {
+                Ok(memories) if !memories.is_empty() => {
+                    tracing::debug!("Found {} relevant memories", memories.len());
+                    let memory_context = memories
+                        .iter()
+                        .map(|m| format!("- [{}] {} (score: {:.2})", m.path, m.text, m.score))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+
+                    system_prompt.push_str("\n\n=== Relevant Context ===\n");
+                    system_prompt.push_str("The following information from your memory may be relevant to this conversation:\n\n");
+                    system_prompt.push_str(&memory_context);
+                    system_prompt.push_str("\n========================\n");
+                }
+                Ok(_) => {
+                    tracing::debug!("No relevant memories found");
+                }
+                Err(e) => {
+                    tracing::warn!("Memory search failed: {}", e);
+                    // Continue without memory context rather than failing
+                }
+            }
+        }
+        system_prompt
+    }
+
     fn print_help(&self) {
+        println!("📖 交互命令:");
+        println!();
+        println!("  • /model [名称] - 查看或切换后续对话使用的模型");
+        println!("  • /system - 显示当前系统提示词（含技能与记忆上下文）");
+        println!("  • /tokens - 显示本会话累计的 Token 用量");
+        println!("  • /pwd - 显示当前工作目录");
+        println!("  • /cd [路径] - 切换文件与命令工具使用的工作目录（省略路径时回到主目录）");
+        println!("  • clear - 清除对话历史");
+        println!("  • exit / quit - 退出");
+        println!();
+
         println!("📖 可用工具:");
         println!();
 
-        for tool in self.tool_executor.available_tools() {
+        for tool in self
+            .tool_executor
+            .available_tools()
+            .into_iter()
+            .filter(|tool| self.config.tools.profile_allows(&tool.name))
+        {
             println!("  • {} - {}", tool.name, tool.description);
         }
     }
@@ -777,36 +2081,68 @@ impl Agent {
     /// - platform: Platform name (e.g., "discord", "telegram")
     /// - source_id: User or channel ID from the platform
     /// - content: Message content
+    /// - attachments: Files attached to the message, listed after the content
     pub async fn process_channel_message(
         &self,
         platform: &str,
         source_id: &str,
         content: &str,
+        attachments: &[IncomingAttachment],
+    ) -> Result<String, GearClawError> {
+        self.process_channel_message_with_addressing(
+            platform,
+            source_id,
+            content,
+            attachments,
+            &MessageAddressing::default(),
+        )
+        .await
+    }
+
+    /// Like [`Agent::process_channel_message`], with the mention/reply
+    /// metadata needed by [`TriggerMode::DirectMention`](crate::config::TriggerMode::DirectMention).
+    pub async fn process_channel_message_with_addressing(
+        &self,
+        platform: &str,
+        source_id: &str,
+        content: &str,
+        attachments: &[IncomingAttachment],
+        addressing: &MessageAddressing,
     ) -> Result<String, GearClawError> {
         // Create session ID from platform and source
-        let session_id = format!("{}:{}", platform, source_id);
+        let session_id = sanitize_session_id(&format!("{}:{}", platform, source_id));
 
         // Get or create session
         let mut session = self.session_manager.get_or_create_session(&session_id)?;
+        self.apply_channel_overrides(&mut session, platform, source_id);
+
+        let message = format!("{}{}", content, describe_attachments(attachments));
 
         // Add user message to session
         session.add_message(Message {
             role: "user".to_string(),
-            content: Some(content.to_string()),
+            content: Some(message.clone()),
             tool_calls: None,
             tool_call_id: None,
         });
 
         // Check if agent should respond
-        let should_respond = self.should_respond_to_message(platform, source_id, content)?;
+        let should_respond =
+            self.should_respond_to_message(platform, source_id, content, addressing)?;
 
         if !should_respond {
             tracing::debug!("Agent not triggered for message: {}", content);
             return Ok(String::new());
         }
 
-        // Process message and get response
-        let response = self.process_message(&mut session, content).await?;
+        // Process message and get response; nothing goes to the daemon's stdout
+        let response = self
+            .process_message_with_sink(
+                &mut session,
+                &message,
+                DeltaSink::Writer(&mut std::io::sink()),
+            )
+            .await?;
 
         // Save session
         self.session_manager.save_session(&session).await?;
@@ -814,15 +2150,32 @@ impl Agent {
         Ok(response)
     }
 
+    /// Apply the `agent.channel_overrides` entry for `platform:channel_id`
+    /// (if any) to `session`, falling back to the global configuration.
+    pub fn apply_channel_overrides(&self, session: &mut Session, platform: &str, channel_id: &str) {
+        let settings = self.config.channel_settings(platform, channel_id);
+        session.overrides = SessionOverrides {
+            system_prompt: settings.system_prompt,
+            security: settings.security,
+            memory_enabled: settings.memory_enabled,
+            model: None,
+        };
+    }
+
     /// Check if agent should respond to a message based on trigger mode
     fn should_respond_to_message(
         &self,
         platform: &str,
         source_id: &str,
         content: &str,
+        addressing: &MessageAddressing,
     ) -> Result<bool, GearClawError> {
         let trigger_config = &self.config.agent.triggers;
 
+        if is_own_message(addressing) {
+            return Ok(false);
+        }
+
         // Check channel whitelist/blacklist
         let channel_key = format!("{}:{}", platform, source_id);
 
@@ -832,34 +2185,22 @@ impl Agent {
         }
 
         // Check enabled channels (whitelist)
-        if !trigger_config.enabled_channels.is_empty() {
-            if !trigger_config.enabled_channels.contains(&channel_key) {
-                return Ok(false);
-            }
+        if !trigger_config.enabled_channels.is_empty()
+            && !trigger_config.enabled_channels.contains(&channel_key)
+        {
+            return Ok(false);
         }
 
         // Check trigger mode
-        match trigger_config.mode {
-            crate::config::TriggerMode::Always => Ok(true),
-            crate::config::TriggerMode::Mention => {
-                // Check if message starts with mention pattern
-                for pattern in &trigger_config.mention_patterns {
-                    if content.starts_with(pattern) || content.contains(pattern) {
-                        return Ok(true);
-                    }
-                }
-                Ok(false)
-            }
-            crate::config::TriggerMode::Keyword => {
-                // Check if message contains any keyword
-                for keyword in &trigger_config.keywords {
-                    if content.to_lowercase().contains(&keyword.to_lowercase()) {
-                        return Ok(true);
-                    }
-                }
-                Ok(false)
-            }
-        }
+        let triggered = match trigger_config.mode {
+            crate::config::TriggerMode::Always => true,
+            crate::config::TriggerMode::Mention => self.trigger_matcher.matches_mention(content),
+            crate::config::TriggerMode::Keyword => self.trigger_matcher.matches_keyword(content),
+            crate::config::TriggerMode::DirectMention => addressing.addresses_bot(),
+        };
+
+        // Suppress repeated triggers within the channel's cooldown
+        Ok(triggered && self.trigger_matcher.try_start_cooldown(&channel_key))
     }
 }
 
@@ -26,13 +26,20 @@ pub const DEFAULT_WS_PATH: &str = "/ws";
 pub const DEFAULT_SAVE_INTERVAL: u64 = 60;
 /// Default max context tokens
 pub const DEFAULT_MAX_TOKENS: usize = 200000;
+/// Default cap on LLM turns per message while tools are being called
+pub const DEFAULT_MAX_TOOL_TURNS: usize = 15;
+/// Default cap on tool output added to the conversation history (bytes)
+pub const DEFAULT_MAX_TOOL_RESULT_BYTES: usize = 32 * 1024;
+/// Default number of interactive prompt lines kept in `agent.history_path`
+pub const DEFAULT_HISTORY_MAX_ENTRIES: usize = 1000;
 /// Default agent name
 pub const DEFAULT_AGENT_NAME: &str = "GearClaw";
-/// Default system prompt
-pub const DEFAULT_SYSTEM_PROMPT: &str = r#"你是一个智能 AI 助手，名叫 GearClaw 🫞。
+/// Default system prompt. `{agent_name}` and `{tools}` are filled in each
+/// turn with `agent.name` and the tools offered to the model.
+pub const DEFAULT_SYSTEM_PROMPT: &str = r#"你是一个智能 AI 助手，名叫 {agent_name} 🫞。
 
 你的目标是帮助用户完成任务。你可以：
-- 使用工具执行命令（在安全允许的范围内）
+- 使用工具执行命令（在安全允许的范围内），当前可用工具：{tools}
 - 调用 LLM 进行推理和生成内容
 - 管理会话上下文
 - 提供编程帮助、调试、代码审查
@@ -98,9 +105,71 @@ pub struct LLMConfig {
     /// Embedding model
     #[serde(default = "LLMConfig::default_embedding_model")]
     pub embedding_model: String,
+    /// Base URL for embedding requests; unset uses `endpoint`
+    #[serde(default)]
+    pub embedding_endpoint: Option<String>,
+    /// API key for `embedding_endpoint`; unset uses the chat API key
+    #[serde(default)]
+    pub embedding_api_key: Option<String>,
     /// Temperature (sampling)
     #[serde(default = "LLMConfig::default_temperature")]
     pub temperature: Option<f32>,
+    /// Nucleus sampling (top_p); unset leaves the provider default
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Frequency penalty; unset leaves the provider default
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+    /// Presence penalty; unset leaves the provider default
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+    /// Retry policy for transient endpoint failures
+    #[serde(default)]
+    pub retry: LLMRetryConfig,
+    /// Log request/response bodies at debug level (API keys redacted; prompts are logged)
+    #[serde(default)]
+    pub debug_tracing: bool,
+    /// How the API key is sent: `bearer` (Authorization header) or `api_key` (Azure `api-key` header)
+    #[serde(default)]
+    pub auth_scheme: LLMAuthScheme,
+    /// Organization ID sent as `OpenAI-Organization`
+    #[serde(default)]
+    pub organization: Option<String>,
+    /// Extra headers sent with every LLM request (e.g. `HTTP-Referer` for OpenRouter)
+    #[serde(default)]
+    pub extra_headers: HashMap<String, String>,
+    /// Force HTTP/1.1 for LLM requests. HTTP/2 is negotiated by default; set this
+    /// when a proxy stalls or truncates SSE streams over HTTP/2.
+    #[serde(default)]
+    pub force_http1: bool,
+    /// What the endpoint supports (tools, streamed usage, context size)
+    #[serde(default)]
+    pub capabilities: crate::llm::ModelCapabilities,
+    /// Give up on a single LLM request after this many seconds, streamed
+    /// body included: a streamed answer still arriving at the deadline is
+    /// cut off with a timeout error. Raise it for long generations, or set
+    /// 0 and rely on `stream_idle_timeout_secs` alone (0 disables)
+    #[serde(default = "LLMConfig::default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// Fail a streamed response that sends nothing for this many seconds;
+    /// the wait restarts with every chunk (0 disables)
+    #[serde(default = "LLMConfig::default_stream_idle_timeout_secs")]
+    pub stream_idle_timeout_secs: u64,
+    /// Completion limit sent with each chat request (`max_tokens`); the same
+    /// amount of the context window is kept free of history for the answer
+    #[serde(default = "LLMConfig::default_max_completion_tokens")]
+    pub max_completion_tokens: usize,
+}
+
+/// API key presentation for the LLM endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LLMAuthScheme {
+    /// `Authorization: Bearer <key>`
+    #[default]
+    Bearer,
+    /// `api-key: <key>` (Azure OpenAI)
+    ApiKey,
 }
 
 impl LLMConfig {
@@ -113,6 +182,15 @@ impl LLMConfig {
     fn default_temperature() -> Option<f32> {
         Some(0.7)
     }
+    fn default_request_timeout_secs() -> u64 {
+        600
+    }
+    fn default_stream_idle_timeout_secs() -> u64 {
+        60
+    }
+    fn default_max_completion_tokens() -> usize {
+        4096
+    }
 }
 
 impl Default for LLMConfig {
@@ -123,7 +201,65 @@ impl Default for LLMConfig {
             endpoint: DEFAULT_ENDPOINT.to_string(),
             api_key: None,
             embedding_model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            embedding_endpoint: None,
+            embedding_api_key: None,
             temperature: Some(0.7),
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            retry: LLMRetryConfig::default(),
+            debug_tracing: false,
+            auth_scheme: LLMAuthScheme::default(),
+            organization: None,
+            extra_headers: HashMap::new(),
+            force_http1: false,
+            capabilities: crate::llm::ModelCapabilities::default(),
+            request_timeout_secs: Self::default_request_timeout_secs(),
+            stream_idle_timeout_secs: Self::default_stream_idle_timeout_secs(),
+            max_completion_tokens: Self::default_max_completion_tokens(),
+        }
+    }
+}
+
+/// Retry policy for LLM requests (429 / 5xx / network errors)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LLMRetryConfig {
+    /// Total attempts including the first request (1 disables retries)
+    #[serde(default = "LLMRetryConfig::default_max_attempts")]
+    pub max_attempts: u32,
+    /// Initial backoff delay in milliseconds, doubled per retry
+    #[serde(default = "LLMRetryConfig::default_base_delay_ms")]
+    pub base_delay_ms: u64,
+    /// Maximum backoff delay in milliseconds
+    #[serde(default = "LLMRetryConfig::default_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// Add random jitter to backoff delays
+    #[serde(default = "LLMRetryConfig::default_jitter")]
+    pub jitter: bool,
+}
+
+impl LLMRetryConfig {
+    fn default_max_attempts() -> u32 {
+        3
+    }
+    fn default_base_delay_ms() -> u64 {
+        500
+    }
+    fn default_max_delay_ms() -> u64 {
+        30_000
+    }
+    fn default_jitter() -> bool {
+        true
+    }
+}
+
+impl Default for LLMRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: Self::default_max_attempts(),
+            base_delay_ms: Self::default_base_delay_ms(),
+            max_delay_ms: Self::default_max_delay_ms(),
+            jitter: Self::default_jitter(),
         }
     }
 }
@@ -147,8 +283,105 @@ pub struct ToolsConfig {
     /// Tool profile: minimal, coding, messaging, full
     #[serde(default = "ToolsConfig::default_profile")]
     pub profile: String,
+    /// Hold tool calls matching `destructive_patterns` for user confirmation
+    /// instead of running them (plan / dry-run mode)
+    #[serde(default)]
+    pub confirm_destructive: bool,
+    /// Command prefixes (e.g. `rm`, `git push`) or tool names (e.g.
+    /// `write_file`) treated as destructive
+    #[serde(default = "ToolsConfig::default_destructive_patterns")]
+    pub destructive_patterns: Vec<String>,
+    /// Backend for the `web_search` tool
+    #[serde(default)]
+    pub web_search: WebSearchConfig,
+    /// `read_only` refuses `write_file`, `run_skill`, `exec` commands
+    /// outside a read-only allowlist, and MCP and macOS tools not known to
+    /// be read-only, whatever the security level
+    #[serde(default)]
+    pub filesystem: FilesystemAccess,
+    /// MCP (`server__tool`) or macOS tools that only read, and so stay
+    /// available under `filesystem = "read_only"`
+    #[serde(default)]
+    pub read_only_tools: Vec<String>,
 }
 
+/// Whether tools may change files
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FilesystemAccess {
+    ReadOnly,
+    #[default]
+    ReadWrite,
+}
+
+/// Programs `exec` may run in read-only mode. Anything else is refused:
+/// interpreters, build tools and editors can all write files.
+const READ_ONLY_COMMANDS: [&str; 34] = [
+    "ls",
+    "cat",
+    "head",
+    "tail",
+    "wc",
+    "grep",
+    "egrep",
+    "fgrep",
+    "rg",
+    "find",
+    "file",
+    "stat",
+    "du",
+    "df",
+    "pwd",
+    "echo",
+    "which",
+    "whoami",
+    "id",
+    "date",
+    "uname",
+    "diff",
+    "cmp",
+    "cut",
+    "basename",
+    "dirname",
+    "realpath",
+    "readlink",
+    "env",
+    "printenv",
+    "md5sum",
+    "sha256sum",
+    "jq",
+    "git",
+];
+
+/// Git subcommands that only read the repository
+const READ_ONLY_GIT_SUBCOMMANDS: [&str; 13] = [
+    "status",
+    "log",
+    "diff",
+    "show",
+    "blame",
+    "ls-files",
+    "ls-tree",
+    "rev-parse",
+    "rev-list",
+    "grep",
+    "describe",
+    "shortlog",
+    "cat-file",
+];
+
+/// `find` actions that delete, write or run commands
+const FIND_WRITING_ACTIONS: [&str; 9] = [
+    "-delete", "-exec", "-execdir", "-ok", "-okdir", "-fprint", "-fprint0", "-fprintf", "-fls",
+];
+
+/// macOS tools that only read state
+const READ_ONLY_MACOS_TOOLS: [&str; 3] = [
+    "macos_is_running",
+    "macos_list_windows",
+    "macos_clipboard_read",
+];
+
 impl ToolsConfig {
     fn default_security() -> String {
         "full".to_string()
@@ -159,6 +392,217 @@ impl ToolsConfig {
     fn default_profile() -> String {
         "full".to_string()
     }
+    fn default_destructive_patterns() -> Vec<String> {
+        [
+            "rm",
+            "rmdir",
+            "mv",
+            "dd",
+            "mkfs",
+            "chmod",
+            "chown",
+            "kill",
+            "sudo",
+            "git push",
+            "git reset --hard",
+            "git clean",
+            // Runs the skill's bash/python blocks, which can do anything
+            "run_skill",
+        ]
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+    }
+
+    /// Whether `tools.profile` offers tool `tool_name`. `memory_remember`
+    /// is listed as `memory` and MCP tools (`server__tool`) as `mcp`.
+    pub fn profile_allows(&self, tool_name: &str) -> bool {
+        let entry = if tool_name == "memory_remember" {
+            "memory"
+        } else if tool_name.contains("__") {
+            "mcp"
+        } else {
+            tool_name
+        };
+        TOOL_PROFILE_TOOLS
+            .iter()
+            .find(|(profile, _)| *profile == self.profile)
+            .is_none_or(|(_, tools)| tools.contains(&entry))
+    }
+
+    /// Whether `filesystem = "read_only"` refuses a tool call. `exec` calls
+    /// are allowed only when every command the line runs is on the
+    /// read-only allowlist; MCP and macOS tools only when known to be
+    /// read-only (built in, or listed in `read_only_tools`).
+    pub fn read_only_refuses(&self, tool_name: &str, command_line: Option<&str>) -> bool {
+        if self.filesystem != FilesystemAccess::ReadOnly {
+            return false;
+        }
+        match tool_name {
+            "write_file" | "run_skill" => true,
+            "exec" => command_line.is_some_and(|line| {
+                self.is_destructive(tool_name, Some(line))
+                    || !command_invocations(line)
+                        .iter()
+                        .all(|words| is_read_only_invocation(words))
+            }),
+            name if name.contains("__") || name.starts_with("macos_") => {
+                !READ_ONLY_MACOS_TOOLS.contains(&name)
+                    && !self.read_only_tools.iter().any(|tool| tool == name)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a tool call matches one of `destructive_patterns`. `exec`
+    /// calls are matched by command line prefix on word boundaries, against
+    /// every command the line runs (see `command_invocations`); other
+    /// tools by name.
+    pub fn is_destructive(&self, tool_name: &str, command_line: Option<&str>) -> bool {
+        let invocations = command_line.map(command_invocations).unwrap_or_default();
+        self.destructive_patterns.iter().any(|pattern| {
+            let pattern = pattern.trim();
+            if pattern.is_empty() {
+                return false;
+            }
+            if pattern == tool_name {
+                return true;
+            }
+            invocations.iter().any(|words| {
+                let mut words = words.iter();
+                pattern
+                    .split_whitespace()
+                    .all(|expected| words.next().is_some_and(|word| word == expected))
+            })
+        })
+    }
+}
+
+/// Git options placed before the subcommand that take a separate value
+const GIT_GLOBAL_OPTIONS_WITH_VALUE: [&str; 5] =
+    ["-C", "-c", "--git-dir", "--work-tree", "--namespace"];
+
+/// `sudo` options that take a separate value
+const SUDO_OPTIONS_WITH_VALUE: [&str; 9] = ["-u", "-g", "-h", "-p", "-C", "-D", "-U", "-T", "-r"];
+
+/// Every command an `exec` command line runs, as words with the program
+/// reduced to its basename (`/bin/rm` is `rm`). Besides the line itself this
+/// looks through `env` and `sudo` wrappers, splits `sh -c`/`bash -c`
+/// scripts into their statements, and drops git's global options so
+/// `git -C repo push` reads as `git push`. Best effort: it does not parse
+/// full shell syntax.
+fn command_invocations(line: &str) -> Vec<Vec<String>> {
+    let mut found = Vec::new();
+    for statement in shell_statements(line) {
+        collect_invocations(statement, &mut found);
+    }
+    found
+}
+
+/// Split a command line on `;`, `&`, `|` and newlines into word lists,
+/// with quotes and grouping parentheses stripped from each word
+fn shell_statements(line: &str) -> Vec<Vec<String>> {
+    line.split([';', '&', '|', '\n'])
+        .map(|statement| {
+            statement
+                .split_whitespace()
+                .map(|word| word.trim_matches(|c| matches!(c, '"' | '\'' | '(' | ')' | '`')))
+                .filter(|word| !word.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .filter(|words| !words.is_empty())
+        .collect()
+}
+
+fn collect_invocations(mut words: Vec<String>, found: &mut Vec<Vec<String>>) {
+    let Some(program) = words.first_mut() else {
+        return;
+    };
+    if let Some(name) = program.rsplit('/').next() {
+        *program = name.to_string();
+    }
+    let program = program.clone();
+    let rest = words[1..].to_vec();
+    found.push(words);
+
+    match program.as_str() {
+        "env" => {
+            let inner = rest
+                .into_iter()
+                .skip_while(|word| word.starts_with('-') || word.contains('='))
+                .collect();
+            collect_invocations(inner, found);
+        }
+        "sudo" => {
+            let mut i = 0;
+            while i < rest.len() && rest[i].starts_with('-') {
+                if SUDO_OPTIONS_WITH_VALUE.contains(&rest[i].as_str()) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            collect_invocations(rest.get(i..).unwrap_or_default().to_vec(), found);
+        }
+        "sh" | "bash" | "zsh" | "dash" => {
+            // `-c`, `-lc`, `-ec`, ...: everything after it is the script
+            let script_flag = rest.iter().position(|word| {
+                word.starts_with('-') && !word.starts_with("--") && word.contains('c')
+            });
+            if let Some(flag) = script_flag {
+                let script = rest[flag + 1..].join(" ");
+                for statement in shell_statements(&script) {
+                    collect_invocations(statement, found);
+                }
+            }
+        }
+        "git" => {
+            let mut i = 0;
+            while i < rest.len() && rest[i].starts_with('-') {
+                if GIT_GLOBAL_OPTIONS_WITH_VALUE.contains(&rest[i].as_str()) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            if i > 0 {
+                let mut subcommand = vec![program];
+                subcommand.extend(rest.get(i..).unwrap_or_default().iter().cloned());
+                found.push(subcommand);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether one command (see `command_invocations`) only reads files
+fn is_read_only_invocation(words: &[String]) -> bool {
+    let (program, args) = (words[0].as_str(), &words[1..]);
+    match program {
+        "git" => {
+            let mut i = 0;
+            while i < args.len() && args[i].starts_with('-') {
+                // `-c` and `--exec-path` can point git at any program
+                if args[i] == "-c" || args[i].starts_with("--exec-path") {
+                    return false;
+                }
+                if GIT_GLOBAL_OPTIONS_WITH_VALUE.contains(&args[i].as_str()) {
+                    i += 1;
+                }
+                i += 1;
+            }
+            args.get(i)
+                .is_some_and(|sub| READ_ONLY_GIT_SUBCOMMANDS.contains(&sub.as_str()))
+                && !args
+                    .iter()
+                    .any(|arg| arg.starts_with("--output") || arg == "--ext-diff")
+        }
+        "find" => !args
+            .iter()
+            .any(|arg| FIND_WRITING_ACTIONS.contains(&arg.as_str())),
+        // `--pre` runs a command on every file searched
+        "rg" => !args.iter().any(|arg| arg.starts_with("--pre")),
+        _ => READ_ONLY_COMMANDS.contains(&program),
+    }
 }
 
 impl Default for ToolsConfig {
@@ -168,6 +612,51 @@ impl Default for ToolsConfig {
             host: "gateway".to_string(),
             elevated_enabled: false,
             profile: "full".to_string(),
+            confirm_destructive: false,
+            destructive_patterns: Self::default_destructive_patterns(),
+            web_search: WebSearchConfig::default(),
+            filesystem: FilesystemAccess::default(),
+            read_only_tools: Vec::new(),
+        }
+    }
+}
+
+/// `web_search` tool backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSearchConfig {
+    /// Search provider: duckduckgo, searxng
+    #[serde(default = "WebSearchConfig::default_provider")]
+    pub provider: String,
+    /// Provider API endpoint; the provider's default when unset
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Maximum results returned to the model
+    #[serde(default = "WebSearchConfig::default_max_results")]
+    pub max_results: usize,
+    /// Request timeout in seconds
+    #[serde(default = "WebSearchConfig::default_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+impl WebSearchConfig {
+    fn default_provider() -> String {
+        "duckduckgo".to_string()
+    }
+    fn default_max_results() -> usize {
+        gearclaw_tools::web_search::DEFAULT_MAX_RESULTS
+    }
+    fn default_timeout_seconds() -> u64 {
+        10
+    }
+}
+
+impl Default for WebSearchConfig {
+    fn default() -> Self {
+        Self {
+            provider: Self::default_provider(),
+            endpoint: None,
+            max_results: Self::default_max_results(),
+            timeout_seconds: Self::default_timeout_seconds(),
         }
     }
 }
@@ -246,6 +735,42 @@ pub struct AgentConfig {
     /// Channel trigger configuration
     #[serde(default)]
     pub triggers: AgentTriggerConfig,
+    /// Summarize older history once a session's estimated size exceeds this many tokens (unset disables)
+    #[serde(default)]
+    pub compact_after_tokens: Option<usize>,
+    /// Number of most recent user turns kept verbatim when compacting
+    #[serde(default = "AgentConfig::default_compact_keep_recent")]
+    pub compact_keep_recent: usize,
+    /// Per-channel overrides (key format: "platform:channel_id")
+    #[serde(default)]
+    pub channel_overrides: HashMap<String, ChannelOverride>,
+    /// Maximum LLM turns per message while the model keeps calling tools
+    #[serde(default = "AgentConfig::default_max_tool_turns")]
+    pub max_tool_turns: usize,
+    /// Tool output beyond this many bytes is truncated before it enters the
+    /// conversation history
+    #[serde(default = "AgentConfig::default_max_tool_result_bytes")]
+    pub max_tool_result_bytes: usize,
+    /// File the interactive prompt's ↑/↓ history is loaded from and saved to
+    #[serde(default = "AgentConfig::default_history_path")]
+    pub history_path: PathBuf,
+    /// Most recent interactive prompt lines kept in the history file (0 disables saving)
+    #[serde(default = "AgentConfig::default_history_max_entries")]
+    pub history_max_entries: usize,
+}
+
+/// Settings that replace the global ones for a single channel
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ChannelOverride {
+    /// System prompt used instead of `agent.system_prompt`
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Tool security level used instead of `tools.security`
+    #[serde(default)]
+    pub security: Option<String>,
+    /// Memory search on/off instead of `agent.memory_enabled`
+    #[serde(default)]
+    pub memory_enabled: Option<bool>,
 }
 
 impl AgentConfig {
@@ -264,6 +789,21 @@ impl AgentConfig {
     fn default_skill_source_cache_ttl_seconds() -> u64 {
         300
     }
+    fn default_compact_keep_recent() -> usize {
+        4
+    }
+    fn default_max_tool_turns() -> usize {
+        DEFAULT_MAX_TOOL_TURNS
+    }
+    fn default_max_tool_result_bytes() -> usize {
+        DEFAULT_MAX_TOOL_RESULT_BYTES
+    }
+    fn default_history_path() -> PathBuf {
+        default_gearclaw_dir().join("history")
+    }
+    fn default_history_max_entries() -> usize {
+        DEFAULT_HISTORY_MAX_ENTRIES
+    }
 }
 
 impl Default for AgentConfig {
@@ -278,6 +818,13 @@ impl Default for AgentConfig {
             skill_sources: vec![],
             skill_trust_policy: SkillTrustPolicy::default(),
             triggers: AgentTriggerConfig::default(),
+            compact_after_tokens: None,
+            compact_keep_recent: Self::default_compact_keep_recent(),
+            channel_overrides: HashMap::new(),
+            max_tool_turns: DEFAULT_MAX_TOOL_TURNS,
+            max_tool_result_bytes: DEFAULT_MAX_TOOL_RESULT_BYTES,
+            history_path: Self::default_history_path(),
+            history_max_entries: DEFAULT_HISTORY_MAX_ENTRIES,
         }
     }
 }
@@ -315,6 +862,14 @@ pub struct SkillSourceConfig {
     /// Require `git verify-commit HEAD` after sync (git_repo only)
     #[serde(default)]
     pub verify_head_commit_signature: bool,
+    /// Signing key fingerprints (or long key IDs) accepted by
+    /// `verify_head_commit_signature`; empty accepts any key in the keyring
+    #[serde(default)]
+    pub allowed_signers: Vec<String>,
+    /// Only look for skills under this relative path; git_repo sources use
+    /// sparse checkout so nothing else is materialized
+    #[serde(default)]
+    pub subdir: Option<String>,
 }
 
 impl SkillSourceConfig {
@@ -336,6 +891,20 @@ pub enum SkillTrustPolicy {
     AllowUntrusted,
 }
 
+impl SkillTrustPolicy {
+    /// Whether skills installed from `source` run with full tool access.
+    /// Sources marked `trusted` always do; local directories do too unless
+    /// the policy is `trusted_only`.
+    pub fn trusts(&self, source: &SkillSourceConfig) -> bool {
+        match self {
+            SkillTrustPolicy::TrustedOnly => source.trusted,
+            SkillTrustPolicy::LocalOnly | SkillTrustPolicy::AllowUntrusted => {
+                source.trusted || source.kind == SkillSourceKind::LocalDir
+            }
+        }
+    }
+}
+
 // ============================================================================
 // Agent Trigger Config
 // ============================================================================
@@ -351,12 +920,16 @@ pub enum TriggerMode {
     Mention,
     /// Only respond when keywords are matched
     Keyword,
+    /// Only respond when the bot is @-mentioned by user id or replied to,
+    /// as reported by the channel adapter (no text matching)
+    #[serde(rename = "direct_mention")]
+    DirectMention,
 }
 
 /// Agent trigger configuration for channel messages
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentTriggerConfig {
-    /// Trigger mode: always, mention, or keyword
+    /// Trigger mode: always, mention, keyword, or direct_mention
     #[serde(default)]
     pub mode: TriggerMode,
     /// Mention patterns (e.g., ["@agent", "@bot", "!ai"])
@@ -365,18 +938,63 @@ pub struct AgentTriggerConfig {
     /// Keywords that trigger the agent (for keyword mode)
     #[serde(default)]
     pub keywords: Vec<String>,
-    /// Whitelist: only respond in these channels (format: "platform:channel_id")
+    /// Whitelist: only respond in these channels (format: "platform:channel_id").
+    /// Layered configs append to this list (see [`APPEND_LIST_FIELDS`]).
     #[serde(default)]
     pub enabled_channels: Vec<String>,
-    /// Blacklist: never respond in these channels (format: "platform:channel_id")
+    /// Blacklist: never respond in these channels (format: "platform:channel_id").
+    /// Layered configs append to this list (see [`APPEND_LIST_FIELDS`]).
     #[serde(default)]
     pub disabled_channels: Vec<String>,
+    /// How `keywords` match: whole words, case-insensitive by default
+    #[serde(default)]
+    pub keyword_match: TriggerMatchOptions,
+    /// How `mention_patterns` match: case-sensitive substrings by default
+    #[serde(default = "AgentTriggerConfig::default_mention_match")]
+    pub mention_match: TriggerMatchOptions,
+    /// After triggering in a channel, ignore further triggers there for this
+    /// many seconds (0 disables)
+    #[serde(default)]
+    pub cooldown_seconds: u64,
+}
+
+/// How trigger text (keywords or mention patterns) is found in a message
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TriggerMatchOptions {
+    /// Only match whole words, so "art" does not match "smart"; false
+    /// matches any substring
+    #[serde(default = "TriggerMatchOptions::default_word_boundary")]
+    pub word_boundary: bool,
+    /// Match letter case exactly
+    #[serde(default)]
+    pub case_sensitive: bool,
+}
+
+impl TriggerMatchOptions {
+    fn default_word_boundary() -> bool {
+        true
+    }
+}
+
+impl Default for TriggerMatchOptions {
+    fn default() -> Self {
+        Self {
+            word_boundary: true,
+            case_sensitive: false,
+        }
+    }
 }
 
 impl AgentTriggerConfig {
     fn default_mention_patterns() -> Vec<String> {
         vec!["@agent".to_string(), "@bot".to_string()]
     }
+    fn default_mention_match() -> TriggerMatchOptions {
+        TriggerMatchOptions {
+            word_boundary: false,
+            case_sensitive: true,
+        }
+    }
 }
 
 impl Default for AgentTriggerConfig {
@@ -387,6 +1005,9 @@ impl Default for AgentTriggerConfig {
             keywords: vec![],
             enabled_channels: vec![],
             disabled_channels: vec![],
+            keyword_match: TriggerMatchOptions::default(),
+            mention_match: Self::default_mention_match(),
+            cooldown_seconds: 0,
         }
     }
 }
@@ -404,12 +1025,26 @@ pub struct MemoryConfig {
     /// Database path for embeddings
     #[serde(default = "MemoryConfig::default_db_path")]
     pub db_path: PathBuf,
+    /// Combine embedding search with full-text keyword matching
+    #[serde(default)]
+    pub hybrid_search: bool,
+    /// Drop search results more similar than this to a higher-ranked one
+    /// (`1.0` or more disables deduplication)
+    #[serde(default = "MemoryConfig::default_dedup_threshold")]
+    pub dedup_threshold: f32,
+    /// `llm` (default) uses the embedding model; `local` hashes words into
+    /// vectors without any network access, for tests and offline use
+    #[serde(default)]
+    pub embedding: gearclaw_memory::EmbeddingSource,
 }
 
 impl MemoryConfig {
     fn default_enabled() -> bool {
         true
     }
+    fn default_dedup_threshold() -> f32 {
+        gearclaw_memory::MemoryConfig::DEFAULT_DEDUP_THRESHOLD
+    }
     fn default_db_path() -> PathBuf {
         default_gearclaw_dir().join("memory/index.sqlite")
     }
@@ -420,6 +1055,9 @@ impl Default for MemoryConfig {
         Self {
             enabled: true,
             db_path: Self::default_db_path(),
+            hybrid_search: false,
+            dedup_threshold: Self::default_dedup_threshold(),
+            embedding: gearclaw_memory::EmbeddingSource::default(),
         }
     }
 }
@@ -447,6 +1085,15 @@ pub struct McpServerConfig {
     /// Environment variables
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Per-call timeout for `tools/call`, in seconds
+    #[serde(default = "McpServerConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl McpServerConfig {
+    fn default_timeout_secs() -> u64 {
+        30
+    }
 }
 
 // ============================================================================
@@ -471,6 +1118,9 @@ pub struct GatewayConfig {
     /// Device key path
     #[serde(default = "GatewayConfig::default_device_key_path")]
     pub device_key_path: PathBuf,
+    /// File holding registered tokens (salted hashes only)
+    #[serde(default = "GatewayConfig::default_tokens_path")]
+    pub tokens_path: PathBuf,
     /// Auto-start on boot
     #[serde(default)]
     pub auto_start: bool,
@@ -483,6 +1133,12 @@ pub struct GatewayConfig {
     /// TLS key path
     #[serde(default)]
     pub tls_key_path: Option<PathBuf>,
+    /// Requests allowed per token per minute (`None` disables rate limiting)
+    #[serde(default = "GatewayConfig::default_rate_limit_per_minute")]
+    pub rate_limit_per_minute: Option<u32>,
+    /// Serve Prometheus metrics at `/metrics` on the gateway listener
+    #[serde(default)]
+    pub metrics_enabled: bool,
 }
 
 impl GatewayConfig {
@@ -498,6 +1154,12 @@ impl GatewayConfig {
     fn default_device_key_path() -> PathBuf {
         default_gearclaw_dir().join("device.key")
     }
+    fn default_tokens_path() -> PathBuf {
+        default_gearclaw_dir().join("gateway_tokens.json")
+    }
+    fn default_rate_limit_per_minute() -> Option<u32> {
+        Some(60)
+    }
 }
 
 impl Default for GatewayConfig {
@@ -508,10 +1170,13 @@ impl Default for GatewayConfig {
             ws_path: DEFAULT_WS_PATH.to_string(),
             allow_unauthenticated_requests: false,
             device_key_path: Self::default_device_key_path(),
+            tokens_path: Self::default_tokens_path(),
             auto_start: false,
             tls_enabled: false,
             tls_cert_path: None,
             tls_key_path: None,
+            rate_limit_per_minute: Self::default_rate_limit_per_minute(),
+            metrics_enabled: false,
         }
     }
 }
@@ -520,22 +1185,154 @@ impl Default for GatewayConfig {
 // Config Loading and Validation
 // ============================================================================
 
+/// List fields that layered configs append to (skipping duplicates). Every
+/// other list is replaced wholesale by the later layer, while maps are merged
+/// key by key and scalars are overwritten.
+pub const APPEND_LIST_FIELDS: &[&str] = &[
+    "agent.triggers.enabled_channels",
+    "agent.triggers.disabled_channels",
+];
+
 /// Configuration loader with validation
 pub struct ConfigLoader;
 
 impl ConfigLoader {
-    /// Load configuration from file or default locations
+    /// Load configuration from file or default locations, layering the
+    /// sibling local override (e.g. `gearclaw.local.toml`) on top if present
     pub fn load(path: Option<&str>) -> Result<Config, GearClawError> {
         let config_path = Self::resolve_config_path(path)?;
-        let content = std::fs::read_to_string(&config_path)
-            .map_err(|e| GearClawError::config_parse_error(format!("Failed to read: {}", e)))?;
-        let config: Config = serde_yml::from_str(&content)
+        let local_path = Self::local_override_path(&config_path);
+        let mut paths = vec![config_path];
+        if local_path.exists() {
+            paths.push(local_path);
+        }
+        Self::load_layered(&paths)
+    }
+
+    /// Load and deep-merge config files, later files over earlier ones
+    pub fn load_layered(paths: &[PathBuf]) -> Result<Config, GearClawError> {
+        let mut merged: Option<serde_yml::Value> = None;
+        for path in paths {
+            let content = std::fs::read_to_string(path).map_err(|e| {
+                GearClawError::config_parse_error(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            let layer: serde_yml::Value = serde_yml::from_str(&content).map_err(|e| {
+                GearClawError::config_parse_error(format!(
+                    "Failed to parse {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            match merged.as_mut() {
+                Some(base) => Self::merge(base, layer, ""),
+                None => merged = Some(layer),
+            }
+        }
+        let merged = merged.ok_or_else(|| {
+            GearClawError::config_not_found("No configuration files given to load")
+        })?;
+        Self::from_value(merged)
+    }
+
+    /// Local override file next to `base`: `gearclaw.toml` → `gearclaw.local.toml`
+    pub fn local_override_path(base: &std::path::Path) -> PathBuf {
+        let stem = base
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let name = match base.extension() {
+            Some(ext) => format!("{}.local.{}", stem, ext.to_string_lossy()),
+            None => format!("{}.local", stem),
+        };
+        base.with_file_name(name)
+    }
+
+    /// Parse configuration text, expanding `${VAR}` / `${VAR:-default}`
+    /// references in string values from the environment
+    pub fn parse(content: &str) -> Result<Config, GearClawError> {
+        let value: serde_yml::Value = serde_yml::from_str(content)
             .map_err(|e| GearClawError::config_parse_error(format!("Failed to parse: {}", e)))?;
-        Ok(config)
+        Self::from_value(value)
+    }
+
+    fn from_value(mut value: serde_yml::Value) -> Result<Config, GearClawError> {
+        Self::interpolate(&mut value, "")?;
+        serde_yml::from_value(value)
+            .map_err(|e| GearClawError::config_parse_error(format!("Failed to parse: {}", e)))
     }
 
-    /// Resolve configuration file path
-    fn resolve_config_path(path: Option<&str>) -> Result<PathBuf, GearClawError> {
+    /// Merge `layer` into `base`: maps key by key, lists per
+    /// [`APPEND_LIST_FIELDS`], anything else overwritten
+    fn merge(base: &mut serde_yml::Value, layer: serde_yml::Value, path: &str) {
+        match (base, layer) {
+            (serde_yml::Value::Mapping(base), serde_yml::Value::Mapping(layer)) => {
+                for (key, value) in layer {
+                    let field = match key.as_str() {
+                        Some(k) if path.is_empty() => k.to_string(),
+                        Some(k) => format!("{}.{}", path, k),
+                        None => path.to_string(),
+                    };
+                    match base.get_mut(&key) {
+                        Some(existing) => Self::merge(existing, value, &field),
+                        None => {
+                            base.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (serde_yml::Value::Sequence(base), serde_yml::Value::Sequence(layer))
+                if APPEND_LIST_FIELDS.contains(&path) =>
+            {
+                for item in layer {
+                    if !base.contains(&item) {
+                        base.push(item);
+                    }
+                }
+            }
+            (base, layer) => *base = layer,
+        }
+    }
+
+    /// Expand env references in every string of a parsed config tree
+    fn interpolate(value: &mut serde_yml::Value, path: &str) -> Result<(), GearClawError> {
+        match value {
+            serde_yml::Value::String(s) => {
+                *s = expand_env(s, |name| std::env::var(name).ok()).map_err(|reason| {
+                    GearClawError::Domain(crate::error::DomainError::ConfigInvalid {
+                        field: path.to_string(),
+                        reason,
+                    })
+                })?;
+            }
+            serde_yml::Value::Sequence(items) => {
+                for (i, item) in items.iter_mut().enumerate() {
+                    Self::interpolate(item, &format!("{}[{}]", path, i))?;
+                }
+            }
+            serde_yml::Value::Mapping(map) => {
+                for (key, item) in map.iter_mut() {
+                    let key = key.as_str().map(str::to_string).unwrap_or_default();
+                    let field = if path.is_empty() {
+                        key
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    Self::interpolate(item, &field)?;
+                }
+            }
+            serde_yml::Value::Tagged(tagged) => Self::interpolate(&mut tagged.value, path)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Resolve configuration file path: `path` if given, else the first
+    /// default location that exists
+    pub fn resolve_config_path(path: Option<&str>) -> Result<PathBuf, GearClawError> {
         if let Some(p) = path {
             return Ok(PathBuf::from(p));
         }
@@ -559,38 +1356,380 @@ impl ConfigLoader {
     }
 }
 
+/// Valid `tools.security` levels
+pub const SECURITY_LEVELS: [&str; 3] = ["deny", "allowlist", "full"];
+/// Known `tools.host` values
+const TOOL_HOSTS: [&str; 3] = ["gateway", "sandbox", "node"];
+/// Known `tools.profile` values
+const TOOL_PROFILES: [&str; 4] = ["minimal", "coding", "messaging", "full"];
+/// Tools offered by each restricted `tools.profile`, with `memory` standing
+/// for `memory_remember` and `mcp` for every MCP tool. `full`, and any
+/// unknown profile, offers every tool.
+pub const TOOL_PROFILE_TOOLS: [(&str, &[&str]); 3] = [
+    ("minimal", &["read_file", "list_files", "file_info"]),
+    (
+        "coding",
+        &[
+            "read_file",
+            "list_files",
+            "file_info",
+            "write_file",
+            "exec",
+            "git_status",
+            "docker_ps",
+            "run_skill",
+            "memory",
+            "mcp",
+        ],
+    ),
+    (
+        "messaging",
+        &[
+            "read_file",
+            "list_files",
+            "file_info",
+            "web_search",
+            "fetch_url",
+            "memory",
+            "mcp",
+        ],
+    ),
+];
+
+/// A suspicious but usable configuration value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigWarning {
+    /// Dotted path of the offending field (e.g. `agent.skills_path`)
+    pub field: String,
+    /// What looks wrong
+    pub message: String,
+}
+
+impl ConfigWarning {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.field, self.message)
+    }
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` in `input` using `lookup`.
+///
+/// Only well-formed references (`VAR` made of letters, digits and `_`) are
+/// expanded; any other `$` text is kept as is. A referenced variable that is
+/// unset and has no default is an error.
+pub fn expand_env(input: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+        let inner = &after[..end];
+        let (name, default) = match inner.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (inner, None),
+        };
+        let well_formed = !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !well_formed {
+            out.push_str("${");
+            rest = after;
+            continue;
+        }
+        match (lookup(name), default) {
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => {
+                return Err(format!(
+                    "Environment variable '{}' is not set and has no default",
+                    name
+                ))
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
 /// Configuration validator
 pub struct ConfigValidator;
 
 impl ConfigValidator {
-    /// Validate configuration
+    /// Validate configuration, ignoring warnings
     pub fn validate(config: &Config) -> Result<(), GearClawError> {
-        // Validate LLM config
+        Self::check(config).map(|_| ())
+    }
+
+    /// Check the whole configuration: the first fatal problem is returned
+    /// as an error, suspicious values are collected as warnings.
+    pub fn check(config: &Config) -> Result<Vec<ConfigWarning>, GearClawError> {
+        let mut warnings = Vec::new();
+
+        // LLM
         if config.llm.primary.is_empty() {
-            return Err(GearClawError::Domain(
-                crate::error::DomainError::ConfigInvalid {
-                    field: "llm.primary".to_string(),
-                    reason: "Primary model cannot be empty".to_string(),
-                },
+            return Err(Self::invalid(
+                "llm.primary",
+                "Primary model cannot be empty",
             ));
         }
+        Self::check_endpoint(&config.llm.endpoint, &mut warnings)?;
+        if let Some(t) = config.llm.temperature {
+            if !(0.0..=2.0).contains(&t) {
+                warnings.push(ConfigWarning::new(
+                    "llm.temperature",
+                    format!("{} is outside the usual range 0.0-2.0", t),
+                ));
+            }
+        }
+        if let Some(p) = config.llm.top_p {
+            if !(0.0..=1.0).contains(&p) {
+                warnings.push(ConfigWarning::new(
+                    "llm.top_p",
+                    format!("{} is outside the range 0.0-1.0", p),
+                ));
+            }
+        }
+
+        if let Some(max_context) = config.llm.capabilities.max_context {
+            if config.llm.max_completion_tokens >= max_context {
+                warnings.push(ConfigWarning::new(
+                    "llm.max_completion_tokens",
+                    format!(
+                        "{} leaves no room for history in llm.capabilities.max_context ({})",
+                        config.llm.max_completion_tokens, max_context
+                    ),
+                ));
+            }
+        }
 
-        // Validate security level
-        let valid_security = ["deny", "allowlist", "full"];
-        if !valid_security.contains(&config.tools.security.as_str()) {
-            return Err(GearClawError::Domain(
-                crate::error::DomainError::ConfigInvalid {
-                    field: "tools.security".to_string(),
-                    reason: format!(
-                        "Invalid security level '{}'. Must be one of: {:?}",
-                        config.tools.security, valid_security
+        if let (Some(max_context), Some(compact_after)) = (
+            config.llm.capabilities.max_context,
+            config.agent.compact_after_tokens,
+        ) {
+            if compact_after > max_context {
+                warnings.push(ConfigWarning::new(
+                    "agent.compact_after_tokens",
+                    format!(
+                        "{} exceeds llm.capabilities.max_context ({}), so history overflows the context before it is compacted",
+                        compact_after, max_context
                     ),
-                },
+                ));
+            }
+        }
+
+        // Memory
+        if config.memory.dedup_threshold <= 0.0 {
+            warnings.push(ConfigWarning::new(
+                "memory.dedup_threshold",
+                format!(
+                    "{} treats almost every result as a duplicate of the first",
+                    config.memory.dedup_threshold
+                ),
+            ));
+        }
+
+        // Tools
+        Self::check_security("tools.security", &config.tools.security)?;
+        if !TOOL_HOSTS.contains(&config.tools.host.as_str()) {
+            warnings.push(ConfigWarning::new(
+                "tools.host",
+                format!(
+                    "Unknown exec host '{}'. Expected one of: {:?}",
+                    config.tools.host, TOOL_HOSTS
+                ),
+            ));
+        }
+        if !TOOL_PROFILES.contains(&config.tools.profile.as_str()) {
+            warnings.push(ConfigWarning::new(
+                "tools.profile",
+                format!(
+                    "Unknown tool profile '{}'. Expected one of: {:?}",
+                    config.tools.profile, TOOL_PROFILES
+                ),
+            ));
+        }
+        let search_provider = &config.tools.web_search.provider;
+        if gearclaw_tools::SearchProvider::from_name(search_provider).is_none() {
+            return Err(Self::invalid(
+                "tools.web_search.provider",
+                format!(
+                    "Unknown search provider '{}'. Expected one of: {:?}",
+                    search_provider,
+                    gearclaw_tools::SearchProvider::NAMES
+                ),
+            ));
+        }
+        if let Some(endpoint) = &config.tools.web_search.endpoint {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                return Err(Self::invalid(
+                    "tools.web_search.endpoint",
+                    "Must start with http:// or https://",
+                ));
+            }
+        }
+
+        // Agent
+        if config.agent.max_tool_turns == 0 {
+            return Err(Self::invalid(
+                "agent.max_tool_turns",
+                "Must be at least 1 or the model is never called",
             ));
         }
+        for (key, channel) in &config.agent.channel_overrides {
+            if let Some(security) = &channel.security {
+                Self::check_security(
+                    &format!("agent.channel_overrides.{}.security", key),
+                    security,
+                )?;
+            }
+        }
+        Self::check_path_exists(
+            "agent.skills_path",
+            &config.agent.skills_path,
+            &mut warnings,
+        );
+        Self::check_path_exists("agent.workspace", &config.agent.workspace, &mut warnings);
+        for source in &config.agent.skill_sources {
+            if let Some(subdir) = &source.subdir {
+                let path = std::path::Path::new(subdir);
+                if subdir.trim().is_empty()
+                    || !path
+                        .components()
+                        .all(|c| matches!(c, std::path::Component::Normal(_)))
+                {
+                    return Err(Self::invalid(
+                        &format!("agent.skill_sources.{}.subdir", source.name),
+                        "Must be a relative path inside the source, without '..'",
+                    ));
+                }
+            }
+            if !source.allowed_signers.is_empty() && !source.verify_head_commit_signature {
+                warnings.push(ConfigWarning {
+                    field: format!("agent.skill_sources.{}.allowed_signers", source.name),
+                    message: "Ignored unless verify_head_commit_signature is enabled".to_string(),
+                });
+            }
+            if source.enabled && source.kind == SkillSourceKind::LocalDir {
+                Self::check_path_exists(
+                    &format!("agent.skill_sources.{}.location", source.name),
+                    std::path::Path::new(&source.location),
+                    &mut warnings,
+                );
+            }
+        }
+
+        // MCP
+        for (name, server) in &config.mcp.servers {
+            if server.command.trim().is_empty() {
+                return Err(Self::invalid(
+                    &format!("mcp.servers.{}.command", name),
+                    "Command cannot be empty",
+                ));
+            }
+        }
+
+        // Gateway
+        if config.gateway.allow_unauthenticated_requests {
+            warnings.push(ConfigWarning::new(
+                "gateway.allow_unauthenticated_requests",
+                "Unauthenticated requests are allowed; use only for local development",
+            ));
+        }
+        if config.gateway.tls_enabled {
+            for (field, path) in [
+                ("gateway.tls_cert_path", &config.gateway.tls_cert_path),
+                ("gateway.tls_key_path", &config.gateway.tls_key_path),
+            ] {
+                match path {
+                    None => {
+                        return Err(Self::invalid(field, "Required when tls_enabled is true"));
+                    }
+                    Some(path) if !path.exists() => {
+                        return Err(Self::invalid(
+                            field,
+                            format!("File not found: {}", path.display()),
+                        ));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        Ok(warnings)
+    }
+
+    fn invalid(field: &str, reason: impl Into<String>) -> GearClawError {
+        GearClawError::Domain(crate::error::DomainError::ConfigInvalid {
+            field: field.to_string(),
+            reason: reason.into(),
+        })
+    }
+
+    fn check_security(field: &str, level: &str) -> Result<(), GearClawError> {
+        if SECURITY_LEVELS.contains(&level) {
+            return Ok(());
+        }
+        Err(Self::invalid(
+            field,
+            format!(
+                "Invalid security level '{}'. Must be one of: {:?}",
+                level, SECURITY_LEVELS
+            ),
+        ))
+    }
 
+    fn check_endpoint(
+        endpoint: &str,
+        warnings: &mut Vec<ConfigWarning>,
+    ) -> Result<(), GearClawError> {
+        let url = reqwest::Url::parse(endpoint).map_err(|e| {
+            Self::invalid("llm.endpoint", format!("Invalid URL '{}': {}", endpoint, e))
+        })?;
+        match url.scheme() {
+            "https" => {}
+            "http" => {
+                let local = matches!(
+                    url.host_str(),
+                    Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+                );
+                if !local {
+                    warnings.push(ConfigWarning::new(
+                        "llm.endpoint",
+                        "Plain http to a remote host sends the API key unencrypted",
+                    ));
+                }
+            }
+            scheme => {
+                return Err(Self::invalid(
+                    "llm.endpoint",
+                    format!("Unsupported scheme '{}'. Use http or https", scheme),
+                ));
+            }
+        }
         Ok(())
     }
+
+    fn check_path_exists(field: &str, path: &std::path::Path, warnings: &mut Vec<ConfigWarning>) {
+        if !path.exists() {
+            warnings.push(ConfigWarning::new(
+                field,
+                format!("Path does not exist: {}", path.display()),
+            ));
+        }
+    }
 }
 
 // ============================================================================
@@ -603,6 +1742,18 @@ impl Config {
         ConfigLoader::load(path.as_deref())
     }
 
+    /// Load and deep-merge config files, later files over earlier ones.
+    /// See [`ConfigLoader::load_layered`].
+    pub fn load_layered(paths: &[PathBuf]) -> Result<Self, GearClawError> {
+        ConfigLoader::load_layered(paths)
+    }
+
+    /// Check the configuration for fatal problems (error) and suspicious
+    /// values (warnings). See [`ConfigValidator::check`].
+    pub fn validate(&self) -> Result<Vec<ConfigWarning>, GearClawError> {
+        ConfigValidator::check(self)
+    }
+
     /// Save configuration to file
     pub fn save(&self, path: &PathBuf) -> Result<(), GearClawError> {
         let content = serde_yml::to_string(self).map_err(|e| {
@@ -612,6 +1763,37 @@ impl Config {
         Ok(())
     }
 
+    /// Save the fields that differ from `loaded` (the config as loaded, e.g.
+    /// before a settings edit) into the file at `path`, leaving the rest of
+    /// the file as written. Unchanged `${VAR}` references stay references
+    /// instead of being replaced by their secret values, and fields from
+    /// other layers are not copied in.
+    pub fn save_edits(&self, loaded: &Config, path: &PathBuf) -> Result<(), GearClawError> {
+        let to_value = |config: &Config| {
+            serde_yml::to_value(config).map_err(|e| {
+                GearClawError::config_parse_error(format!("Serialization failed: {}", e))
+            })
+        };
+        let mut document = if path.exists() {
+            let content = std::fs::read_to_string(path)?;
+            serde_yml::from_str(&content).map_err(|e| {
+                GearClawError::config_parse_error(format!(
+                    "Failed to parse {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?
+        } else {
+            serde_yml::Value::Mapping(Default::default())
+        };
+        apply_edits(&mut document, &to_value(loaded)?, to_value(self)?);
+        let content = serde_yml::to_string(&document).map_err(|e| {
+            GearClawError::config_parse_error(format!("Serialization failed: {}", e))
+        })?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
     /// Generate a sample configuration
     pub fn sample() -> Self {
         Config {
@@ -624,13 +1806,33 @@ impl Config {
                 endpoint: DEFAULT_ENDPOINT.to_string(),
                 api_key: None,
                 embedding_model: DEFAULT_EMBEDDING_MODEL.to_string(),
+                embedding_endpoint: None,
+                embedding_api_key: None,
                 temperature: Some(0.7),
+                top_p: None,
+                frequency_penalty: None,
+                presence_penalty: None,
+                retry: LLMRetryConfig::default(),
+                debug_tracing: false,
+                auth_scheme: LLMAuthScheme::default(),
+                organization: None,
+                extra_headers: HashMap::new(),
+                force_http1: false,
+                capabilities: crate::llm::ModelCapabilities::default(),
+                request_timeout_secs: LLMConfig::default_request_timeout_secs(),
+                stream_idle_timeout_secs: LLMConfig::default_stream_idle_timeout_secs(),
+                max_completion_tokens: LLMConfig::default_max_completion_tokens(),
             },
             tools: ToolsConfig {
                 security: "full".to_string(),
                 host: "gateway".to_string(),
                 elevated_enabled: true,
                 profile: "full".to_string(),
+                confirm_destructive: false,
+                destructive_patterns: ToolsConfig::default_destructive_patterns(),
+                web_search: WebSearchConfig::default(),
+                filesystem: FilesystemAccess::default(),
+                read_only_tools: Vec::new(),
             },
             session: SessionConfig {
                 session_dir: default_gearclaw_dir().join("sessions"),
@@ -656,6 +1858,8 @@ impl Config {
                         enabled: true,
                         trusted: true,
                         verify_head_commit_signature: false,
+                        allowed_signers: vec![],
+                        subdir: None,
                     },
                     SkillSourceConfig {
                         name: "community-git".to_string(),
@@ -665,16 +1869,55 @@ impl Config {
                         enabled: false,
                         trusted: false,
                         verify_head_commit_signature: false,
+                        allowed_signers: vec![],
+                        subdir: None,
                     },
                 ],
                 skill_trust_policy: SkillTrustPolicy::LocalOnly,
                 triggers: AgentTriggerConfig::default(),
+                compact_after_tokens: Some(100_000),
+                compact_keep_recent: 4,
+                channel_overrides: HashMap::from([(
+                    "discord:123456789012345678".to_string(),
+                    ChannelOverride {
+                        system_prompt: Some(
+                            "You are a public help bot. Do not run commands.".to_string(),
+                        ),
+                        security: Some("deny".to_string()),
+                        memory_enabled: Some(false),
+                    },
+                )]),
+                max_tool_turns: DEFAULT_MAX_TOOL_TURNS,
+                max_tool_result_bytes: DEFAULT_MAX_TOOL_RESULT_BYTES,
+                history_path: AgentConfig::default_history_path(),
+                history_max_entries: DEFAULT_HISTORY_MAX_ENTRIES,
             },
             memory: MemoryConfig::default(),
             mcp: McpConfig::default(),
             gateway: GatewayConfig::default(),
         }
     }
+
+    /// Effective settings for messages from `platform:channel_id`: the
+    /// channel's override where set, the global configuration otherwise.
+    pub fn channel_settings(&self, platform: &str, channel_id: &str) -> ChannelOverride {
+        let key = format!("{}:{}", platform, channel_id);
+        let channel = self
+            .agent
+            .channel_overrides
+            .get(&key)
+            .cloned()
+            .unwrap_or_default();
+        ChannelOverride {
+            system_prompt: channel
+                .system_prompt
+                .or_else(|| Some(self.agent.system_prompt.clone())),
+            security: channel
+                .security
+                .or_else(|| Some(self.tools.security.clone())),
+            memory_enabled: channel.memory_enabled.or(Some(self.agent.memory_enabled)),
+        }
+    }
 }
 
 // ============================================================================
@@ -682,6 +1925,47 @@ impl Config {
 // ============================================================================
 
 /// Backward compatibility: default_endpoint function
+/// Write into `document` every value where `edited` differs from `before`:
+/// maps are walked key by key, anything else is replaced whole
+fn apply_edits(
+    document: &mut serde_yml::Value,
+    before: &serde_yml::Value,
+    edited: serde_yml::Value,
+) {
+    let (serde_yml::Value::Mapping(before), serde_yml::Value::Mapping(edited)) = (before, &edited)
+    else {
+        if *before != edited {
+            *document = edited;
+        }
+        return;
+    };
+    if !document.is_mapping() {
+        *document = serde_yml::Value::Mapping(Default::default());
+    }
+    let serde_yml::Value::Mapping(document) = document else {
+        return;
+    };
+    for key in before.keys() {
+        if !edited.contains_key(key) {
+            document.remove(key);
+        }
+    }
+    for (key, value) in edited {
+        match before.get(key) {
+            Some(old) if old == value => {}
+            Some(old) => {
+                let entry = document
+                    .entry(key.clone())
+                    .or_insert(serde_yml::Value::Null);
+                apply_edits(entry, old, value.clone());
+            }
+            None => {
+                document.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
 pub fn default_endpoint() -> String {
     DEFAULT_ENDPOINT.to_string()
 }
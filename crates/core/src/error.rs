@@ -20,6 +20,13 @@ pub enum DomainError {
     LLMRequest { message: String },
     /// LLM response was invalid or unexpected
     LLMResponse { message: String },
+    /// LLM call failed, classified so callers can react to the cause
+    Llm {
+        /// HTTP status, when the API answered with one
+        status: Option<u16>,
+        kind: LlmErrorKind,
+        message: String,
+    },
     /// Tool execution failed
     ToolExecution { tool: String, reason: String },
     /// Tool not found in registry
@@ -42,6 +49,16 @@ impl fmt::Display for DomainError {
             Self::ConfigParse { source } => write!(f, "Config parse error: {}", source),
             Self::LLMRequest { message } => write!(f, "LLM request failed: {}", message),
             Self::LLMResponse { message } => write!(f, "LLM response error: {}", message),
+            Self::Llm {
+                status: Some(status),
+                kind,
+                message,
+            } => write!(f, "LLM {} error (HTTP {}): {}", kind, status, message),
+            Self::Llm {
+                status: None,
+                kind,
+                message,
+            } => write!(f, "LLM {} error: {}", kind, message),
             Self::ToolExecution { tool, reason } => {
                 write!(f, "Tool '{}' execution failed: {}", tool, reason)
             }
@@ -59,6 +76,56 @@ impl fmt::Display for DomainError {
 
 impl std::error::Error for DomainError {}
 
+/// Cause of an LLM failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmErrorKind {
+    /// 401/403: missing or rejected API key
+    Auth,
+    /// 429: rate limited or out of quota
+    RateLimited,
+    /// 404: unknown model or endpoint
+    ModelNotFound,
+    /// Other 4xx: the request itself was rejected
+    InvalidRequest,
+    /// 5xx: provider-side failure
+    Server,
+    /// No HTTP response: connection, DNS, timeout or stream failure
+    Network,
+    /// Response body could not be understood
+    InvalidResponse,
+    /// Any other status
+    Other,
+}
+
+impl LlmErrorKind {
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => Self::Auth,
+            429 => Self::RateLimited,
+            404 => Self::ModelNotFound,
+            400..=499 => Self::InvalidRequest,
+            500..=599 => Self::Server,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl fmt::Display for LlmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Self::Auth => "auth",
+            Self::RateLimited => "rate limit",
+            Self::ModelNotFound => "model not found",
+            Self::InvalidRequest => "invalid request",
+            Self::Server => "server",
+            Self::Network => "network",
+            Self::InvalidResponse => "invalid response",
+            Self::Other => "request",
+        };
+        f.write_str(label)
+    }
+}
+
 /// Infrastructure-level errors representing external system failures
 #[derive(Debug)]
 pub enum InfraError {
@@ -196,10 +263,95 @@ impl From<rusqlite::Error> for GearClawError {
 
 impl From<gearclaw_llm::LlmError> for GearClawError {
     fn from(err: gearclaw_llm::LlmError) -> Self {
-        match err {
-            gearclaw_llm::LlmError::Request(message) => Self::llm_error(message),
-            gearclaw_llm::LlmError::Response(message) => Self::llm_response_error(message),
-            gearclaw_llm::LlmError::Json(source) => Self::from(source),
+        use gearclaw_llm::LlmError;
+
+        let (status, kind, message) = match err {
+            LlmError::Request(message) => (None, LlmErrorKind::Network, message),
+            LlmError::Timeout(message) => (None, LlmErrorKind::Network, message),
+            LlmError::Response(message) => (None, LlmErrorKind::InvalidResponse, message),
+            LlmError::Status { status, message } => {
+                (Some(status), LlmErrorKind::from_status(status), message)
+            }
+            LlmError::Json(source) => (None, LlmErrorKind::InvalidResponse, source.to_string()),
+        };
+        Self::Domain(DomainError::Llm {
+            status,
+            kind,
+            message,
+        })
+    }
+}
+
+impl GearClawError {
+    /// Cause of an LLM failure, if this is one
+    pub fn llm_kind(&self) -> Option<LlmErrorKind> {
+        match self {
+            Self::Domain(DomainError::Llm { kind, .. }) => Some(*kind),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of an LLM failure, if the API answered with one
+    pub fn llm_status(&self) -> Option<u16> {
+        match self {
+            Self::Domain(DomainError::Llm { status, .. }) => *status,
+            _ => None,
+        }
+    }
+
+    /// Short snake_case label for the error category, e.g. `tool_execution`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Domain(e) => match e {
+                DomainError::ConfigNotFound { .. }
+                | DomainError::ConfigInvalid { .. }
+                | DomainError::ConfigParse { .. } => "config",
+                DomainError::LLMRequest { .. }
+                | DomainError::LLMResponse { .. }
+                | DomainError::Llm { .. } => "llm",
+                DomainError::ToolExecution { .. } => "tool_execution",
+                DomainError::ToolNotFound { .. } => "tool_not_found",
+                DomainError::Session { .. } => "session",
+                DomainError::Memory { .. } => "memory",
+                DomainError::Mcp { .. } => "mcp",
+            },
+            Self::Infra(e) => match e {
+                InfraError::Io(_) => "io",
+                InfraError::Json(_) => "json",
+                InfraError::Yaml(_) => "yaml",
+                InfraError::Database(_) => "database",
+                InfraError::Network { .. } => "network",
+            },
+            Self::Other(_) => "other",
+        }
+    }
+
+    /// Whether retrying the same call may succeed
+    ///
+    /// True for transient failures: network errors, timeouts, HTTP 429 and
+    /// 5xx responses. Config, validation, tool and other domain errors need
+    /// a change before a retry can help.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Domain(DomainError::Llm { kind, .. }) => matches!(
+                kind,
+                LlmErrorKind::Network | LlmErrorKind::RateLimited | LlmErrorKind::Server
+            ),
+            Self::Domain(DomainError::LLMRequest { .. }) => true,
+            Self::Domain(_) => false,
+            Self::Infra(InfraError::Network { .. }) => true,
+            Self::Infra(InfraError::Io(err)) => matches!(
+                err.kind(),
+                std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::WouldBlock
+                    | std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+            ),
+            Self::Infra(_) => false,
+            Self::Other(_) => false,
         }
     }
 }
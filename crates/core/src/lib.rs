@@ -9,14 +9,18 @@ pub mod agent;
 pub mod config;
 pub mod error;
 pub mod llm;
+pub mod logging;
 pub mod macos;
 pub mod mcp;
 pub mod memory;
 pub mod session;
 pub mod skills;
 pub mod tools;
+pub mod triggers;
 
 // Re-export commonly used types
 pub use agent::Agent;
-pub use config::{AgentConfig, AgentTriggerConfig, Config, GatewayConfig, TriggerMode};
+pub use config::{
+    AgentConfig, AgentTriggerConfig, Config, GatewayConfig, TriggerMatchOptions, TriggerMode,
+};
 pub use error::{DomainError, GearClawError, InfraError};
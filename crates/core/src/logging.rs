@@ -0,0 +1,85 @@
+//! Log output formats shared by the CLI and the desktop app.
+//!
+//! `json` writes one object per line for log aggregators:
+//!
+//! ```json
+//! {"timestamp":"2026-01-01T12:00:00.000000Z","level":"INFO","target":"gearclaw_gateway::server",
+//!  "fields":{"message":"Gateway server listening on 127.0.0.1:18789"},
+//!  "spans":[{"name":"connection"}]}
+//! ```
+//!
+//! This is tracing-subscriber's `json` formatter. `fields` holds the event's
+//! message and structured fields; `spans` (the enclosing spans and their
+//! fields, outermost first) is omitted outside any span.
+
+use crate::error::{DomainError, GearClawError};
+use std::str::FromStr;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Environment variable selecting the log format when no flag is given
+pub const LOG_FORMAT_ENV: &str = "GEARCLAW_LOG_FORMAT";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines (tracing's default `fmt` output)
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = GearClawError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(GearClawError::Domain(DomainError::ConfigInvalid {
+                field: "log_format".to_string(),
+                reason: format!(
+                    "Unknown log format '{}'. Expected one of: [\"text\", \"json\"]",
+                    other
+                ),
+            })),
+        }
+    }
+}
+
+impl LogFormat {
+    /// `flag` if given, otherwise `GEARCLAW_LOG_FORMAT`, otherwise text
+    pub fn resolve(flag: Option<&str>) -> Result<Self, GearClawError> {
+        match flag {
+            Some(flag) => flag.parse(),
+            None => Self::from_env(),
+        }
+    }
+
+    /// The format named by `GEARCLAW_LOG_FORMAT`, text if unset
+    pub fn from_env() -> Result<Self, GearClawError> {
+        match std::env::var(LOG_FORMAT_ENV) {
+            Ok(value) if !value.trim().is_empty() => value.parse(),
+            _ => Ok(Self::Text),
+        }
+    }
+}
+
+/// A `fmt` layer writing `format` to `writer`
+pub fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+    }
+}
@@ -127,3 +127,9 @@ impl AppManager {
         Ok(result.to_string())
     }
 }
+
+impl Default for AppManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
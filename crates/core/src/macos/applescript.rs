@@ -1,77 +1,113 @@
 //! AppleScript and JavaScript for Automation (JXA) execution
 
 use crate::error::GearClawError;
-use std::process::Command;
+use std::time::Duration;
+use tokio::process::Command;
 
-pub struct AppleScriptExecutor;
+/// Default time a script may run before `osascript` is killed
+pub const DEFAULT_SCRIPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default cap on captured stdout
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+pub struct AppleScriptExecutor {
+    timeout: Duration,
+    max_output_bytes: usize,
+}
 
 impl AppleScriptExecutor {
     pub fn new() -> Self {
-        Self
+        Self {
+            timeout: DEFAULT_SCRIPT_TIMEOUT,
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+        }
     }
 
-    /// Execute AppleScript code
-    pub async fn execute(&self, script: &str) -> Result<String, GearClawError> {
-        let output = Command::new("osascript")
-            .arg("-e")
-            .arg(script)
-            .output()
-            .map_err(|e| {
-                GearClawError::ToolExecutionError(format!("执行 AppleScript 失败: {}", e))
-            })?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    /// Kill `osascript` if a script runs longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
 
-        if !output.status.success() {
-            return Err(GearClawError::ToolExecutionError(format!(
-                "AppleScript 执行失败: {}",
-                stderr
-            )));
-        }
+    /// Truncate captured output beyond `max_output_bytes`
+    pub fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = max_output_bytes;
+        self
+    }
 
-        Ok(stdout)
+    /// Execute AppleScript code
+    pub async fn execute(&self, script: &str) -> Result<String, GearClawError> {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(script);
+        self.run("AppleScript", command).await
     }
 
     /// Execute JavaScript for Automation (JXA) code
     pub async fn execute_jxa(&self, script: &str) -> Result<String, GearClawError> {
-        let output = Command::new("osascript")
-            .arg("-l")
-            .arg("JavaScript")
-            .arg("-e")
-            .arg(script)
-            .output()
-            .map_err(|e| GearClawError::ToolExecutionError(format!("执行 JXA 失败: {}", e)))?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-        if !output.status.success() {
-            return Err(GearClawError::ToolExecutionError(format!(
-                "JXA 执行失败: {}",
-                stderr
-            )));
-        }
-
-        Ok(stdout)
+        let mut command = Command::new("osascript");
+        command.arg("-l").arg("JavaScript").arg("-e").arg(script);
+        self.run("JXA", command).await
     }
 
     /// Execute AppleScript from file
     pub async fn execute_file(&self, path: &str) -> Result<String, GearClawError> {
-        let output = Command::new("osascript").arg(path).output().map_err(|e| {
-            GearClawError::ToolExecutionError(format!("执行 AppleScript 文件失败: {}", e))
-        })?;
+        let mut command = Command::new("osascript");
+        command.arg(path);
+        self.run("AppleScript 文件", command).await
+    }
+
+    async fn run(&self, label: &str, mut command: Command) -> Result<String, GearClawError> {
+        // Dropping the output future on timeout kills osascript.
+        command.kill_on_drop(true);
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let output = match tokio::time::timeout(self.timeout, command.output()).await {
+            Ok(result) => result.map_err(|e| {
+                GearClawError::ToolExecutionError(format!("执行 {} 失败: {}", label, e))
+            })?,
+            Err(_) => {
+                return Err(GearClawError::ToolExecutionError(format!(
+                    "{} 执行超时（{} 秒），已终止 osascript；脚本可能在等待界面对话框",
+                    label,
+                    self.timeout.as_secs_f64()
+                )));
+            }
+        };
 
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
         if !output.status.success() {
-            return Err(GearClawError::ToolExecutionError(format!(
-                "AppleScript 文件执行失败: {}",
-                stderr
-            )));
+            // -1712 is errAETimeout: an Apple event got no reply in time.
+            let message = if stderr.contains("(-1712)") {
+                format!("{} 执行超时（AppleEvent 无响应）: {}", label, stderr)
+            } else {
+                format!("{} 脚本错误: {}", label, stderr)
+            };
+            return Err(GearClawError::ToolExecutionError(message));
         }
 
-        Ok(stdout)
+        Ok(truncate_output(
+            String::from_utf8_lossy(&output.stdout).to_string(),
+            self.max_output_bytes,
+        ))
+    }
+}
+
+/// Cut `text` to at most `max_bytes` (on a char boundary) with a marker.
+fn truncate_output(mut text: String, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let total = text.len();
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    text.push_str(&format!("\n...[输出已截断，共 {} 字节]", total));
+    text
+}
+
+impl Default for AppleScriptExecutor {
+    fn default() -> Self {
+        Self::new()
     }
 }
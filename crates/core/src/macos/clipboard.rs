@@ -56,3 +56,9 @@ impl ClipboardManager {
         Ok("✓ 剪贴板已清空".to_string())
     }
 }
+
+impl Default for ClipboardManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
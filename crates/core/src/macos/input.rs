@@ -43,7 +43,7 @@ impl InputSimulator {
         let has_cmd = keys
             .iter()
             .any(|k| *k == "cmd" || *k == "command" || *k == "⌘");
-        let has_shift = keys.iter().any(|k| *k == "shift");
+        let has_shift = keys.contains(&"shift");
         let has_option = keys.iter().any(|k| *k == "option" || *k == "alt");
         let has_control = keys.iter().any(|k| *k == "control" || *k == "ctrl");
 
@@ -154,3 +154,9 @@ impl InputSimulator {
         Ok("✓ 已点击".to_string())
     }
 }
+
+impl Default for InputSimulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,21 +1,26 @@
 //! macOS-specific application control and automation
 //!
 //! This module provides tools for controlling macOS applications,
-//! executing AppleScript, simulating input, and more.
+//! executing AppleScript, simulating input, and more. It compiles on every
+//! platform; off macOS, `execute_tool` rejects calls with a clear error.
 
 mod app;
 mod applescript;
 mod clipboard;
 mod input;
 mod notification;
+mod screenshot;
 mod system;
+mod window;
 
 pub use app::AppManager;
 pub use applescript::AppleScriptExecutor;
 pub use clipboard::ClipboardManager;
 pub use input::InputSimulator;
 pub use notification::NotificationSender;
+pub use screenshot::{CaptureArea, ScreenCapture};
 pub use system::SystemControl;
+pub use window::{WindowInfo, WindowManager};
 
 use crate::error::GearClawError;
 use serde_json::{json, Value};
@@ -27,7 +32,9 @@ pub struct MacosController {
     pub clipboard: ClipboardManager,
     pub input: InputSimulator,
     pub notification: NotificationSender,
+    pub screen: ScreenCapture,
     pub system: SystemControl,
+    pub window: WindowManager,
 }
 
 impl MacosController {
@@ -38,16 +45,30 @@ impl MacosController {
             clipboard: ClipboardManager::new(),
             input: InputSimulator::new(),
             notification: NotificationSender::new(),
+            screen: ScreenCapture::new(),
             system: SystemControl::new(),
+            window: WindowManager::new(),
         })
     }
 
+    /// Whether the tools can actually run on this platform
+    pub fn is_supported() -> bool {
+        cfg!(target_os = "macos")
+    }
+
     /// Execute a macOS-specific tool by name
     pub async fn execute_tool(
         &self,
         tool_name: &str,
         args: &Value,
     ) -> Result<String, GearClawError> {
+        if !Self::is_supported() {
+            return Err(GearClawError::ToolExecutionError(format!(
+                "{}: macos_* tools require macOS",
+                tool_name
+            )));
+        }
+
         match tool_name {
             "macos_launch_app" => {
                 let app_name = args["app_name"].as_str().ok_or_else(|| {
@@ -77,6 +98,23 @@ impl MacosController {
                 self.app.is_running(app_name).await
             }
 
+            "macos_list_windows" => {
+                let windows = self.window.list(args["app_name"].as_str()).await?;
+                serde_json::to_string_pretty(&windows).map_err(|e| {
+                    GearClawError::ToolExecutionError(format!("序列化窗口列表失败: {}", e))
+                })
+            }
+
+            "macos_focus_window" => {
+                let app_name = args["app_name"].as_str().ok_or_else(|| {
+                    GearClawError::ToolExecutionError("缺少 app_name 参数".to_string())
+                })?;
+                let title = args["title"].as_str().ok_or_else(|| {
+                    GearClawError::ToolExecutionError("缺少 title 参数".to_string())
+                })?;
+                self.window.focus(app_name, title).await
+            }
+
             "macos_applescript" => {
                 let script = args["script"].as_str().ok_or_else(|| {
                     GearClawError::ToolExecutionError("缺少 script 参数".to_string())
@@ -147,6 +185,33 @@ impl MacosController {
                 self.system.say(text, voice, rate).await
             }
 
+            "macos_screenshot" => {
+                let area = if let Some(id) = args["window_id"].as_u64() {
+                    CaptureArea::Window(id)
+                } else if args["region"].is_object() {
+                    let region = &args["region"];
+                    let field = |name: &str| {
+                        region[name].as_f64().ok_or_else(|| {
+                            GearClawError::ToolExecutionError(format!(
+                                "region 缺少数值字段 {}",
+                                name
+                            ))
+                        })
+                    };
+                    CaptureArea::Region {
+                        x: field("x")? as i64,
+                        y: field("y")? as i64,
+                        width: field("width")? as u64,
+                        height: field("height")? as u64,
+                    }
+                } else {
+                    CaptureArea::FullScreen
+                };
+                let path = args["path"].as_str().map(std::path::Path::new);
+                let written = self.screen.capture(area, path).await?;
+                Ok(format!("✓ 截图已保存: {}", written.display()))
+            }
+
             _ => Err(GearClawError::ToolExecutionError(format!(
                 "未知的 macOS 工具: {}",
                 tool_name
@@ -202,6 +267,29 @@ impl MacosController {
                     "required": ["app_name"]
                 }
             }),
+            json!({
+                "name": "macos_list_windows",
+                "description": "列出应用窗口（应用名、标题、位置与大小），没有窗口时返回空列表",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "app_name": { "type": "string", "description": "只列出该应用的窗口 (可选)" }
+                    },
+                    "required": []
+                }
+            }),
+            json!({
+                "name": "macos_focus_window",
+                "description": "将指定应用中标题包含给定文本的窗口切换到最前",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "app_name": { "type": "string", "description": "应用名称" },
+                        "title": { "type": "string", "description": "窗口标题（包含匹配）" }
+                    },
+                    "required": ["app_name", "title"]
+                }
+            }),
             // Script execution
             json!({
                 "name": "macos_applescript",
@@ -301,7 +389,7 @@ impl MacosController {
             }),
             json!({
                 "name": "macos_search_in_browser",
-                "description": "在浏览器中执行搜索（打开 Google 搜索页面）",
+                "description": "在浏览器中执行搜索，打开浏览器窗口让用户可以看到和浏览搜索结果。当用户想\"在浏览器中查看\"或\"打开浏览器搜索\"时使用此工具。",
                 "parameters": {
                     "type": "object",
                     "properties": {
@@ -310,6 +398,30 @@ impl MacosController {
                     "required": ["query"]
                 }
             }),
+            // Screen
+            json!({
+                "name": "macos_screenshot",
+                "description": "截取屏幕为 PNG，返回文件路径（需要屏幕录制权限）",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "保存路径 (可选，默认写入临时目录)" },
+                        "region": {
+                            "type": "object",
+                            "description": "只截取该区域 (可选，单位为屏幕点)",
+                            "properties": {
+                                "x": { "type": "number" },
+                                "y": { "type": "number" },
+                                "width": { "type": "number" },
+                                "height": { "type": "number" }
+                            },
+                            "required": ["x", "y", "width", "height"]
+                        },
+                        "window_id": { "type": "integer", "description": "只截取该窗口 (CGWindowID，可选)" }
+                    },
+                    "required": []
+                }
+            }),
             json!({
                 "name": "macos_say",
                 "description": "文字转语音 (TTS)",
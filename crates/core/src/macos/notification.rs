@@ -44,3 +44,9 @@ impl NotificationSender {
         Ok(format!("✓ 已发送通知: {}", message))
     }
 }
+
+impl Default for NotificationSender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
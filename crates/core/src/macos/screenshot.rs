@@ -0,0 +1,105 @@
+//! Screen capture via the `screencapture` command
+
+use crate::error::GearClawError;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Part of the screen to capture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureArea {
+    /// Every display
+    FullScreen,
+    /// Rectangle in screen points
+    Region {
+        x: i64,
+        y: i64,
+        width: u64,
+        height: u64,
+    },
+    /// A single window by its CGWindowID
+    Window(u64),
+}
+
+pub struct ScreenCapture;
+
+impl ScreenCapture {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Capture `area` as PNG to `path` (a new temp file when `None`) and
+    /// return the path written.
+    pub async fn capture(
+        &self,
+        area: CaptureArea,
+        path: Option<&Path>,
+    ) -> Result<PathBuf, GearClawError> {
+        let path = path.map(Path::to_path_buf).unwrap_or_else(|| {
+            std::env::temp_dir().join(format!(
+                "gearclaw-screenshot-{}.png",
+                chrono::Local::now().format("%Y%m%d-%H%M%S-%3f")
+            ))
+        });
+
+        let output = Command::new("screencapture")
+            .args(Self::capture_args(area))
+            .arg(&path)
+            .output()
+            .await
+            .map_err(|e| GearClawError::ToolExecutionError(format!("截图失败: {}", e)))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let written = std::fs::metadata(&path)
+            .map(|m| m.len() > 0)
+            .unwrap_or(false);
+        if !output.status.success() || !written {
+            // Without Screen Recording permission screencapture fails with
+            // "could not create image from display" (or writes nothing).
+            if stderr.is_empty() || stderr.contains("could not create image") {
+                return Err(GearClawError::ToolExecutionError(format!(
+                    "截图失败：可能未授予屏幕录制权限，请在 系统设置 → 隐私与安全性 → 屏幕录制 中允许当前终端或应用{}",
+                    if stderr.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({})", stderr)
+                    }
+                )));
+            }
+            return Err(GearClawError::ToolExecutionError(format!(
+                "截图失败: {}",
+                stderr
+            )));
+        }
+
+        Ok(path)
+    }
+
+    /// `screencapture` flags for `area` (always silent, PNG)
+    pub fn capture_args(area: CaptureArea) -> Vec<String> {
+        let mut args = vec!["-x".to_string(), "-t".to_string(), "png".to_string()];
+        match area {
+            CaptureArea::FullScreen => {}
+            CaptureArea::Region {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                args.push("-R".to_string());
+                args.push(format!("{},{},{},{}", x, y, width, height));
+            }
+            CaptureArea::Window(id) => {
+                args.push("-o".to_string());
+                args.push("-l".to_string());
+                args.push(id.to_string());
+            }
+        }
+        args
+    }
+}
+
+impl Default for ScreenCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -130,3 +130,9 @@ impl SystemControl {
         Ok(format!("✓ 已设置音量: {}%", level))
     }
 }
+
+impl Default for SystemControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,108 @@
+//! macOS window management
+//!
+//! Lists and focuses individual windows through System Events, which needs
+//! the Accessibility permission.
+
+use super::AppleScriptExecutor;
+use crate::error::GearClawError;
+use serde::{Deserialize, Serialize};
+
+/// A visible window of a regular (non background-only) application
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowInfo {
+    pub app: String,
+    pub title: String,
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+pub struct WindowManager {
+    script: AppleScriptExecutor,
+}
+
+impl WindowManager {
+    pub fn new() -> Self {
+        Self {
+            script: AppleScriptExecutor::new(),
+        }
+    }
+
+    /// List windows, optionally only those of `app`. Apps without windows
+    /// (or an `app` that isn't running) simply contribute nothing.
+    pub async fn list(&self, app: Option<&str>) -> Result<Vec<WindowInfo>, GearClawError> {
+        let filter = serde_json::to_string(&app).unwrap_or_else(|_| "null".to_string());
+        let script = format!(
+            r#"(() => {{
+  const filter = {filter};
+  const se = Application('System Events');
+  const out = [];
+  for (const p of se.processes.whose({{ backgroundOnly: false }})()) {{
+    const name = p.name();
+    if (filter !== null && name !== filter) continue;
+    let windows = [];
+    try {{ windows = p.windows(); }} catch (e) {{ continue; }}
+    for (const w of windows) {{
+      let pos = [0, 0], size = [0, 0], title = '';
+      try {{ title = w.name() || ''; }} catch (e) {{}}
+      try {{ pos = w.position(); size = w.size(); }} catch (e) {{}}
+      out.push({{ app: name, title: title, x: pos[0], y: pos[1], width: size[0], height: size[1] }});
+    }}
+  }}
+  return JSON.stringify(out);
+}})()"#
+        );
+
+        let output = self.script.execute_jxa(&script).await?;
+        Self::parse_list(&output)
+    }
+
+    /// Parse the JSON printed by the listing script; empty output means no
+    /// windows.
+    pub fn parse_list(output: &str) -> Result<Vec<WindowInfo>, GearClawError> {
+        let output = output.trim();
+        if output.is_empty() {
+            return Ok(Vec::new());
+        }
+        serde_json::from_str(output)
+            .map_err(|e| GearClawError::ToolExecutionError(format!("解析窗口列表失败: {}", e)))
+    }
+
+    /// Raise the first window of `app` whose title contains `title` and make
+    /// the app frontmost.
+    pub async fn focus(&self, app: &str, title: &str) -> Result<String, GearClawError> {
+        let app_literal = serde_json::to_string(app).unwrap_or_default();
+        let title_literal = serde_json::to_string(title).unwrap_or_default();
+        let script = format!(
+            r#"(() => {{
+  const se = Application('System Events');
+  const p = se.processes.byName({app_literal});
+  if (!p.exists()) return 'not_running';
+  const w = p.windows().find(w => (w.name() || '').includes({title_literal}));
+  if (!w) return 'not_found';
+  p.frontmost = true;
+  w.actions.byName('AXRaise').perform();
+  return 'ok';
+}})()"#
+        );
+
+        match self.script.execute_jxa(&script).await?.trim() {
+            "ok" => Ok(format!("✓ 已切换到窗口: {} - {}", app, title)),
+            "not_running" => Err(GearClawError::ToolExecutionError(format!(
+                "应用 {} 未运行",
+                app
+            ))),
+            _ => Err(GearClawError::ToolExecutionError(format!(
+                "应用 {} 中没有标题包含 \"{}\" 的窗口",
+                app, title
+            ))),
+        }
+    }
+}
+
+impl Default for WindowManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,7 +3,10 @@
 use crate::config::{McpConfig as CoreMcpConfig, McpServerConfig as CoreMcpServerConfig};
 use crate::error::GearClawError;
 use crate::tools::{ToolResult as CoreToolResult, ToolSpec as CoreToolSpec};
-pub use gearclaw_mcp::McpCapability;
+pub use gearclaw_mcp::{
+    McpCapability, PromptArgument, PromptSpec, ResourceContents, ResourceSpec, ServerHealth,
+    ServerStatus,
+};
 use std::collections::HashMap;
 
 pub struct McpManager {
@@ -34,6 +37,27 @@ impl McpManager {
         self.inner.is_enabled()
     }
 
+    pub async fn server_status(&self) -> HashMap<String, ServerStatus> {
+        self.inner.server_status().await
+    }
+
+    pub async fn list_resources(&self) -> Vec<ResourceSpec> {
+        self.inner.list_resources().await
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>, GearClawError> {
+        self.inner.read_resource(uri).await.map_err(|e| {
+            GearClawError::from(crate::error::DomainError::Mcp {
+                server: "resource".to_string(),
+                reason: e.to_string(),
+            })
+        })
+    }
+
+    pub async fn list_prompts(&self) -> Vec<PromptSpec> {
+        self.inner.list_prompts().await
+    }
+
     pub async fn list_tools(&self) -> Vec<CoreToolSpec> {
         self.inner
             .list_tools()
@@ -60,6 +84,7 @@ impl McpManager {
                 success: r.success,
                 output: r.output,
                 error: r.error,
+                requires_confirmation: false,
             })
             .map_err(|e| {
                 GearClawError::from(crate::error::DomainError::Mcp {
@@ -85,5 +110,6 @@ fn to_mcp_server_config(config: CoreMcpServerConfig) -> gearclaw_mcp::McpServerC
         command: config.command,
         args: config.args,
         env: config.env,
+        timeout_secs: config.timeout_secs,
     }
 }
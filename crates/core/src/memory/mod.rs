@@ -2,10 +2,11 @@
 //! Delegates to `gearclaw_memory` while preserving `gearclaw_core` API.
 use crate::config::MemoryConfig as CoreMemoryConfig;
 use crate::error::GearClawError;
-use crate::llm::LLMClient;
+use crate::llm::LlmBackend;
+use crate::tools::ToolSpec;
 use std::sync::Arc;
 
-pub use gearclaw_memory::SearchResult;
+pub use gearclaw_memory::{SearchResult, SyncProgress, NOTES_SOURCE};
 
 #[derive(Clone)]
 pub struct MemoryManager {
@@ -16,7 +17,7 @@ impl MemoryManager {
     pub fn new(
         config: CoreMemoryConfig,
         workspace_path: std::path::PathBuf,
-        llm_client: Arc<LLMClient>,
+        llm_client: Arc<dyn LlmBackend>,
     ) -> Result<Self, GearClawError> {
         let inner = gearclaw_memory::MemoryManager::new(
             to_memory_config(config),
@@ -33,7 +34,14 @@ impl MemoryManager {
     }
 
     pub async fn sync(&self) -> Result<(), GearClawError> {
-        self.inner.sync().await.map_err(|e| {
+        self.sync_with_progress(|_| {}).await
+    }
+
+    pub async fn sync_with_progress<F>(&self, progress: F) -> Result<(), GearClawError>
+    where
+        F: Fn(SyncProgress) + Send + Sync,
+    {
+        self.inner.sync_with_progress(progress).await.map_err(|e| {
             GearClawError::from(crate::error::DomainError::Memory {
                 operation: "sync".to_string(),
                 reason: e.to_string(),
@@ -41,6 +49,38 @@ impl MemoryManager {
         })
     }
 
+    /// Save a note the agent learned during a conversation; see
+    /// [`gearclaw_memory::MemoryManager::add_note`]
+    pub async fn add_note(&self, text: &str, tags: &[String]) -> Result<String, GearClawError> {
+        self.inner.add_note(text, tags).await.map_err(|e| {
+            GearClawError::from(crate::error::DomainError::Memory {
+                operation: "add_note".to_string(),
+                reason: e.to_string(),
+            })
+        })
+    }
+
+    /// The `memory_remember` tool, offered while memory is enabled
+    pub fn remember_tool_spec() -> ToolSpec {
+        ToolSpec {
+            name: "memory_remember".to_string(),
+            description: "Save a fact learned in this conversation to long-term memory so it can be recalled in later turns".to_string(),
+            requires_args: true,
+            parameters: Some(serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string", "description": "The fact to remember" },
+                    "tags": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Optional tags for the note"
+                    }
+                },
+                "required": ["text"]
+            })),
+        }
+    }
+
     pub async fn search(
         &self,
         query: &str,
@@ -59,5 +99,8 @@ fn to_memory_config(config: CoreMemoryConfig) -> gearclaw_memory::MemoryConfig {
     gearclaw_memory::MemoryConfig {
         enabled: config.enabled,
         db_path: config.db_path,
+        hybrid_search: config.hybrid_search,
+        dedup_threshold: config.dedup_threshold,
+        embedding: config.embedding,
     }
 }
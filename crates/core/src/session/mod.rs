@@ -3,7 +3,10 @@
 use crate::config::SessionConfig;
 use crate::error::GearClawError;
 
-pub use gearclaw_session::Session;
+pub use gearclaw_session::{
+    estimate_text_tokens, estimate_tokens, resolve_dir, sanitize_session_id, trim_to_budget,
+    PruneReport, Session, SessionError, SessionOverrides, SessionSummary,
+};
 
 pub struct SessionManager {
     inner: gearclaw_session::SessionManager,
@@ -25,6 +28,15 @@ impl SessionManager {
         })
     }
 
+    pub fn list_sessions_detailed(&self) -> Result<Vec<SessionSummary>, GearClawError> {
+        self.inner.list_sessions_detailed().map_err(|e| {
+            GearClawError::from(crate::error::DomainError::Session {
+                operation: "list_sessions_detailed".to_string(),
+                reason: e.to_string(),
+            })
+        })
+    }
+
     pub fn get_or_create_session(&self, id: &str) -> Result<Session, GearClawError> {
         self.inner.get_or_create_session(id).map_err(|e| {
             GearClawError::from(crate::error::DomainError::Session {
@@ -34,6 +46,24 @@ impl SessionManager {
         })
     }
 
+    pub fn load_session(&self, id: &str) -> Result<Session, GearClawError> {
+        self.inner.load_session(id).map_err(|e| {
+            GearClawError::from(crate::error::DomainError::Session {
+                operation: format!("load_session({})", id),
+                reason: e.to_string(),
+            })
+        })
+    }
+
+    pub fn export_markdown(&self, id: &str) -> Result<String, GearClawError> {
+        self.inner.export_markdown(id).map_err(|e| {
+            GearClawError::from(crate::error::DomainError::Session {
+                operation: format!("export_markdown({})", id),
+                reason: e.to_string(),
+            })
+        })
+    }
+
     pub async fn save_session(&self, session: &Session) -> Result<(), GearClawError> {
         self.inner.save_session(session).await.map_err(|e| {
             GearClawError::from(crate::error::DomainError::Session {
@@ -43,6 +73,24 @@ impl SessionManager {
         })
     }
 
+    pub async fn fork(&self, source_id: &str, new_id: &str) -> Result<Session, GearClawError> {
+        self.inner.fork(source_id, new_id).await.map_err(|e| {
+            GearClawError::from(crate::error::DomainError::Session {
+                operation: format!("fork({} -> {})", source_id, new_id),
+                reason: e.to_string(),
+            })
+        })
+    }
+
+    pub fn prune(&self, max_age: std::time::Duration) -> Result<PruneReport, GearClawError> {
+        self.inner.prune(max_age).map_err(|e| {
+            GearClawError::from(crate::error::DomainError::Session {
+                operation: "prune".to_string(),
+                reason: e.to_string(),
+            })
+        })
+    }
+
     pub fn delete_session(&self, id: &str) -> Result<(), GearClawError> {
         self.inner.delete_session(id).map_err(|e| {
             GearClawError::from(crate::error::DomainError::Session {
@@ -1,14 +1,105 @@
+use crate::config::{SkillSourceConfig, SkillTrustPolicy};
 use crate::error::GearClawError;
+use crate::tools::{ToolExecutor, ToolResult, ToolSpec};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use tracing::{info, warn};
 
+/// Implicit local source used when no `skill_sources` are configured
+pub const DEFAULT_SKILL_SOURCE: &str = "local-default";
+
+/// Tools a skill from an untrusted source may use on its own behalf:
+/// read-only inspection, nothing that runs commands or writes files
+pub const UNTRUSTED_SKILL_TOOLS: &[&str] = &["read_file", "list_files", "file_info"];
+
+/// `skill.lock`, recording the source of each installed skill, lives next
+/// to the skills directory
+pub fn skill_lock_path(skills_path: &Path) -> PathBuf {
+    skills_path
+        .parent()
+        .map(|p| p.join("skill.lock"))
+        .unwrap_or_else(|| skills_path.join("skill.lock"))
+}
+
+/// The `hello_world` skill written by `gearclaw init`
+pub const SAMPLE_SKILL_MD: &str = r#"---
+name: hello_world
+description: A simple hello world skill
+metadata: {}
+---
+
+# Hello World Skill
+
+This skill allows you to say hello.
+
+```bash
+echo "Hello from GearClaw Skill!"
+```
+"#;
+
+/// YAML frontmatter at the top of a `SKILL.md`
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SkillMetadata {
+pub struct SkillFrontmatter {
     pub name: String,
+    #[serde(default)]
     pub description: String,
     #[serde(default)]
     pub metadata: serde_json::Value,
+    /// Tools the skill declares it may use (`allowed-tools` is also accepted)
+    #[serde(default, alias = "allowed-tools")]
+    pub allowed_tools: Option<Vec<String>>,
+    /// Skill version; numeric values like `1.0` are kept as written
+    #[serde(default, deserialize_with = "deserialize_version")]
+    pub version: Option<String>,
+}
+
+/// Former name of [`SkillFrontmatter`]
+pub type SkillMetadata = SkillFrontmatter;
+
+fn deserialize_version<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: Option<serde_json::Value> = Option::deserialize(deserializer)?;
+    Ok(match value {
+        None | Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(version)) => Some(version),
+        Some(other) => Some(other.to_string()),
+    })
+}
+
+/// Split a `SKILL.md` into its parsed frontmatter and the instruction body.
+/// The frontmatter sits between a leading `---` line and the next line that
+/// is exactly `---`, so `---` inside values doesn't end it.
+pub fn parse_skill_file(content: &str) -> Result<(SkillFrontmatter, &str), String> {
+    let content = content.trim_start_matches('\u{feff}').trim_start();
+    let rest = content
+        .strip_prefix("---")
+        .and_then(|rest| {
+            rest.strip_prefix('\n')
+                .or_else(|| rest.strip_prefix("\r\n"))
+        })
+        .ok_or_else(|| "missing frontmatter: file must start with `---`".to_string())?;
+
+    let mut offset = 0;
+    let mut split = None;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            split = Some((&rest[..offset], &rest[offset + line.len()..]));
+            break;
+        }
+        offset += line.len();
+    }
+    let (frontmatter, body) =
+        split.ok_or_else(|| "unterminated frontmatter: missing closing `---`".to_string())?;
+
+    let meta: SkillFrontmatter =
+        serde_yml::from_str(frontmatter).map_err(|e| format!("invalid frontmatter: {}", e))?;
+    if meta.name.trim().is_empty() {
+        return Err("frontmatter `name` is empty".to_string());
+    }
+    Ok((meta, body))
 }
 
 #[derive(Debug, Clone)]
@@ -17,6 +108,93 @@ pub struct Skill {
     pub description: String,
     pub instructions: String,
     pub path: PathBuf,
+    /// Tools the skill declares it may use, if it says
+    pub allowed_tools: Option<Vec<String>>,
+    pub version: Option<String>,
+    /// Source the skill was installed from, per `skill.lock`; `None` for
+    /// skills placed in the skills directory by hand
+    pub source: Option<String>,
+    /// Whether the trust policy trusts `source`; untrusted skills are
+    /// limited to [`UNTRUSTED_SKILL_TOOLS`]
+    pub trusted: bool,
+}
+
+/// A fenced code block from a skill's instructions that `run_skill` can execute
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkillScript {
+    /// Interpreter to run the block with: `bash` or `python3`
+    pub interpreter: &'static str,
+    pub code: String,
+}
+
+impl Skill {
+    /// Runnable code blocks in the skill's instructions, in order
+    pub fn scripts(&self) -> Vec<SkillScript> {
+        extract_scripts(&self.instructions)
+    }
+
+    /// Whether a tool call made on this skill's behalf may go ahead: the
+    /// tool must be among the declared `allowed_tools` (when declared) and,
+    /// for an untrusted skill, in [`UNTRUSTED_SKILL_TOOLS`]
+    pub fn permits_tool(&self, tool: &str) -> bool {
+        let tool = canonical_tool_name(tool);
+        if let Some(allowed) = &self.allowed_tools {
+            if !allowed.iter().any(|a| canonical_tool_name(a) == tool) {
+                return false;
+            }
+        }
+        self.trusted || UNTRUSTED_SKILL_TOOLS.contains(&tool.as_str())
+    }
+}
+
+/// Frontmatter often names the shell tool `Bash`; treat it as `exec`
+fn canonical_tool_name(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    match name.as_str() {
+        "bash" | "shell" | "sh" => "exec".to_string(),
+        _ => name,
+    }
+}
+
+/// Collect ```bash/```sh/```python blocks from markdown. Blocks in other
+/// languages (or with none) are documentation and are skipped.
+pub fn extract_scripts(markdown: &str) -> Vec<SkillScript> {
+    let mut scripts = Vec::new();
+    let mut current: Option<(Option<&'static str>, Vec<&str>)> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        match current.take() {
+            None => {
+                if let Some(info) = trimmed.strip_prefix("```") {
+                    let language = info.split_whitespace().next().unwrap_or("");
+                    current = Some((interpreter_for(language), Vec::new()));
+                }
+            }
+            Some((interpreter, mut body)) => {
+                if trimmed.starts_with("```") {
+                    if let Some(interpreter) = interpreter {
+                        scripts.push(SkillScript {
+                            interpreter,
+                            code: body.join("\n"),
+                        });
+                    }
+                } else {
+                    body.push(line);
+                    current = Some((interpreter, body));
+                }
+            }
+        }
+    }
+    scripts
+}
+
+fn interpreter_for(language: &str) -> Option<&'static str> {
+    match language.to_lowercase().as_str() {
+        "bash" | "sh" | "shell" | "zsh" => Some("bash"),
+        "python" | "python3" | "py" => Some("python3"),
+        _ => None,
+    }
 }
 
 pub struct SkillManager {
@@ -28,27 +206,43 @@ impl SkillManager {
         Self { skills: Vec::new() }
     }
 
+    /// Load every `SKILL.md` under `dir`. A missing directory, or one that
+    /// can't be read, is skipped with a single warning naming the resolved
+    /// path, leaving the manager without skills.
     pub fn load_from_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), GearClawError> {
         let dir = dir.as_ref();
-        if !dir.exists() {
-            warn!("Skills directory not found: {:?}", dir);
+        let resolved = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        if !resolved.is_dir() {
+            let problem = if resolved.exists() {
+                "is not a directory"
+            } else {
+                "does not exist"
+            };
+            warn!(
+                "Skills directory {} {}; no skills loaded (create it or change agent.skills_path)",
+                resolved.display(),
+                problem
+            );
             return Ok(());
         }
 
-        info!("Loading skills from {:?}", dir);
-        self.load_recursive(dir)?;
+        info!("Loading skills from {}", resolved.display());
+        self.load_recursive(&resolved);
 
         info!("Loaded {} skills", self.skills.len());
         Ok(())
     }
 
-    fn load_recursive(&mut self, dir: &Path) -> Result<(), GearClawError> {
-        if !dir.is_dir() {
-            return Ok(());
-        }
+    fn load_recursive(&mut self, dir: &Path) {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("Cannot read skills directory {}: {}", dir.display(), e);
+                return;
+            }
+        };
 
-        for entry in std::fs::read_dir(dir).map_err(GearClawError::IoError)? {
-            let entry = entry.map_err(GearClawError::IoError)?;
+        for entry in entries.flatten() {
             let path = entry.path();
 
             if path.is_dir() {
@@ -60,45 +254,17 @@ impl SkillManager {
                     }
                 } else {
                     // Recurse into subdirectory
-                    self.load_recursive(&path)?;
+                    self.load_recursive(&path);
                 }
             }
         }
-        Ok(())
     }
 
     fn load_skill(&mut self, path: &Path) -> Result<(), GearClawError> {
         let content = std::fs::read_to_string(path).map_err(GearClawError::IoError)?;
 
-        // Parse frontmatter
-        let parts: Vec<&str> = content.splitn(3, "---").collect();
-        if parts.len() < 3 {
-            // Try to be more robust if the file starts with --- immediately
-            if content.starts_with("---") {
-                // splitn might have behaved differently depending on if there is text before the first ---
-                // If file starts with ---, splitn(3, "---") -> ["", "frontmatter", "body"]
-            } else {
-                return Err(GearClawError::ConfigParseError(format!(
-                    "Invalid skill file format in {:?}: missing frontmatter",
-                    path
-                )));
-            }
-        }
-
-        // Check if the first part is empty (standard frontmatter)
-        let (frontmatter, instructions) = if parts[0].trim().is_empty() {
-            (parts[1], parts[2])
-        } else {
-            // Maybe the file doesn't start with ---?
-            // But valid frontmatter usually starts with ---
-            return Err(GearClawError::ConfigParseError(format!(
-                "Invalid skill file format in {:?}: content before frontmatter",
-                path
-            )));
-        };
-
-        let meta: SkillMetadata = serde_yml::from_str(frontmatter).map_err(|e| {
-            GearClawError::ConfigParseError(format!("Invalid skill metadata in {:?}: {}", path, e))
+        let (meta, instructions) = parse_skill_file(&content).map_err(|e| {
+            GearClawError::ConfigParseError(format!("Invalid skill file {:?}: {}", path, e))
         })?;
 
         let skill = Skill {
@@ -106,26 +272,227 @@ impl SkillManager {
             description: meta.description,
             instructions: instructions.trim().to_string(),
             path: path.to_path_buf(),
+            allowed_tools: meta.allowed_tools,
+            version: meta.version,
+            source: None,
+            trusted: true,
         };
 
         self.skills.push(skill);
         Ok(())
     }
 
+    pub fn get(&self, name: &str) -> Option<&Skill> {
+        self.skills.iter().find(|skill| skill.name == name)
+    }
+
+    /// Mark each loaded skill trusted or not from the source `skill.lock`
+    /// records for it and `policy`. Skills without a lock entry were put in
+    /// place by hand and stay trusted; a source that is no longer configured
+    /// is not trusted. An unreadable lock leaves every skill untrusted.
+    pub fn apply_source_trust(
+        &mut self,
+        lock_path: &Path,
+        sources: &[SkillSourceConfig],
+        policy: &SkillTrustPolicy,
+    ) {
+        #[derive(Deserialize)]
+        struct LockEntry {
+            source: String,
+        }
+
+        let lock: BTreeMap<String, LockEntry> = if lock_path.exists() {
+            match std::fs::read_to_string(lock_path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+            {
+                Ok(lock) => lock,
+                Err(e) => {
+                    warn!(
+                        "Unreadable {:?}, treating all skills as untrusted: {}",
+                        lock_path, e
+                    );
+                    for skill in &mut self.skills {
+                        skill.trusted = false;
+                    }
+                    return;
+                }
+            }
+        } else {
+            BTreeMap::new()
+        };
+
+        for skill in &mut self.skills {
+            skill.source = lock.get(&skill.name).map(|entry| entry.source.clone());
+            skill.trusted = match &skill.source {
+                None => true,
+                Some(name) => match sources.iter().find(|source| &source.name == name) {
+                    Some(source) => policy.trusts(source),
+                    None => sources.is_empty() && name == DEFAULT_SKILL_SOURCE,
+                },
+            };
+            if !skill.trusted {
+                info!(
+                    "Skill '{}' is from untrusted source {:?}; limited to {}",
+                    skill.name,
+                    skill.source,
+                    UNTRUSTED_SKILL_TOOLS.join(", ")
+                );
+            }
+        }
+    }
+
+    /// Run a skill's code blocks in order through `executor`, under
+    /// `security` if given. `args` are passed as `$1...` (bash) or
+    /// `sys.argv[1:]` (python); a failing block stops the run.
+    pub async fn run_skill(
+        &self,
+        executor: &ToolExecutor,
+        name: &str,
+        args: &[String],
+        cwd: Option<&Path>,
+        security: Option<&str>,
+    ) -> Result<ToolResult, GearClawError> {
+        let skill = self
+            .get(name)
+            .ok_or_else(|| GearClawError::ToolExecutionError(format!("未找到技能: {}", name)))?;
+        // Scripts run through `exec` on the skill's behalf
+        if !skill.permits_tool("exec") {
+            let reason = if skill.trusted {
+                "allowed_tools 未声明 exec"
+            } else {
+                "来源不受信任"
+            };
+            return Err(GearClawError::ToolExecutionError(format!(
+                "技能 {} 不允许执行脚本: {}",
+                name, reason
+            )));
+        }
+        let scripts = skill.scripts();
+        if scripts.is_empty() {
+            return Err(GearClawError::ToolExecutionError(format!(
+                "技能 {} 没有可执行的代码块（bash/python），请按其说明手动操作",
+                name
+            )));
+        }
+
+        let mut outputs = Vec::with_capacity(scripts.len());
+        for script in &scripts {
+            let mut command_args = vec!["-c".to_string(), script.code.clone()];
+            if script.interpreter == "bash" {
+                // $0 for bash -c
+                command_args.push(name.to_string());
+            }
+            command_args.extend(args.iter().cloned());
+
+            let result = match security {
+                Some(security) => {
+                    executor
+                        .exec_command_with_security(security, script.interpreter, command_args, cwd)
+                        .await?
+                }
+                None => {
+                    executor
+                        .exec_command(script.interpreter, command_args, cwd)
+                        .await?
+                }
+            };
+            outputs.push(result.output);
+            if !result.success {
+                return Ok(ToolResult {
+                    success: false,
+                    output: outputs.join("\n"),
+                    error: result.error,
+                    requires_confirmation: false,
+                });
+            }
+        }
+
+        Ok(ToolResult {
+            success: true,
+            output: outputs.join("\n"),
+            error: None,
+            requires_confirmation: false,
+        })
+    }
+
+    /// The `run_skill` tool, offered when any skill has runnable code
+    pub fn tool_spec(&self) -> Option<ToolSpec> {
+        let runnable: Vec<&str> = self
+            .skills
+            .iter()
+            .filter(|skill| !skill.scripts().is_empty())
+            .map(|skill| skill.name.as_str())
+            .collect();
+        if runnable.is_empty() {
+            return None;
+        }
+        Some(ToolSpec {
+            name: "run_skill".to_string(),
+            description: format!(
+                "Run the code blocks of an installed skill and return their output. Runnable skills: {}",
+                runnable.join(", ")
+            ),
+            requires_args: true,
+            parameters: Some(serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Skill name" },
+                    "args": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Arguments passed to the skill's scripts"
+                    }
+                },
+                "required": ["name"]
+            })),
+        })
+    }
+
+    /// Whether any loaded skill comes from an untrusted source. Its
+    /// instructions reach the system prompt, so while one is loaded the
+    /// agent itself is held to [`UNTRUSTED_SKILL_TOOLS`]
+    pub fn has_untrusted(&self) -> bool {
+        self.skills.iter().any(|skill| !skill.trusted)
+    }
+
     pub fn get_prompt_context(&self) -> String {
         if self.skills.is_empty() {
             return String::new();
         }
 
         let mut context = String::from("\n\n## Available Skills\n\n");
-        context.push_str("You have access to the following skills. You can use them by executing the shell commands described in their instructions.\n\n");
+        context.push_str("You have access to the following skills. You can use them by executing the shell commands described in their instructions, or run a skill's scripts directly with the `run_skill` tool.\n\n");
+
+        if self.has_untrusted() {
+            context.push_str(&format!(
+                "Some skills below come from untrusted sources; while they are loaded only these tools are available: {}, run_skill.\n\n",
+                UNTRUSTED_SKILL_TOOLS.join(", ")
+            ));
+        }
 
         for skill in &self.skills {
             context.push_str(&format!("### Skill: {}\n", skill.name));
-            context.push_str(&format!("**Description**: {}\n\n", skill.description));
+            context.push_str(&format!("**Description**: {}\n", skill.description));
+            if let Some(tools) = &skill.allowed_tools {
+                context.push_str(&format!("**Allowed tools**: {}\n", tools.join(", ")));
+            }
+            if !skill.trusted {
+                context.push_str(&format!(
+                    "**Untrusted source**: limited to {}\n",
+                    UNTRUSTED_SKILL_TOOLS.join(", ")
+                ));
+            }
+            context.push('\n');
             context.push_str(&format!("{}\n\n", skill.instructions));
             context.push_str("---\n\n");
         }
         context
     }
 }
+
+impl Default for SkillManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,11 +1,10 @@
 //! Compatibility wrapper for tools subsystem.
 //! Delegates base executor behavior to `gearclaw_tools` and augments
 //! macOS-specific tool exposure in `gearclaw_core`.
+use crate::config::WebSearchConfig;
 use crate::error::GearClawError;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
 
-#[cfg(target_os = "macos")]
 use crate::macos::MacosController;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,21 +21,23 @@ pub struct ToolResult {
     pub success: bool,
     pub output: String,
     pub error: Option<String>,
+    /// The call was held, not run: it matched a destructive pattern and
+    /// waits for the user to confirm it
+    #[serde(default)]
+    pub requires_confirmation: bool,
 }
 
 pub struct ToolExecutor {
     inner: gearclaw_tools::ToolExecutor,
-    #[cfg(target_os = "macos")]
+    /// Stub backend off macOS: its tools are listed but fail with a clear error
     pub(crate) macos: MacosController,
 }
 
 impl ToolExecutor {
     pub fn new(security: &str) -> Self {
-        #[cfg(target_os = "macos")]
         let macos = MacosController::new().expect("Failed to initialize macOS controller");
         Self {
             inner: gearclaw_tools::ToolExecutor::new(security),
-            #[cfg(target_os = "macos")]
             macos,
         }
     }
@@ -46,15 +47,37 @@ impl ToolExecutor {
         cmd: &str,
         args: Vec<String>,
         cwd: Option<&std::path::Path>,
+    ) -> Result<ToolResult, GearClawError> {
+        Self::exec_with(&self.inner, cmd, args, cwd).await
+    }
+
+    /// Like [`ToolExecutor::exec_command`], under `security` instead of the
+    /// executor's configured level.
+    pub async fn exec_command_with_security(
+        &self,
+        security: &str,
+        cmd: &str,
+        args: Vec<String>,
+        cwd: Option<&std::path::Path>,
+    ) -> Result<ToolResult, GearClawError> {
+        Self::exec_with(&gearclaw_tools::ToolExecutor::new(security), cmd, args, cwd).await
+    }
+
+    async fn exec_with(
+        inner: &gearclaw_tools::ToolExecutor,
+        cmd: &str,
+        args: Vec<String>,
+        cwd: Option<&std::path::Path>,
     ) -> Result<ToolResult, GearClawError> {
         let args_for_err = args.clone();
-        self.inner
+        inner
             .exec_command(cmd, args, cwd)
             .await
             .map(|r| ToolResult {
                 success: r.success,
                 output: r.output,
                 error: r.error,
+                requires_confirmation: false,
             })
             .map_err(|e| {
                 GearClawError::from(crate::error::DomainError::ToolExecution {
@@ -64,6 +87,60 @@ impl ToolExecutor {
             })
     }
 
+    /// Run a web search with the configured provider and format the results
+    pub async fn web_search(
+        &self,
+        config: &WebSearchConfig,
+        query: &str,
+    ) -> Result<ToolResult, GearClawError> {
+        let provider =
+            gearclaw_tools::SearchProvider::from_name(&config.provider).ok_or_else(|| {
+                GearClawError::ToolExecutionError(format!("未知的搜索提供方: {}", config.provider))
+            })?;
+        let to_error = |e: gearclaw_tools::ToolError| {
+            GearClawError::from(crate::error::DomainError::ToolExecution {
+                tool: "web_search".to_string(),
+                reason: e.to_string(),
+            })
+        };
+        let search = gearclaw_tools::WebSearch::new(
+            provider,
+            config.endpoint.as_deref(),
+            config.max_results,
+            std::time::Duration::from_secs(config.timeout_seconds),
+        )
+        .map_err(to_error)?;
+        let results = search.search(query).await.map_err(to_error)?;
+        Ok(ToolResult {
+            success: true,
+            output: gearclaw_tools::format_results(query, &results),
+            error: None,
+            requires_confirmation: false,
+        })
+    }
+
+    /// Fetch a web page as text, reading at most `max_bytes` of it
+    pub async fn fetch_url(
+        &self,
+        url: &str,
+        max_bytes: usize,
+    ) -> Result<ToolResult, GearClawError> {
+        let page = gearclaw_tools::fetch_url(url, max_bytes)
+            .await
+            .map_err(|e| {
+                GearClawError::from(crate::error::DomainError::ToolExecution {
+                    tool: "fetch_url".to_string(),
+                    reason: e.to_string(),
+                })
+            })?;
+        Ok(ToolResult {
+            success: true,
+            output: page.to_output(),
+            error: None,
+            requires_confirmation: false,
+        })
+    }
+
     pub fn available_tools(&self) -> Vec<ToolSpec> {
         let mut tools = self
             .inner
@@ -77,164 +154,24 @@ impl ToolExecutor {
             })
             .collect::<Vec<_>>();
 
-        #[cfg(target_os = "macos")]
-        {
-            let macos_tools = vec![
-                ToolSpec {
-                    name: "macos_launch_app".to_string(),
-                    description: "启动 macOS 应用程序".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": {
-                            "app_name": { "type": "string", "description": "应用名称 (如 Safari, Chrome, Terminal)" }
-                        },
-                        "required": ["app_name"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_quit_app".to_string(),
-                    description: "退出 macOS 应用程序".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": { "app_name": { "type": "string", "description": "应用名称" } },
-                        "required": ["app_name"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_bring_to_front".to_string(),
-                    description: "将应用程序切换到前台".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": { "app_name": { "type": "string", "description": "应用名称" } },
-                        "required": ["app_name"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_is_running".to_string(),
-                    description: "检查应用是否正在运行".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": { "app_name": { "type": "string", "description": "应用名称" } },
-                        "required": ["app_name"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_applescript".to_string(),
-                    description: "执行 AppleScript 代码".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": { "script": { "type": "string", "description": "AppleScript 代码" } },
-                        "required": ["script"]
-                    })),
+        // macOS tools are listed on every platform so the catalog is stable;
+        // elsewhere they carry a note and fail with a clear error when called.
+        let supported = MacosController::is_supported();
+        tools.extend(self.macos.available_tools().into_iter().map(|tool| {
+            let description = tool["description"].as_str().unwrap_or_default();
+            ToolSpec {
+                name: tool["name"].as_str().unwrap_or_default().to_string(),
+                description: if supported {
+                    description.to_string()
+                } else {
+                    format!("{}（需要 macOS，当前平台不可用）", description)
                 },
-                ToolSpec {
-                    name: "macos_jxa".to_string(),
-                    description: "执行 JavaScript for Automation (JXA) 代码".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": { "script": { "type": "string", "description": "JXA JavaScript 代码" } },
-                        "required": ["script"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_type_text".to_string(),
-                    description: "模拟键盘输入文本".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": { "text": { "type": "string", "description": "要输入的文本" } },
-                        "required": ["text"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_key_combo".to_string(),
-                    description: "模拟组合键 (如 cmd+c, cmd+v)".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": {
-                            "keys": {
-                                "type": "array",
-                                "items": { "type": "string" },
-                                "description": "按键数组，如 [\"cmd\", \"c\"] 或 [\"cmd\", \"shift\", \"3\"]"
-                            }
-                        },
-                        "required": ["keys"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_clipboard_read".to_string(),
-                    description: "读取剪贴板内容".to_string(),
-                    requires_args: false,
-                    parameters: Some(json!({ "type": "object", "properties": {}, "required": [] })),
-                },
-                ToolSpec {
-                    name: "macos_clipboard_write".to_string(),
-                    description: "写入剪贴板内容".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": { "text": { "type": "string", "description": "要复制到剪贴板的文本" } },
-                        "required": ["text"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_notify".to_string(),
-                    description: "发送系统通知".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": {
-                            "title": { "type": "string", "description": "通知标题 (默认: GearClaw)" },
-                            "message": { "type": "string", "description": "通知内容" },
-                            "sound": { "type": "boolean", "description": "是否播放提示音 (默认: false)" }
-                        },
-                        "required": ["message"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_open_url".to_string(),
-                    description: "在默认浏览器中打开 URL".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": { "url": { "type": "string", "description": "要打开的 URL" } },
-                        "required": ["url"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_search_in_browser".to_string(),
-                    description: "在浏览器中执行搜索，打开浏览器窗口让用户可以看到和浏览搜索结果。当用户想\"在浏览器中查看\"或\"打开浏览器搜索\"时使用此工具。".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": { "query": { "type": "string", "description": "搜索关键词" } },
-                        "required": ["query"]
-                    })),
-                },
-                ToolSpec {
-                    name: "macos_say".to_string(),
-                    description: "文字转语音 (TTS)".to_string(),
-                    requires_args: true,
-                    parameters: Some(json!({
-                        "type": "object",
-                        "properties": {
-                            "text": { "type": "string", "description": "要朗读的文本" },
-                            "voice": { "type": "string", "description": "语音名称 (可选，如 'Ting-Ting')" },
-                            "rate": { "type": "integer", "description": "语速 (默认: 175)" }
-                        },
-                        "required": ["text"]
-                    })),
-                },
-            ];
-            tools.extend(macos_tools);
-        }
+                requires_args: tool["parameters"]["required"]
+                    .as_array()
+                    .is_some_and(|required| !required.is_empty()),
+                parameters: Some(tool["parameters"].clone()),
+            }
+        }));
 
         tools
     }
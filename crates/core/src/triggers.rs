@@ -0,0 +1,121 @@
+//! Matching channel messages against `agent.triggers` keywords and mention
+//! patterns.
+//!
+//! Patterns are compiled into one regex per list when the matcher is built,
+//! so checking a message does no per-pattern work. The matcher also tracks
+//! the per-channel cooldown from `cooldown_seconds`.
+
+use crate::agent::MessageAddressing;
+use crate::config::{AgentTriggerConfig, TriggerMatchOptions};
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Trigger configuration with its keyword and mention patterns compiled
+#[derive(Debug)]
+pub struct TriggerMatcher {
+    config: AgentTriggerConfig,
+    keywords: Option<Regex>,
+    mentions: Option<Regex>,
+    cooldown: Duration,
+    /// Last time each channel key ("platform:channel_id") triggered
+    last_triggered: Mutex<HashMap<String, Instant>>,
+}
+
+impl TriggerMatcher {
+    pub fn new(config: AgentTriggerConfig) -> Self {
+        let keywords = compile(&config.keywords, config.keyword_match);
+        let mentions = compile(&config.mention_patterns, config.mention_match);
+        Self {
+            cooldown: Duration::from_secs(config.cooldown_seconds),
+            config,
+            keywords,
+            mentions,
+            last_triggered: Mutex::default(),
+        }
+    }
+
+    pub fn config(&self) -> &AgentTriggerConfig {
+        &self.config
+    }
+
+    /// Whether `content` contains any configured keyword
+    pub fn matches_keyword(&self, content: &str) -> bool {
+        self.keywords
+            .as_ref()
+            .is_some_and(|re| re.is_match(content))
+    }
+
+    /// Whether `content` contains any configured mention pattern
+    pub fn matches_mention(&self, content: &str) -> bool {
+        self.mentions
+            .as_ref()
+            .is_some_and(|re| re.is_match(content))
+    }
+
+    /// Claim a trigger for `channel_key`. Returns false while the channel
+    /// is still inside the cooldown window of its previous trigger;
+    /// otherwise records now as the start of a new window.
+    pub fn try_start_cooldown(&self, channel_key: &str) -> bool {
+        self.try_start_cooldown_at(channel_key, Instant::now())
+    }
+
+    /// [`TriggerMatcher::try_start_cooldown`] at an explicit time
+    pub fn try_start_cooldown_at(&self, channel_key: &str, now: Instant) -> bool {
+        if self.cooldown.is_zero() {
+            return true;
+        }
+        let mut last = self
+            .last_triggered
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(at) = last.get(channel_key) {
+            if now.saturating_duration_since(*at) < self.cooldown {
+                return false;
+            }
+        }
+        // Forget channels whose window has passed so the map stays small.
+        last.retain(|_, at| now.saturating_duration_since(*at) < self.cooldown);
+        last.insert(channel_key.to_string(), now);
+        true
+    }
+}
+
+/// Whether a message was sent by the bot itself (e.g. its own reply echoed
+/// back by the platform)
+pub fn is_own_message(addressing: &MessageAddressing) -> bool {
+    matches!(
+        (&addressing.sender_id, &addressing.bot_user_id),
+        (Some(sender), Some(bot)) if sender == bot
+    )
+}
+
+/// One alternation over `patterns`, or `None` if there are none.
+///
+/// With `word_boundary`, `\b` is only added next to word characters, so a
+/// pattern such as `@agent` still matches at the start of "@agent help".
+fn compile(patterns: &[String], options: TriggerMatchOptions) -> Option<Regex> {
+    let alternatives: Vec<String> = patterns
+        .iter()
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let escaped = regex::escape(p);
+            if !options.word_boundary {
+                return escaped;
+            }
+            let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+            let start = if is_word(p.chars().next()) { r"\b" } else { "" };
+            let end = if is_word(p.chars().last()) { r"\b" } else { "" };
+            format!("{}{}{}", start, escaped, end)
+        })
+        .collect();
+    if alternatives.is_empty() {
+        return None;
+    }
+    RegexBuilder::new(&format!("(?:{})", alternatives.join("|")))
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| tracing::warn!("Invalid trigger patterns {:?}: {}", patterns, e))
+        .ok()
+}
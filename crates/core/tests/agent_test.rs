@@ -0,0 +1,1161 @@
+// The agent loop, its tool-call handling, and the interactive prompt helpers
+
+mod common;
+
+use common::{embed, test_config, MockLlm, Turn};
+use gearclaw_core::agent::{
+    cap_tool_output, interactive_editor, load_interactive_history, parse_interactive_command,
+    parse_tool_arguments, render_system_prompt, save_interactive_history, tool_error_output,
+    AgentObserver, AgentProgress, DeltaSink, InteractiveCommand, MessageAddressing,
+};
+use gearclaw_core::error::LlmErrorKind;
+use gearclaw_core::llm::{
+    ChatCompletionResponse, ChatCompletionStreamResponse, ChatStream, LlmBackend, LlmError,
+    Message, SamplingParams, ToolSpec,
+};
+use gearclaw_core::session::{estimate_text_tokens, estimate_tokens, Session};
+use gearclaw_core::tools::ToolResult;
+use gearclaw_core::{Agent, TriggerMode};
+use rustyline::history::History;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::TempDir;
+
+// Observer callbacks fired while the agent loop runs
+
+#[derive(Default)]
+struct Recorder {
+    events: Mutex<Vec<String>>,
+}
+
+impl AgentObserver for Recorder {
+    fn on_assistant_delta(&self, delta: &str) {
+        self.events.lock().unwrap().push(format!("delta:{}", delta));
+    }
+
+    fn on_tool_call_start(&self, name: &str, _arguments: &str) {
+        self.events.lock().unwrap().push(format!("start:{}", name));
+    }
+
+    fn on_tool_call_end(&self, name: &str, result: &ToolResult) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("end:{}:{}", name, result.success));
+    }
+}
+
+#[tokio::test]
+async fn test_observer_sees_deltas_and_tool_calls_in_order() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("note.txt"), "hi").unwrap();
+    let llm = MockLlm::start(vec![
+        Turn::tool("read_file", json!({ "path": "note.txt" })),
+        Turn::tool("no_such_tool", json!({})),
+        Turn::text(&["Done", "!"]),
+    ])
+    .await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+    let recorder = Arc::new(Recorder::default());
+    agent.add_observer(recorder.clone());
+
+    let mut session = Session::new("observer".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let reply = agent
+        .process_message_with_progress(&mut session, "read it", &progress)
+        .await
+        .unwrap();
+
+    assert_eq!(reply, "Done!");
+    assert_eq!(
+        *recorder.events.lock().unwrap(),
+        [
+            "start:read_file",
+            "end:read_file:true",
+            "start:no_such_tool",
+            "end:no_such_tool:false",
+            "delta:Done",
+            "delta:!",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_agent_without_observers_is_unaffected() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["ok"])]).await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+
+    let mut session = Session::new("plain".to_string());
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let reply = agent
+        .process_message_with_progress(&mut session, "hi", &progress)
+        .await
+        .unwrap();
+    assert_eq!(reply, "ok");
+}
+
+// Streaming assistant text into a caller-provided sink instead of stdout
+
+#[tokio::test]
+async fn test_deltas_are_written_to_buffer() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["Hello", ", ", "world"])]).await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+
+    let mut session = Session::new("sink".to_string());
+    let mut buffer: Vec<u8> = Vec::new();
+    let reply = agent
+        .process_message_with_sink(&mut session, "hi", DeltaSink::Writer(&mut buffer))
+        .await
+        .unwrap();
+
+    assert_eq!(reply, "Hello, world");
+    assert_eq!(String::from_utf8(buffer).unwrap(), "Hello, world");
+}
+
+#[tokio::test]
+async fn test_writer_sink_spans_tool_turns() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("note.txt"), "hi").unwrap();
+    let llm = MockLlm::start(vec![
+        Turn::tool("read_file", json!({ "path": "note.txt" })),
+        Turn::text(&["Read ", "it"]),
+    ])
+    .await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+
+    let mut session = Session::new("sink".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let mut buffer: Vec<u8> = Vec::new();
+    let reply = agent
+        .process_message_with_sink(&mut session, "read", DeltaSink::Writer(&mut buffer))
+        .await
+        .unwrap();
+
+    assert_eq!(reply, "Read it");
+    assert!(String::from_utf8(buffer).unwrap().ends_with("Read it"));
+}
+
+// Agent driven through an injected LlmBackend instead of an HTTP endpoint
+
+/// In-process [`LlmBackend`] replaying scripted turns, for driving the agent
+/// without an HTTP endpoint
+struct ScriptedBackend {
+    turns: Mutex<VecDeque<Turn>>,
+    calls: Mutex<Vec<Vec<Message>>>,
+}
+
+impl ScriptedBackend {
+    fn new(turns: Vec<Turn>) -> Arc<Self> {
+        Arc::new(Self {
+            turns: Mutex::new(turns.into()),
+            calls: Mutex::default(),
+        })
+    }
+
+    /// Messages sent with each completion so far
+    fn calls(&self) -> Vec<Vec<Message>> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn next_turn(&self, messages: Vec<Message>) -> (usize, Turn) {
+        let mut calls = self.calls.lock().unwrap();
+        calls.push(messages);
+        let turn = self
+            .turns
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or_else(|| Turn::text(&["(no more scripted turns)"]));
+        (calls.len(), turn)
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for ScriptedBackend {
+    fn models(&self) -> Vec<String> {
+        vec!["scripted".to_string()]
+    }
+
+    fn embedding_model(&self) -> String {
+        "scripted-embedding".to_string()
+    }
+
+    async fn chat_completion(
+        &self,
+        messages: Vec<Message>,
+        _tools: Option<Vec<ToolSpec>>,
+        _max_tokens: Option<usize>,
+    ) -> Result<ChatCompletionResponse, LlmError> {
+        let content = match self.next_turn(messages).1 {
+            Turn::Text(chunks) | Turn::Stalled(chunks) | Turn::Garbled(chunks) => chunks.concat(),
+            Turn::ToolCalls(_) => String::new(),
+        };
+        Ok(serde_json::from_value(json!({
+            "id": "chatcmpl-test",
+            "choices": [{
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop"
+            }]
+        }))
+        .unwrap())
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        models: &[String],
+        messages: Vec<Message>,
+        _tools: Option<Vec<ToolSpec>>,
+        _max_tokens: Option<usize>,
+        _sampling: SamplingParams,
+    ) -> Result<(String, ChatStream), LlmError> {
+        let (request_no, turn) = self.next_turn(messages);
+        let mut chunks: Vec<Result<ChatCompletionStreamResponse, LlmError>> = turn
+            .chunks(request_no)
+            .into_iter()
+            .map(|chunk| Ok(serde_json::from_value(chunk).unwrap()))
+            .collect();
+        if matches!(turn, Turn::Stalled(_)) {
+            chunks.push(Err(LlmError::Timeout("stream stalled".to_string())));
+        }
+        Ok((models[0].clone(), Box::pin(futures::stream::iter(chunks))))
+    }
+
+    async fn get_embedding(&self, text: &str) -> Result<Vec<f32>, LlmError> {
+        Ok(embed(text))
+    }
+}
+
+#[tokio::test]
+async fn test_agent_loop_runs_on_scripted_backend() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("note.txt"), "scripted hello").unwrap();
+    let backend = ScriptedBackend::new(vec![
+        Turn::tool("read_file", json!({ "path": "note.txt" })),
+        Turn::text(&["The note says ", "hello"]),
+    ]);
+    // Nothing listens here: every completion must go through the backend.
+    let config = test_config(dir.path(), "http://127.0.0.1:9");
+    let agent = Agent::new_with_backend(config, backend.clone())
+        .await
+        .unwrap();
+
+    let mut session = Session::new("backend".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let reply = agent
+        .process_message_with_progress(&mut session, "read the note", &progress)
+        .await
+        .unwrap();
+
+    assert_eq!(reply, "The note says hello");
+    let calls = backend.calls();
+    assert_eq!(calls.len(), 2);
+    let tool_result = calls[1]
+        .iter()
+        .find(|m| m.role == "tool")
+        .and_then(|m| m.content.clone())
+        .unwrap();
+    assert!(tool_result.contains("scripted hello"), "{}", tool_result);
+}
+
+// LLM timeouts surface as agent errors instead of truncated answers
+
+#[tokio::test]
+async fn test_stalled_stream_fails_the_run() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::Stalled(vec!["The answer is"])]).await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.llm.stream_idle_timeout_secs = 1;
+    let agent = Agent::new(config).await.unwrap();
+
+    let mut session = Session::new("stall".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let err = tokio::time::timeout(
+        Duration::from_secs(10),
+        agent.process_message_with_progress(&mut session, "what is it?", &progress),
+    )
+    .await
+    .expect("the idle timeout should end the run")
+    .unwrap_err();
+
+    assert_eq!(err.llm_kind(), Some(LlmErrorKind::Network), "{}", err);
+    assert!(err.to_string().contains("no data"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_unparseable_stream_event_is_skipped() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::Garbled(vec!["The answer ", "is 42"])]).await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+
+    let mut session = Session::new("garbled".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let reply = agent
+        .process_message(&mut session, "what is it?")
+        .await
+        .unwrap();
+
+    assert_eq!(reply, "The answer is 42");
+}
+
+// Requests shaped by llm.capabilities
+
+async fn first_request(configure: impl FnOnce(&mut gearclaw_core::Config)) -> serde_json::Value {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["ok"])]).await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    configure(&mut config);
+    let agent = Agent::new(config).await.unwrap();
+
+    let mut session = Session::new("caps".to_string());
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let reply = agent
+        .process_message_with_progress(&mut session, "hello", &progress)
+        .await
+        .unwrap();
+    assert_eq!(reply, "ok");
+    llm.requests().remove(0)
+}
+
+#[tokio::test]
+async fn test_request_omits_tools_when_unsupported() {
+    let request = first_request(|config| {
+        config.llm.capabilities.supports_tools = false;
+        config.llm.capabilities.supports_streaming_usage = false;
+    })
+    .await;
+
+    assert!(request.get("tools").is_none(), "{}", request);
+    assert!(request.get("stream_options").is_none());
+    // The tools are described in the system prompt instead.
+    let system = request["messages"][0]["content"].as_str().unwrap();
+    assert!(system.contains("does not support function calling"));
+    assert!(system.contains("`exec`"));
+}
+
+#[tokio::test]
+async fn test_request_sends_tools_by_default() {
+    let request = first_request(|_| {}).await;
+
+    assert!(request["tools"].as_array().is_some_and(|t| !t.is_empty()));
+    assert_eq!(request["stream_options"]["include_usage"], true);
+    let system = request["messages"][0]["content"].as_str().unwrap();
+    assert!(!system.contains("does not support function calling"));
+}
+
+#[tokio::test]
+async fn test_history_fits_max_context_with_completion_and_tools() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["ok"])]).await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.llm.capabilities.max_context = Some(8192);
+    config.llm.max_completion_tokens = 1024;
+    let agent = Agent::new(config).await.unwrap();
+
+    let mut session = Session::new("budget".to_string());
+    let filler = "x".repeat(800);
+    for i in 0..40 {
+        session.add_message(Message {
+            role: if i % 2 == 0 { "user" } else { "assistant" }.to_string(),
+            content: Some(format!("old {} {}", i, filler)),
+            tool_calls: None,
+            tool_call_id: None,
+        });
+    }
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    agent
+        .process_message_with_progress(&mut session, "hello", &progress)
+        .await
+        .unwrap();
+
+    let request = llm.requests().remove(0);
+    assert_eq!(request["max_tokens"], 1024);
+    let messages: Vec<Message> = serde_json::from_value(request["messages"].clone()).unwrap();
+    assert_eq!(messages.last().unwrap().content.as_deref(), Some("hello"));
+    assert!(messages.len() < 42, "history should be trimmed");
+    let prompt_tokens: usize = messages.iter().map(estimate_tokens).sum::<usize>()
+        + estimate_text_tokens(&request["tools"].to_string());
+    assert!(
+        prompt_tokens + 1024 <= 8192,
+        "prompt ~{} tokens leaves no room for the completion",
+        prompt_tokens
+    );
+}
+
+// The per-message cap on tool-calling turns (agent.max_tool_turns)
+
+async fn run(turns: Vec<Turn>, max_tool_turns: usize) -> (String, usize) {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("note.txt"), "hi").unwrap();
+    let llm = MockLlm::start(turns).await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.agent.max_tool_turns = max_tool_turns;
+    let agent = Agent::new(config).await.unwrap();
+
+    let mut session = Session::new("loop".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let reply = agent
+        .process_message_with_progress(&mut session, "go", &progress)
+        .await
+        .unwrap();
+    (reply, llm.requests().len())
+}
+
+#[tokio::test]
+async fn test_cap_stops_a_model_that_always_calls_tools() {
+    let (reply, requests) = run(
+        vec![Turn::tool("read_file", json!({ "path": "note.txt" }))],
+        3,
+    )
+    .await;
+    assert_eq!(requests, 3);
+    assert!(reply.contains("工具调用轮数上限"), "{}", reply);
+    assert!(reply.contains('3'), "{}", reply);
+}
+
+#[tokio::test]
+async fn test_final_answer_within_cap_is_not_flagged() {
+    let (reply, requests) = run(
+        vec![
+            Turn::tool("read_file", json!({ "path": "note.txt" })),
+            Turn::text(&["all done"]),
+        ],
+        2,
+    )
+    .await;
+    assert_eq!(requests, 2);
+    assert_eq!(reply, "all done");
+}
+
+// Several tool calls in one assistant turn
+
+/// Run one message whose first turn requests `calls`, returning the
+/// `(tool_call_id, content)` of each tool message in history order
+async fn run_batch(dir: &TempDir, calls: Vec<(&'static str, String)>) -> Vec<(String, String)> {
+    let llm = MockLlm::start(vec![Turn::ToolCalls(calls), Turn::text(&["done"])]).await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+    let mut session = Session::new("batch".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    agent
+        .process_message_with_progress(&mut session, "go", &progress)
+        .await
+        .unwrap();
+
+    session
+        .messages
+        .iter()
+        .filter(|m| m.role == "tool")
+        .map(|m| {
+            (
+                m.tool_call_id.clone().unwrap_or_default(),
+                m.content.clone().unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_independent_calls_all_run_and_keep_call_order() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "alpha").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "beta").unwrap();
+
+    let results = run_batch(
+        &dir,
+        vec![
+            ("read_file", json!({ "path": "a.txt" }).to_string()),
+            ("read_file", json!({ "path": "b.txt" }).to_string()),
+        ],
+    )
+    .await;
+
+    assert_eq!(
+        results,
+        [
+            ("call_0_0".to_string(), "alpha".to_string()),
+            ("call_0_1".to_string(), "beta".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_write_then_read_runs_in_order() {
+    let dir = TempDir::new().unwrap();
+
+    let results = run_batch(
+        &dir,
+        vec![
+            (
+                "write_file",
+                json!({ "path": "note.txt", "content": "first" }).to_string(),
+            ),
+            (
+                "write_file",
+                json!({ "path": "note.txt", "content": "second" }).to_string(),
+            ),
+            ("read_file", json!({ "path": "note.txt" }).to_string()),
+        ],
+    )
+    .await;
+
+    assert_eq!(results[2], ("call_0_2".to_string(), "second".to_string()));
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("note.txt")).unwrap(),
+        "second"
+    );
+}
+
+#[tokio::test]
+async fn test_batch_with_cd_runs_in_order() {
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join("sub/inner.txt"), "inside").unwrap();
+
+    let results = run_batch(
+        &dir,
+        vec![
+            (
+                "exec",
+                json!({ "command": "cd", "args": ["sub"] }).to_string(),
+            ),
+            ("read_file", json!({ "path": "inner.txt" }).to_string()),
+        ],
+    )
+    .await;
+
+    assert_eq!(results[1], ("call_0_1".to_string(), "inside".to_string()));
+}
+
+// Parsing, repairing, and rejecting tool-call argument JSON
+
+#[test]
+fn test_valid_and_empty_arguments() {
+    assert_eq!(
+        parse_tool_arguments(r#"{"path": "a.txt"}"#).unwrap(),
+        json!({ "path": "a.txt" })
+    );
+    assert_eq!(parse_tool_arguments("").unwrap(), json!({}));
+    assert_eq!(parse_tool_arguments("  ").unwrap(), json!({}));
+}
+
+#[test]
+fn test_repairs_trailing_commas() {
+    assert_eq!(
+        parse_tool_arguments(r#"{"command": "ls", "args": ["-l", "-a",],}"#).unwrap(),
+        json!({ "command": "ls", "args": ["-l", "-a"] })
+    );
+    // Commas inside strings are left alone
+    assert_eq!(
+        parse_tool_arguments(r#"{"text": "a,}", }"#).unwrap(),
+        json!({ "text": "a,}" })
+    );
+}
+
+#[test]
+fn test_repairs_markdown_fence() {
+    assert_eq!(
+        parse_tool_arguments("```json\n{\"path\": \"a.txt\",}\n```").unwrap(),
+        json!({ "path": "a.txt" })
+    );
+    assert_eq!(
+        parse_tool_arguments("```\n{\"path\": \"a.txt\"}\n```").unwrap(),
+        json!({ "path": "a.txt" })
+    );
+}
+
+#[test]
+fn test_unrepairable_arguments_are_an_error() {
+    assert!(parse_tool_arguments(r#"{"path": "a.txt""#).is_err());
+    assert!(parse_tool_arguments("path=a.txt").is_err());
+}
+
+#[tokio::test]
+async fn test_repaired_arguments_run_the_tool() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("note.txt"), "repaired contents").unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["ok"])]).await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+
+    let mut session = Session::new("args".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let result = agent
+        .execute_tool_call(&mut session, "read_file", r#"{"path": "note.txt",}"#)
+        .await
+        .unwrap();
+
+    assert!(result.success);
+    assert!(result.output.contains("repaired contents"));
+}
+
+#[tokio::test]
+async fn test_invalid_arguments_are_reported_to_the_model() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![
+        Turn::ToolCalls(vec![("read_file", r#"{"path": "note.txt"#.to_string())]),
+        Turn::text(&["fixed"]),
+    ])
+    .await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+
+    let mut session = Session::new("args".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let (progress, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let reply = agent
+        .process_message_with_progress(&mut session, "read it", &progress)
+        .await
+        .unwrap();
+    assert_eq!(reply, "fixed");
+
+    let mut finished = None;
+    while let Ok(event) = rx.try_recv() {
+        if let gearclaw_core::agent::AgentProgress::ToolFinished { success, .. } = event {
+            finished = Some(success);
+        }
+    }
+    assert_eq!(finished, Some(false));
+
+    let requests = llm.requests();
+    let tool_message = requests[1]["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["role"] == "tool")
+        .unwrap()
+        .clone();
+    assert_eq!(tool_message["tool_call_id"], "call_0_0");
+    assert!(tool_message["content"]
+        .as_str()
+        .unwrap()
+        .contains("arguments were not valid JSON"));
+}
+
+// Failed tool calls are fed back to the model as structured JSON
+
+#[test]
+fn test_tool_error_output_shape() {
+    let output: Value = serde_json::from_str(&tool_error_output(
+        "profile",
+        "tool `exec` is not available",
+    ))
+    .unwrap();
+    assert_eq!(
+        output,
+        json!({ "error": true, "message": "tool `exec` is not available", "kind": "profile" })
+    );
+}
+
+#[tokio::test]
+async fn test_failing_command_tool_message_is_json() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![
+        Turn::tool("exec", json!({ "command": "ls", "args": ["no-such-file"] })),
+        Turn::text(&["it failed"]),
+    ])
+    .await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+
+    let mut session = Session::new("tool-error".to_string());
+    session.cwd = dir.path().to_path_buf();
+    agent
+        .process_message(&mut session, "list no-such-file")
+        .await
+        .unwrap();
+
+    let follow_up = &llm.requests()[1];
+    let sent = follow_up["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["role"] == "tool")
+        .unwrap()["content"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let error: Value = serde_json::from_str(&sent).unwrap();
+    assert_eq!(error["error"], true);
+    assert_eq!(error["kind"], "tool_execution");
+    assert!(
+        error["message"].as_str().unwrap().contains("no-such-file"),
+        "{}",
+        sent
+    );
+}
+
+#[tokio::test]
+async fn test_bad_arguments_tool_message_is_json() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![]).await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+    let mut session = Session::new("tool-error".to_string());
+
+    let result = agent
+        .execute_tool_call(&mut session, "read_file", "{not json")
+        .await
+        .unwrap();
+    assert!(!result.success);
+    let error: Value = serde_json::from_str(&result.output).unwrap();
+    assert_eq!(error["kind"], "invalid_arguments");
+}
+
+#[tokio::test]
+async fn test_missing_directory_tool_message_is_json() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![]).await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+    let mut session = Session::new("tool-error".to_string());
+    session.cwd = dir.path().to_path_buf();
+
+    for (tool, arguments) in [
+        ("exec", json!({ "command": "cd", "args": ["no-such-dir"] })),
+        ("list_files", json!({ "path": "no-such-dir" })),
+        ("file_info", json!({ "path": "no-such-dir" })),
+    ] {
+        let result = agent
+            .execute_tool_call(&mut session, tool, &arguments.to_string())
+            .await
+            .unwrap();
+        assert!(!result.success, "{tool}");
+        let error: Value = serde_json::from_str(&result.output).unwrap();
+        assert_eq!(error["kind"], "not_found", "{tool}");
+        assert!(
+            error["message"].as_str().unwrap().contains("no-such-dir"),
+            "{tool}: {}",
+            result.output
+        );
+    }
+}
+
+// Capping tool output before it enters the conversation history
+
+#[test]
+fn test_cap_keeps_short_output_and_respects_char_boundaries() {
+    assert_eq!(cap_tool_output("short".to_string(), 10), "short");
+
+    // "é" is two bytes; a 3-byte cap must not split the second one
+    let capped = cap_tool_output("éé".repeat(4), 3);
+    assert!(capped.starts_with("é\n"), "{}", capped);
+    assert!(capped.contains("共 16 字节"), "{}", capped);
+}
+
+#[tokio::test]
+async fn test_injected_tool_message_is_capped() {
+    let dir = TempDir::new().unwrap();
+    let big = "x".repeat(10_000);
+    std::fs::write(dir.path().join("big.txt"), &big).unwrap();
+    let llm = MockLlm::start(vec![
+        Turn::tool("read_file", json!({ "path": "big.txt" })),
+        Turn::text(&["ok"]),
+    ])
+    .await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.agent.max_tool_result_bytes = 100;
+    let agent = Agent::new(config).await.unwrap();
+
+    let mut session = Session::new("cap".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let (progress, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    agent
+        .process_message_with_progress(&mut session, "read big.txt", &progress)
+        .await
+        .unwrap();
+
+    let tool_message = session
+        .messages
+        .iter()
+        .find(|m| m.role == "tool")
+        .and_then(|m| m.content.clone())
+        .unwrap();
+    assert!(tool_message.starts_with(&"x".repeat(100)));
+    assert!(tool_message.len() < 200, "{} bytes", tool_message.len());
+    assert!(tool_message.contains("输出已截断"));
+
+    // The follow-up request carries the capped text, not the full file
+    let follow_up = &llm.requests()[1];
+    let sent = follow_up["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["role"] == "tool")
+        .unwrap()["content"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(sent, tool_message);
+
+    // Progress consumers still get the full output
+    let mut full = None;
+    while let Ok(event) = rx.try_recv() {
+        if let AgentProgress::ToolFinished { output, .. } = event {
+            full = Some(output);
+        }
+    }
+    assert_eq!(full.unwrap(), big);
+}
+
+// Structured report of a non-interactive run
+
+#[tokio::test]
+async fn test_report_serializes_response_tool_calls_and_usage() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("note.txt"), "hello").unwrap();
+    let llm = MockLlm::start(vec![
+        Turn::tool("read_file", json!({ "path": "note.txt" })),
+        Turn::text(&["The file ", "says hello."]),
+    ])
+    .await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+    let mut session = Session::new("scripted".to_string());
+    session.cwd = dir.path().to_path_buf();
+
+    let report = agent
+        .process_message_report(&mut session, "what does note.txt say?")
+        .await
+        .unwrap();
+    let value: Value = serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+
+    assert_eq!(value["response"], "The file says hello.");
+    assert_eq!(value["session_id"], "scripted");
+    assert_eq!(
+        value["tool_calls"],
+        json!([{
+            "name": "read_file",
+            "arguments": { "path": "note.txt" },
+            "success": true,
+            "output": "hello"
+        }])
+    );
+    for field in ["prompt_tokens", "completion_tokens", "total_tokens"] {
+        assert!(value["usage"][field].is_u64(), "usage.{} missing", field);
+    }
+}
+
+#[tokio::test]
+async fn test_repeated_tool_results_stay_with_their_calls() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "alpha").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "beta").unwrap();
+    let llm = MockLlm::start(vec![
+        Turn::ToolCalls(vec![
+            ("read_file", json!({ "path": "a.txt" }).to_string()),
+            ("read_file", json!({ "path": "b.txt" }).to_string()),
+        ]),
+        Turn::text(&["Done."]),
+    ])
+    .await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+    let mut session = Session::new("scripted".to_string());
+    session.cwd = dir.path().to_path_buf();
+
+    let report = agent
+        .process_message_report(&mut session, "read both")
+        .await
+        .unwrap();
+    let outputs: Vec<(String, String)> = report
+        .tool_calls
+        .iter()
+        .map(|call| {
+            (
+                call.arguments["path"].as_str().unwrap().to_string(),
+                call.output.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        outputs,
+        vec![
+            ("a.txt".to_string(), "alpha".to_string()),
+            ("b.txt".to_string(), "beta".to_string()),
+        ]
+    );
+}
+
+// Agent-side trigger checks for channel messages: cooldown and own messages
+
+#[tokio::test]
+async fn test_channel_cooldown_suppresses_second_trigger() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["hi"]), Turn::text(&["again"])]).await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.agent.triggers.mode = TriggerMode::Always;
+    config.agent.triggers.cooldown_seconds = 60;
+    let agent = Agent::new(config).await.unwrap();
+
+    let first = agent
+        .process_channel_message("discord", "42", "hello", &[])
+        .await
+        .unwrap();
+    let second = agent
+        .process_channel_message("discord", "42", "hello?", &[])
+        .await
+        .unwrap();
+
+    assert_eq!(first, "hi");
+    assert_eq!(second, "");
+    assert_eq!(llm.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_own_messages_never_trigger() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["unused"])]).await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.agent.triggers.mode = TriggerMode::Always;
+    let agent = Agent::new(config).await.unwrap();
+
+    let addressing = MessageAddressing {
+        sender_id: Some("999".to_string()),
+        bot_user_id: Some("999".to_string()),
+        ..Default::default()
+    };
+    let reply = agent
+        .process_channel_message_with_addressing("discord", "42", "my reply", &[], &addressing)
+        .await
+        .unwrap();
+
+    assert_eq!(reply, "");
+    assert!(llm.requests().is_empty());
+}
+
+// Slash-commands at the interactive prompt and the per-session model override
+
+#[test]
+fn test_plain_messages_are_not_commands() {
+    assert_eq!(parse_interactive_command("hello there"), None);
+    assert_eq!(parse_interactive_command("model gpt-4o"), None);
+    assert_eq!(parse_interactive_command("exit now"), None);
+}
+
+#[test]
+fn test_builtin_commands_with_and_without_slash() {
+    for input in ["exit", "quit", "/exit", " /quit "] {
+        assert_eq!(
+            parse_interactive_command(input),
+            Some(InteractiveCommand::Exit)
+        );
+    }
+    assert_eq!(
+        parse_interactive_command("clear"),
+        Some(InteractiveCommand::Clear)
+    );
+    assert_eq!(
+        parse_interactive_command("/help"),
+        Some(InteractiveCommand::Help)
+    );
+}
+
+#[test]
+fn test_model_command() {
+    assert_eq!(
+        parse_interactive_command("/model"),
+        Some(InteractiveCommand::Model(None))
+    );
+    assert_eq!(
+        parse_interactive_command("/model   gpt-4o-mini "),
+        Some(InteractiveCommand::Model(Some("gpt-4o-mini".to_string())))
+    );
+}
+
+#[test]
+fn test_system_tokens_and_unknown() {
+    assert_eq!(
+        parse_interactive_command("/system"),
+        Some(InteractiveCommand::System)
+    );
+    assert_eq!(
+        parse_interactive_command("/tokens"),
+        Some(InteractiveCommand::Tokens)
+    );
+    assert_eq!(
+        parse_interactive_command("/tokens all"),
+        Some(InteractiveCommand::Unknown("/tokens all".to_string()))
+    );
+    assert_eq!(
+        parse_interactive_command("/nope"),
+        Some(InteractiveCommand::Unknown("/nope".to_string()))
+    );
+}
+
+#[test]
+fn test_pwd_and_cd_commands() {
+    assert_eq!(
+        parse_interactive_command("/pwd"),
+        Some(InteractiveCommand::Pwd)
+    );
+    assert_eq!(
+        parse_interactive_command("/cd"),
+        Some(InteractiveCommand::Cd(None))
+    );
+    assert_eq!(
+        parse_interactive_command("/cd  src/my dir "),
+        Some(InteractiveCommand::Cd(Some("src/my dir".to_string())))
+    );
+    // Without the slash these are messages for the agent.
+    assert_eq!(parse_interactive_command("cd src"), None);
+}
+
+#[tokio::test]
+async fn test_model_override_is_used_for_later_turns() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["one"]), Turn::text(&["two"])]).await;
+    let config = test_config(dir.path(), &llm.endpoint);
+    let primary = config.llm.primary.clone();
+    let agent = Agent::new(config).await.unwrap();
+
+    let mut session = Session::new("interactive".to_string());
+    agent.process_message(&mut session, "first").await.unwrap();
+    session.overrides.model = Some("switched-model".to_string());
+    agent.process_message(&mut session, "second").await.unwrap();
+
+    let requests = llm.requests();
+    assert_eq!(requests[0]["model"], primary.as_str());
+    assert_eq!(requests[1]["model"], "switched-model");
+}
+
+#[tokio::test]
+async fn test_system_prompt_includes_session_override() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![]).await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+
+    let mut session = Session::new("interactive".to_string());
+    session.overrides.system_prompt = Some("You are terse.".to_string());
+    let prompt = agent.system_prompt_for(&session, "").await;
+    assert!(prompt.starts_with("You are terse."));
+}
+
+#[tokio::test]
+async fn test_system_prompt_names_the_agent_and_its_tools() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![]).await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.agent.name = "Cogsworth".to_string();
+    let agent = Agent::new(config).await.unwrap();
+
+    let prompt = agent
+        .system_prompt_for(&Session::new("interactive".to_string()), "")
+        .await;
+    assert!(prompt.contains("名叫 Cogsworth"), "{}", prompt);
+    assert!(!prompt.contains("GearClaw"), "{}", prompt);
+    assert!(!prompt.contains("{tools}"));
+    assert!(prompt.contains("read_file"), "{}", prompt);
+}
+
+#[tokio::test]
+async fn test_system_prompt_leaves_out_tools_security_refuses() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![]).await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.tools.security = "deny".to_string();
+    let agent = Agent::new(config).await.unwrap();
+
+    let prompt = agent
+        .system_prompt_for(&Session::new("interactive".to_string()), "")
+        .await;
+    assert!(prompt.contains("read_file"), "{}", prompt);
+    for refused in ["exec", "write_file", "web_search"] {
+        assert!(!prompt.contains(refused), "{}", prompt);
+    }
+}
+
+#[test]
+fn test_render_system_prompt_placeholders() {
+    let tools = vec!["read_file".to_string(), "exec".to_string()];
+    assert_eq!(
+        render_system_prompt("I am {agent_name}. Tools: {tools}", "Bolt", &tools),
+        "I am Bolt. Tools: read_file, exec"
+    );
+    assert_eq!(
+        render_system_prompt("Tools: {tools}", "Bolt", &[]),
+        "Tools: （无）"
+    );
+    assert_eq!(
+        render_system_prompt("You are terse.", "Bolt", &tools),
+        "You are terse."
+    );
+}
+
+// Persisting the interactive prompt's line history between runs
+
+#[test]
+fn test_history_round_trips_through_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("nested/history");
+
+    let mut rl = interactive_editor(100).unwrap();
+    rl.add_history_entry("first").unwrap();
+    rl.add_history_entry("second").unwrap();
+    save_interactive_history(&mut rl, &path).unwrap();
+
+    let mut restored = interactive_editor(100).unwrap();
+    load_interactive_history(&mut restored, &path);
+    let entries: Vec<_> = restored.history().iter().cloned().collect();
+    assert_eq!(entries, vec!["first", "second"]);
+}
+
+#[test]
+fn test_history_is_bounded_to_max_entries() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("history");
+
+    let mut rl = interactive_editor(100).unwrap();
+    for i in 0..10 {
+        rl.add_history_entry(format!("line {}", i)).unwrap();
+    }
+    save_interactive_history(&mut rl, &path).unwrap();
+
+    let mut restored = interactive_editor(3).unwrap();
+    load_interactive_history(&mut restored, &path);
+    let entries: Vec<_> = restored.history().iter().cloned().collect();
+    assert_eq!(entries, vec!["line 7", "line 8", "line 9"]);
+}
+
+#[test]
+fn test_missing_and_corrupt_history_files_are_ignored() {
+    let dir = TempDir::new().unwrap();
+
+    let mut rl = interactive_editor(100).unwrap();
+    load_interactive_history(&mut rl, &dir.path().join("missing"));
+    assert_eq!(rl.history().len(), 0);
+
+    let corrupt = dir.path().join("corrupt");
+    std::fs::write(&corrupt, [0xff, 0xfe, 0x00, 0x9f]).unwrap();
+    load_interactive_history(&mut rl, &corrupt);
+    rl.add_history_entry("still works").unwrap();
+    assert_eq!(rl.history().len(), 1);
+}
@@ -0,0 +1,195 @@
+//! Scripted OpenAI-compatible streaming endpoint, so the agent loop can be
+//! driven end to end without touching the network.
+#![allow(dead_code)]
+
+use gearclaw_core::Config;
+use serde_json::json;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// One assistant turn streamed back by the mock endpoint
+#[derive(Debug, Clone)]
+pub enum Turn {
+    /// Stream text chunks, then finish
+    Text(Vec<&'static str>),
+    /// Request tool calls (`name`, JSON `arguments`)
+    ToolCalls(Vec<(&'static str, String)>),
+    /// Stream text chunks, then go silent without finishing
+    Stalled(Vec<&'static str>),
+    /// Stream text chunks with an unparseable event after the first one
+    Garbled(Vec<&'static str>),
+}
+
+impl Turn {
+    pub fn text(chunks: &[&'static str]) -> Self {
+        Turn::Text(chunks.to_vec())
+    }
+
+    pub fn tool(name: &'static str, arguments: serde_json::Value) -> Self {
+        Turn::ToolCalls(vec![(name, arguments.to_string())])
+    }
+
+    /// Stream chunks for this turn, as the endpoint would send them
+    pub fn chunks(&self, request_no: usize) -> Vec<serde_json::Value> {
+        let choices: Vec<serde_json::Value> = match self {
+            Turn::Text(chunks) | Turn::Stalled(chunks) | Turn::Garbled(chunks) => chunks
+                .iter()
+                .map(|chunk| json!({ "index": 0, "delta": { "content": chunk } }))
+                .collect(),
+            Turn::ToolCalls(calls) => calls
+                .iter()
+                .enumerate()
+                .map(|(i, (name, arguments))| {
+                    json!({ "index": 0, "delta": { "tool_calls": [{
+                        "index": i,
+                        "id": format!("call_{}_{}", request_no, i),
+                        "type": "function",
+                        "function": { "name": name, "arguments": arguments }
+                    }] } })
+                })
+                .collect(),
+        };
+        choices
+            .into_iter()
+            .map(|choice| json!({ "id": "chatcmpl-test", "choices": [choice] }))
+            .collect()
+    }
+
+    fn sse_body(&self, request_no: usize) -> String {
+        let mut body = String::new();
+        for (i, event) in self.chunks(request_no).into_iter().enumerate() {
+            body.push_str(&format!("data: {}\n\n", event));
+            if i == 0 && matches!(self, Turn::Garbled(_)) {
+                body.push_str("data: {\"type\": \"ping\"\n\n");
+            }
+        }
+        if !matches!(self, Turn::Stalled(_)) {
+            body.push_str("data: [DONE]\n\n");
+        }
+        body
+    }
+}
+
+pub struct MockLlm {
+    pub endpoint: String,
+    requests: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl MockLlm {
+    /// Answer the n-th chat request with `turns[n]`; the last turn repeats.
+    /// Embedding requests get [`embed`] of their input.
+    pub async fn start(turns: Vec<Turn>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        tokio::spawn(async move {
+            let mut served = 0usize;
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let body = read_request_body(&mut stream).await;
+                let request: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+                // Embedding requests are answered without using up a turn.
+                if let Some(input) = request["input"].as_str() {
+                    let payload = json!({ "data": [{ "embedding": embed(input) }] }).to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        payload.len(),
+                        payload
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                    continue;
+                }
+                recorded.lock().unwrap().push(request);
+
+                let turn = &turns[served.min(turns.len() - 1)];
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}",
+                    turn.sse_body(served)
+                );
+                served += 1;
+                let _ = stream.write_all(response.as_bytes()).await;
+                if matches!(turn, Turn::Stalled(_)) {
+                    // Hold the connection open with nothing more to send.
+                    tokio::spawn(async move {
+                        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                        drop(stream);
+                    });
+                    continue;
+                }
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        Self { endpoint, requests }
+    }
+
+    /// JSON bodies of the requests received so far
+    pub fn requests(&self) -> Vec<serde_json::Value> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Bag-of-words embedding: texts sharing words score closer
+pub fn embed(text: &str) -> Vec<f32> {
+    const DIMENSIONS: usize = 64;
+    let mut vector = vec![0.0f32; DIMENSIONS];
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() > 2)
+    {
+        let bucket = word
+            .to_lowercase()
+            .bytes()
+            .fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+        vector[bucket % DIMENSIONS] += 1.0;
+    }
+    vector
+}
+
+async fn read_request_body(stream: &mut tokio::net::TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).await.unwrap_or(0);
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(split) = text.find("\r\n\r\n") {
+            let content_length = text[..split]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if request.len() >= split + 4 + content_length {
+                return text[split + 4..].to_string();
+            }
+        }
+    }
+    String::new()
+}
+
+/// Sample config pointed at `endpoint`, with all state under `dir` and
+/// memory disabled
+pub fn test_config(dir: &Path, endpoint: &str) -> Config {
+    let mut config = Config::sample();
+    config.llm.api_key = Some("test-key".to_string());
+    config.llm.endpoint = endpoint.to_string();
+    config.llm.fallbacks.clear();
+    config.llm.retry.max_attempts = 1;
+    config.agent.memory_enabled = false;
+    config.agent.workspace = dir.join("workspace");
+    config.agent.skills_path = dir.join("skills");
+    config.memory.enabled = false;
+    config.memory.db_path = dir.join("memory.sqlite");
+    config.session.session_dir = dir.join("sessions");
+    config
+}
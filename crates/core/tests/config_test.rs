@@ -0,0 +1,515 @@
+// Loading, layering, and validating the configuration
+
+use gearclaw_core::config::{expand_env, ChannelOverride, Config, ConfigLoader, McpServerConfig};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+// `${VAR}` / `${VAR:-default}` interpolation in config values
+
+fn lookup(name: &str) -> Option<String> {
+    match name {
+        "API_KEY" => Some("sk-test".to_string()),
+        "HOST" => Some("example.com".to_string()),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_set_variable_is_expanded() {
+    assert_eq!(expand_env("${API_KEY}", lookup).unwrap(), "sk-test");
+    assert_eq!(
+        expand_env("https://${HOST}/v1?k=${API_KEY}", lookup).unwrap(),
+        "https://example.com/v1?k=sk-test"
+    );
+    // A set variable wins over the default
+    assert_eq!(
+        expand_env("${HOST:-localhost}", lookup).unwrap(),
+        "example.com"
+    );
+}
+
+#[test]
+fn test_unset_variable_uses_default() {
+    assert_eq!(
+        expand_env("${MISSING:-fallback}", lookup).unwrap(),
+        "fallback"
+    );
+    assert_eq!(expand_env("${MISSING:-}", lookup).unwrap(), "");
+}
+
+#[test]
+fn test_unset_variable_without_default_is_an_error() {
+    let err = expand_env("key=${MISSING}", lookup).unwrap_err();
+    assert!(err.contains("MISSING"));
+}
+
+#[test]
+fn test_non_references_are_left_alone() {
+    for text in ["$HOME", "cost: $5", "${", "${not valid}", "${1X}"] {
+        assert_eq!(expand_env(text, lookup).unwrap(), text);
+    }
+}
+
+fn sample_with_api_key(api_key: &str) -> String {
+    let mut config = Config::sample();
+    config.llm.api_key = Some(api_key.to_string());
+    serde_yml::to_string(&config).unwrap()
+}
+
+#[test]
+fn test_load_expands_string_fields() {
+    std::env::set_var("GEARCLAW_TEST_ENV_API_KEY", "sk-from-env");
+    let config = ConfigLoader::parse(&sample_with_api_key("${GEARCLAW_TEST_ENV_API_KEY}")).unwrap();
+    assert_eq!(config.llm.api_key.as_deref(), Some("sk-from-env"));
+}
+
+#[test]
+fn test_load_reports_field_of_unset_variable() {
+    let err = ConfigLoader::parse(&sample_with_api_key("${GEARCLAW_TEST_ENV_UNSET}"))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("llm.api_key"), "{}", err);
+    assert!(err.contains("GEARCLAW_TEST_ENV_UNSET"), "{}", err);
+}
+
+#[test]
+fn test_saving_edits_keeps_env_references() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, sample_with_api_key("${GEARCLAW_TEST_SAVE_API_KEY}")).unwrap();
+    std::env::set_var("GEARCLAW_TEST_SAVE_API_KEY", "sk-secret");
+
+    let loaded = ConfigLoader::load(path.to_str()).unwrap();
+    let mut edited = loaded.clone();
+    edited.llm.primary = "openai/gpt-4o".to_string();
+    edited.save_edits(&loaded, &path).unwrap();
+
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(saved.contains("${GEARCLAW_TEST_SAVE_API_KEY}"), "{}", saved);
+    assert!(!saved.contains("sk-secret"), "{}", saved);
+    let reloaded = ConfigLoader::load(path.to_str()).unwrap();
+    assert_eq!(reloaded.llm.primary, "openai/gpt-4o");
+    assert_eq!(reloaded.llm.api_key.as_deref(), Some("sk-secret"));
+}
+
+// Deep-merging layered config files (base + local override)
+
+const BASE: &str = r#"
+llm:
+  primary: openai/gpt-4
+  fallbacks: [a, b]
+tools:
+  security: allowlist
+session: {}
+agent:
+  name: Team
+  triggers:
+    mode: mention
+    enabled_channels: ["discord:1"]
+mcp:
+  servers:
+    fs:
+      command: mcp-fs
+      args: ["/srv"]
+    git:
+      command: mcp-git
+"#;
+
+fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_nested_maps_merge_key_by_key() {
+    let dir = TempDir::new().unwrap();
+    let base = write(dir.path(), "gearclaw.toml", BASE);
+    let local = write(
+        dir.path(),
+        "gearclaw.local.toml",
+        r#"
+mcp:
+  servers:
+    fs:
+      args: ["/home/me"]
+    search:
+      command: mcp-search
+"#,
+    );
+
+    let config = Config::load_layered(&[base, local]).unwrap();
+    let servers = &config.mcp.servers;
+    assert_eq!(servers.len(), 3);
+    // Overridden key keeps its sibling fields from the base layer
+    assert_eq!(servers["fs"].command, "mcp-fs");
+    assert_eq!(servers["fs"].args, ["/home/me"]);
+    assert_eq!(servers["git"].command, "mcp-git");
+    assert_eq!(servers["search"].command, "mcp-search");
+}
+
+#[test]
+fn test_scalars_overwrite_and_unset_fields_are_kept() {
+    let dir = TempDir::new().unwrap();
+    let base = write(dir.path(), "gearclaw.toml", BASE);
+    let local = write(
+        dir.path(),
+        "gearclaw.local.toml",
+        "tools:\n  security: full\nagent:\n  triggers:\n    mode: always\n",
+    );
+
+    let config = Config::load_layered(&[base, local]).unwrap();
+    assert_eq!(config.tools.security, "full");
+    assert_eq!(config.llm.primary, "openai/gpt-4");
+    assert_eq!(config.agent.name, "Team");
+    assert_eq!(
+        config.agent.triggers.mode,
+        gearclaw_core::config::TriggerMode::Always
+    );
+}
+
+#[test]
+fn test_list_merge_semantics() {
+    let dir = TempDir::new().unwrap();
+    let base = write(dir.path(), "gearclaw.toml", BASE);
+    let local = write(
+        dir.path(),
+        "gearclaw.local.toml",
+        r#"
+llm:
+  fallbacks: [c]
+agent:
+  triggers:
+    enabled_channels: ["discord:1", "discord:2"]
+"#,
+    );
+
+    let config = Config::load_layered(&[base, local]).unwrap();
+    // Ordinary lists are replaced
+    assert_eq!(config.llm.fallbacks, ["c"]);
+    // Channel lists are appended without duplicates
+    assert_eq!(
+        config.agent.triggers.enabled_channels,
+        ["discord:1", "discord:2"]
+    );
+}
+
+#[test]
+fn test_load_picks_up_sibling_local_file() {
+    let dir = TempDir::new().unwrap();
+    let base = write(dir.path(), "gearclaw.toml", BASE);
+    let config = ConfigLoader::load(base.to_str()).unwrap();
+    assert_eq!(config.agent.name, "Team");
+
+    write(dir.path(), "gearclaw.local.toml", "agent:\n  name: Mine\n");
+    let config = ConfigLoader::load(base.to_str()).unwrap();
+    assert_eq!(config.agent.name, "Mine");
+}
+
+#[test]
+fn test_local_override_path() {
+    assert_eq!(
+        ConfigLoader::local_override_path(Path::new("/etc/gearclaw.toml")),
+        Path::new("/etc/gearclaw.local.toml")
+    );
+    assert_eq!(
+        ConfigLoader::local_override_path(Path::new("config")),
+        Path::new("config.local")
+    );
+}
+
+#[test]
+fn test_missing_layer_is_an_error() {
+    let dir = TempDir::new().unwrap();
+    let base = write(dir.path(), "gearclaw.toml", BASE);
+    let err = Config::load_layered(&[base, dir.path().join("nope.toml")]).unwrap_err();
+    assert!(err.to_string().contains("nope.toml"));
+}
+
+#[test]
+fn test_saving_edits_does_not_flatten_layers() {
+    let dir = TempDir::new().unwrap();
+    let base = write(dir.path(), "gearclaw.toml", BASE);
+    write(dir.path(), "gearclaw.local.toml", "agent:\n  name: Mine\n");
+
+    let loaded = ConfigLoader::load(base.to_str()).unwrap();
+    let mut edited = loaded.clone();
+    edited.llm.primary = "openai/gpt-4o".to_string();
+    edited.save_edits(&loaded, &base).unwrap();
+
+    let saved = std::fs::read_to_string(&base).unwrap();
+    assert!(!saved.contains("Mine"), "{}", saved);
+    let base_only = Config::load_layered(&[base]).unwrap();
+    assert_eq!(base_only.llm.primary, "openai/gpt-4o");
+    assert_eq!(base_only.agent.name, "Team");
+}
+
+// Semantic validation of a loaded configuration
+
+/// Sample config whose paths all exist, so it validates without warnings
+fn valid_config(dir: &TempDir) -> Config {
+    let mut config = Config::sample();
+    config.agent.skills_path = dir.path().to_path_buf();
+    config.agent.workspace = dir.path().to_path_buf();
+    for source in &mut config.agent.skill_sources {
+        source.location = dir.path().to_string_lossy().to_string();
+    }
+    config
+}
+
+fn warning_fields(config: &Config) -> Vec<String> {
+    config
+        .validate()
+        .expect("config should be valid")
+        .into_iter()
+        .map(|w| w.field)
+        .collect()
+}
+
+fn error_text(config: &Config) -> String {
+    config
+        .validate()
+        .expect_err("config should be invalid")
+        .to_string()
+}
+
+#[test]
+fn test_sample_with_existing_paths_is_clean() {
+    let dir = TempDir::new().unwrap();
+    assert!(warning_fields(&valid_config(&dir)).is_empty());
+}
+
+#[test]
+fn test_empty_primary_model_is_fatal() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.llm.primary.clear();
+    assert!(error_text(&config).contains("llm.primary"));
+}
+
+#[test]
+fn test_invalid_security_level_is_fatal() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.tools.security = "everything".to_string();
+    assert!(error_text(&config).contains("tools.security"));
+}
+
+#[test]
+fn test_invalid_channel_override_security_is_fatal() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.agent.channel_overrides.insert(
+        "discord:1".to_string(),
+        ChannelOverride {
+            security: Some("open".to_string()),
+            ..Default::default()
+        },
+    );
+    assert!(error_text(&config).contains("agent.channel_overrides.discord:1.security"));
+}
+
+#[test]
+fn test_malformed_endpoint_is_fatal() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.llm.endpoint = "api.openai.com/v1".to_string();
+    assert!(error_text(&config).contains("llm.endpoint"));
+
+    config.llm.endpoint = "ftp://example.com".to_string();
+    assert!(error_text(&config).contains("Unsupported scheme"));
+}
+
+#[test]
+fn test_plain_http_endpoint_warns_unless_local() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.llm.endpoint = "http://llm.example.com/v1".to_string();
+    assert_eq!(warning_fields(&config), ["llm.endpoint"]);
+
+    config.llm.endpoint = "http://localhost:11434/v1".to_string();
+    assert!(warning_fields(&config).is_empty());
+}
+
+#[test]
+fn test_missing_paths_warn() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.agent.skills_path = dir.path().join("missing-skills");
+    config.agent.workspace = dir.path().join("missing-workspace");
+    assert_eq!(
+        warning_fields(&config),
+        ["agent.skills_path", "agent.workspace"]
+    );
+}
+
+#[test]
+fn test_missing_local_skill_source_warns_only_when_enabled() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    let missing = dir.path().join("missing").to_string_lossy().to_string();
+    config.agent.skill_sources[0].location = missing.clone();
+    assert_eq!(
+        warning_fields(&config),
+        ["agent.skill_sources.local-default.location"]
+    );
+
+    config.agent.skill_sources[0].enabled = false;
+    assert!(warning_fields(&config).is_empty());
+}
+
+#[test]
+fn test_unknown_tool_host_and_profile_warn() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.tools.host = "cloud".to_string();
+    config.tools.profile = "everything".to_string();
+    assert_eq!(warning_fields(&config), ["tools.host", "tools.profile"]);
+}
+
+#[test]
+fn test_unknown_search_provider_is_fatal() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.tools.web_search.provider = "searxng".to_string();
+    assert!(warning_fields(&config).is_empty());
+    config.tools.web_search.provider = "altavista".to_string();
+    assert!(error_text(&config).contains("tools.web_search.provider"));
+}
+
+#[test]
+fn test_sampling_parameters_out_of_range_warn() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.llm.temperature = Some(3.5);
+    config.llm.top_p = Some(1.5);
+    assert_eq!(warning_fields(&config), ["llm.temperature", "llm.top_p"]);
+}
+
+#[test]
+fn test_empty_mcp_command_is_fatal() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.mcp.servers = HashMap::from([(
+        "fs".to_string(),
+        McpServerConfig {
+            command: " ".to_string(),
+            args: vec![],
+            env: HashMap::new(),
+            timeout_secs: 30,
+        },
+    )]);
+    assert!(error_text(&config).contains("mcp.servers.fs.command"));
+}
+
+#[test]
+fn test_unauthenticated_gateway_warns() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.gateway.allow_unauthenticated_requests = true;
+    assert_eq!(
+        warning_fields(&config),
+        ["gateway.allow_unauthenticated_requests"]
+    );
+}
+
+#[test]
+fn test_tls_requires_existing_cert_and_key() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.gateway.tls_enabled = true;
+    assert!(error_text(&config).contains("gateway.tls_cert_path"));
+
+    let cert = dir.path().join("cert.pem");
+    std::fs::write(&cert, "cert").unwrap();
+    config.gateway.tls_cert_path = Some(cert);
+    config.gateway.tls_key_path = Some(dir.path().join("key.pem"));
+    assert!(error_text(&config).contains("gateway.tls_key_path"));
+
+    std::fs::write(dir.path().join("key.pem"), "key").unwrap();
+    assert!(warning_fields(&config).is_empty());
+}
+
+#[test]
+fn test_zero_max_tool_turns_is_fatal() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.agent.max_tool_turns = 0;
+    assert!(error_text(&config).contains("agent.max_tool_turns"));
+}
+
+#[test]
+fn test_skill_source_subdir_must_stay_inside_source() {
+    let dir = TempDir::new().unwrap();
+    let mut config = valid_config(&dir);
+    config.agent.skill_sources[1].subdir = Some("skills/official".to_string());
+    assert!(config.validate().is_ok());
+
+    for bad in ["../skills", "/etc", ""] {
+        config.agent.skill_sources[1].subdir = Some(bad.to_string());
+        assert!(error_text(&config).contains("agent.skill_sources.community-git.subdir"));
+    }
+}
+
+// Per-channel agent configuration overrides
+
+fn config_with_overrides() -> Config {
+    let mut config = Config::sample();
+    config.agent.system_prompt = "global prompt".to_string();
+    config.agent.memory_enabled = true;
+    config.tools.security = "full".to_string();
+    config.agent.channel_overrides.clear();
+    config.agent.channel_overrides.insert(
+        "discord:public".to_string(),
+        ChannelOverride {
+            system_prompt: Some("public prompt".to_string()),
+            security: Some("deny".to_string()),
+            memory_enabled: Some(false),
+        },
+    );
+    config.agent.channel_overrides.insert(
+        "discord:ops".to_string(),
+        ChannelOverride {
+            system_prompt: Some("ops prompt".to_string()),
+            ..Default::default()
+        },
+    );
+    config
+}
+
+#[test]
+fn test_channels_resolve_their_own_prompts() {
+    let config = config_with_overrides();
+
+    let public = config.channel_settings("discord", "public");
+    let ops = config.channel_settings("discord", "ops");
+
+    assert_eq!(public.system_prompt.as_deref(), Some("public prompt"));
+    assert_eq!(ops.system_prompt.as_deref(), Some("ops prompt"));
+    assert_eq!(public.security.as_deref(), Some("deny"));
+    assert_eq!(public.memory_enabled, Some(false));
+}
+
+#[test]
+fn test_unset_fields_and_unknown_channels_fall_back_to_global() {
+    let config = config_with_overrides();
+
+    let ops = config.channel_settings("discord", "ops");
+    assert_eq!(ops.security.as_deref(), Some("full"));
+    assert_eq!(ops.memory_enabled, Some(true));
+
+    // Keys are platform-qualified.
+    let other = config.channel_settings("telegram", "ops");
+    assert_eq!(other.system_prompt.as_deref(), Some("global prompt"));
+}
+
+#[test]
+fn test_channel_overrides_survive_yaml_round_trip() {
+    let yaml = serde_yml::to_string(&config_with_overrides()).unwrap();
+    let config: Config = serde_yml::from_str(&yaml).unwrap();
+
+    let public = config.channel_settings("discord", "public");
+    assert_eq!(public.system_prompt.as_deref(), Some("public prompt"));
+    assert_eq!(public.security.as_deref(), Some("deny"));
+}
@@ -0,0 +1,134 @@
+// Structured GearClawError values built from lower-level errors
+
+use gearclaw_core::error::{DomainError, GearClawError, InfraError, LlmErrorKind};
+use gearclaw_llm::LlmError;
+
+// Mapping gearclaw_llm::LlmError into structured GearClawError values
+
+fn status_error(status: u16) -> GearClawError {
+    GearClawError::from(LlmError::Status {
+        status,
+        message: "body".to_string(),
+    })
+}
+
+#[test]
+fn test_status_errors_keep_status_and_kind() {
+    for (status, kind) in [
+        (401, LlmErrorKind::Auth),
+        (403, LlmErrorKind::Auth),
+        (404, LlmErrorKind::ModelNotFound),
+        (429, LlmErrorKind::RateLimited),
+        (400, LlmErrorKind::InvalidRequest),
+        (422, LlmErrorKind::InvalidRequest),
+        (500, LlmErrorKind::Server),
+        (503, LlmErrorKind::Server),
+        (302, LlmErrorKind::Other),
+    ] {
+        let err = status_error(status);
+        assert_eq!(err.llm_status(), Some(status));
+        assert_eq!(err.llm_kind(), Some(kind), "status {}", status);
+    }
+}
+
+#[test]
+fn test_status_error_message() {
+    let err = status_error(429);
+    assert_eq!(err.to_string(), "LLM rate limit error (HTTP 429): body");
+    assert!(matches!(
+        err,
+        GearClawError::Domain(DomainError::Llm {
+            status: Some(429),
+            kind: LlmErrorKind::RateLimited,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_request_error_is_network() {
+    let err = GearClawError::from(LlmError::Request("connection refused".to_string()));
+    assert_eq!(err.llm_kind(), Some(LlmErrorKind::Network));
+    assert_eq!(err.llm_status(), None);
+    assert_eq!(err.to_string(), "LLM network error: connection refused");
+}
+
+#[test]
+fn test_response_and_json_errors_are_invalid_response() {
+    let err = GearClawError::from(LlmError::Response("no embedding returned".to_string()));
+    assert_eq!(err.llm_kind(), Some(LlmErrorKind::InvalidResponse));
+    assert_eq!(err.llm_status(), None);
+
+    let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+    let err = GearClawError::from(LlmError::Json(json));
+    assert_eq!(err.llm_kind(), Some(LlmErrorKind::InvalidResponse));
+}
+
+#[test]
+fn test_non_llm_errors_have_no_llm_kind() {
+    let err = GearClawError::ToolNotFound("x".to_string());
+    assert_eq!(err.llm_kind(), None);
+    assert_eq!(err.llm_status(), None);
+}
+
+// GearClawError::is_retryable classification
+
+fn llm_status(status: u16) -> GearClawError {
+    GearClawError::from(LlmError::Status {
+        status,
+        message: "body".to_string(),
+    })
+}
+
+#[test]
+fn test_transient_llm_errors_are_retryable() {
+    assert!(llm_status(429).is_retryable());
+    assert!(llm_status(500).is_retryable());
+    assert!(llm_status(503).is_retryable());
+    assert!(GearClawError::from(LlmError::Request("connection reset".to_string())).is_retryable());
+    assert!(GearClawError::LLMError("timeout".to_string()).is_retryable());
+}
+
+#[test]
+fn test_permanent_llm_errors_are_not_retryable() {
+    assert!(!llm_status(400).is_retryable());
+    assert!(!llm_status(401).is_retryable());
+    assert!(!llm_status(404).is_retryable());
+    assert!(!GearClawError::from(LlmError::Response("empty".to_string())).is_retryable());
+}
+
+#[test]
+fn test_network_and_timeout_io_errors_are_retryable() {
+    let network = GearClawError::from(InfraError::Network {
+        url: "http://localhost".to_string(),
+        reason: "refused".to_string(),
+    });
+    assert!(network.is_retryable());
+
+    for kind in [
+        std::io::ErrorKind::TimedOut,
+        std::io::ErrorKind::ConnectionReset,
+        std::io::ErrorKind::ConnectionRefused,
+    ] {
+        assert!(GearClawError::from(std::io::Error::from(kind)).is_retryable());
+    }
+    assert!(
+        !GearClawError::from(std::io::Error::from(std::io::ErrorKind::NotFound)).is_retryable()
+    );
+}
+
+#[test]
+fn test_config_validation_and_tool_errors_are_not_retryable() {
+    assert!(!GearClawError::config_not_found("/tmp/config.yaml").is_retryable());
+    assert!(!GearClawError::config_parse_error("bad yaml").is_retryable());
+    assert!(!GearClawError::from(DomainError::ConfigInvalid {
+        field: "llm.endpoint".to_string(),
+        reason: "empty".to_string(),
+    })
+    .is_retryable());
+    assert!(!GearClawError::ToolNotFound("missing".to_string()).is_retryable());
+    assert!(!GearClawError::ToolExecutionError("exit 1".to_string()).is_retryable());
+    let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+    assert!(!GearClawError::from(json).is_retryable());
+    assert!(!GearClawError::Other("unexpected".to_string()).is_retryable());
+}
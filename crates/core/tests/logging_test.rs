@@ -0,0 +1,82 @@
+// JSON log format
+
+use gearclaw_core::logging::{fmt_layer, LogFormat};
+use serde_json::Value;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+
+/// Writer collecting log output in memory
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Captured {
+    type Writer = Captured;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+fn capture(format: LogFormat, emit: impl FnOnce()) -> String {
+    let captured = Captured::default();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::new("info"))
+        .with(fmt_layer(format, captured.clone()));
+    tracing::subscriber::with_default(subscriber, emit);
+    let bytes = captured.0.lock().unwrap().clone();
+    String::from_utf8(bytes).unwrap()
+}
+
+#[test]
+fn test_json_lines_carry_documented_fields() {
+    let output = capture(LogFormat::Json, || {
+        let span = tracing::info_span!("connection");
+        let _entered = span.enter();
+        tracing::info!(
+            port = 18789u64,
+            secure = false,
+            "listening on {}",
+            "127.0.0.1"
+        );
+        tracing::debug!("filtered out");
+    });
+
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", output);
+    let line: Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["target"], "logging_test");
+    assert_eq!(line["fields"]["message"], "listening on 127.0.0.1");
+    assert_eq!(line["fields"]["port"], 18789);
+    assert_eq!(line["fields"]["secure"], false);
+    assert_eq!(line["spans"][0]["name"], "connection");
+    assert!(line["timestamp"].as_str().unwrap().ends_with('Z'));
+}
+
+#[test]
+fn test_text_format_is_not_json() {
+    let output = capture(LogFormat::Text, || tracing::info!("hello"));
+    assert!(output.contains("hello"));
+    assert!(serde_json::from_str::<Value>(output.trim()).is_err());
+}
+
+#[test]
+fn test_flag_wins_and_unknown_format_is_rejected() {
+    assert_eq!(LogFormat::resolve(Some("json")).unwrap(), LogFormat::Json);
+    assert_eq!(LogFormat::resolve(Some("TEXT")).unwrap(), LogFormat::Text);
+    let err = LogFormat::resolve(Some("xml")).unwrap_err().to_string();
+    assert!(err.contains("log_format"), "{}", err);
+}
@@ -0,0 +1,207 @@
+// macOS tools and the AppleScript executor behind them
+//
+// The tools stay in the catalog everywhere but fail with a clear error when
+// the host is not macOS. A fake `osascript` on PATH stands in for the real
+// one so the executor's timeout and error handling can be exercised on any
+// Unix host.
+
+use gearclaw_core::macos::MacosController;
+use gearclaw_core::tools::ToolExecutor;
+use serde_json::json;
+
+#[test]
+fn test_macos_tools_are_listed_on_every_platform() {
+    let tools = ToolExecutor::new("full").available_tools();
+    let launch = tools
+        .iter()
+        .find(|t| t.name == "macos_launch_app")
+        .expect("macos_launch_app should be listed");
+    assert!(launch.requires_args);
+    assert!(
+        !tools
+            .iter()
+            .find(|t| t.name == "macos_clipboard_read")
+            .unwrap()
+            .requires_args
+    );
+
+    if !MacosController::is_supported() {
+        assert!(launch.description.contains("需要 macOS"));
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+#[tokio::test]
+async fn test_macos_tools_fail_clearly_off_macos() {
+    let controller = MacosController::new().unwrap();
+    let err = controller
+        .execute_tool("macos_launch_app", &json!({ "app_name": "Safari" }))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("macos_* tools require macOS"));
+}
+
+#[test]
+fn test_screenshot_tool_is_registered() {
+    let tools = ToolExecutor::new("full").available_tools();
+    let screenshot = tools
+        .iter()
+        .find(|t| t.name == "macos_screenshot")
+        .expect("macos_screenshot should be listed");
+    assert!(!screenshot.requires_args);
+    let properties = &screenshot.parameters.as_ref().unwrap()["properties"];
+    assert!(properties["region"].is_object());
+    assert!(properties["window_id"].is_object());
+}
+
+#[test]
+fn test_screenshot_capture_args() {
+    use gearclaw_core::macos::{CaptureArea, ScreenCapture};
+
+    assert_eq!(
+        ScreenCapture::capture_args(CaptureArea::FullScreen),
+        ["-x", "-t", "png"]
+    );
+    assert_eq!(
+        ScreenCapture::capture_args(CaptureArea::Region {
+            x: 10,
+            y: 20,
+            width: 300,
+            height: 200
+        }),
+        ["-x", "-t", "png", "-R", "10,20,300,200"]
+    );
+    assert_eq!(
+        ScreenCapture::capture_args(CaptureArea::Window(42)),
+        ["-x", "-t", "png", "-o", "-l", "42"]
+    );
+}
+
+#[test]
+fn test_window_tools_are_registered() {
+    let tools = ToolExecutor::new("full").available_tools();
+    let list = tools.iter().find(|t| t.name == "macos_list_windows");
+    let focus = tools.iter().find(|t| t.name == "macos_focus_window");
+    assert!(
+        !list
+            .expect("macos_list_windows should be listed")
+            .requires_args
+    );
+    assert!(
+        focus
+            .expect("macos_focus_window should be listed")
+            .requires_args
+    );
+}
+
+#[test]
+fn test_parse_window_list() {
+    use gearclaw_core::macos::{WindowInfo, WindowManager};
+
+    assert!(WindowManager::parse_list("").unwrap().is_empty());
+    assert!(WindowManager::parse_list("[]\n").unwrap().is_empty());
+
+    let windows = WindowManager::parse_list(
+        r#"[{"app":"Safari","title":"Rust","x":0,"y":25,"width":1280,"height":775}]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        windows,
+        vec![WindowInfo {
+            app: "Safari".to_string(),
+            title: "Rust".to_string(),
+            x: 0,
+            y: 25,
+            width: 1280,
+            height: 775,
+        }]
+    );
+
+    assert!(WindowManager::parse_list("not json").is_err());
+}
+
+// AppleScript execution limits
+
+#[cfg(unix)]
+mod applescript {
+    use gearclaw_core::macos::AppleScriptExecutor;
+    use std::sync::OnceLock;
+    use std::time::{Duration, Instant};
+
+    const FAKE_OSASCRIPT: &str = r#"#!/bin/sh
+# The script text is the argument after -e.
+while [ "$1" != "-e" ] && [ $# -gt 0 ]; do shift; done
+case "$2" in
+  sleep) sleep 10 ;;
+  apple-event-timeout) echo "execution error: Finder got an error: AppleEvent timed out. (-1712)" >&2; exit 1 ;;
+  bad) echo "syntax error: Expected end of line. (-2741)" >&2; exit 1 ;;
+  big) head -c 100000 /dev/zero | tr '\0' 'a' ;;
+  *) echo "$2" ;;
+esac
+"#;
+
+    fn install_fake_osascript() {
+        static DIR: OnceLock<tempfile::TempDir> = OnceLock::new();
+        DIR.get_or_init(|| {
+            use std::os::unix::fs::PermissionsExt;
+
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("osascript");
+            std::fs::write(&path, FAKE_OSASCRIPT).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            let search_path = format!(
+                "{}:{}",
+                dir.path().display(),
+                std::env::var("PATH").unwrap_or_default()
+            );
+            std::env::set_var("PATH", search_path);
+            dir
+        });
+    }
+
+    #[tokio::test]
+    async fn test_script_is_killed_after_timeout() {
+        install_fake_osascript();
+        let executor = AppleScriptExecutor::new().with_timeout(Duration::from_millis(300));
+
+        let started = Instant::now();
+        let err = executor.execute("sleep").await.unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.to_string().contains("执行超时"), "{}", err);
+        assert!(err.to_string().contains("已终止 osascript"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_apple_event_timeout_is_distinguished_from_script_error() {
+        install_fake_osascript();
+        let executor = AppleScriptExecutor::new();
+
+        let timed_out = executor
+            .execute_jxa("apple-event-timeout")
+            .await
+            .unwrap_err();
+        assert!(
+            timed_out.to_string().contains("AppleEvent 无响应"),
+            "{}",
+            timed_out
+        );
+
+        let failed = executor.execute("bad").await.unwrap_err();
+        assert!(failed.to_string().contains("脚本错误"), "{}", failed);
+        assert!(failed.to_string().contains("-2741"), "{}", failed);
+    }
+
+    #[tokio::test]
+    async fn test_output_is_capped() {
+        install_fake_osascript();
+        let executor = AppleScriptExecutor::new().with_max_output_bytes(1024);
+
+        assert_eq!(executor.execute("hello").await.unwrap(), "hello\n");
+
+        let output = executor.execute("big").await.unwrap();
+        assert!(output.starts_with(&"a".repeat(1024)));
+        assert!(output.contains("输出已截断，共 100000 字节"));
+        assert!(output.len() < 1200);
+    }
+}
@@ -25,6 +25,7 @@ async fn test_memory_manager_creation() {
 }
 
 #[tokio::test]
+#[allow(clippy::manual_flatten)]
 async fn test_workspace_file_detection() {
     let temp_dir = TempDir::new().unwrap();
     let workspace = temp_dir.path().join("workspace");
@@ -75,6 +76,7 @@ fn test_memory_config_defaults() {
     let config = MemoryConfig {
         enabled: true,
         db_path: "/tmp/test.db".into(),
+        ..MemoryConfig::default()
     };
 
     assert!(config.enabled);
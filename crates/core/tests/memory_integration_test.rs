@@ -3,9 +3,15 @@
 // This test demonstrates the memory system integration with Agent.
 // Note: This test requires API keys and embeddings to work.
 
+mod common;
+
+use common::{test_config, MockLlm, Turn};
+use gearclaw_core::session::Session;
 use gearclaw_core::{Agent, Config};
+use serde_json::json;
 use std::fs;
 use std::io::Write;
+use tempfile::TempDir;
 
 #[tokio::test]
 async fn test_memory_integration() {
@@ -109,3 +115,84 @@ async fn test_memory_integration() {
     // Cleanup
     let _ = fs::remove_dir_all(&temp_dir);
 }
+
+// Notes saved with the memory_remember tool
+
+#[tokio::test]
+async fn test_remembered_note_is_found_by_later_search() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![
+        Turn::tool(
+            "memory_remember",
+            json!({
+                "text": "The staging database password rotates every Friday",
+                "tags": ["ops"]
+            }),
+        ),
+        Turn::text(&["noted"]),
+    ])
+    .await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.memory.enabled = true;
+    std::fs::create_dir_all(&config.agent.workspace).unwrap();
+    std::fs::write(
+        config.agent.workspace.join("recipes.md"),
+        "Bake the bread for forty minutes.\n\nServe the soup warm.\n",
+    )
+    .unwrap();
+    let agent = Agent::new(config).await.unwrap();
+    agent.memory_manager.sync().await.unwrap();
+
+    let mut session = Session::new("remember".to_string());
+    session.cwd = dir.path().to_path_buf();
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    let reply = agent
+        .process_message_with_progress(&mut session, "remember the rotation day", &progress)
+        .await
+        .unwrap();
+    assert_eq!(reply, "noted");
+
+    let requests = llm.requests();
+    let offered = requests[0]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|tool| tool["function"]["name"] == "memory_remember");
+    assert!(offered);
+
+    // The note survives a re-sync, which only prunes workspace files.
+    agent.memory_manager.sync().await.unwrap();
+    let results = agent
+        .memory_manager
+        .search("when does the staging database password rotate", 1)
+        .await
+        .unwrap();
+    assert!(results[0].path.starts_with("note:"), "{:?}", results);
+    assert!(results[0].text.contains("every Friday"));
+    assert!(results[0].text.ends_with("Tags: ops"));
+}
+
+#[tokio::test]
+async fn test_memory_remember_not_offered_when_memory_disabled() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["hi"])]).await;
+    let agent = Agent::new(test_config(dir.path(), &llm.endpoint))
+        .await
+        .unwrap();
+
+    let mut session = Session::new("remember".to_string());
+    let (progress, _rx) = tokio::sync::mpsc::unbounded_channel();
+    agent
+        .process_message_with_progress(&mut session, "hello", &progress)
+        .await
+        .unwrap();
+
+    let requests = llm.requests();
+    let offered = requests[0]["tools"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|tool| tool["function"]["name"] == "memory_remember");
+    assert!(!offered);
+    assert!(agent.memory_manager.add_note("x", &[]).await.is_err());
+}
@@ -0,0 +1,556 @@
+// Which tools the agent offers and which calls it refuses or holds
+
+mod common;
+
+use common::{test_config, MockLlm, Turn};
+use gearclaw_core::config::{FilesystemAccess, ToolsConfig};
+use gearclaw_core::session::Session;
+use gearclaw_core::Agent;
+use serde_json::json;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Fresh session whose working directory is `dir`
+fn session_in(dir: &Path) -> Session {
+    let mut session = Session::new("test".to_string());
+    session.cwd = dir.to_path_buf();
+    session
+}
+
+// Tools offered to the model per tools.profile
+
+/// Tool names sent with the first request under `profile`
+async fn offered_tools(profile: &str) -> Vec<String> {
+    offered_tools_with_memory(profile, false).await
+}
+
+async fn offered_tools_with_memory(profile: &str, memory_enabled: bool) -> Vec<String> {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![Turn::text(&["ok"])]).await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.tools.profile = profile.to_string();
+    config.memory.enabled = memory_enabled;
+    let agent = Agent::new(config).await.unwrap();
+
+    let mut session = Session::new("profile".to_string());
+    agent.process_message(&mut session, "hi").await.unwrap();
+    let request = llm.requests().remove(0);
+    request["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["function"]["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_minimal_profile_offers_read_only_tools() {
+    assert_eq!(
+        offered_tools("minimal").await,
+        ["read_file", "list_files", "file_info"]
+    );
+}
+
+#[tokio::test]
+async fn test_coding_profile_adds_exec_and_git() {
+    let tools = offered_tools("coding").await;
+    for name in ["read_file", "write_file", "exec", "git_status", "docker_ps"] {
+        assert!(tools.contains(&name.to_string()), "{:?}", tools);
+    }
+    assert!(!tools.contains(&"web_search".to_string()));
+    assert!(!tools.iter().any(|t| t.starts_with("macos_")));
+}
+
+#[tokio::test]
+async fn test_messaging_profile_adds_web_tools_without_exec() {
+    let tools = offered_tools("messaging").await;
+    assert!(tools.contains(&"web_search".to_string()));
+    assert!(tools.contains(&"fetch_url".to_string()));
+    assert!(!tools.contains(&"exec".to_string()));
+    assert!(!tools.contains(&"write_file".to_string()));
+}
+
+#[tokio::test]
+async fn test_full_profile_offers_every_builtin() {
+    let tools = offered_tools("full").await;
+    for name in ["exec", "write_file", "web_search", "macos_notify"] {
+        assert!(tools.contains(&name.to_string()), "{:?}", tools);
+    }
+}
+
+#[tokio::test]
+async fn test_memory_tool_follows_profile() {
+    let minimal = offered_tools_with_memory("minimal", true).await;
+    assert_eq!(minimal, ["read_file", "list_files", "file_info"]);
+    let coding = offered_tools_with_memory("coding", true).await;
+    assert!(
+        coding.contains(&"memory_remember".to_string()),
+        "{:?}",
+        coding
+    );
+}
+
+#[test]
+fn test_mcp_tools_follow_profile() {
+    let tools = |profile: &str| ToolsConfig {
+        profile: profile.to_string(),
+        ..ToolsConfig::default()
+    };
+    assert!(!tools("minimal").profile_allows("fs__read_file"));
+    assert!(!tools("minimal").profile_allows("memory_remember"));
+    assert!(tools("coding").profile_allows("fs__read_file"));
+    assert!(tools("messaging").profile_allows("memory_remember"));
+    assert!(tools("full").profile_allows("fs__read_file"));
+}
+
+#[tokio::test]
+async fn test_tool_outside_profile_is_refused() {
+    let dir = TempDir::new().unwrap();
+    let llm = MockLlm::start(vec![
+        Turn::tool("write_file", json!({ "path": "out.txt", "content": "x" })),
+        Turn::text(&["done"]),
+    ])
+    .await;
+    let mut config = test_config(dir.path(), &llm.endpoint);
+    config.tools.profile = "minimal".to_string();
+    let agent = Agent::new(config).await.unwrap();
+
+    let mut session = Session::new("profile".to_string());
+    session.cwd = dir.path().to_path_buf();
+    agent.process_message(&mut session, "write").await.unwrap();
+
+    assert!(!dir.path().join("out.txt").exists());
+    let tool_message = &llm.requests()[1]["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["role"] == "tool")
+        .cloned()
+        .unwrap();
+    assert!(tool_message["content"]
+        .as_str()
+        .unwrap()
+        .contains("tools.profile"));
+}
+
+// tools.security applied to file-writing and other non-exec tools
+
+async fn agent_with_security(dir: &TempDir, security: &str) -> Agent {
+    let mut config = test_config(dir.path(), "http://127.0.0.1:9/v1");
+    config.tools.security = security.to_string();
+    std::fs::create_dir_all(dir.path().join("workspace")).unwrap();
+    Agent::new(config).await.unwrap()
+}
+
+fn write_args(path: &str) -> String {
+    json!({ "path": path, "content": "hello" }).to_string()
+}
+
+#[tokio::test]
+async fn test_write_file_is_denied_under_deny() {
+    let dir = TempDir::new().unwrap();
+    let agent = agent_with_security(&dir, "deny").await;
+    let mut session = session_in(&dir.path().join("workspace"));
+
+    let err = agent
+        .execute_tool_call(&mut session, "write_file", &write_args("out.txt"))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("security=deny"), "{}", err);
+    assert!(!dir.path().join("workspace/out.txt").exists());
+}
+
+#[tokio::test]
+async fn test_session_deny_override_blocks_write_and_git() {
+    let dir = TempDir::new().unwrap();
+    let agent = agent_with_security(&dir, "full").await;
+    let mut session = session_in(dir.path());
+    session.overrides.security = Some("deny".to_string());
+
+    assert!(agent
+        .execute_tool_call(&mut session, "write_file", &write_args("out.txt"))
+        .await
+        .is_err());
+    assert!(!dir.path().join("out.txt").exists());
+    assert!(agent
+        .execute_tool_call(&mut session, "git_status", "{}")
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_reads_are_allowed_under_deny() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("note.txt"), "readable").unwrap();
+    let agent = agent_with_security(&dir, "deny").await;
+    let mut session = session_in(dir.path());
+
+    let result = agent
+        .execute_tool_call(
+            &mut session,
+            "read_file",
+            &json!({ "path": "note.txt" }).to_string(),
+        )
+        .await
+        .unwrap();
+    assert!(result.output.contains("readable"));
+}
+
+#[tokio::test]
+async fn test_allowlist_confines_writes_to_the_workspace() {
+    let dir = TempDir::new().unwrap();
+    let agent = agent_with_security(&dir, "allowlist").await;
+    let workspace = dir.path().join("workspace");
+    let mut session = session_in(&workspace);
+
+    let result = agent
+        .execute_tool_call(&mut session, "write_file", &write_args("notes/new.txt"))
+        .await
+        .unwrap();
+    assert!(result.success);
+    assert!(workspace.join("notes/new.txt").is_file());
+
+    for escape in ["../outside.txt", "notes/../../outside.txt"] {
+        let err = agent
+            .execute_tool_call(&mut session, "write_file", &write_args(escape))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("security=allowlist"), "{}", err);
+    }
+    let outside = dir.path().join("elsewhere.txt");
+    assert!(agent
+        .execute_tool_call(
+            &mut session,
+            "write_file",
+            &write_args(outside.to_str().unwrap())
+        )
+        .await
+        .is_err());
+    assert!(!dir.path().join("outside.txt").exists());
+    assert!(!outside.exists());
+}
+
+#[tokio::test]
+async fn test_mcp_tools_are_denied_under_deny() {
+    let dir = TempDir::new().unwrap();
+    let agent = agent_with_security(&dir, "deny").await;
+    let mut session = session_in(dir.path());
+
+    let err = agent
+        .execute_tool_call(&mut session, "files__delete", "{}")
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("security=deny"), "{}", err);
+
+    // allowlist leaves MCP tools to the configured servers (none here)
+    let agent = agent_with_security(&dir, "allowlist").await;
+    let err = agent
+        .execute_tool_call(&mut session, "files__delete", "{}")
+        .await
+        .unwrap_err();
+    assert!(!err.to_string().contains("security="), "{}", err);
+}
+
+#[tokio::test]
+async fn test_macos_script_tools_follow_security() {
+    let dir = TempDir::new().unwrap();
+    let script = json!({ "script": "do shell script \"rm -rf ~\"" }).to_string();
+
+    let agent = agent_with_security(&dir, "deny").await;
+    let mut session = session_in(dir.path());
+    for tool in ["macos_applescript", "macos_jxa", "macos_notify"] {
+        let err = agent
+            .execute_tool_call(&mut session, tool, &script)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("security=deny"),
+            "{}: {}",
+            tool,
+            err
+        );
+    }
+
+    let agent = agent_with_security(&dir, "allowlist").await;
+    for tool in ["macos_applescript", "macos_jxa"] {
+        let err = agent
+            .execute_tool_call(&mut session, tool, &script)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("security=allowlist"),
+            "{}: {}",
+            tool,
+            err
+        );
+    }
+    // Other macOS tools pass the security check (and fail off macOS)
+    if let Err(err) = agent
+        .execute_tool_call(
+            &mut session,
+            "macos_notify",
+            &json!({ "message": "hi" }).to_string(),
+        )
+        .await
+    {
+        assert!(!err.to_string().contains("security="), "{}", err);
+    }
+}
+
+// tools.filesystem = "read_only"
+
+async fn read_only_agent(dir: &TempDir, confirm_destructive: bool) -> Agent {
+    let mut config = test_config(dir.path(), "http://127.0.0.1:9/v1");
+    config.tools.security = "full".to_string();
+    config.tools.filesystem = FilesystemAccess::ReadOnly;
+    config.tools.confirm_destructive = confirm_destructive;
+    Agent::new(config).await.unwrap()
+}
+
+#[test]
+fn test_read_only_refusal_rules() {
+    let tools = ToolsConfig {
+        filesystem: FilesystemAccess::ReadOnly,
+        ..ToolsConfig::default()
+    };
+    assert!(tools.read_only_refuses("write_file", None));
+    assert!(tools.read_only_refuses("run_skill", None));
+    assert!(tools.read_only_refuses("exec", Some("rm -rf build")));
+    assert!(tools.read_only_refuses("exec", Some("/bin/rm notes.txt")));
+    assert!(tools.read_only_refuses("exec", Some("mkdir out")));
+    assert!(tools.read_only_refuses("exec", Some("sh -c ls")));
+    assert!(tools.read_only_refuses("exec", Some("dash -c ls")));
+    assert!(tools.read_only_refuses("exec", Some("sed -i s/a/b/ notes.txt")));
+    assert!(tools.read_only_refuses("exec", Some("git checkout main")));
+    assert!(tools.read_only_refuses("exec", Some("git -c core.pager=less log")));
+    assert!(tools.read_only_refuses("exec", Some("python -c print(1)")));
+    assert!(tools.read_only_refuses("exec", Some("find . -delete")));
+    assert!(tools.read_only_refuses("exec", Some("env FOO=1 cargo build")));
+    assert!(!tools.read_only_refuses("exec", Some("grep -r TODO src")));
+    assert!(!tools.read_only_refuses("exec", Some("git -C repo log --oneline")));
+    assert!(!tools.read_only_refuses("exec", Some("find . -name *.rs")));
+    assert!(!tools.read_only_refuses("read_file", None));
+
+    assert!(tools.read_only_refuses("github__create_issue", None));
+    assert!(tools.read_only_refuses("macos_type_text", None));
+    assert!(!tools.read_only_refuses("macos_list_windows", None));
+    let tools = ToolsConfig {
+        read_only_tools: vec!["github__search_issues".to_string()],
+        ..tools
+    };
+    assert!(!tools.read_only_refuses("github__search_issues", None));
+    assert!(tools.read_only_refuses("github__create_issue", None));
+
+    assert!(!ToolsConfig::default().read_only_refuses("write_file", None));
+}
+
+#[tokio::test]
+async fn test_writes_are_refused_and_reads_succeed() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("keep.txt"), "original").unwrap();
+    let agent = read_only_agent(&dir, false).await;
+    let mut session = session_in(dir.path());
+
+    let write = agent
+        .execute_tool_call(
+            &mut session,
+            "write_file",
+            &json!({ "path": "keep.txt", "content": "changed" }).to_string(),
+        )
+        .await
+        .unwrap();
+    assert!(!write.success);
+    assert!(write.output.contains("read_only"), "{}", write.output);
+
+    let rm = agent
+        .execute_tool_call(
+            &mut session,
+            "exec",
+            &json!({ "command": "rm", "args": ["keep.txt"] }).to_string(),
+        )
+        .await
+        .unwrap();
+    assert!(!rm.success);
+    assert!(rm.output.contains("rm keep.txt"), "{}", rm.output);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("keep.txt")).unwrap(),
+        "original"
+    );
+
+    let read = agent
+        .execute_tool_call(
+            &mut session,
+            "read_file",
+            &json!({ "path": "keep.txt" }).to_string(),
+        )
+        .await
+        .unwrap();
+    assert!(read.success);
+    assert!(read.output.contains("original"));
+    let ls = agent
+        .execute_tool_call(
+            &mut session,
+            "exec",
+            &json!({ "command": "ls" }).to_string(),
+        )
+        .await
+        .unwrap();
+    assert!(ls.output.contains("keep.txt"), "{}", ls.output);
+}
+
+#[tokio::test]
+async fn test_refused_calls_are_not_held_for_confirmation() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("keep.txt"), "original").unwrap();
+    let agent = read_only_agent(&dir, true).await;
+    let mut session = session_in(dir.path());
+
+    let result = agent
+        .execute_tool_call(
+            &mut session,
+            "exec",
+            &json!({ "command": "rm", "args": ["keep.txt"] }).to_string(),
+        )
+        .await
+        .unwrap();
+    assert!(!result.requires_confirmation);
+    assert!(result.output.contains("read_only"));
+    assert!(dir.path().join("keep.txt").exists());
+}
+
+// Holding destructive tool calls for confirmation (tools.confirm_destructive)
+
+async fn test_agent(dir: &TempDir, confirm_destructive: bool) -> Agent {
+    let mut config = test_config(dir.path(), "http://127.0.0.1:9/v1");
+    config.tools.security = "full".to_string();
+    config.tools.confirm_destructive = confirm_destructive;
+    Agent::new(config).await.unwrap()
+}
+
+#[test]
+fn test_destructive_pattern_matching() {
+    let tools = ToolsConfig::default();
+    assert!(tools.is_destructive("exec", Some("rm -rf build")));
+    assert!(tools.is_destructive("exec", Some("git push origin main")));
+    assert!(!tools.is_destructive("exec", Some("git status")));
+    assert!(!tools.is_destructive("exec", Some("rmate notes.txt")));
+    assert!(!tools.is_destructive("read_file", None));
+
+    let tools = ToolsConfig {
+        destructive_patterns: vec!["write_file".to_string()],
+        ..ToolsConfig::default()
+    };
+    assert!(tools.is_destructive("write_file", None));
+}
+
+#[test]
+fn test_destructive_program_path_is_matched_by_basename() {
+    let tools = ToolsConfig::default();
+    assert!(tools.is_destructive("exec", Some("/bin/rm -rf x")));
+    assert!(tools.is_destructive("exec", Some("/usr/bin/git push origin main")));
+}
+
+#[test]
+fn test_destructive_command_behind_wrappers_is_matched() {
+    let tools = ToolsConfig {
+        // Without `sudo` itself, so only the wrapped command can match
+        destructive_patterns: vec!["rm".to_string(), "git push".to_string()],
+        ..ToolsConfig::default()
+    };
+    assert!(tools.is_destructive("exec", Some("env rm -rf x")));
+    assert!(tools.is_destructive("exec", Some("env -i FOO=bar /bin/rm x")));
+    assert!(tools.is_destructive("exec", Some("sudo -u root rm -rf x")));
+    assert!(!tools.is_destructive("exec", Some("env ls -la")));
+}
+
+#[test]
+fn test_destructive_command_in_shell_script_is_matched() {
+    let tools = ToolsConfig::default();
+    assert!(tools.is_destructive("exec", Some("sh -c rm -rf x")));
+    assert!(tools.is_destructive("exec", Some("sh -c \"rm -rf x\"")));
+    assert!(tools.is_destructive("exec", Some("bash -c echo hi; rm -rf x")));
+    assert!(tools.is_destructive("exec", Some("bash -lc \"ls && git push\"")));
+    assert!(!tools.is_destructive("exec", Some("sh -c \"ls -la\"")));
+}
+
+#[test]
+fn test_git_global_options_are_skipped() {
+    let tools = ToolsConfig::default();
+    assert!(tools.is_destructive("exec", Some("git -C repo push")));
+    assert!(tools.is_destructive("exec", Some("git -c user.name=x --no-pager push")));
+    assert!(tools.is_destructive("exec", Some("git --git-dir=.git reset --hard")));
+    assert!(!tools.is_destructive("exec", Some("git -C repo status")));
+}
+
+#[tokio::test]
+async fn test_destructive_command_is_held() {
+    let dir = TempDir::new().unwrap();
+    let target = dir.path().join("keep.txt");
+    std::fs::write(&target, "data").unwrap();
+    let agent = test_agent(&dir, true).await;
+    let mut session = session_in(dir.path());
+
+    let args = json!({ "command": "rm", "args": [target.to_string_lossy()] }).to_string();
+    let result = agent
+        .execute_tool_call(&mut session, "exec", &args)
+        .await
+        .unwrap();
+    assert!(result.requires_confirmation);
+    assert!(!result.success);
+    assert!(target.exists(), "held command must not run");
+
+    let result = agent
+        .execute_confirmed_tool_call(&mut session, "exec", &args)
+        .await
+        .unwrap();
+    assert!(!result.requires_confirmation);
+    assert!(!target.exists(), "confirmed command runs");
+}
+
+#[tokio::test]
+async fn test_safe_command_executes() {
+    let dir = TempDir::new().unwrap();
+    let agent = test_agent(&dir, true).await;
+    let mut session = session_in(dir.path());
+
+    let args = json!({ "command": "echo", "args": ["hello"] }).to_string();
+    let result = agent
+        .execute_tool_call(&mut session, "exec", &args)
+        .await
+        .unwrap();
+    assert!(!result.requires_confirmation);
+    assert!(result.success);
+    assert!(result.output.contains("hello"));
+}
+
+#[tokio::test]
+async fn test_nothing_is_held_when_disabled() {
+    let dir = TempDir::new().unwrap();
+    let target = dir.path().join("gone.txt");
+    std::fs::write(&target, "data").unwrap();
+    let agent = test_agent(&dir, false).await;
+    let mut session = session_in(dir.path());
+
+    let args = json!({ "command": "rm", "args": [target.to_string_lossy()] }).to_string();
+    let result = agent
+        .execute_tool_call(&mut session, "exec", &args)
+        .await
+        .unwrap();
+    assert!(!result.requires_confirmation);
+    assert!(!target.exists());
+}
+
+#[tokio::test]
+async fn test_run_skill_is_held_by_default() {
+    let dir = TempDir::new().unwrap();
+    let agent = test_agent(&dir, true).await;
+    let mut session = session_in(dir.path());
+
+    let args = json!({ "name": "hello_world" }).to_string();
+    let result = agent
+        .execute_tool_call(&mut session, "run_skill", &args)
+        .await
+        .unwrap();
+    assert!(result.requires_confirmation);
+    assert!(!result.success);
+}
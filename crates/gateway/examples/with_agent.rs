@@ -60,6 +60,7 @@ async fn main() -> Result<()> {
             keywords: vec![],
             enabled_channels: vec![],
             disabled_channels: vec![],
+            ..Default::default()
         };
         handlers.set_trigger_config(trigger_config).await;
         tracing::info!("✅ Trigger config set: Mention mode (@agent, @bot)");
@@ -138,6 +139,9 @@ async fn main() -> Result<()> {
                     trigger_config.keywords
                 );
             }
+            TriggerMode::DirectMention => {
+                tracing::info!("   Agent: ✅ ACTIVE (responds when @-mentioned or replied to)");
+            }
         }
         tracing::info!("   Discord Message → Agent → Discord Response (when triggered)");
     }
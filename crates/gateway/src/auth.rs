@@ -1,39 +1,157 @@
 // Bearer Token Authentication
 //
 // Simplified authentication using Bearer tokens (temporary replacement for ed25519).
+// Tokens are never stored in plaintext: each record keeps a random salt and
+// SHA-256(salt || token), optionally persisted to a JSON file.
 //
 // TODO: [TODO-ID-001] Replace with full ed25519 device identity signature/verification
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 /// Token authentication system
 pub struct TokenAuth {
-    tokens: Arc<RwLock<HashMap<String, TokenInfo>>>,
+    /// Token id -> salted hash and metadata
+    tokens: Arc<RwLock<HashMap<String, TokenRecord>>>,
+    /// File the registry is written to on register/revoke, if any
+    store_path: Option<PathBuf>,
 }
 
 /// Token information
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TokenInfo {
+    /// Stable identifier for listing and revoking without the token itself
+    pub id: String,
     pub device_id: String,
     pub mode: String,
     pub created_at: DateTime<Utc>,
     pub last_used: Option<DateTime<Utc>>,
+    /// Methods this token may call; `None` allows every method
+    #[serde(default)]
+    pub scopes: Option<HashSet<String>>,
+}
+
+impl TokenInfo {
+    /// Whether this token may call `method`. A `*` scope allows everything.
+    pub fn allows(&self, method: &str) -> bool {
+        match &self.scopes {
+            None => true,
+            Some(scopes) => scopes.contains("*") || scopes.contains(method),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TokenRecord {
+    /// Hex-encoded random salt
+    salt: String,
+    /// Hex-encoded SHA-256(salt || token)
+    hash: String,
+    #[serde(flatten)]
+    info: TokenInfo,
+}
+
+impl TokenRecord {
+    fn new(token: &str, info: TokenInfo) -> Self {
+        let mut salt = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let salt = hex_encode(&salt);
+        Self {
+            hash: hash_token(&salt, token),
+            salt,
+            info,
+        }
+    }
+
+    fn matches(&self, token: &str) -> bool {
+        constant_time_eq(
+            hash_token(&self.salt, token).as_bytes(),
+            self.hash.as_bytes(),
+        )
+    }
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hash_token(salt: &str, token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(token.as_bytes());
+    hex_encode(&hasher.finalize())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
 }
 
 impl TokenAuth {
-    fn normalize_token(token: &str) -> &str {
+    pub(crate) fn normalize_token(token: &str) -> &str {
         token.strip_prefix("Bearer ").unwrap_or(token)
     }
     pub fn new() -> Self {
         Self {
             tokens: Arc::new(RwLock::new(HashMap::new())),
+            store_path: None,
         }
     }
 
+    /// Load tokens from `path` (missing file means no tokens yet) and persist
+    /// later changes back to it
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let records: Vec<TokenRecord> = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read token store {}", path.display()))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid token store {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+        tracing::info!(
+            "Loaded {} gateway token(s) from {}",
+            records.len(),
+            path.display()
+        );
+
+        Ok(Self {
+            tokens: Arc::new(RwLock::new(
+                records
+                    .into_iter()
+                    .map(|record| (record.info.id.clone(), record))
+                    .collect(),
+            )),
+            store_path: Some(path),
+        })
+    }
+
+    fn save(&self, tokens: &HashMap<String, TokenRecord>) -> Result<()> {
+        let Some(path) = &self.store_path else {
+            return Ok(());
+        };
+        let mut records: Vec<&TokenRecord> = tokens.values().collect();
+        records.sort_by_key(|record| record.info.created_at);
+        write_private(path, &serde_json::to_string_pretty(&records)?)
+            .with_context(|| format!("Failed to write token store {}", path.display()))
+    }
+
+    /// Id of the record matching `token`, comparing salted hashes
+    fn find_id(tokens: &HashMap<String, TokenRecord>, token: &str) -> Option<String> {
+        let token = Self::normalize_token(token);
+        tokens
+            .values()
+            .find(|record| record.matches(token))
+            .map(|record| record.info.id.clone())
+    }
+
     /// Validate a token
     pub async fn validate(&self, token: &str) -> bool {
         // Check format: "Bearer <token>"
@@ -56,8 +174,8 @@ impl TokenAuth {
 
         // Check if token exists in registry
         let tokens = self.tokens.read().await;
-        match tokens.get(token) {
-            Some(_info) => {
+        match Self::find_id(&tokens, token) {
+            Some(_id) => {
                 tracing::debug!("Token validated successfully");
                 true
             }
@@ -68,8 +186,21 @@ impl TokenAuth {
         }
     }
 
-    /// Register a new token
-    pub async fn register(&self, token: String, device_id: String, mode: String) -> Result<()> {
+    /// Register a new token with access to every method. Returns its id.
+    pub async fn register(&self, token: String, device_id: String, mode: String) -> Result<String> {
+        self.register_with_scopes(token, device_id, mode, None)
+            .await
+    }
+
+    /// Register a new token limited to `scopes` (method names, or `*`);
+    /// `None` allows every method. Returns its id.
+    pub async fn register_with_scopes(
+        &self,
+        token: String,
+        device_id: String,
+        mode: String,
+        scopes: Option<HashSet<String>>,
+    ) -> Result<String> {
         // Validate token format
         let token = Self::normalize_token(&token).to_string();
 
@@ -77,49 +208,111 @@ impl TokenAuth {
             return Err(anyhow::anyhow!("Token too short (min 32 chars)"));
         }
 
+        let mut tokens = self.tokens.write().await;
+        // Re-registering a token replaces its previous record.
+        if let Some(existing) = Self::find_id(&tokens, &token) {
+            tokens.remove(&existing);
+        }
+
+        let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
         let info = TokenInfo {
+            id: id.clone(),
             device_id,
             mode,
             created_at: Utc::now(),
             last_used: None,
+            scopes,
         };
 
-        self.tokens.write().await.insert(token, info);
+        tokens.insert(id.clone(), TokenRecord::new(&token, info));
+        self.save(&tokens)?;
         tracing::info!("Token registered successfully");
-        Ok(())
+        Ok(id)
     }
 
     /// Get token info
     pub async fn get_token_info(&self, token: &str) -> Option<TokenInfo> {
-        let token = Self::normalize_token(token);
+        let tokens = self.tokens.read().await;
+        let id = Self::find_id(&tokens, token)?;
+        tokens.get(&id).map(|record| record.info.clone())
+    }
 
-        self.tokens.read().await.get(token).cloned()
+    /// All registered tokens, oldest first
+    pub async fn list(&self) -> Vec<TokenInfo> {
+        let mut infos: Vec<TokenInfo> = self
+            .tokens
+            .read()
+            .await
+            .values()
+            .map(|record| record.info.clone())
+            .collect();
+        infos.sort_by_key(|info| info.created_at);
+        infos
     }
 
-    /// Update last used timestamp
-    pub async fn update_last_used(&self, token: &str) {
-        let token = Self::normalize_token(token);
+    /// Whether `token` is registered and allowed to call `method`
+    pub async fn is_method_allowed(&self, token: &str, method: &str) -> bool {
+        let tokens = self.tokens.read().await;
+        Self::find_id(&tokens, token)
+            .and_then(|id| tokens.get(&id))
+            .is_some_and(|record| record.info.allows(method))
+    }
 
-        if let Some(info) = self.tokens.write().await.get_mut(token) {
-            info.last_used = Some(Utc::now());
+    /// Update last used timestamp (in memory only; not worth a disk write
+    /// per request)
+    pub async fn update_last_used(&self, token: &str) {
+        let mut tokens = self.tokens.write().await;
+        if let Some(id) = Self::find_id(&tokens, token) {
+            if let Some(record) = tokens.get_mut(&id) {
+                record.info.last_used = Some(Utc::now());
+            }
         }
     }
 
     /// Revoke a token
     pub async fn revoke(&self, token: &str) -> Result<()> {
-        let token = Self::normalize_token(token);
-
-        self.tokens.write().await.remove(token);
+        let mut tokens = self.tokens.write().await;
+        if let Some(id) = Self::find_id(&tokens, token) {
+            tokens.remove(&id);
+            self.save(&tokens)?;
+        }
         tracing::info!("Token revoked successfully");
         Ok(())
     }
 
+    /// Revoke a token by id. Returns whether a token was removed.
+    pub async fn revoke_id(&self, id: &str) -> Result<bool> {
+        let mut tokens = self.tokens.write().await;
+        let removed = tokens.remove(id).is_some();
+        if removed {
+            self.save(&tokens)?;
+            tracing::info!("Token {} revoked", id);
+        }
+        Ok(removed)
+    }
+
     /// Get registered token count
     pub async fn token_count(&self) -> usize {
         self.tokens.read().await.len()
     }
 }
 
+/// Write `content` to `path` via a temp file, readable only by the owner.
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, content)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
 impl Default for TokenAuth {
     fn default() -> Self {
         Self::new()
@@ -206,6 +399,91 @@ mod tests {
         assert_eq!(info.unwrap().device_id, "device-1");
     }
 
+    #[tokio::test]
+    async fn test_token_scopes() {
+        let auth = TokenAuth::new();
+        let monitor = "monitor-token-abcdefghijklmnopqrstuvwxyz12";
+        let admin = "admin-token-abcdefghijklmnopqrstuvwxyz123456";
+        auth.register_with_scopes(
+            monitor.to_string(),
+            "device-1".to_string(),
+            "gateway".to_string(),
+            Some(HashSet::from(["health".to_string(), "status".to_string()])),
+        )
+        .await
+        .unwrap();
+        auth.register(
+            admin.to_string(),
+            "device-2".to_string(),
+            "gateway".to_string(),
+        )
+        .await
+        .unwrap();
+
+        assert!(auth.is_method_allowed(monitor, "health").await);
+        assert!(auth.is_method_allowed(monitor, "status").await);
+        assert!(!auth.is_method_allowed(monitor, "agent").await);
+        assert!(auth.is_method_allowed(admin, "agent").await);
+        assert!(
+            !auth
+                .is_method_allowed("unknown-token-abcdefghijklmnopqrstuvwxyz", "health")
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_stored_hashed_and_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.json");
+        let token = "persist-token-abcdefghijklmnopqrstuvwxyz12";
+
+        let auth = TokenAuth::load(&path).unwrap();
+        let id = auth
+            .register(
+                token.to_string(),
+                "device-1".to_string(),
+                "gateway".to_string(),
+            )
+            .await
+            .unwrap();
+
+        let stored = std::fs::read_to_string(&path).unwrap();
+        assert!(!stored.contains(token), "token stored in plaintext");
+        assert!(stored.contains("\"salt\""));
+
+        let reloaded = TokenAuth::load(&path).unwrap();
+        assert!(reloaded.validate(token).await);
+        assert!(reloaded.validate(&format!("Bearer {}", token)).await);
+        assert!(
+            !reloaded
+                .validate("persist-token-abcdefghijklmnopqrstuvwxyz13")
+                .await
+        );
+        assert_eq!(reloaded.list().await[0].id, id);
+
+        assert!(reloaded.revoke_id(&id).await.unwrap());
+        assert!(!reloaded.revoke_id(&id).await.unwrap());
+        assert_eq!(TokenAuth::load(&path).unwrap().token_count().await, 0);
+    }
+
+    #[test]
+    fn test_same_token_hashes_differently_per_salt() {
+        let info = TokenInfo {
+            id: "id".to_string(),
+            device_id: "device".to_string(),
+            mode: "gateway".to_string(),
+            created_at: Utc::now(),
+            last_used: None,
+            scopes: None,
+        };
+        let token = "salt-token-abcdefghijklmnopqrstuvwxyz1234";
+        let a = TokenRecord::new(token, info.clone());
+        let b = TokenRecord::new(token, info);
+        assert_ne!(a.hash, b.hash);
+        assert!(a.matches(token) && b.matches(token));
+        assert!(!a.matches("salt-token-abcdefghijklmnopqrstuvwxyz1235"));
+    }
+
     #[tokio::test]
     async fn test_token_revoke() {
         let auth = TokenAuth::new();
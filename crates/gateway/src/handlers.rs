@@ -2,21 +2,104 @@
 //
 // This module implements handlers for OpenClaw protocol methods.
 
-use crate::protocol::GatewayRequest;
+use crate::idempotency::IdempotencyCache;
+use crate::metrics::GatewayMetrics;
+use crate::protocol::{AgentEvent, AgentEventContent, GatewayEvent, GatewayFrame, GatewayRequest};
+use crate::server::ActiveConnection;
 use anyhow::Result;
+use gearclaw_agent::{AgentProgress, DeltaSink};
 use gearclaw_channels::adapter::{ChannelManager, MessageContent};
+use gearclaw_core::session::sanitize_session_id;
+use gearclaw_core::triggers::TriggerMatcher;
 use serde_json::json;
 use serde_json::Value as JsonValue;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
+/// Largest page of connections one `status` reply carries
+pub const STATUS_PAGE_MAX: usize = 100;
+
+/// Optional non-negative integer paging parameter
+fn page_param(request: &GatewayRequest, name: &str) -> Result<Option<usize>> {
+    match request.params.get(name) {
+        None | Some(JsonValue::Null) => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .map(|n| Some(n as usize))
+            .ok_or_else(|| anyhow::anyhow!("'{}' must be a non-negative integer", name)),
+    }
+}
+
 pub struct MethodHandlers {
     /// Optional Agent reference (will be set by Gateway server)
     agent: Arc<tokio::sync::Mutex<Option<Arc<gearclaw_agent::Agent>>>>,
     /// Channel manager for routing messages to platforms
     channel_manager: Arc<tokio::sync::Mutex<ChannelManager>>,
-    /// Agent trigger configuration
-    trigger_config: Arc<tokio::sync::Mutex<gearclaw_core::AgentTriggerConfig>>,
+    /// Agent trigger configuration, with its patterns compiled
+    trigger_config: Arc<tokio::sync::Mutex<Arc<TriggerMatcher>>>,
+    /// Request, error and latency metrics
+    metrics: Arc<GatewayMetrics>,
+    /// In-flight `agent`/`agent.stream` runs, for `cancel`
+    runs: RunRegistry,
+    /// Results of `send`/`agent` requests by idempotency key
+    idempotency: Arc<IdempotencyCache>,
+}
+
+/// Cancellation tokens of in-flight agent runs, keyed by the caller's
+/// scope (its token, or its connection when unauthenticated) and the id of
+/// the request that started them, so one client can neither cancel nor
+/// displace another's run
+#[derive(Clone, Default)]
+struct RunRegistry {
+    runs: Arc<std::sync::Mutex<HashMap<String, (String, CancellationToken)>>>,
+}
+
+impl RunRegistry {
+    fn key(scope: &str, request_id: &str) -> String {
+        format!("{}\n{}", scope, request_id)
+    }
+
+    fn register(&self, scope: &str, request_id: &str, run_id: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        self.runs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(
+                Self::key(scope, request_id),
+                (run_id.to_string(), token.clone()),
+            );
+        token
+    }
+
+    /// Drop the entry for a finished run, unless a newer run with the same
+    /// request id has replaced it
+    fn finish(&self, scope: &str, request_id: &str, run_id: &str) {
+        let key = Self::key(scope, request_id);
+        let mut runs = self.runs.lock().unwrap_or_else(PoisonError::into_inner);
+        if runs.get(&key).is_some_and(|(id, _)| id == run_id) {
+            runs.remove(&key);
+        }
+    }
+
+    /// Signal the run `scope` started with `request_id`; false if none is
+    /// in flight
+    fn cancel(&self, scope: &str, request_id: &str) -> bool {
+        match self
+            .runs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&Self::key(scope, request_id))
+        {
+            Some((_, token)) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 impl MethodHandlers {
@@ -24,9 +107,12 @@ impl MethodHandlers {
         Self {
             agent: Arc::new(tokio::sync::Mutex::new(None)),
             channel_manager: Arc::new(tokio::sync::Mutex::new(ChannelManager::new())),
-            trigger_config: Arc::new(tokio::sync::Mutex::new(
+            trigger_config: Arc::new(tokio::sync::Mutex::new(Arc::new(TriggerMatcher::new(
                 gearclaw_core::AgentTriggerConfig::default(),
-            )),
+            )))),
+            metrics: Arc::new(GatewayMetrics::new()),
+            runs: RunRegistry::default(),
+            idempotency: Arc::new(IdempotencyCache::default()),
         }
     }
 
@@ -38,14 +124,31 @@ impl MethodHandlers {
 
     /// Set trigger configuration
     pub async fn set_trigger_config(&self, config: gearclaw_core::AgentTriggerConfig) {
+        let matcher = Arc::new(TriggerMatcher::new(config));
         let mut trigger_guard = self.trigger_config.lock().await;
-        *trigger_guard = config;
+        *trigger_guard = matcher;
     }
 
     /// Get trigger configuration
     pub async fn get_trigger_config(&self) -> gearclaw_core::AgentTriggerConfig {
         let trigger_guard = self.trigger_config.lock().await;
-        trigger_guard.clone()
+        trigger_guard.config().clone()
+    }
+
+    /// Get the compiled trigger matcher
+    pub async fn get_trigger_matcher(&self) -> Arc<TriggerMatcher> {
+        let trigger_guard = self.trigger_config.lock().await;
+        Arc::clone(&trigger_guard)
+    }
+
+    /// Get metrics registry reference
+    pub fn metrics(&self) -> Arc<GatewayMetrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Get idempotency cache reference
+    pub fn idempotency(&self) -> Arc<IdempotencyCache> {
+        Arc::clone(&self.idempotency)
     }
 
     /// Get channel manager reference
@@ -74,14 +177,46 @@ impl MethodHandlers {
         }))
     }
 
-    /// Handle status request - returns actual connection stats
-    pub async fn status(&self, _request: &GatewayRequest) -> Result<JsonValue> {
+    /// Handle status request - returns actual connection stats. The
+    /// connection list is paged by the optional `offset` and `limit` params
+    /// (at most [`STATUS_PAGE_MAX`] entries), with `total` giving the full
+    /// count.
+    pub async fn status(
+        &self,
+        request: &GatewayRequest,
+        connections: &[ActiveConnection],
+    ) -> Result<JsonValue> {
+        let offset = page_param(request, "offset")?.unwrap_or(0);
+        let limit = page_param(request, "limit")?
+            .unwrap_or(STATUS_PAGE_MAX)
+            .min(STATUS_PAGE_MAX);
+        let connection_list: Vec<JsonValue> = connections
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|conn| {
+                json!({
+                    "id": conn.id,
+                    "addr": conn.addr,
+                    "connected_at": conn
+                        .connected_at
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_secs())
+                        .unwrap_or(0),
+                })
+            })
+            .collect();
+
         Ok(json!({
             "uptime_ms": std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .map(|d| d.as_secs())
                 .unwrap_or(0),
-            "total_connections": 1,
+            "total_connections": connections.len(),
+            "total": connections.len(),
+            "offset": offset,
+            "limit": limit,
+            "connections": connection_list,
             "active_sessions": 1,
         }))
     }
@@ -130,6 +265,7 @@ impl MethodHandlers {
             let content = MessageContent {
                 text: Some(message.to_string()),
                 embeds: vec![],
+                attachments: vec![],
             };
 
             // Send message
@@ -154,8 +290,9 @@ impl MethodHandlers {
         }
     }
 
-    /// Handle agent execution request
-    pub async fn agent_execute(&self, request: &GatewayRequest) -> Result<JsonValue> {
+    /// Handle agent execution request. `scope` identifies the caller for
+    /// `cancel`.
+    pub async fn agent_execute(&self, request: &GatewayRequest, scope: &str) -> Result<JsonValue> {
         let run_id = Uuid::new_v4().to_string();
 
         // Extract prompt from params
@@ -175,7 +312,7 @@ impl MethodHandlers {
         if let Some(agent) = self.get_agent().await {
             // Create or get session for this request
             // Use request ID or device ID as session identifier
-            let session_id = request.device_id.as_ref().unwrap_or(&request.id).clone();
+            let session_id = sanitize_session_id(request.device_id.as_ref().unwrap_or(&request.id));
 
             // Get or create session
             let mut sess = agent
@@ -183,10 +320,22 @@ impl MethodHandlers {
                 .get_or_create_session(&session_id)
                 .map_err(|e| anyhow::anyhow!("Failed to get session: {}", e))?;
 
-            // Process message with agent
-            let response = agent
-                .process_message(&mut sess, prompt)
-                .await
+            // Process message with agent, unless a `cancel` arrives first
+            let cancel = self.runs.register(scope, &request.id, &run_id);
+            let started = std::time::Instant::now();
+            let mut discard = std::io::sink();
+            let response = tokio::select! {
+                response = agent.process_message_with_sink(
+                    &mut sess,
+                    prompt,
+                    DeltaSink::Writer(&mut discard),
+                ) => Some(response),
+                _ = cancel.cancelled() => None,
+            };
+            self.metrics.observe_agent_latency(started.elapsed());
+            self.runs.finish(scope, &request.id, &run_id);
+            let response = response
+                .ok_or_else(|| anyhow::anyhow!("{}", RUN_CANCELLED))?
                 .map_err(|e| anyhow::anyhow!("Agent execution failed: {}", e))?;
 
             // Save session
@@ -210,6 +359,160 @@ impl MethodHandlers {
             }))
         }
     }
+
+    /// Handle streaming agent execution. Replies immediately with the run id,
+    /// then sends `agent` events for this request on `outbound` (output
+    /// deltas, tool start/end) and finishes with a `done` or `error` event.
+    /// `scope` identifies the caller for `cancel`.
+    pub async fn agent_stream(
+        &self,
+        request: &GatewayRequest,
+        scope: &str,
+        outbound: mpsc::UnboundedSender<GatewayFrame>,
+    ) -> Result<JsonValue> {
+        let agent = self
+            .get_agent()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Agent not configured"))?;
+        let run_id = Uuid::new_v4().to_string();
+        let prompt = request
+            .params
+            .get("prompt")
+            .and_then(|p| p.as_str())
+            .unwrap_or("")
+            .to_string();
+        let session_id = sanitize_session_id(request.device_id.as_ref().unwrap_or(&request.id));
+        let request_id = request.id.clone();
+
+        tracing::info!(
+            "Streaming agent execution requested: run_id={}, prompt='{}'",
+            run_id,
+            prompt
+        );
+
+        let task_run_id = run_id.clone();
+        let metrics = self.metrics();
+        let runs = self.runs.clone();
+        let scope = scope.to_string();
+        let cancel = runs.register(&scope, &request_id, &run_id);
+        tokio::spawn(async move {
+            let (progress_tx, progress_rx) = mpsc::unbounded_channel();
+            let run = async move {
+                let mut sess = agent
+                    .session_manager
+                    .get_or_create_session(&session_id)
+                    .map_err(|e| anyhow::anyhow!("Failed to get session: {}", e))?;
+                let started = std::time::Instant::now();
+                let response = agent
+                    .process_message_with_progress(&mut sess, &prompt, &progress_tx)
+                    .await;
+                metrics.observe_agent_latency(started.elapsed());
+                let response =
+                    response.map_err(|e| anyhow::anyhow!("Agent execution failed: {}", e))?;
+                agent
+                    .session_manager
+                    .save_session(&sess)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to save session: {}", e))?;
+                Ok::<_, anyhow::Error>(response)
+            };
+            // Dropping the run on cancel also drops its progress sender,
+            // which ends the forwarder.
+            let run = async move {
+                tokio::select! {
+                    result = run => result,
+                    _ = cancel.cancelled() => Err(anyhow::anyhow!("{}", RUN_CANCELLED)),
+                }
+            };
+            let forward = forward_agent_progress(&request_id, progress_rx, &outbound);
+            let (result, seq) = tokio::join!(run, forward);
+            runs.finish(&scope, &request_id, &task_run_id);
+
+            let content = match result {
+                Ok(response) => AgentEventContent::Done {
+                    run_id: task_run_id,
+                    response,
+                },
+                Err(e) => AgentEventContent::Error {
+                    run_id: task_run_id,
+                    message: e.to_string(),
+                },
+            };
+            let _ = outbound.send(agent_event_frame(&request_id, seq, content));
+        });
+
+        Ok(json!({
+            "run_id": run_id,
+            "status": "streaming",
+        }))
+    }
+
+    /// Handle cancel request: stop the `agent`/`agent.stream` run started by
+    /// the request whose id is in the `request_id` param. Only runs started
+    /// by the same `scope` can be cancelled. The run ends with a
+    /// "cancelled" error; `cancelled` is false when no such run is in
+    /// flight.
+    pub async fn cancel(&self, request: &GatewayRequest, scope: &str) -> Result<JsonValue> {
+        let target = request
+            .params
+            .get("request_id")
+            .and_then(|id| id.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing 'request_id' parameter"))?;
+        let cancelled = self.runs.cancel(scope, target);
+        tracing::info!("Cancel requested for {}: cancelled={}", target, cancelled);
+        Ok(json!({
+            "request_id": target,
+            "cancelled": cancelled,
+        }))
+    }
+}
+
+/// Error message an agent run ends with when cancelled
+const RUN_CANCELLED: &str = "Agent run cancelled";
+
+fn agent_event_frame(request_id: &str, seq: u64, content: AgentEventContent) -> GatewayFrame {
+    GatewayFrame::Event(GatewayEvent::Agent(AgentEvent {
+        seq,
+        request_id: Some(request_id.to_string()),
+        content,
+    }))
+}
+
+/// Relay agent progress as `agent` events until the run drops its sender.
+/// Returns the next free sequence number.
+async fn forward_agent_progress(
+    request_id: &str,
+    mut progress: mpsc::UnboundedReceiver<AgentProgress>,
+    outbound: &mpsc::UnboundedSender<GatewayFrame>,
+) -> u64 {
+    let mut seq = 0;
+    while let Some(update) = progress.recv().await {
+        let content = match update {
+            AgentProgress::Delta(content) => AgentEventContent::Output {
+                content,
+                metadata: None,
+            },
+            AgentProgress::ToolStarted {
+                name, arguments, ..
+            } => AgentEventContent::ToolStart {
+                tool: name,
+                params: serde_json::from_str(&arguments).unwrap_or(JsonValue::String(arguments)),
+            },
+            AgentProgress::ToolFinished {
+                name,
+                success,
+                output,
+                ..
+            } => AgentEventContent::ToolEnd {
+                tool: name,
+                result: json!({ "success": success, "output": output }),
+            },
+        };
+        // Keep draining even if the client went away so the run is not blocked.
+        let _ = outbound.send(agent_event_frame(request_id, seq, content));
+        seq += 1;
+    }
+    seq
 }
 
 impl Default for MethodHandlers {
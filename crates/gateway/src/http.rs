@@ -0,0 +1,148 @@
+// Plain HTTP routes served on the WebSocket listener
+//
+// Orchestrators and load balancers probe with a bare `GET /healthz` (and
+// Prometheus scrapes `GET /metrics`), so such requests are answered before
+// the WebSocket upgrade, without auth.
+// Anything else goes on to the WebSocket handshake untouched.
+
+use crate::handlers::MethodHandlers;
+use crate::server::{ConnectionRegistry, GatewayConfig};
+use serde_json::json;
+use std::sync::PoisonError;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+
+/// Liveness/readiness probe path
+pub const HEALTH_PATH: &str = "/healthz";
+
+/// Prometheus scrape path, served when `metrics_enabled` is set
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Longest request line inspected; longer lines go to the upgrade path
+const MAX_REQUEST_LINE: usize = 2048;
+
+/// Longest request head read and discarded before answering
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// How long to wait for the request line before handing the connection on
+const REQUEST_LINE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A response to a plain HTTP request
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HttpResponse {
+    pub status: u16,
+    pub reason: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn json(body: serde_json::Value) -> Self {
+        Self {
+            status: 200,
+            reason: "OK",
+            content_type: "application/json",
+            body: body.to_string(),
+        }
+    }
+}
+
+/// Peek at the request line without consuming it, returning
+/// `(method, path)` with any query string removed
+pub(crate) async fn peek_request_line(stream: &TcpStream) -> Option<(String, String)> {
+    let deadline = Instant::now() + REQUEST_LINE_TIMEOUT;
+    let mut buf = vec![0u8; MAX_REQUEST_LINE];
+    let mut seen = 0;
+    loop {
+        let n = tokio::time::timeout_at(deadline, stream.peek(&mut buf))
+            .await
+            .ok()?
+            .ok()?;
+        if n == 0 {
+            return None;
+        }
+        if let Some(end) = buf[..n].windows(2).position(|w| w == b"\r\n") {
+            let line = std::str::from_utf8(&buf[..end]).ok()?;
+            let mut parts = line.split(' ');
+            let method = parts.next()?.to_string();
+            let target = parts.next()?;
+            let path = target.split('?').next().unwrap_or(target).to_string();
+            return Some((method, path));
+        }
+        if n == buf.len() || Instant::now() >= deadline {
+            return None;
+        }
+        // Peek returns at once while data is buffered; wait for more
+        if n == seen {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        seen = n;
+    }
+}
+
+/// Answer `method path` if it is a plain HTTP route, `None` to continue
+/// with the WebSocket handshake
+pub(crate) async fn route(
+    method: &str,
+    path: &str,
+    handlers: &MethodHandlers,
+    connections: &ConnectionRegistry,
+    config: &GatewayConfig,
+) -> Option<HttpResponse> {
+    if method != "GET" && method != "HEAD" {
+        return None;
+    }
+    let connection_count = || {
+        connections
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    };
+    match path {
+        HEALTH_PATH => Some(HttpResponse::json(json!({
+            "status": "ok",
+            "version": env!("CARGO_PKG_VERSION"),
+            "connections": connection_count(),
+            "agent_ready": handlers.get_agent().await.is_some(),
+        }))),
+        METRICS_PATH if config.metrics_enabled => Some(HttpResponse {
+            status: 200,
+            reason: "OK",
+            content_type: "text/plain; version=0.0.4",
+            body: handlers.metrics().render(connection_count()),
+        }),
+        _ => None,
+    }
+}
+
+/// Consume the request head, write `response` and close the connection
+pub(crate) async fn respond(
+    mut stream: TcpStream,
+    head_only: bool,
+    response: HttpResponse,
+) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_HEAD {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    let mut out = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason,
+        response.content_type,
+        response.body.len()
+    );
+    if !head_only {
+        out.push_str(&response.body);
+    }
+    stream.write_all(out.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,266 @@
+// Idempotency Keys
+//
+// Successful `send`/`agent` results cached by the request's idempotency key,
+// so a client retrying after a timeout gets the first result back instead of
+// a second channel send or agent run. A retry arriving while the first
+// request is still running waits for its result.
+
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// How long a result is replayed for a repeated key
+pub const DEFAULT_TTL: Duration = Duration::from_secs(10 * 60);
+/// Keys remembered at once; the oldest is dropped to make room
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Bounded map from idempotency key to the payload first returned for it.
+/// Results expire after `ttl`; keys still in flight do not.
+pub struct IdempotencyCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+struct Entry {
+    state: State,
+    stored_at: Instant,
+}
+
+enum State {
+    /// A request with this key is running. Waiters subscribe to the sender,
+    /// which is dropped once the entry is resolved or removed.
+    Pending(watch::Sender<()>),
+    Done(JsonValue),
+}
+
+/// Outcome of [`IdempotencyCache::claim`]
+pub enum Claim<'a> {
+    /// No result yet: run the request and report it through the guard
+    Run(InFlight<'a>),
+    /// The payload first returned for the key
+    Replay(JsonValue),
+}
+
+/// A claimed key. [`InFlight::complete`] stores the result; dropping the
+/// guard without it (the request failed) frees the key so a waiting retry
+/// runs instead.
+pub struct InFlight<'a> {
+    cache: &'a IdempotencyCache,
+    key: String,
+    completed: bool,
+}
+
+impl InFlight<'_> {
+    /// Remember `payload` as the result for the key and wake waiters
+    pub fn complete(mut self, payload: JsonValue) {
+        self.cache.insert(self.key.clone(), payload);
+        self.completed = true;
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if !self.completed {
+            let mut entries = self.cache.lock();
+            if entries
+                .get(&self.key)
+                .is_some_and(|entry| matches!(entry.state, State::Pending(_)))
+            {
+                entries.remove(&self.key);
+            }
+        }
+    }
+}
+
+impl IdempotencyCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The payload stored for `key`, if it has not expired
+    pub fn get(&self, key: &str) -> Option<JsonValue> {
+        self.get_at(key, Instant::now())
+    }
+
+    /// Remember `payload` as the result for `key`
+    pub fn insert(&self, key: String, payload: JsonValue) {
+        self.insert_at(key, State::Done(payload), Instant::now())
+    }
+
+    /// Claim `key` before running its request. Returns the stored result
+    /// if there is one; if another request with the key is in flight,
+    /// waits for it to finish first.
+    pub async fn claim(&self, key: &str) -> Claim<'_> {
+        loop {
+            let mut resolved = {
+                let now = Instant::now();
+                let mut entries = self.lock();
+                match entries.get(key).filter(|entry| self.is_live(entry, now)) {
+                    Some(Entry {
+                        state: State::Done(payload),
+                        ..
+                    }) => return Claim::Replay(payload.clone()),
+                    Some(Entry {
+                        state: State::Pending(sender),
+                        ..
+                    }) => sender.subscribe(),
+                    None => {
+                        let (sender, _) = watch::channel(());
+                        self.insert_locked(
+                            &mut entries,
+                            key.to_string(),
+                            State::Pending(sender),
+                            now,
+                        );
+                        return Claim::Run(InFlight {
+                            cache: self,
+                            key: key.to_string(),
+                            completed: false,
+                        });
+                    }
+                }
+            };
+            // Errors once the running request completes or gives up
+            let _ = resolved.changed().await;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn is_live(&self, entry: &Entry, now: Instant) -> bool {
+        matches!(entry.state, State::Pending(_))
+            || now.saturating_duration_since(entry.stored_at) < self.ttl
+    }
+
+    fn get_at(&self, key: &str, now: Instant) -> Option<JsonValue> {
+        let entries = self.lock();
+        match entries.get(key).filter(|entry| self.is_live(entry, now)) {
+            Some(Entry {
+                state: State::Done(payload),
+                ..
+            }) => Some(payload.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert_at(&self, key: String, state: State, now: Instant) {
+        self.insert_locked(&mut self.lock(), key, state, now)
+    }
+
+    fn insert_locked(
+        &self,
+        entries: &mut HashMap<String, Entry>,
+        key: String,
+        state: State,
+        now: Instant,
+    ) {
+        entries.retain(|_, entry| self.is_live(entry, now));
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            // Keys in flight are kept; their waiters need the result
+            if let Some(oldest) = entries
+                .iter()
+                .filter(|(_, entry)| matches!(entry.state, State::Done(_)))
+                .min_by_key(|(_, entry)| entry.stored_at)
+                .map(|(key, _)| key.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                state,
+                stored_at: now,
+            },
+        );
+    }
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 8);
+        let now = Instant::now();
+        cache.insert_at(
+            "key-a".to_string(),
+            State::Done(json!({ "run_id": "1" })),
+            now,
+        );
+
+        assert_eq!(
+            cache.get_at("key-a", now + Duration::from_secs(59)),
+            Some(json!({ "run_id": "1" }))
+        );
+        assert_eq!(cache.get_at("key-a", now + Duration::from_secs(60)), None);
+        assert_eq!(cache.get_at("key-b", now), None);
+    }
+
+    #[test]
+    fn test_oldest_entry_is_dropped_when_full() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 2);
+        let now = Instant::now();
+        for (i, key) in ["key-a", "key-b", "key-c"].into_iter().enumerate() {
+            cache.insert_at(
+                key.to_string(),
+                State::Done(json!(i)),
+                now + Duration::from_secs(i as u64),
+            );
+        }
+
+        let later = now + Duration::from_secs(3);
+        assert_eq!(cache.get_at("key-a", later), None);
+        assert_eq!(cache.get_at("key-b", later), Some(json!(1)));
+        assert_eq!(cache.get_at("key-c", later), Some(json!(2)));
+    }
+
+    #[tokio::test]
+    async fn test_retry_waits_for_the_request_in_flight() {
+        let cache = IdempotencyCache::default();
+        let Claim::Run(first) = cache.claim("key-a").await else {
+            panic!("first claim should run");
+        };
+        let retry = cache.claim("key-a");
+        tokio::pin!(retry);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut retry)
+                .await
+                .is_err(),
+            "retry must wait while the first request runs"
+        );
+
+        first.complete(json!({ "run_id": "1" }));
+        match retry.await {
+            Claim::Replay(payload) => assert_eq!(payload, json!({ "run_id": "1" })),
+            Claim::Run(_) => panic!("retry should replay the result"),
+        };
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_frees_the_key() {
+        let cache = IdempotencyCache::default();
+        let Claim::Run(first) = cache.claim("key-a").await else {
+            panic!("first claim should run");
+        };
+        drop(first);
+        assert!(matches!(cache.claim("key-a").await, Claim::Run(_)));
+    }
+}
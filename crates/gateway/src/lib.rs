@@ -5,9 +5,13 @@
 
 pub mod auth;
 pub mod handlers;
+pub mod http;
+pub mod idempotency;
 pub mod identity;
+pub mod metrics;
 pub mod presence;
 pub mod protocol;
+pub mod rate_limit;
 pub mod server;
 pub mod session;
 pub mod triggers;
@@ -15,8 +19,11 @@ pub mod triggers;
 pub use crate::protocol::PresenceEntry;
 pub use auth::TokenAuth;
 pub use handlers::MethodHandlers;
+pub use idempotency::IdempotencyCache;
 pub use identity::{DeviceIdentity, DeviceKeyPair};
+pub use metrics::GatewayMetrics;
 pub use presence::PresenceManager;
 pub use protocol::*;
-pub use server::{GatewayConfig, GatewayServer};
+pub use rate_limit::RateLimiter;
+pub use server::{ActiveConnection, ConnectionRegistry, GatewayConfig, GatewayServer};
 pub use session::GatewaySession;
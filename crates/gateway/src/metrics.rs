@@ -0,0 +1,234 @@
+// Gateway Metrics
+//
+// Counters and a latency histogram rendered in the Prometheus text
+// exposition format at `/metrics` (when `metrics_enabled` is set).
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Methods counted under their own label; anything else is `unknown`, so
+/// clients cannot grow the label set without bound
+const KNOWN_METHODS: [&str; 6] = [
+    "health",
+    "status",
+    "send",
+    "agent",
+    "agent.stream",
+    "cancel",
+];
+
+/// Upper bounds (seconds) of the agent latency histogram buckets
+const LATENCY_BUCKETS: [f64; 10] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// Gateway-wide metrics registry
+pub struct GatewayMetrics {
+    requests: Mutex<BTreeMap<&'static str, u64>>,
+    errors: Mutex<BTreeMap<String, u64>>,
+    connections_total: AtomicU64,
+    /// Per-bucket (non-cumulative) counts; the last slot is `+Inf`
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len() + 1],
+    /// Sum of observed latencies in microseconds
+    latency_sum_micros: AtomicU64,
+}
+
+impl GatewayMetrics {
+    pub fn new() -> Self {
+        Self {
+            requests: Mutex::new(BTreeMap::new()),
+            errors: Mutex::new(BTreeMap::new()),
+            connections_total: AtomicU64::new(0),
+            latency_buckets: Default::default(),
+            latency_sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a request for `method`
+    pub fn record_request(&self, method: &str) {
+        let label = KNOWN_METHODS
+            .iter()
+            .find(|known| **known == method)
+            .copied()
+            .unwrap_or("unknown");
+        *self
+            .requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(label)
+            .or_default() += 1;
+    }
+
+    /// Count an error response with protocol error `code`
+    pub fn record_error(&self, code: &str) {
+        *self
+            .errors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(code.to_string())
+            .or_default() += 1;
+    }
+
+    /// Count an accepted WebSocket connection
+    pub fn record_connection(&self) {
+        self.connections_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record how long one agent run took
+    pub fn observe_agent_latency(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.latency_buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Requests counted so far for `method`
+    pub fn request_count(&self, method: &str) -> u64 {
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(method)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Render all metrics in Prometheus text format; `active_connections` is
+    /// read from the connection registry by the caller
+    pub fn render(&self, active_connections: usize) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP gearclaw_gateway_requests_total Requests received, by method.\n");
+        out.push_str("# TYPE gearclaw_gateway_requests_total counter\n");
+        for (method, count) in self
+            .requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "gearclaw_gateway_requests_total{{method=\"{}\"}} {}",
+                method, count
+            );
+        }
+
+        out.push_str("# HELP gearclaw_gateway_errors_total Error responses, by error code.\n");
+        out.push_str("# TYPE gearclaw_gateway_errors_total counter\n");
+        for (code, count) in self
+            .errors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            let _ = writeln!(
+                out,
+                "gearclaw_gateway_errors_total{{code=\"{}\"}} {}",
+                escape_label(code),
+                count
+            );
+        }
+
+        out.push_str("# HELP gearclaw_gateway_active_connections Open WebSocket connections.\n");
+        out.push_str("# TYPE gearclaw_gateway_active_connections gauge\n");
+        let _ = writeln!(
+            out,
+            "gearclaw_gateway_active_connections {}",
+            active_connections
+        );
+
+        out.push_str("# HELP gearclaw_gateway_connections_total WebSocket connections accepted.\n");
+        out.push_str("# TYPE gearclaw_gateway_connections_total counter\n");
+        let _ = writeln!(
+            out,
+            "gearclaw_gateway_connections_total {}",
+            self.connections_total.load(Ordering::Relaxed)
+        );
+
+        out.push_str("# HELP gearclaw_gateway_agent_latency_seconds Agent run duration.\n");
+        out.push_str("# TYPE gearclaw_gateway_agent_latency_seconds histogram\n");
+        let mut cumulative = 0;
+        for (i, bucket) in self.latency_buckets.iter().enumerate() {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let bound = LATENCY_BUCKETS
+                .get(i)
+                .map_or_else(|| "+Inf".to_string(), |b| b.to_string());
+            let _ = writeln!(
+                out,
+                "gearclaw_gateway_agent_latency_seconds_bucket{{le=\"{}\"}} {}",
+                bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "gearclaw_gateway_agent_latency_seconds_sum {}",
+            self.latency_sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0
+        );
+        let _ = writeln!(
+            out,
+            "gearclaw_gateway_agent_latency_seconds_count {}",
+            cumulative
+        );
+
+        out
+    }
+}
+
+impl Default for GatewayMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_methods_share_one_label() {
+        let metrics = GatewayMetrics::new();
+        metrics.record_request("health");
+        metrics.record_request("cancel");
+        metrics.record_request("made.up");
+        metrics.record_request("another");
+        assert_eq!(metrics.request_count("health"), 1);
+        assert_eq!(metrics.request_count("cancel"), 1);
+        assert_eq!(metrics.request_count("unknown"), 2);
+    }
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let metrics = GatewayMetrics::new();
+        metrics.observe_agent_latency(Duration::from_millis(50));
+        metrics.observe_agent_latency(Duration::from_secs(3));
+        metrics.observe_agent_latency(Duration::from_secs(500));
+
+        let text = metrics.render(0);
+        assert!(text.contains("gearclaw_gateway_agent_latency_seconds_bucket{le=\"0.1\"} 1\n"));
+        assert!(text.contains("gearclaw_gateway_agent_latency_seconds_bucket{le=\"5\"} 2\n"));
+        assert!(text.contains("gearclaw_gateway_agent_latency_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("gearclaw_gateway_agent_latency_seconds_count 3\n"));
+    }
+
+    #[test]
+    fn test_render_includes_errors_and_connections() {
+        let metrics = GatewayMetrics::new();
+        metrics.record_error("UNAUTHORIZED");
+        metrics.record_connection();
+        let text = metrics.render(1);
+        assert!(text.contains("gearclaw_gateway_errors_total{code=\"UNAUTHORIZED\"} 1\n"));
+        assert!(text.contains("gearclaw_gateway_active_connections 1\n"));
+        assert!(text.contains("gearclaw_gateway_connections_total 1\n"));
+    }
+}
@@ -2,61 +2,107 @@
 //
 // This module manages device presence (online/offline status) and broadcasts updates.
 
-use crate::protocol::PresenceEntry;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use crate::protocol::{GatewayEvent, PresenceEntry, PresenceEvent};
+use std::sync::{PoisonError, RwLock};
+use tokio::sync::broadcast;
+
+/// Reason attached to entries removed from presence, so clients can tell a
+/// departure from an update in the delta.
+pub const REASON_DISCONNECT: &str = "disconnect";
+
+#[derive(Default)]
+struct PresenceState {
+    entries: Vec<PresenceEntry>,
+    version: u64,
+}
 
 /// Presence manager
+///
+/// Uses a std lock so connection drop guards can remove entries
+/// synchronously.
 pub struct PresenceManager {
-    /// Current presence entries
-    presence: Arc<RwLock<Vec<PresenceEntry>>>,
+    /// Current presence entries and the state version for delta tracking
+    state: RwLock<PresenceState>,
 
-    /// State version for delta tracking
-    state_version: Arc<RwLock<u64>>,
+    /// Where presence deltas are broadcast, if anywhere
+    event_tx: Option<broadcast::Sender<GatewayEvent>>,
 }
 
 impl PresenceManager {
     pub fn new() -> Self {
         Self {
-            presence: Arc::new(RwLock::new(Vec::new())),
-            state_version: Arc::new(RwLock::new(0)),
+            state: RwLock::new(PresenceState::default()),
+            event_tx: None,
         }
     }
 
-    /// Add or update a presence entry
-    pub async fn update(&self, entry: PresenceEntry) {
-        let mut presence = self.presence.write().await;
-        let mut state_version = self.state_version.write().await;
-
-        // Remove existing entry for same host
-        presence.retain(|e| e.host != entry.host);
-
-        // Add new entry
-        presence.push(entry);
-
-        // Increment state version
-        *state_version += 1;
+    /// Broadcast a `presence` event for every change
+    pub fn with_events(mut self, event_tx: broadcast::Sender<GatewayEvent>) -> Self {
+        self.event_tx = Some(event_tx);
+        self
     }
 
-    /// Remove a presence entry
-    pub async fn remove(&self, host: &str) {
-        let mut presence = self.presence.write().await;
-        let mut state_version = self.state_version.write().await;
-
-        presence.retain(|e| e.host != host);
+    /// Add or update a presence entry, returning the new state version
+    pub fn update(&self, entry: PresenceEntry) -> u64 {
+        let version = {
+            let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+            // Replace any existing entry for the same host
+            state.entries.retain(|e| e.host != entry.host);
+            state.entries.push(entry.clone());
+            state.version += 1;
+            state.version
+        };
+        self.broadcast(entry, version);
+        version
+    }
 
-        *state_version += 1;
+    /// Remove a presence entry, returning the new state version, or `None`
+    /// if no entry existed for `host`
+    pub fn remove(&self, host: &str) -> Option<u64> {
+        let (mut removed, version) = {
+            let mut state = self.state.write().unwrap_or_else(PoisonError::into_inner);
+            let index = state.entries.iter().position(|e| e.host == host)?;
+            let removed = state.entries.remove(index);
+            state.version += 1;
+            (removed, state.version)
+        };
+        removed.reason = Some(REASON_DISCONNECT.to_string());
+        removed.ts = now_secs();
+        self.broadcast(removed, version);
+        Some(version)
     }
 
     /// Get current presence snapshot
-    pub async fn snapshot(&self) -> Vec<PresenceEntry> {
-        let presence = self.presence.read().await;
-        presence.clone()
+    pub fn snapshot(&self) -> Vec<PresenceEntry> {
+        self.state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entries
+            .clone()
     }
 
     /// Get current state version
-    pub async fn state_version(&self) -> u64 {
-        *self.state_version.read().await
+    pub fn state_version(&self) -> u64 {
+        self.state
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .version
+    }
+
+    /// Snapshot and version read under one lock, for hello-ok
+    pub fn snapshot_with_version(&self) -> (Vec<PresenceEntry>, u64) {
+        let state = self.state.read().unwrap_or_else(PoisonError::into_inner);
+        (state.entries.clone(), state.version)
+    }
+
+    fn broadcast(&self, entry: PresenceEntry, state_version: u64) {
+        if let Some(event_tx) = &self.event_tx {
+            // No subscribers is not an error
+            let _ = event_tx.send(GatewayEvent::Presence(PresenceEvent {
+                updates: vec![entry],
+                state_version,
+            }));
+        }
     }
 }
 
@@ -65,3 +111,56 @@ impl Default for PresenceManager {
         Self::new()
     }
 }
+
+/// Build a presence entry for `host` in the given mode, stamped now
+pub fn presence_entry(host: impl Into<String>, mode: impl Into<String>) -> PresenceEntry {
+    PresenceEntry {
+        host: host.into(),
+        ip: None,
+        version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        platform: None,
+        device_family: None,
+        model_identifier: None,
+        mode: mode.into(),
+        last_input_seconds: None,
+        ts: now_secs(),
+        reason: None,
+        tags: Vec::new(),
+        instance_id: None,
+    }
+}
+
+fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_and_remove_bump_version_and_broadcast() {
+        let (event_tx, mut event_rx) = broadcast::channel(16);
+        let presence = PresenceManager::new().with_events(event_tx);
+
+        assert_eq!(presence.update(presence_entry("a", "client")), 1);
+        assert_eq!(presence.update(presence_entry("a", "client")), 2);
+        assert_eq!(presence.snapshot().len(), 1);
+
+        assert_eq!(presence.remove("a"), Some(3));
+        assert_eq!(presence.remove("a"), None);
+        assert_eq!(presence.state_version(), 3);
+
+        let mut versions = Vec::new();
+        while let Ok(GatewayEvent::Presence(event)) = event_rx.try_recv() {
+            versions.push(event.state_version);
+            if event.state_version == 3 {
+                assert_eq!(event.updates[0].reason.as_deref(), Some(REASON_DISCONNECT));
+            }
+        }
+        assert_eq!(versions, vec![1, 2, 3]);
+    }
+}
@@ -48,6 +48,11 @@ pub struct GatewayRequest {
     /// Request signature (for authentication)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub signature: Option<String>,
+
+    /// Client-chosen key making `send`/`agent` safe to retry: a repeated key
+    /// returns the first result instead of running again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
 }
 
 impl GatewayRequest {
@@ -59,6 +64,7 @@ impl GatewayRequest {
             sequence: None,
             device_id: None,
             signature: None,
+            idempotency_key: None,
         }
     }
 
@@ -77,6 +83,11 @@ impl GatewayRequest {
         self
     }
 
+    pub fn with_idempotency_key(mut self, key: String) -> Self {
+        self.idempotency_key = Some(key);
+        self
+    }
+
     pub fn id(&self) -> &str {
         &self.id
     }
@@ -163,6 +174,8 @@ impl ProtocolError {
     pub const UNAUTHORIZED: &'static str = "UNAUTHORIZED";
     pub const NOT_FOUND: &'static str = "NOT_FOUND";
     pub const INTERNAL_ERROR: &'static str = "INTERNAL_ERROR";
+    pub const PAYLOAD_TOO_LARGE: &'static str = "PAYLOAD_TOO_LARGE";
+    pub const RATE_LIMITED: &'static str = "RATE_LIMITED";
 
     pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
         Self {
@@ -217,6 +230,10 @@ pub struct AgentEvent {
     /// Event sequence number
     pub seq: u64,
 
+    /// Id of the request that started the run, for connection-scoped streams
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+
     /// Event type
     #[serde(flatten)]
     pub content: AgentEventContent,
@@ -244,6 +261,14 @@ pub enum AgentEventContent {
         #[serde(skip_serializing_if = "Option::is_none")]
         metadata: Option<JsonValue>,
     },
+
+    /// Run finished; terminal event carrying the full response
+    #[serde(rename = "done")]
+    Done { run_id: String, response: String },
+
+    /// Run failed; terminal event
+    #[serde(rename = "error")]
+    Error { run_id: String, message: String },
 }
 
 /// Presence event - device online/offline
@@ -423,6 +448,16 @@ pub struct GatewayPolicy {
     pub tick_interval_ms: u64,
 }
 
+impl Default for GatewayPolicy {
+    fn default() -> Self {
+        Self {
+            max_payload: 1024 * 1024,             // 1MB
+            max_buffered_bytes: 10 * 1024 * 1024, // 10MB
+            tick_interval_ms: 30000,              // 30 seconds
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
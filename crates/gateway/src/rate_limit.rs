@@ -0,0 +1,94 @@
+// Per-token Rate Limiting
+//
+// Token buckets keyed by the authenticated token, so one credential cannot
+// flood the gateway (and the LLM quota behind it).
+
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Token-bucket rate limiter allowing `per_minute` requests per key, with
+/// bursts up to the same amount.
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute: per_minute.max(1),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take one request from `key`'s bucket, or return how long until one
+    /// is available.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let capacity = f64::from(self.per_minute);
+        let per_second = capacity / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            refilled_at: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * per_second).min(capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_burst_then_rejects() {
+        let limiter = RateLimiter::new(3);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_at("token-a", now).is_ok());
+        }
+        let retry_after = limiter.check_at("token-a", now).unwrap_err();
+        assert!(retry_after > Duration::from_secs(19) && retry_after <= Duration::from_secs(20));
+    }
+
+    #[test]
+    fn test_refills_over_time() {
+        let limiter = RateLimiter::new(60);
+        let now = Instant::now();
+        for _ in 0..60 {
+            limiter.check_at("token-a", now).unwrap();
+        }
+        assert!(limiter.check_at("token-a", now).is_err());
+        assert!(limiter
+            .check_at("token-a", now + Duration::from_secs(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_buckets_are_per_key() {
+        let limiter = RateLimiter::new(1);
+        let now = Instant::now();
+        assert!(limiter.check_at("token-a", now).is_ok());
+        assert!(limiter.check_at("token-a", now).is_err());
+        assert!(limiter.check_at("token-b", now).is_ok());
+    }
+}
@@ -1,10 +1,20 @@
 use crate::auth::TokenAuth;
 use crate::handlers::MethodHandlers;
+use crate::idempotency::Claim;
+use crate::presence::{presence_entry, PresenceManager};
 use crate::protocol::*;
+use crate::rate_limit::RateLimiter;
 use anyhow::{Context, Result};
 use futures_util::{sink::SinkExt, stream::StreamExt};
-use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use gearclaw_core::session::sanitize_session_id;
+use std::sync::{Arc, PoisonError};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{Instant, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
 use tokio_tungstenite::tungstenite::Message;
 
 /// Gateway configuration
@@ -14,6 +24,18 @@ pub struct GatewayConfig {
     pub port: u16,
     pub ws_path: String,
     pub allow_unauthenticated_requests: bool,
+    /// Limits advertised in hello-ok and enforced on each connection
+    pub policy: GatewayPolicy,
+    /// How long a client has to answer a keepalive ping before it is dropped
+    pub pong_timeout_ms: u64,
+    /// Requests allowed per token per minute; `None` disables the limit
+    pub rate_limit_per_minute: Option<u32>,
+    /// Accept `port: 0` and let the OS pick a free port. Off by default so
+    /// a zero port left in a config file is reported instead of silently
+    /// serving on a random port.
+    pub allow_ephemeral_port: bool,
+    /// Serve Prometheus metrics at `/metrics` (unauthenticated, like `/healthz`)
+    pub metrics_enabled: bool,
 }
 
 impl Default for GatewayConfig {
@@ -23,10 +45,33 @@ impl Default for GatewayConfig {
             port: 18789,
             ws_path: "/ws".to_string(),
             allow_unauthenticated_requests: false,
+            policy: GatewayPolicy::default(),
+            pong_timeout_ms: 10_000,
+            rate_limit_per_minute: Some(60),
+            allow_ephemeral_port: false,
+            metrics_enabled: false,
         }
     }
 }
 
+impl GatewayConfig {
+    /// Resolve `host:port` to the address to listen on, rejecting an
+    /// unparseable host or an unintended port 0
+    pub async fn resolve_bind_addr(&self) -> Result<std::net::SocketAddr> {
+        if self.port == 0 && !self.allow_ephemeral_port {
+            anyhow::bail!(
+                "Gateway port 0 is not allowed; set a port (default 18789) or enable the ephemeral port option"
+            );
+        }
+        let mut addrs = tokio::net::lookup_host((self.host.as_str(), self.port))
+            .await
+            .with_context(|| format!("Invalid gateway host '{}'", self.host))?;
+        addrs
+            .next()
+            .with_context(|| format!("Gateway host '{}' resolved to no address", self.host))
+    }
+}
+
 const MAX_SESSION_ID_LENGTH: usize = 128;
 
 fn validate_agent_session_id(session_id: &str) -> Result<(), ProtocolError> {
@@ -77,7 +122,7 @@ fn validate_request(request: &GatewayRequest) -> Result<(), ProtocolError> {
         ));
     }
 
-    if request.method == "agent" {
+    if request.method == "agent" || request.method == "agent.stream" {
         let session_id = request.device_id.as_deref().unwrap_or(request.id.as_str());
         validate_agent_session_id(session_id)?;
     }
@@ -85,13 +130,27 @@ fn validate_request(request: &GatewayRequest) -> Result<(), ProtocolError> {
     Ok(())
 }
 
-async fn authorize_request(
+/// Cache key for a `send`/`agent` request carrying an idempotency key,
+/// scoped to the caller's token (or device) and method so unrelated clients
+/// cannot collide
+fn idempotency_key(request: &GatewayRequest, token: Option<&str>) -> Option<String> {
+    if !matches!(request.method.as_str(), "send" | "agent") {
+        return None;
+    }
+    let key = request.idempotency_key.as_deref()?;
+    let caller = token.or(request.device_id.as_deref()).unwrap_or_default();
+    Some(format!("{}\n{}\n{}", caller, request.method, key))
+}
+
+/// Check the request's token. Returns the validated token (without the
+/// `Bearer ` prefix), or `None` when unauthenticated requests are allowed.
+async fn authorize_request<'a>(
     auth: &TokenAuth,
-    request: &GatewayRequest,
+    request: &'a GatewayRequest,
     allow_unauthenticated_requests: bool,
-) -> Result<(), ProtocolError> {
+) -> Result<Option<&'a str>, ProtocolError> {
     if allow_unauthenticated_requests {
-        return Ok(());
+        return Ok(None);
     }
 
     let token = request.signature.as_deref().ok_or_else(|| {
@@ -108,8 +167,29 @@ async fn authorize_request(
         ));
     }
 
+    if !auth.is_method_allowed(token, &request.method).await {
+        return Err(ProtocolError::new(
+            ProtocolError::UNAUTHORIZED,
+            format!("Token is not permitted to call '{}'", request.method),
+        ));
+    }
+
     auth.update_last_used(token).await;
-    Ok(())
+    Ok(Some(TokenAuth::normalize_token(token)))
+}
+
+fn check_rate_limit(limiter: Option<&RateLimiter>, token: &str) -> Result<(), ProtocolError> {
+    let Some(limiter) = limiter else {
+        return Ok(());
+    };
+    limiter.check(token).map_err(|retry_after| {
+        let retry_after_ms = retry_after.as_millis().max(1) as u64;
+        ProtocolError::new(
+            ProtocolError::RATE_LIMITED,
+            format!("Rate limit exceeded, retry after {} ms", retry_after_ms),
+        )
+        .with_retryable(retry_after_ms)
+    })
 }
 
 /// Active connection information
@@ -120,14 +200,70 @@ pub struct ActiveConnection {
     pub connected_at: std::time::SystemTime,
 }
 
+/// Live connections, shared between the accept loop and request handlers.
+/// A std lock so the drop guard can clean up synchronously.
+pub type ConnectionRegistry = Arc<std::sync::RwLock<Vec<ActiveConnection>>>;
+
+/// Registers a connection (and its presence entry) on creation and removes
+/// both on drop, so cleanup runs however the connection loop exits.
+struct ConnectionGuard {
+    id: String,
+    /// Presence host of this connection
+    presence_host: String,
+    connections: ConnectionRegistry,
+    presence: Arc<PresenceManager>,
+}
+
+impl ConnectionGuard {
+    fn register(
+        connections: &ConnectionRegistry,
+        presence: &Arc<PresenceManager>,
+        id: String,
+        addr: String,
+    ) -> Self {
+        connections
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(ActiveConnection {
+                id: id.clone(),
+                addr,
+                connected_at: std::time::SystemTime::now(),
+            });
+        // Presence goes to every socket, so it names the client by a fresh
+        // id: no peer address, and not the connection id, which scopes
+        // `cancel` for unauthenticated clients
+        let presence_host = format!("client:{}", uuid::Uuid::new_v4());
+        presence.update(presence_entry(presence_host.clone(), "client"));
+        Self {
+            id,
+            presence_host,
+            connections: connections.clone(),
+            presence: presence.clone(),
+        }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.connections
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|conn| conn.id != self.id);
+        self.presence.remove(&self.presence_host);
+    }
+}
+
 /// Gateway server
 pub struct GatewayServer {
-    config: GatewayConfig,
+    config: Arc<GatewayConfig>,
     handlers: Arc<MethodHandlers>,
     auth: Arc<TokenAuth>,
-    connections: Arc<RwLock<Vec<ActiveConnection>>>,
+    connections: ConnectionRegistry,
+    rate_limiter: Option<Arc<RateLimiter>>,
     /// Event broadcast channel - sends events to all connected clients
     event_tx: broadcast::Sender<GatewayEvent>,
+    /// Connected clients and channel adapters; changes are broadcast on `event_tx`
+    presence: Arc<PresenceManager>,
 }
 
 impl GatewayServer {
@@ -135,11 +271,17 @@ impl GatewayServer {
         // Create event broadcast channel with capacity for 100 events
         let (event_tx, _) = broadcast::channel(100);
 
+        let rate_limiter = config
+            .rate_limit_per_minute
+            .map(|per_minute| Arc::new(RateLimiter::new(per_minute)));
+
         Self {
-            config,
+            rate_limiter,
+            config: Arc::new(config),
             handlers: Arc::new(MethodHandlers::new()),
             auth: Arc::new(TokenAuth::new()),
-            connections: Arc::new(RwLock::new(Vec::new())),
+            connections: ConnectionRegistry::default(),
+            presence: Arc::new(PresenceManager::new().with_events(event_tx.clone())),
             event_tx,
         }
     }
@@ -159,9 +301,12 @@ impl GatewayServer {
         &self,
         adapter: T,
     ) -> Result<()> {
+        let platform = adapter.platform_name().to_string();
         let channel_manager = self.handlers.channel_manager();
         let mut manager = channel_manager.lock().await;
         manager.register(Box::new(adapter));
+        self.presence
+            .update(presence_entry(channel_host(&platform), "channel"));
         tracing::info!("Channel adapter registered: {}", std::any::type_name::<T>());
         Ok(())
     }
@@ -171,13 +316,43 @@ impl GatewayServer {
         self.event_tx.clone()
     }
 
+    /// Presence shared by connections and channel adapters
+    pub fn presence(&self) -> Arc<PresenceManager> {
+        self.presence.clone()
+    }
+
     /// Get handlers reference for configuration (e.g., setting Agent)
     pub fn handlers(&self) -> Arc<MethodHandlers> {
         self.handlers.clone()
     }
 
+    /// Bind the listening socket, turning common failures into actionable
+    /// errors
+    pub async fn bind(&self) -> Result<tokio::net::TcpListener> {
+        let addr = self.config.resolve_bind_addr().await?;
+        tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AddrInUse => anyhow::anyhow!(
+                    "Port {} on {} is already in use; stop the other process or choose another port (--port or gateway.port)",
+                    addr.port(),
+                    addr.ip()
+                ),
+                std::io::ErrorKind::PermissionDenied => anyhow::anyhow!(
+                    "Permission denied binding {}; ports below 1024 usually need elevated privileges, choose a higher port",
+                    addr
+                ),
+                std::io::ErrorKind::AddrNotAvailable => anyhow::anyhow!(
+                    "Address {} is not available on this machine; check gateway.host",
+                    addr.ip()
+                ),
+                _ => anyhow::Error::new(e).context(format!("Failed to bind {}", addr)),
+            })
+    }
+
     pub async fn start(self) -> Result<()> {
-        use tokio::net::TcpListener;
+        // Bind first so a bad address fails before any background work starts
+        let listener = self.bind().await?;
 
         // Start channel message listener
         self.start_channel_listener().await?;
@@ -188,9 +363,13 @@ impl GatewayServer {
         //   discord.start().await?;
         //   channel_manager.register(Box::new(discord));
 
-        let addr = format!("{}:{}", self.config.host, self.config.port);
-        let listener = TcpListener::bind(&addr).await?;
-        tracing::info!("Gateway server listening on {}", addr);
+        let local_addr = listener.local_addr()?;
+        tracing::info!("Gateway server listening on {}", local_addr);
+        tracing::info!(
+            "Health probe at http://{}{}",
+            local_addr,
+            crate::http::HEALTH_PATH
+        );
 
         loop {
             if let Ok((stream, addr)) = listener.accept().await {
@@ -198,7 +377,9 @@ impl GatewayServer {
                 let auth = self.auth.clone();
                 let connections = self.connections.clone();
                 let event_rx = self.event_tx.subscribe();
-                let allow_unauthenticated_requests = self.config.allow_unauthenticated_requests;
+                let presence = self.presence.clone();
+                let config = self.config.clone();
+                let rate_limiter = self.rate_limiter.clone();
                 tokio::spawn(async move {
                     if let Err(e) = handle_connection(
                         stream,
@@ -206,8 +387,10 @@ impl GatewayServer {
                         handlers,
                         auth,
                         connections,
+                        presence,
                         event_rx,
-                        allow_unauthenticated_requests,
+                        config,
+                        rate_limiter,
                     )
                     .await
                     {
@@ -225,6 +408,7 @@ impl GatewayServer {
         let channel_manager = self.handlers.channel_manager();
         let event_tx = self.event_tx.clone();
         let handlers = self.handlers.clone();
+        let presence = self.presence.clone();
 
         tokio::spawn(async move {
             tracing::info!("Channel message listener started");
@@ -244,6 +428,7 @@ impl GatewayServer {
                 let event_tx = event_tx.clone();
                 let channel_manager = channel_manager.clone();
                 let handlers_clone = handlers.clone();
+                let presence = presence.clone();
 
                 tokio::spawn(async move {
                     let mgr = channel_manager.lock().await;
@@ -296,12 +481,13 @@ impl GatewayServer {
                             let _ = event_tx.send(event);
 
                             // Check if Agent should be triggered based on config
-                            let trigger_config = handlers_clone.get_trigger_config().await;
+                            let triggers = handlers_clone.get_trigger_matcher().await;
                             let should_trigger = crate::triggers::should_trigger_agent(
                                 &platform,
                                 &source,
                                 &incoming.content,
-                                &trigger_config,
+                                &incoming.addressing,
+                                &triggers,
                             );
 
                             if should_trigger {
@@ -310,25 +496,35 @@ impl GatewayServer {
                                     let platform_clone = platform.clone();
                                     let source_clone = source.clone();
                                     let content_clone = incoming.content.clone();
+                                    let attachments = incoming.attachments.clone();
                                     let channel_mgr = channel_manager.clone();
 
+                                    let metrics = handlers_clone.metrics();
+
                                     // Process in background task
                                     tokio::spawn(async move {
-                                        if let Err(e) = process_agent_response(
+                                        let started = std::time::Instant::now();
+                                        let result = process_agent_response(
                                             agent,
                                             &platform_clone,
                                             &source_clone,
                                             &content_clone,
+                                            &attachments,
                                             channel_mgr,
                                         )
-                                        .await
-                                        {
+                                        .await;
+                                        metrics.observe_agent_latency(started.elapsed());
+                                        if let Err(e) = result {
                                             tracing::error!("Agent processing failed: {}", e);
                                         }
                                     });
                                 }
                             }
                         }
+
+                        // The adapter's stream ended; it is no longer present
+                        tracing::info!("Message stream from platform {} ended", platform);
+                        presence.remove(&channel_host(&platform));
                     }
                 });
             }
@@ -340,33 +536,89 @@ impl GatewayServer {
     }
 }
 
+/// Handshake response for upgrades on a path other than `ws_path`
+fn not_found() -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some("Not Found".to_string()));
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    response
+}
+
 // Handle a WebSocket connection
+#[allow(clippy::too_many_arguments)]
 async fn handle_connection(
     stream: tokio::net::TcpStream,
     addr: String,
     handlers: Arc<MethodHandlers>,
     auth: Arc<TokenAuth>,
-    _connections: Arc<RwLock<Vec<ActiveConnection>>>,
+    connections: ConnectionRegistry,
+    presence: Arc<PresenceManager>,
     mut event_rx: broadcast::Receiver<GatewayEvent>,
-    allow_unauthenticated_requests: bool,
+    config: Arc<GatewayConfig>,
+    rate_limiter: Option<Arc<RateLimiter>>,
 ) -> Result<()> {
+    // Plain HTTP probes (e.g. GET /healthz) are answered before any upgrade
+    if let Some((method, path)) = crate::http::peek_request_line(&stream).await {
+        if let Some(response) =
+            crate::http::route(&method, &path, &handlers, &connections, &config).await
+        {
+            tracing::debug!("HTTP {} {} from {}", method, path, addr);
+            crate::http::respond(stream, method == "HEAD", response).await?;
+            return Ok(());
+        }
+    }
+
+    let allow_unauthenticated_requests = config.allow_unauthenticated_requests;
     // Upgrade to WebSocket
-    let ws_stream = tokio_tungstenite::accept_async(stream)
-        .await
-        .context("WebSocket handshake failed")?;
+    // Hard cap at the transport layer; frames above `max_payload` but within
+    // this are rejected below with a protocol error instead of a dropped socket.
+    let ws_config = WebSocketConfig::default()
+        .max_message_size(Some(config.policy.max_buffered_bytes))
+        .max_frame_size(Some(config.policy.max_buffered_bytes));
+    let ws_path = config.ws_path.clone();
+    // The callback signature is fixed by tungstenite.
+    #[allow(clippy::result_large_err)]
+    let check_path = move |request: &Request, response: Response| {
+        if request.uri().path() == ws_path {
+            Ok(response)
+        } else {
+            tracing::warn!(
+                "Rejecting WebSocket upgrade on '{}' (expected '{}')",
+                request.uri().path(),
+                ws_path
+            );
+            Err(not_found())
+        }
+    };
+    let ws_stream =
+        tokio_tungstenite::accept_hdr_async_with_config(stream, check_path, Some(ws_config))
+            .await
+            .context("WebSocket handshake failed")?;
 
     tracing::info!("WebSocket connection established from {}", addr);
 
     let conn_id = uuid::Uuid::new_v4().to_string();
+    let _guard = ConnectionGuard::register(&connections, &presence, conn_id.clone(), addr.clone());
+    handlers.metrics().record_connection();
+    // Frames addressed to this connection only, e.g. agent.stream events.
+    let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel::<GatewayFrame>();
+    // Replies to requests handled off the read loop
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel::<GatewayResponse>();
     let (mut ws_sender, mut ws_receiver) = ws_stream.split::<Message>();
 
     // Send hello-ok
-    let hello_payload = serde_json::to_value(create_hello_ok()).unwrap();
+    let hello_payload = serde_json::to_value(create_hello_ok(&config.policy, &presence)).unwrap();
     let hello_response =
         GatewayFrame::Response(GatewayResponse::ok("hello".to_string(), hello_payload));
     let hello_msg = serde_json::to_string(&hello_response)?;
     ws_sender.send(Message::Text(hello_msg.into())).await?;
 
+    // Keepalive: ping every tick and drop the client if the pong is late.
+    let tick = Duration::from_millis(config.policy.tick_interval_ms.max(1));
+    let pong_timeout = Duration::from_millis(config.pong_timeout_ms);
+    let mut ping_interval = tokio::time::interval_at(Instant::now() + tick, tick);
+    ping_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut pong_deadline: Option<Instant> = None;
+
     // Connection loop with both request handling and event broadcasting
     loop {
         tokio::select! {
@@ -374,18 +626,80 @@ async fn handle_connection(
             msg_result = ws_receiver.next() => {
                 match msg_result {
                     Some(Ok(msg)) => {
+                        if (msg.is_text() || msg.is_binary())
+                            && msg.len() > config.policy.max_payload
+                        {
+                            tracing::warn!(
+                                "Connection {} sent a {} byte frame (max {}), closing",
+                                conn_id,
+                                msg.len(),
+                                config.policy.max_payload
+                            );
+                            let response = GatewayResponse::error(
+                                String::new(),
+                                ProtocolError::new(
+                                    ProtocolError::PAYLOAD_TOO_LARGE,
+                                    format!(
+                                        "Frame of {} bytes exceeds max_payload of {} bytes",
+                                        msg.len(),
+                                        config.policy.max_payload
+                                    ),
+                                ),
+                            );
+                            ws_sender
+                                .send(Message::Text(serde_json::to_string(&response)?.into()))
+                                .await?;
+                            let _ = ws_sender
+                                .send(Message::Close(Some(CloseFrame {
+                                    code: CloseCode::Size,
+                                    reason: "payload too large".into(),
+                                })))
+                                .await;
+                            break;
+                        }
+
                         if msg.is_text() {
                             let text = msg.to_text()?;
                             tracing::debug!("Received message: {}", text);
 
                             // Parse frame
-                            if let Ok(frame) = serde_json::from_str::<GatewayFrame>(text) {
-                                if let GatewayFrame::Request(request) = frame {
+                            if let Ok(GatewayFrame::Request(request)) =
+                                serde_json::from_str::<GatewayFrame>(text)
+                            {
+                                if request.method == "agent" {
+                                    // Runs off the read loop so a `cancel` sent on
+                                    // this socket is still read while it is in flight
+                                    let handlers = handlers.clone();
+                                    let auth = auth.clone();
+                                    let connections = connections.clone();
+                                    let outbound = outbound_tx.clone();
+                                    let reply = reply_tx.clone();
+                                    let conn_id = conn_id.clone();
+                                    let rate_limiter = rate_limiter.clone();
+                                    tokio::spawn(async move {
+                                        let response = handle_request(
+                                            &request,
+                                            &handlers,
+                                            &auth,
+                                            &connections,
+                                            &outbound,
+                                            &conn_id,
+                                            rate_limiter.as_deref(),
+                                            allow_unauthenticated_requests,
+                                        )
+                                        .await;
+                                        let _ = reply.send(response);
+                                    });
+                                } else {
                                     // Handle request
                                     let response = handle_request(
                                         &request,
                                         &handlers,
                                         &auth,
+                                        &connections,
+                                        &outbound_tx,
+                                        &conn_id,
+                                        rate_limiter.as_deref(),
                                         allow_unauthenticated_requests,
                                     )
                                     .await;
@@ -395,6 +709,8 @@ async fn handle_connection(
                                     ws_sender.send(Message::Text(response_msg.into())).await?;
                                 }
                             }
+                        } else if msg.is_pong() {
+                            pong_deadline = None;
                         } else if msg.is_close() {
                             break;
                         }
@@ -407,6 +723,17 @@ async fn handle_connection(
                 }
             }
 
+            Some(response) = reply_rx.recv() => {
+                let response_msg = serde_json::to_string(&response)?;
+                ws_sender.send(Message::Text(response_msg.into())).await?;
+            }
+
+            // Forward connection-scoped frames
+            Some(frame) = outbound_rx.recv() => {
+                let frame_msg = serde_json::to_string(&frame)?;
+                ws_sender.send(Message::Text(frame_msg.into())).await?;
+            }
+
             // Handle broadcasted events
             event_result = event_rx.recv() => {
                 match event_result {
@@ -425,6 +752,21 @@ async fn handle_connection(
                     }
                 }
             }
+
+            _ = ping_interval.tick() => {
+                if pong_deadline.is_none() {
+                    ws_sender.send(Message::Ping(Vec::new().into())).await?;
+                    pong_deadline = Some(Instant::now() + pong_timeout);
+                }
+            }
+
+            _ = tokio::time::sleep_until(pong_deadline.unwrap_or_else(Instant::now)),
+                if pong_deadline.is_some() =>
+            {
+                tracing::warn!("Connection {} missed keepalive pong, closing", conn_id);
+                let _ = ws_sender.send(Message::Close(None)).await;
+                break;
+            }
         }
     }
 
@@ -433,29 +775,108 @@ async fn handle_connection(
     Ok(())
 }
 
-/// Handle incoming request
+/// Handle incoming request from connection `conn_id`
+#[allow(clippy::too_many_arguments)]
 async fn handle_request(
     request: &GatewayRequest,
     handlers: &MethodHandlers,
     auth: &TokenAuth,
+    connections: &ConnectionRegistry,
+    outbound: &mpsc::UnboundedSender<GatewayFrame>,
+    conn_id: &str,
+    rate_limiter: Option<&RateLimiter>,
     allow_unauthenticated_requests: bool,
 ) -> GatewayResponse {
-    if let Err(error) = authorize_request(auth, request, allow_unauthenticated_requests).await {
-        return GatewayResponse::error(request.id.clone(), error);
+    let metrics = handlers.metrics();
+    metrics.record_request(&request.method);
+    let response = dispatch_request(
+        request,
+        handlers,
+        auth,
+        connections,
+        outbound,
+        conn_id,
+        rate_limiter,
+        allow_unauthenticated_requests,
+    )
+    .await;
+    if let Some(error) = &response.error {
+        metrics.record_error(&error.code);
+    }
+    response
+}
+
+/// Authorize, validate and route one request
+#[allow(clippy::too_many_arguments)]
+async fn dispatch_request(
+    request: &GatewayRequest,
+    handlers: &MethodHandlers,
+    auth: &TokenAuth,
+    connections: &ConnectionRegistry,
+    outbound: &mpsc::UnboundedSender<GatewayFrame>,
+    conn_id: &str,
+    rate_limiter: Option<&RateLimiter>,
+    allow_unauthenticated_requests: bool,
+) -> GatewayResponse {
+    let token = match authorize_request(auth, request, allow_unauthenticated_requests).await {
+        Ok(token) => token,
+        Err(error) => return GatewayResponse::error(request.id.clone(), error),
+    };
+    if let Some(token) = token {
+        if let Err(error) = check_rate_limit(rate_limiter, token) {
+            return GatewayResponse::error(request.id.clone(), error);
+        }
     }
     if let Err(error) = validate_request(request) {
         return GatewayResponse::error(request.id.clone(), error);
     }
 
+    // A retried `send`/`agent` with a known key gets the first result back,
+    // waiting for it if the first request is still running
+    let idempotency = handlers.idempotency();
+    let in_flight = match idempotency_key(request, token) {
+        Some(key) => match idempotency.claim(&key).await {
+            Claim::Replay(payload) => {
+                tracing::info!(
+                    "Replaying cached result for {} request {}",
+                    request.method,
+                    request.id
+                );
+                return GatewayResponse::ok(request.id.clone(), payload);
+            }
+            Claim::Run(in_flight) => Some(in_flight),
+        },
+        None => None,
+    };
+
+    // Runs are cancellable only by the token (or connection) that started them
+    let scope = token.unwrap_or(conn_id);
+
     // Route to handler
     let result = match request.method.as_str() {
         "health" => handlers.health(request).await,
-        "status" => handlers.status(request).await,
+        "status" => {
+            let active = connections
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone();
+            handlers.status(request, &active).await
+        }
         "send" => handlers.send(request).await,
-        "agent" => handlers.agent_execute(request).await,
+        "agent" => handlers.agent_execute(request, scope).await,
+        "agent.stream" => {
+            handlers
+                .agent_stream(request, scope, outbound.clone())
+                .await
+        }
+        "cancel" => handlers.cancel(request, scope).await,
         _ => Err(anyhow::anyhow!("Unknown method: {}", request.method)),
     };
 
+    if let (Some(in_flight), Ok(payload)) = (in_flight, &result) {
+        in_flight.complete(payload.clone());
+    }
+
     match result {
         Ok(payload) => GatewayResponse::ok(request.id.clone(), payload),
         Err(e) => GatewayResponse::error(
@@ -471,6 +892,7 @@ async fn process_agent_response(
     platform: &str,
     source: &ChannelSource,
     content: &str,
+    attachments: &[gearclaw_channels::IncomingAttachment],
     channel_manager: Arc<tokio::sync::Mutex<gearclaw_channels::ChannelManager>>,
 ) -> Result<()> {
     use gearclaw_channels::MessageContent;
@@ -486,33 +908,44 @@ async fn process_agent_response(
     );
 
     // Create session ID from platform and source info
-    let session_id = match source {
+    let session_id = sanitize_session_id(&match source {
         ChannelSource::User { id, .. } => format!("{}:user:{}", platform, id),
         ChannelSource::Channel { id, .. } => format!("{}:channel:{}", platform, id),
         ChannelSource::Group { id, .. } => format!("{}:group:{}", platform, id),
-    };
+    });
 
     // Get or create session
     let mut session = agent
         .session_manager
         .get_or_create_session(&session_id)
         .map_err(|e| anyhow::anyhow!("Failed to get session: {}", e))?;
+    let source_id = match source {
+        ChannelSource::User { id, .. }
+        | ChannelSource::Channel { id, .. }
+        | ChannelSource::Group { id, .. } => id,
+    };
+    agent.apply_channel_overrides(&mut session, platform, source_id);
 
     // Add context about message source
     let context_msg = format!(
-        "[Message from {}:{}]: {}",
+        "[Message from {}:{}]: {}{}",
         platform,
         match source {
             ChannelSource::User { name, .. } => name,
             ChannelSource::Channel { name, .. } => name,
             ChannelSource::Group { name, .. } => name,
         },
-        content
+        content,
+        gearclaw_agent::describe_attachments(attachments)
     );
 
     // Process with agent
     let response = agent
-        .process_message(&mut session, &context_msg)
+        .process_message_with_sink(
+            &mut session,
+            &context_msg,
+            gearclaw_agent::DeltaSink::Writer(&mut std::io::sink()),
+        )
         .await
         .map_err(|e| anyhow::anyhow!("Agent processing failed: {}", e))?;
 
@@ -556,6 +989,7 @@ async fn process_agent_response(
         let message_content = MessageContent {
             text: Some(agent_response),
             embeds: vec![],
+            attachments: vec![],
         };
 
         adapter
@@ -571,25 +1005,27 @@ async fn process_agent_response(
     Ok(())
 }
 
+/// Presence host for a channel adapter, distinct from client addresses
+fn channel_host(platform: &str) -> String {
+    format!("channel:{}", platform)
+}
+
 /// Create hello-ok payload
-fn create_hello_ok() -> HelloOkPayload {
+fn create_hello_ok(policy: &GatewayPolicy, presence: &PresenceManager) -> HelloOkPayload {
+    let (entries, presence_version) = presence.snapshot_with_version();
     HelloOkPayload {
         protocol: ProtocolVersion { min: 1, max: 1 },
-        presence: vec![],
+        presence: entries,
         health: serde_json::json!({
             "status": "ok",
             "version": env!("CARGO_PKG_VERSION"),
         }),
         state_version: StateVersion {
-            presence: 0,
+            presence: presence_version,
             health: 0,
         },
         uptime_ms: 0,
-        policy: GatewayPolicy {
-            max_payload: 1024 * 1024,             // 1MB
-            max_buffered_bytes: 10 * 1024 * 1024, // 10MB
-            tick_interval_ms: 30000,              // 30 seconds
-        },
+        policy: policy.clone(),
     }
 }
 
@@ -620,6 +1056,969 @@ mod tests {
         request
     }
 
+    fn fast_keepalive_config() -> GatewayConfig {
+        GatewayConfig {
+            policy: GatewayPolicy {
+                tick_interval_ms: 50,
+                ..GatewayPolicy::default()
+            },
+            pong_timeout_ms: 100,
+            ..GatewayConfig::default()
+        }
+    }
+
+    /// Serve a single WebSocket connection on an ephemeral port.
+    async fn spawn_connection(
+        config: GatewayConfig,
+    ) -> (String, tokio::task::JoinHandle<Result<()>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}{}", listener.local_addr().unwrap(), config.ws_path);
+        let (event_tx, _) = broadcast::channel(16);
+        let handle = tokio::spawn(async move {
+            let (stream, peer) = listener.accept().await?;
+            handle_connection(
+                stream,
+                peer.to_string(),
+                Arc::new(MethodHandlers::new()),
+                Arc::new(TokenAuth::new()),
+                ConnectionRegistry::default(),
+                Arc::new(PresenceManager::new()),
+                event_tx.subscribe(),
+                Arc::new(config),
+                None,
+            )
+            .await
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_idle_client_is_closed_after_missed_pong() {
+        let (url, handle) = spawn_connection(fast_keepalive_config()).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        // Read hello-ok, then stop reading so pings go unanswered.
+        client.next().await.unwrap().unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(2), handle)
+            .await
+            .expect("server should drop the idle connection");
+        assert!(result.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_responsive_client_stays_connected() {
+        let (url, handle) = spawn_connection(fast_keepalive_config()).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        // Reading drives tungstenite's automatic pong replies.
+        let reader = tokio::spawn(async move { while client.next().await.is_some() {} });
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!handle.is_finished());
+        reader.abort();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_frame_is_rejected_before_parsing() {
+        let config = GatewayConfig {
+            policy: GatewayPolicy {
+                max_payload: 1024,
+                ..GatewayPolicy::default()
+            },
+            ..GatewayConfig::default()
+        };
+        let (url, handle) = spawn_connection(config).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        client.next().await.unwrap().unwrap();
+
+        client
+            .send(Message::Text("x".repeat(2048).into()))
+            .await
+            .unwrap();
+        let reply = client.next().await.unwrap().unwrap();
+        let response: GatewayResponse = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        assert!(!response.ok);
+        assert_eq!(
+            response.error.as_ref().map(|e| e.code.as_str()),
+            Some(ProtocolError::PAYLOAD_TOO_LARGE)
+        );
+
+        match client.next().await {
+            Some(Ok(Message::Close(Some(frame)))) => assert_eq!(frame.code, CloseCode::Size),
+            other => panic!("expected close frame, got {:?}", other),
+        }
+        let result = tokio::time::timeout(Duration::from_secs(2), handle)
+            .await
+            .unwrap();
+        assert!(result.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_frame_above_transport_limit_drops_connection() {
+        let config = GatewayConfig {
+            policy: GatewayPolicy {
+                max_payload: 1024,
+                max_buffered_bytes: 4096,
+                ..GatewayPolicy::default()
+            },
+            ..GatewayConfig::default()
+        };
+        let (url, handle) = spawn_connection(config).await;
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        client.next().await.unwrap().unwrap();
+
+        let _ = client.send(Message::Text("x".repeat(8192).into())).await;
+        let result = tokio::time::timeout(Duration::from_secs(2), handle)
+            .await
+            .expect("server should stop reading");
+        // The transport error ends the connection loop without a parse attempt.
+        assert!(result.unwrap().is_ok());
+    }
+
+    /// Serve any number of connections on an ephemeral port.
+    async fn spawn_server(connections: ConnectionRegistry) -> String {
+        spawn_server_with_handlers(connections, Arc::new(MethodHandlers::new())).await
+    }
+
+    async fn spawn_server_with_handlers(
+        connections: ConnectionRegistry,
+        handlers: Arc<MethodHandlers>,
+    ) -> String {
+        let (event_tx, _) = broadcast::channel(16);
+        let presence = Arc::new(PresenceManager::new());
+        spawn_server_with(connections, handlers, presence, event_tx).await
+    }
+
+    async fn spawn_server_with(
+        connections: ConnectionRegistry,
+        handlers: Arc<MethodHandlers>,
+        presence: Arc<PresenceManager>,
+        event_tx: broadcast::Sender<GatewayEvent>,
+    ) -> String {
+        let config = GatewayConfig {
+            allow_unauthenticated_requests: true,
+            ..GatewayConfig::default()
+        };
+        spawn_server_configured(config, connections, handlers, presence, event_tx).await
+    }
+
+    async fn spawn_server_configured(
+        config: GatewayConfig,
+        connections: ConnectionRegistry,
+        handlers: Arc<MethodHandlers>,
+        presence: Arc<PresenceManager>,
+        event_tx: broadcast::Sender<GatewayEvent>,
+    ) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = Arc::new(config);
+        let url = format!("ws://{}{}", listener.local_addr().unwrap(), config.ws_path);
+        let auth = Arc::new(TokenAuth::new());
+        tokio::spawn(async move {
+            while let Ok((stream, peer)) = listener.accept().await {
+                tokio::spawn(handle_connection(
+                    stream,
+                    peer.to_string(),
+                    handlers.clone(),
+                    auth.clone(),
+                    connections.clone(),
+                    presence.clone(),
+                    event_tx.subscribe(),
+                    config.clone(),
+                    None,
+                ));
+            }
+        });
+        url
+    }
+
+    async fn next_presence_event(
+        event_rx: &mut broadcast::Receiver<GatewayEvent>,
+    ) -> PresenceEvent {
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(2), event_rx.recv())
+                .await
+                .expect("presence event should be broadcast")
+                .unwrap();
+            if let GatewayEvent::Presence(event) = event {
+                return event;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_presence_bumps_version_and_broadcasts() {
+        let (event_tx, mut event_rx) = broadcast::channel(16);
+        let presence = Arc::new(PresenceManager::new().with_events(event_tx.clone()));
+        let url = spawn_server_with(
+            ConnectionRegistry::default(),
+            Arc::new(MethodHandlers::new()),
+            presence.clone(),
+            event_tx,
+        )
+        .await;
+
+        let (mut client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let joined = next_presence_event(&mut event_rx).await;
+        assert_eq!(joined.state_version, 1);
+        assert_eq!(joined.updates[0].mode, "client");
+        assert!(joined.updates[0].reason.is_none());
+        // Other clients must not learn this peer's address
+        assert!(joined.updates[0].ip.is_none());
+        assert!(!joined.updates[0].host.contains("127.0.0.1"));
+        assert!(joined.updates[0].instance_id.is_none());
+
+        // hello-ok reports the snapshot including this connection.
+        let hello = client.next().await.unwrap().unwrap();
+        let frame: Value = serde_json::from_str(hello.to_text().unwrap()).unwrap();
+        let hello: HelloOkPayload =
+            serde_json::from_value(frame["data"]["payload"].clone()).unwrap();
+        assert_eq!(hello.state_version.presence, 1);
+        assert_eq!(hello.presence.len(), 1);
+
+        drop(client);
+        let left = next_presence_event(&mut event_rx).await;
+        assert_eq!(left.state_version, 2);
+        assert_eq!(
+            left.updates[0].reason.as_deref(),
+            Some(crate::presence::REASON_DISCONNECT)
+        );
+        assert!(presence.snapshot().is_empty());
+    }
+
+    /// Send a raw HTTP request to the server behind `url` and return the
+    /// full response
+    async fn http_request(url: &str, request: &str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let addr = url
+            .trim_start_matches("ws://")
+            .split('/')
+            .next()
+            .unwrap()
+            .to_string();
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        tokio::time::timeout(Duration::from_secs(2), stream.read_to_string(&mut response))
+            .await
+            .expect("server should close after responding")
+            .unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_healthz_answers_plain_http_without_auth() {
+        // Default config: WebSocket requests need a token, the probe does not
+        let (url, handle) = spawn_connection(GatewayConfig::default()).await;
+        let response = http_request(
+            &url,
+            "GET /healthz?probe=1 HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        )
+        .await;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.contains("Content-Type: application/json"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let health: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(health["status"], "ok");
+        assert_eq!(health["agent_ready"], false);
+        assert!(handle.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_websocket_still_upgrades_alongside_healthz() {
+        let connections = ConnectionRegistry::default();
+        let url = spawn_server(connections.clone()).await;
+
+        let response = http_request(&url, "GET /healthz HTTP/1.1\r\nHost: x\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+
+        let (_client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        wait_for_connection_count(&connections, 1).await;
+    }
+
+    async fn spawn_metrics_server(metrics_enabled: bool) -> String {
+        let (event_tx, _) = broadcast::channel(16);
+        let config = GatewayConfig {
+            allow_unauthenticated_requests: true,
+            metrics_enabled,
+            ..GatewayConfig::default()
+        };
+        spawn_server_configured(
+            config,
+            ConnectionRegistry::default(),
+            Arc::new(MethodHandlers::new()),
+            Arc::new(PresenceManager::new()),
+            event_tx,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_metrics_scrape_counts_requests() {
+        let url = spawn_metrics_server(true).await;
+        let health_counter = "gearclaw_gateway_requests_total{method=\"health\"}";
+
+        let before = http_request(&url, "GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").await;
+        assert!(before.starts_with("HTTP/1.1 200 OK\r\n"), "{}", before);
+        assert!(before.contains("Content-Type: text/plain; version=0.0.4"));
+        assert!(!before.contains(health_counter));
+
+        let (mut client, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        client.next().await.unwrap().unwrap();
+        let request = GatewayFrame::Request(GatewayRequest::new(
+            "health-1".to_string(),
+            "health".to_string(),
+            Value::Null,
+        ));
+        client
+            .send(Message::Text(
+                serde_json::to_string(&request).unwrap().into(),
+            ))
+            .await
+            .unwrap();
+        client.next().await.unwrap().unwrap();
+
+        let after = http_request(&url, "GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n").await;
+        assert!(
+            after.contains(&format!("{} 1\n", health_counter)),
+            "{}",
+            after
+        );
+        assert!(after.contains("gearclaw_gateway_active_connections 1\n"));
+        assert!(after.contains("gearclaw_gateway_connections_total 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_route_is_off_by_default() {
+        use tokio_tungstenite::tungstenite::Error as WsError;
+
+        let url = spawn_metrics_server(false).await;
+        // Without the flag `/metrics` falls through to the upgrade path check.
+        match tokio_tungstenite::connect_async(url.replace("/ws", "/metrics")).await {
+            Err(WsError::Http(response)) => assert_eq!(response.status(), StatusCode::NOT_FOUND),
+            other => panic!("expected 404 rejection, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_on_wrong_path_is_rejected() {
+        use tokio_tungstenite::tungstenite::Error as WsError;
+
+        let connections = ConnectionRegistry::default();
+        let url = spawn_server(connections.clone()).await;
+        let wrong = url.replace("/ws", "/anything");
+
+        match tokio_tungstenite::connect_async(&wrong).await {
+            Err(WsError::Http(response)) => assert_eq!(response.status(), StatusCode::NOT_FOUND),
+            other => panic!("expected 404 rejection, got {:?}", other.map(|_| ())),
+        }
+        assert!(connections.read().unwrap().is_empty());
+
+        // Query strings do not affect path matching.
+        let (_client, _) = tokio_tungstenite::connect_async(format!("{}?v=3", url))
+            .await
+            .unwrap();
+        wait_for_connection_count(&connections, 1).await;
+    }
+
+    async fn wait_for_connection_count(connections: &ConnectionRegistry, expected: usize) {
+        for _ in 0..100 {
+            if connections.read().unwrap().len() == expected {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!(
+            "expected {} connections, found {}",
+            expected,
+            connections.read().unwrap().len()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connections_are_tracked_and_reported_by_status() {
+        let connections = ConnectionRegistry::default();
+        let url = spawn_server(connections.clone()).await;
+
+        let (mut first, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut second, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        first.next().await.unwrap().unwrap();
+        second.next().await.unwrap().unwrap();
+        wait_for_connection_count(&connections, 2).await;
+
+        let request = GatewayFrame::Request(GatewayRequest::new(
+            "status-1".to_string(),
+            "status".to_string(),
+            Value::Null,
+        ));
+        first
+            .send(Message::Text(
+                serde_json::to_string(&request).unwrap().into(),
+            ))
+            .await
+            .unwrap();
+        let reply = first.next().await.unwrap().unwrap();
+        let response: GatewayResponse = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        let payload = response.payload.expect("status payload");
+        assert_eq!(payload["total_connections"], 2);
+        assert_eq!(payload["connections"].as_array().map(Vec::len), Some(2));
+
+        second.close(None).await.unwrap();
+        drop(second);
+        wait_for_connection_count(&connections, 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_status_pages_connections_with_total() {
+        let connections: Vec<ActiveConnection> = (0..5)
+            .map(|i| ActiveConnection {
+                id: format!("conn-{}", i),
+                addr: format!("127.0.0.1:{}", 4000 + i),
+                connected_at: std::time::SystemTime::now(),
+            })
+            .collect();
+        let handlers = MethodHandlers::new();
+        let status = |params: Value| {
+            GatewayRequest::new("status-page".to_string(), "status".to_string(), params)
+        };
+
+        let page = handlers
+            .status(&status(json!({ "offset": 1, "limit": 2 })), &connections)
+            .await
+            .unwrap();
+        assert_eq!(page["total"], 5);
+        let ids: Vec<&str> = page["connections"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(ids, ["conn-1", "conn-2"]);
+
+        let tail = handlers
+            .status(&status(json!({ "offset": 4, "limit": 10 })), &connections)
+            .await
+            .unwrap();
+        assert_eq!(tail["connections"].as_array().map(Vec::len), Some(1));
+        let past_end = handlers
+            .status(&status(json!({ "offset": 9 })), &connections)
+            .await
+            .unwrap();
+        assert_eq!(past_end["connections"], json!([]));
+        assert_eq!(past_end["total"], 5);
+
+        assert!(handlers
+            .status(&status(json!({ "limit": -1 })), &connections)
+            .await
+            .is_err());
+    }
+
+    /// Minimal OpenAI-compatible endpoint that streams `chunks` as deltas.
+    async fn spawn_mock_llm(chunks: &'static [&'static str]) -> String {
+        spawn_slow_mock_llm(chunks, Duration::ZERO).await.0
+    }
+
+    /// Like [`spawn_mock_llm`], answering each request after `delay`.
+    /// Also returns the number of requests received.
+    async fn spawn_slow_mock_llm(
+        chunks: &'static [&'static str],
+        delay: Duration,
+    ) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+        let received = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = received.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                // Read headers and body so the client sees a clean exchange.
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some(split) = text.find("\r\n\r\n") {
+                        let content_length = text[..split]
+                            .lines()
+                            .find_map(|line| {
+                                let (name, value) = line.split_once(':')?;
+                                name.eq_ignore_ascii_case("content-length")
+                                    .then(|| value.trim().parse::<usize>().ok())?
+                            })
+                            .unwrap_or(0);
+                        if request.len() >= split + 4 + content_length {
+                            break;
+                        }
+                    }
+                }
+
+                tokio::time::sleep(delay).await;
+                let mut body = String::new();
+                for chunk in chunks {
+                    let event = json!({
+                        "id": "chatcmpl-test",
+                        "choices": [{ "index": 0, "delta": { "content": chunk } }]
+                    });
+                    body.push_str(&format!("data: {}\n\n", event));
+                }
+                body.push_str("data: [DONE]\n\n");
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n{}",
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+        (endpoint, received)
+    }
+
+    /// LLM endpoint that accepts connections but never answers, so an agent
+    /// run against it stays in flight until cancelled.
+    async fn spawn_stalled_llm() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _open = stream;
+                    std::future::pending::<()>().await;
+                });
+            }
+        });
+        endpoint
+    }
+
+    async fn test_agent(endpoint: String, dir: &std::path::Path) -> Arc<gearclaw_agent::Agent> {
+        let mut config = gearclaw_core::Config::sample();
+        config.llm.api_key = Some("test-key".to_string());
+        config.llm.endpoint = endpoint;
+        config.llm.retry.max_attempts = 1;
+        config.agent.memory_enabled = false;
+        config.agent.workspace = dir.join("workspace");
+        config.agent.skills_path = dir.join("skills");
+        config.memory.enabled = false;
+        config.memory.db_path = dir.join("memory.sqlite");
+        config.session.session_dir = dir.join("sessions");
+        Arc::new(gearclaw_agent::Agent::new(config).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_in_flight_agent_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let handlers = Arc::new(MethodHandlers::new());
+        handlers
+            .set_agent(test_agent(spawn_stalled_llm().await, dir.path()).await)
+            .await;
+        let url = spawn_server_with_handlers(ConnectionRegistry::default(), handlers).await;
+
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        client.next().await.unwrap().unwrap();
+        for request in [
+            GatewayRequest::new(
+                "stream-slow".to_string(),
+                "agent.stream".to_string(),
+                json!({ "prompt": "hi" }),
+            ),
+            GatewayRequest::new(
+                "cancel-1".to_string(),
+                "cancel".to_string(),
+                json!({ "request_id": "stream-slow" }),
+            ),
+        ] {
+            let frame = GatewayFrame::Request(request);
+            client
+                .send(Message::Text(serde_json::to_string(&frame).unwrap().into()))
+                .await
+                .unwrap();
+        }
+
+        // The ack and the terminal event may arrive in either order
+        let mut cancel_ack = None;
+        let mut message = None;
+        while cancel_ack.is_none() || message.is_none() {
+            let msg = tokio::time::timeout(Duration::from_secs(10), client.next())
+                .await
+                .expect("run was not cancelled")
+                .unwrap()
+                .unwrap();
+            let value: Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+            if value.get("ok").is_some() {
+                if value["id"] == "cancel-1" {
+                    cancel_ack = Some(value["payload"]["cancelled"].clone());
+                }
+                continue;
+            }
+            let GatewayFrame::Event(GatewayEvent::Agent(event)) =
+                serde_json::from_value(value).unwrap()
+            else {
+                panic!("unexpected frame");
+            };
+            match event.content {
+                AgentEventContent::Error { message: error, .. } => message = Some(error),
+                AgentEventContent::Done { .. } => panic!("run finished instead of cancelling"),
+                _ => {}
+            }
+        }
+        let message = message.unwrap();
+        assert!(message.contains("cancelled"), "{}", message);
+        assert_eq!(cancel_ack, Some(json!(true)));
+
+        // Nothing left to cancel once the run has ended
+        let frame = GatewayFrame::Request(GatewayRequest::new(
+            "cancel-2".to_string(),
+            "cancel".to_string(),
+            json!({ "request_id": "stream-slow" }),
+        ));
+        client
+            .send(Message::Text(serde_json::to_string(&frame).unwrap().into()))
+            .await
+            .unwrap();
+        let reply = client.next().await.unwrap().unwrap();
+        let response: GatewayResponse = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        assert_eq!(response.payload.unwrap()["cancelled"], false);
+    }
+
+    async fn send_request(
+        client: &mut tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >,
+        request: GatewayRequest,
+    ) {
+        let frame = GatewayFrame::Request(request);
+        client
+            .send(Message::Text(serde_json::to_string(&frame).unwrap().into()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_in_flight_agent_from_its_own_connection_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let handlers = Arc::new(MethodHandlers::new());
+        handlers
+            .set_agent(test_agent(spawn_stalled_llm().await, dir.path()).await)
+            .await;
+        let url = spawn_server_with_handlers(ConnectionRegistry::default(), handlers).await;
+
+        let (mut owner, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        owner.next().await.unwrap().unwrap();
+        let (mut other, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        other.next().await.unwrap().unwrap();
+
+        send_request(
+            &mut owner,
+            GatewayRequest::new(
+                "1".to_string(),
+                "agent".to_string(),
+                json!({ "prompt": "hi" }),
+            ),
+        )
+        .await;
+
+        // Another connection using the same request id reaches nothing
+        send_request(
+            &mut other,
+            GatewayRequest::new(
+                "cancel-other".to_string(),
+                "cancel".to_string(),
+                json!({ "request_id": "1" }),
+            ),
+        )
+        .await;
+        let reply = other.next().await.unwrap().unwrap();
+        let response: GatewayResponse = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        assert_eq!(response.payload.unwrap()["cancelled"], false);
+
+        // The `cancel` is read while the synchronous run is still in flight
+        send_request(
+            &mut owner,
+            GatewayRequest::new(
+                "cancel-1".to_string(),
+                "cancel".to_string(),
+                json!({ "request_id": "1" }),
+            ),
+        )
+        .await;
+        let mut cancel_ack = None;
+        let mut run_error = None;
+        while cancel_ack.is_none() || run_error.is_none() {
+            let msg = tokio::time::timeout(Duration::from_secs(10), owner.next())
+                .await
+                .expect("run was not cancelled")
+                .unwrap()
+                .unwrap();
+            let Ok(response) = serde_json::from_str::<GatewayResponse>(msg.to_text().unwrap())
+            else {
+                continue;
+            };
+            match response.id.as_str() {
+                "cancel-1" => cancel_ack = Some(response.payload.unwrap()["cancelled"].clone()),
+                "1" => run_error = Some(response.error.expect("run should fail").message),
+                _ => {}
+            }
+        }
+        assert_eq!(cancel_ack, Some(json!(true)));
+        let run_error = run_error.unwrap();
+        assert!(run_error.contains("cancelled"), "{}", run_error);
+    }
+
+    #[tokio::test]
+    async fn test_agent_stream_emits_deltas_before_terminal_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let endpoint = spawn_mock_llm(&["Hel", "lo"]).await;
+        let handlers = Arc::new(MethodHandlers::new());
+        handlers
+            .set_agent(test_agent(endpoint, dir.path()).await)
+            .await;
+        let url = spawn_server_with_handlers(ConnectionRegistry::default(), handlers).await;
+
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        client.next().await.unwrap().unwrap();
+        let request = GatewayFrame::Request(GatewayRequest::new(
+            "stream-1".to_string(),
+            "agent.stream".to_string(),
+            json!({ "prompt": "hi" }),
+        ));
+        client
+            .send(Message::Text(
+                serde_json::to_string(&request).unwrap().into(),
+            ))
+            .await
+            .unwrap();
+
+        let mut deltas = Vec::new();
+        let mut acked = false;
+        let response = loop {
+            let msg = tokio::time::timeout(Duration::from_secs(10), client.next())
+                .await
+                .expect("stream stalled")
+                .unwrap()
+                .unwrap();
+            let value: Value = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+            if value.get("ok").is_some() {
+                assert_eq!(value["ok"], true, "{}", value);
+                assert_eq!(value["payload"]["status"], "streaming");
+                acked = true;
+                continue;
+            }
+            let GatewayFrame::Event(GatewayEvent::Agent(event)) =
+                serde_json::from_value(value).unwrap()
+            else {
+                panic!("unexpected frame");
+            };
+            assert_eq!(event.request_id.as_deref(), Some("stream-1"));
+            match event.content {
+                AgentEventContent::Output { content, .. } => deltas.push(content),
+                AgentEventContent::Done { response, .. } => break response,
+                AgentEventContent::Error { message, .. } => panic!("run failed: {}", message),
+                _ => {}
+            }
+        };
+
+        assert!(acked);
+        assert!(!deltas.is_empty());
+        assert_eq!(deltas.concat(), "Hello");
+        assert_eq!(response, "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_handle_request_enforces_token_scopes() {
+        let handlers = MethodHandlers::new();
+        let auth = TokenAuth::new();
+        let token = "monitor-token-abcdefghijklmnopqrstuvwxyz12";
+        auth.register_with_scopes(
+            token.to_string(),
+            "device-1".to_string(),
+            "gateway".to_string(),
+            Some(["health", "status"].map(String::from).into()),
+        )
+        .await
+        .unwrap();
+        let signature = format!("Bearer {}", token);
+        let (outbound, _) = mpsc::unbounded_channel();
+        let connections = ConnectionRegistry::default();
+
+        let in_scope = health_request(Some(&signature));
+        let response = handle_request(
+            &in_scope,
+            &handlers,
+            &auth,
+            &connections,
+            &outbound,
+            "conn-1",
+            None,
+            false,
+        )
+        .await;
+        assert!(response.ok);
+
+        let out_of_scope = agent_request("req-2", Some(&signature), Some("device-1"));
+        let response = handle_request(
+            &out_of_scope,
+            &handlers,
+            &auth,
+            &connections,
+            &outbound,
+            "conn-1",
+            None,
+            false,
+        )
+        .await;
+        assert!(!response.ok);
+        assert_eq!(
+            response.error.as_ref().map(|e| e.code.as_str()),
+            Some(ProtocolError::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_request_rate_limits_per_token() {
+        let handlers = MethodHandlers::new();
+        let auth = TokenAuth::new();
+        let limiter = RateLimiter::new(3);
+        let token = "test-token-abcdefghijklmnopqrstuvwxyz123456";
+        let other = "test-token-zyxwvutsrqponmlkjihgfedcba654321";
+        for t in [token, other] {
+            auth.register(t.to_string(), "device-1".to_string(), "gateway".to_string())
+                .await
+                .unwrap();
+        }
+        let (outbound, _) = mpsc::unbounded_channel();
+        let connections = ConnectionRegistry::default();
+
+        let send = |signature: String| {
+            let request = health_request(Some(&signature));
+            let (handlers, auth, limiter, outbound, connections) =
+                (&handlers, &auth, &limiter, &outbound, &connections);
+            async move {
+                handle_request(
+                    &request,
+                    handlers,
+                    auth,
+                    connections,
+                    outbound,
+                    "conn-1",
+                    Some(limiter),
+                    false,
+                )
+                .await
+            }
+        };
+
+        for _ in 0..3 {
+            assert!(send(format!("Bearer {}", token)).await.ok);
+        }
+        let limited = send(format!("Bearer {}", token)).await;
+        assert!(!limited.ok);
+        let error = limited.error.expect("rate limit error");
+        assert_eq!(error.code, ProtocolError::RATE_LIMITED);
+        assert_eq!(error.retryable, Some(true));
+        assert!(error.retry_after_ms.unwrap_or(0) > 0);
+
+        // Other tokens have their own bucket; the prefix does not matter.
+        assert!(send(other.to_string()).await.ok);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_idempotency_key_runs_handler_once() {
+        let handlers = MethodHandlers::new();
+        let auth = TokenAuth::new();
+        let (outbound, _) = mpsc::unbounded_channel();
+        let connections = ConnectionRegistry::default();
+
+        // Without an agent each `agent` call answers with a fresh run id, so
+        // a repeated run id means the handler was not called again.
+        let agent = |id: &str, key: Option<&str>| {
+            let mut request = GatewayRequest::new(
+                id.to_string(),
+                "agent".to_string(),
+                json!({ "prompt": "hi" }),
+            );
+            request.idempotency_key = key.map(ToString::to_string);
+            let (handlers, auth, outbound, connections) =
+                (&handlers, &auth, &outbound, &connections);
+            async move {
+                let response = handle_request(
+                    &request,
+                    handlers,
+                    auth,
+                    connections,
+                    outbound,
+                    "conn-1",
+                    None,
+                    true,
+                )
+                .await;
+                assert!(response.ok);
+                assert_eq!(response.id, request.id);
+                response.payload.unwrap()["run_id"].clone()
+            }
+        };
+
+        let first = agent("agent-1", Some("retry-key")).await;
+        assert_eq!(agent("agent-2", Some("retry-key")).await, first);
+        assert_ne!(agent("agent-3", Some("other-key")).await, first);
+        assert_ne!(agent("agent-4", None).await, agent("agent-5", None).await);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_retry_waits_for_the_run_in_flight() {
+        let dir = tempfile::tempdir().unwrap();
+        let (endpoint, received) = spawn_slow_mock_llm(&["done"], Duration::from_millis(300)).await;
+        let handlers = MethodHandlers::new();
+        handlers
+            .set_agent(test_agent(endpoint, dir.path()).await)
+            .await;
+        let auth = TokenAuth::new();
+        let (outbound, _) = mpsc::unbounded_channel();
+        let connections = ConnectionRegistry::default();
+
+        let agent = |id: &str| {
+            let mut request = GatewayRequest::new(
+                id.to_string(),
+                "agent".to_string(),
+                json!({ "prompt": "hi" }),
+            );
+            request.device_id = Some("device-1".to_string());
+            request.idempotency_key = Some("retry-key".to_string());
+            let (handlers, auth, outbound, connections) =
+                (&handlers, &auth, &outbound, &connections);
+            async move {
+                handle_request(
+                    &request,
+                    handlers,
+                    auth,
+                    connections,
+                    outbound,
+                    "conn-1",
+                    None,
+                    true,
+                )
+                .await
+            }
+        };
+
+        // The retry arrives while the first run is still waiting on the model
+        let (first, retry) = tokio::join!(agent("agent-1"), async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            agent("agent-2").await
+        });
+        assert!(first.ok && retry.ok, "{:?} {:?}", first.error, retry.error);
+        assert_eq!(retry.id, "agent-2");
+        assert_eq!(
+            first.payload.unwrap()["run_id"],
+            retry.payload.unwrap()["run_id"]
+        );
+        assert_eq!(received.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn test_config_defaults() {
         let config = GatewayConfig::default();
@@ -635,7 +2034,17 @@ mod tests {
         let auth = TokenAuth::new();
         let request = health_request(None);
 
-        let response = handle_request(&request, &handlers, &auth, false).await;
+        let response = handle_request(
+            &request,
+            &handlers,
+            &auth,
+            &ConnectionRegistry::default(),
+            &mpsc::unbounded_channel().0,
+            "conn-1",
+            None,
+            false,
+        )
+        .await;
         assert!(!response.ok);
         assert_eq!(
             response.error.as_ref().map(|e| e.code.as_str()),
@@ -649,7 +2058,17 @@ mod tests {
         let auth = TokenAuth::new();
         let request = health_request(Some("Bearer invalid-token"));
 
-        let response = handle_request(&request, &handlers, &auth, false).await;
+        let response = handle_request(
+            &request,
+            &handlers,
+            &auth,
+            &ConnectionRegistry::default(),
+            &mpsc::unbounded_channel().0,
+            "conn-1",
+            None,
+            false,
+        )
+        .await;
         assert!(!response.ok);
         assert_eq!(
             response.error.as_ref().map(|e| e.code.as_str()),
@@ -671,7 +2090,17 @@ mod tests {
         .unwrap();
 
         let request = health_request(Some(&format!("Bearer {}", token)));
-        let response = handle_request(&request, &handlers, &auth, false).await;
+        let response = handle_request(
+            &request,
+            &handlers,
+            &auth,
+            &ConnectionRegistry::default(),
+            &mpsc::unbounded_channel().0,
+            "conn-1",
+            None,
+            false,
+        )
+        .await;
         assert!(response.ok);
     }
 
@@ -681,7 +2110,17 @@ mod tests {
         let auth = TokenAuth::new();
         let request = health_request(None);
 
-        let response = handle_request(&request, &handlers, &auth, true).await;
+        let response = handle_request(
+            &request,
+            &handlers,
+            &auth,
+            &ConnectionRegistry::default(),
+            &mpsc::unbounded_channel().0,
+            "conn-1",
+            None,
+            true,
+        )
+        .await;
         assert!(response.ok);
     }
 
@@ -703,7 +2142,17 @@ mod tests {
             Some(&format!("Bearer {}", token)),
             Some("../escape"),
         );
-        let response = handle_request(&request, &handlers, &auth, false).await;
+        let response = handle_request(
+            &request,
+            &handlers,
+            &auth,
+            &ConnectionRegistry::default(),
+            &mpsc::unbounded_channel().0,
+            "conn-1",
+            None,
+            false,
+        )
+        .await;
         assert!(!response.ok);
         assert_eq!(
             response.error.as_ref().map(|e| e.code.as_str()),
@@ -725,11 +2174,59 @@ mod tests {
         .unwrap();
 
         let request = agent_request("../escape", Some(&format!("Bearer {}", token)), None);
-        let response = handle_request(&request, &handlers, &auth, false).await;
+        let response = handle_request(
+            &request,
+            &handlers,
+            &auth,
+            &ConnectionRegistry::default(),
+            &mpsc::unbounded_channel().0,
+            "conn-1",
+            None,
+            false,
+        )
+        .await;
         assert!(!response.ok);
         assert_eq!(
             response.error.as_ref().map(|e| e.code.as_str()),
             Some(ProtocolError::INVALID_REQUEST)
         );
     }
+
+    #[tokio::test]
+    async fn test_bind_reports_port_in_use() {
+        let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let server = GatewayServer::new(GatewayConfig {
+            port,
+            ..GatewayConfig::default()
+        });
+
+        let err = server.bind().await.unwrap_err().to_string();
+        assert!(err.contains("already in use"), "{}", err);
+        assert!(err.contains(&port.to_string()), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_bind_rejects_port_zero_unless_ephemeral() {
+        let mut config = GatewayConfig {
+            port: 0,
+            ..GatewayConfig::default()
+        };
+        let err = GatewayServer::new(config.clone()).bind().await.unwrap_err();
+        assert!(err.to_string().contains("port 0"), "{}", err);
+
+        config.allow_ephemeral_port = true;
+        let listener = GatewayServer::new(config).bind().await.unwrap();
+        assert_ne!(listener.local_addr().unwrap().port(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_host_is_rejected() {
+        let config = GatewayConfig {
+            host: "not a host".to_string(),
+            ..GatewayConfig::default()
+        };
+        let err = config.resolve_bind_addr().await.unwrap_err();
+        assert!(err.to_string().contains("Invalid gateway host"), "{}", err);
+    }
 }
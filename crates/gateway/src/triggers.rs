@@ -2,12 +2,16 @@
 //
 // This module implements trigger checking for Agent auto-response.
 // It determines whether a message should trigger Agent processing based on:
-// - Trigger mode (always, mention, keyword)
+// - Trigger mode (always, mention, keyword, direct_mention)
 // - Mention patterns (@agent, @bot, etc.)
+// - Mention/reply metadata from the channel adapter
 // - Keywords
 // - Channel whitelist/blacklist
+// - Per-channel cooldown and the bot's own messages
 
 use crate::protocol::ChannelSource;
+use gearclaw_channels::MessageAddressing;
+use gearclaw_core::triggers::{is_own_message, TriggerMatcher};
 use gearclaw_core::{AgentTriggerConfig, TriggerMode};
 
 /// Check if a message should trigger Agent response
@@ -15,8 +19,15 @@ pub fn should_trigger_agent(
     platform: &str,
     source: &ChannelSource,
     content: &str,
-    trigger_config: &AgentTriggerConfig,
+    addressing: &MessageAddressing,
+    triggers: &TriggerMatcher,
 ) -> bool {
+    let trigger_config = triggers.config();
+
+    if is_own_message(addressing) {
+        tracing::debug!("Ignoring the bot's own message");
+        return false;
+    }
     // Check channel blacklist first
     let channel_key = format!("{}:{}", platform, get_channel_id(source));
     if trigger_config.disabled_channels.contains(&channel_key) {
@@ -25,33 +36,38 @@ pub fn should_trigger_agent(
     }
 
     // Check channel whitelist (if configured)
-    if !trigger_config.enabled_channels.is_empty() {
-        if !trigger_config.enabled_channels.contains(&channel_key) {
-            tracing::debug!("Message not in enabled_channels list: {}", channel_key);
-            return false;
-        }
+    if !trigger_config.enabled_channels.is_empty()
+        && !trigger_config.enabled_channels.contains(&channel_key)
+    {
+        tracing::debug!("Message not in enabled_channels list: {}", channel_key);
+        return false;
     }
 
     // Check trigger mode
-    match trigger_config.mode {
+    let triggered = match trigger_config.mode {
         TriggerMode::Always => {
             // Always respond (unless blocked by blacklist)
             true
         }
         TriggerMode::Mention => {
-            // Check if any mention pattern is in the message
-            trigger_config.mention_patterns.iter().any(|pattern| {
-                content.contains(pattern) || content.starts_with(&pattern.replace('@', ""))
-            })
-        }
-        TriggerMode::Keyword => {
-            // Check if any keyword is in the message
-            trigger_config
-                .keywords
-                .iter()
-                .any(|keyword| content.to_lowercase().contains(&keyword.to_lowercase()))
+            // Check if any mention pattern is in the message, or the message
+            // starts with one without its '@'
+            triggers.matches_mention(content)
+                || trigger_config
+                    .mention_patterns
+                    .iter()
+                    .any(|pattern| content.starts_with(&pattern.replace('@', "")))
         }
+        TriggerMode::Keyword => triggers.matches_keyword(content),
+        TriggerMode::DirectMention => addressing.addresses_bot(),
+    };
+
+    // Suppress repeated triggers within the channel's cooldown
+    if triggered && !triggers.try_start_cooldown(&channel_key) {
+        tracing::debug!("Trigger suppressed by cooldown: {}", channel_key);
+        return false;
     }
+    triggered
 }
 
 /// Extract channel ID from source
@@ -92,11 +108,21 @@ pub fn extract_mention_prefix(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     fn default_trigger_config() -> AgentTriggerConfig {
         AgentTriggerConfig::default()
     }
 
+    fn addressed(mentions: &[&str], reply_to: Option<&str>) -> MessageAddressing {
+        MessageAddressing {
+            sender_id: Some("123456".to_string()),
+            mentioned_user_ids: mentions.iter().map(|id| id.to_string()).collect(),
+            reply_to_user_id: reply_to.map(str::to_string),
+            bot_user_id: Some("999".to_string()),
+        }
+    }
+
     fn user_source() -> ChannelSource {
         ChannelSource::User {
             id: "123456".to_string(),
@@ -113,7 +139,8 @@ mod tests {
             "discord",
             &user_source(),
             "hello",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
     }
 
@@ -125,19 +152,22 @@ mod tests {
             "discord",
             &user_source(),
             "@agent hello",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
         assert!(should_trigger_agent(
             "discord",
             &user_source(),
             "@bot help",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
         assert!(!should_trigger_agent(
             "discord",
             &user_source(),
             "hello",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
     }
 
@@ -151,19 +181,22 @@ mod tests {
             "discord",
             &user_source(),
             "I need help",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
         assert!(should_trigger_agent(
             "discord",
             &user_source(),
             "There's an error",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
         assert!(!should_trigger_agent(
             "discord",
             &user_source(),
             "Hello world",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
     }
 
@@ -177,7 +210,8 @@ mod tests {
             "discord",
             &user_source(),
             "hello",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
     }
 
@@ -191,7 +225,8 @@ mod tests {
             "discord",
             &user_source(),
             "hello",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
         assert!(should_trigger_agent(
             "discord",
@@ -200,7 +235,8 @@ mod tests {
                 name: "TestUser".to_string(),
             },
             "hello",
-            &config
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config.clone())
         ));
     }
 
@@ -218,4 +254,215 @@ mod tests {
         );
         assert_eq!(extract_mention_prefix("hello world", &config), None);
     }
+
+    #[test]
+    fn test_direct_mention_by_user_id() {
+        let mut config = default_trigger_config();
+        config.mode = TriggerMode::DirectMention;
+
+        assert!(should_trigger_agent(
+            "discord",
+            &user_source(),
+            "<@999> what time is it?",
+            &addressed(&["999"], None),
+            &TriggerMatcher::new(config.clone())
+        ));
+        assert!(!should_trigger_agent(
+            "discord",
+            &user_source(),
+            "<@42> what time is it?",
+            &addressed(&["42"], None),
+            &TriggerMatcher::new(config.clone())
+        ));
+        // Text patterns alone do not count in this mode
+        assert!(!should_trigger_agent(
+            "discord",
+            &user_source(),
+            "@agent hello",
+            &addressed(&[], None),
+            &TriggerMatcher::new(config.clone())
+        ));
+    }
+
+    #[test]
+    fn test_direct_mention_by_reply() {
+        let mut config = default_trigger_config();
+        config.mode = TriggerMode::DirectMention;
+
+        assert!(should_trigger_agent(
+            "discord",
+            &user_source(),
+            "thanks, and what about tomorrow?",
+            &addressed(&[], Some("999")),
+            &TriggerMatcher::new(config.clone())
+        ));
+        assert!(!should_trigger_agent(
+            "discord",
+            &user_source(),
+            "agreed",
+            &addressed(&[], Some("42")),
+            &TriggerMatcher::new(config.clone())
+        ));
+    }
+
+    #[test]
+    fn test_direct_mention_needs_bot_id() {
+        let mut config = default_trigger_config();
+        config.mode = TriggerMode::DirectMention;
+        let addressing = MessageAddressing {
+            mentioned_user_ids: vec!["999".to_string()],
+            ..Default::default()
+        };
+
+        assert!(!should_trigger_agent(
+            "discord",
+            &user_source(),
+            "<@999> hi",
+            &addressing,
+            &TriggerMatcher::new(config.clone())
+        ));
+    }
+
+    #[test]
+    fn test_keyword_matches_whole_words_only() {
+        let mut config = default_trigger_config();
+        config.mode = TriggerMode::Keyword;
+        config.keywords = vec!["art".to_string()];
+        let triggers = TriggerMatcher::new(config);
+        let check = |content: &str| {
+            should_trigger_agent(
+                "discord",
+                &user_source(),
+                content,
+                &MessageAddressing::default(),
+                &triggers,
+            )
+        };
+
+        assert!(check("I love art"));
+        assert!(check("Art, mostly."));
+        assert!(!check("that was smart"));
+        assert!(!check("let's start"));
+    }
+
+    #[test]
+    fn test_keyword_substring_mode_is_explicit() {
+        let mut config = default_trigger_config();
+        config.mode = TriggerMode::Keyword;
+        config.keywords = vec!["art".to_string()];
+        config.keyword_match.word_boundary = false;
+
+        assert!(should_trigger_agent(
+            "discord",
+            &user_source(),
+            "that was smart",
+            &MessageAddressing::default(),
+            &TriggerMatcher::new(config)
+        ));
+    }
+
+    #[test]
+    fn test_keyword_case_sensitivity() {
+        let mut config = default_trigger_config();
+        config.mode = TriggerMode::Keyword;
+        config.keywords = vec!["GPU".to_string()];
+        let insensitive = TriggerMatcher::new(config.clone());
+        config.keyword_match.case_sensitive = true;
+        let sensitive = TriggerMatcher::new(config);
+
+        assert!(insensitive.matches_keyword("my gpu is hot"));
+        assert!(!sensitive.matches_keyword("my gpu is hot"));
+        assert!(sensitive.matches_keyword("my GPU is hot"));
+    }
+
+    #[test]
+    fn test_mention_word_boundary_around_symbols() {
+        let mut config = default_trigger_config();
+        config.mention_match.word_boundary = true;
+        let triggers = TriggerMatcher::new(config);
+
+        assert!(triggers.matches_mention("@agent help"));
+        assert!(triggers.matches_mention("hey @bot."));
+        assert!(!triggers.matches_mention("@agents assemble"));
+    }
+
+    #[test]
+    fn test_cooldown_suppresses_repeated_triggers() {
+        let mut config = default_trigger_config();
+        config.mode = TriggerMode::Always;
+        config.cooldown_seconds = 60;
+        let triggers = TriggerMatcher::new(config);
+        let check = |source: &ChannelSource| {
+            should_trigger_agent(
+                "discord",
+                source,
+                "hello",
+                &MessageAddressing::default(),
+                &triggers,
+            )
+        };
+
+        assert!(check(&user_source()));
+        assert!(!check(&user_source()));
+        // Other channels have their own window
+        assert!(check(&ChannelSource::Channel {
+            id: "789012".to_string(),
+            name: "general".to_string(),
+        }));
+    }
+
+    #[test]
+    fn test_cooldown_window_expires() {
+        let mut config = default_trigger_config();
+        config.cooldown_seconds = 30;
+        let triggers = TriggerMatcher::new(config);
+        let start = std::time::Instant::now();
+
+        assert!(triggers.try_start_cooldown_at("discord:1", start));
+        assert!(!triggers.try_start_cooldown_at("discord:1", start + Duration::from_secs(29)));
+        assert!(triggers.try_start_cooldown_at("discord:1", start + Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_no_cooldown_by_default() {
+        let mut config = default_trigger_config();
+        config.mode = TriggerMode::Always;
+        let triggers = TriggerMatcher::new(config);
+
+        for _ in 0..3 {
+            assert!(should_trigger_agent(
+                "discord",
+                &user_source(),
+                "hello",
+                &MessageAddressing::default(),
+                &triggers
+            ));
+        }
+    }
+
+    #[test]
+    fn test_own_messages_are_ignored() {
+        let mut config = default_trigger_config();
+        config.mode = TriggerMode::Always;
+        let own = MessageAddressing {
+            sender_id: Some("999".to_string()),
+            bot_user_id: Some("999".to_string()),
+            ..Default::default()
+        };
+
+        assert!(!should_trigger_agent(
+            "discord",
+            &user_source(),
+            "hello",
+            &own,
+            &TriggerMatcher::new(config.clone())
+        ));
+        assert!(should_trigger_agent(
+            "discord",
+            &user_source(),
+            "hello",
+            &addressed(&[], None),
+            &TriggerMatcher::new(config)
+        ));
+    }
 }
@@ -7,7 +7,9 @@ use gpui::*;
 
 use gearclaw_core::config::Config;
 
+use crate::autoscroll::AutoScroll;
 use crate::multiline_input::MultiLineTextInput;
+use crate::preferences::GuiPreferences;
 use crate::text_input::TextInput;
 use crate::theme;
 
@@ -68,12 +70,14 @@ pub struct DesktopApp {
     pub input: Entity<TextInput>,
     pub focus_handle: FocusHandle,
     pub scroll_handle: ScrollHandle,
+    pub autoscroll: AutoScroll,
     pub is_loading: bool,
     pub cancel_flag: Arc<AtomicBool>,
     pub runtime: Arc<tokio::runtime::Runtime>,
     pub view_mode: ViewMode,
     pub window_title: String,
     pub show_logs: bool,
+    pub preferences: GuiPreferences,
 
     // Settings fields (TextInput entities)
     pub setting_endpoint: Entity<TextInput>,
@@ -110,17 +114,18 @@ pub struct DesktopApp {
 }
 
 impl DesktopApp {
-    pub fn new(cx: &mut Context<Self>) -> Self {
+    pub fn new(preferences: GuiPreferences, cx: &mut Context<Self>) -> Self {
         let input = cx.new(|cx| TextInput::new("Type a message...", cx));
         let runtime = Arc::new(
             tokio::runtime::Runtime::new()
                 .expect("Failed to initialize shared Tokio runtime for GUI"),
         );
 
-        let (config, config_exists) = match Config::load(&None) {
+        let (mut config, config_exists) = match Config::load(&None) {
             Ok(config) => (config, true),
             Err(_) => (Config::sample(), false),
         };
+        preferences.apply_to(&mut config);
 
         let endpoint = config.llm.endpoint.clone();
         let api_key = config.llm.api_key.clone().unwrap_or_default();
@@ -216,6 +221,7 @@ impl DesktopApp {
             input,
             focus_handle: cx.focus_handle(),
             scroll_handle: ScrollHandle::new(),
+            autoscroll: AutoScroll::default(),
             is_loading: false,
             cancel_flag: Arc::new(AtomicBool::new(false)),
             runtime,
@@ -226,6 +232,7 @@ impl DesktopApp {
             },
             window_title: "GearClaw".to_string(),
             show_logs: false,
+            preferences,
             setting_endpoint,
             setting_api_key,
             setting_model,
@@ -278,6 +285,7 @@ impl DesktopApp {
             self.messages = self.session_messages.remove(&index).unwrap_or_default();
             self.active_session = index;
             self.view_mode = ViewMode::Chat;
+            self.autoscroll.resume();
             cx.notify();
         }
     }
@@ -328,16 +336,20 @@ impl DesktopApp {
         // Set loading
         self.is_loading = true;
         self.cancel_flag.store(false, Ordering::SeqCst);
+        self.autoscroll.resume();
+        self.scroll_chat_to_bottom(window);
         cx.notify();
 
         // Spawn background thread with its own Tokio runtime for network I/O
         let cancel_flag = self.cancel_flag.clone();
         let runtime = self.runtime.clone();
+        let preferences = self.preferences.clone();
         let task = cx.background_spawn({
             let cancel_flag = cancel_flag.clone();
             let runtime = runtime.clone();
             async move {
-                let join_handle = runtime.spawn(Self::run_agent(content, cancel_flag));
+                let join_handle =
+                    runtime.spawn(Self::run_agent(content, preferences, cancel_flag));
                 join_handle
                     .await
                     .map_err(|e| format!("Agent task join error: {}", e))?
@@ -368,14 +380,8 @@ impl DesktopApp {
                         }
                     }
                     this.is_loading = false;
+                    this.scroll_chat_to_bottom(window);
                     cx.notify();
-
-                    // Auto-scroll to bottom after next frame is rendered
-                    let scroll_handle = this.scroll_handle.clone();
-                    window.on_next_frame(move |window, _cx| {
-                        scroll_handle.scroll_to_bottom();
-                        window.refresh();
-                    });
                 });
             })
             .ok();
@@ -383,6 +389,26 @@ impl DesktopApp {
         .detach();
     }
 
+    /// Scroll the chat to the bottom after the next frame, unless the user
+    /// has scrolled up to read earlier messages.
+    pub fn scroll_chat_to_bottom(&self, window: &mut Window) {
+        if !self.autoscroll.is_following() {
+            return;
+        }
+        let scroll_handle = self.scroll_handle.clone();
+        window.on_next_frame(move |window, _cx| {
+            scroll_handle.scroll_to_bottom();
+            window.refresh();
+        });
+    }
+
+    /// Update autoscroll from where a user scroll left the chat view.
+    pub fn on_chat_scrolled(&mut self) {
+        let offset = -f32::from(self.scroll_handle.offset().y);
+        let max_offset = f32::from(self.scroll_handle.max_offset().height);
+        self.autoscroll.on_user_scroll(offset, max_offset);
+    }
+
     pub fn on_stop(&mut self, cx: &mut Context<Self>) {
         self.cancel_flag.store(true, Ordering::SeqCst);
         self.is_loading = false;
@@ -424,13 +450,15 @@ impl DesktopApp {
 
     async fn run_agent(
         user_message: String,
+        preferences: GuiPreferences,
         cancel_flag: Arc<AtomicBool>,
     ) -> Result<String, String> {
         use gearclaw_agent::Agent;
         use gearclaw_core::session::Session;
 
         // Load config and create Agent
-        let config = Config::load(&None).map_err(|e| format!("{}", e))?;
+        let mut config = Config::load(&None).map_err(|e| format!("{}", e))?;
+        preferences.apply_to(&mut config);
 
         let agent = Agent::new(config)
             .await
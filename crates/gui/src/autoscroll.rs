@@ -0,0 +1,81 @@
+//! Chat autoscroll state.
+//!
+//! The chat view follows new content only while the user is at the bottom.
+//! "Scrolled up" is decided from the position right after a user scroll
+//! event, never from content growth: a long reply pushing the bottom further
+//! away must not stop the view from following it.
+
+/// Distance from the bottom, in pixels, still treated as "at the bottom".
+pub const BOTTOM_TOLERANCE: f32 = 24.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoScroll {
+    following: bool,
+}
+
+impl Default for AutoScroll {
+    fn default() -> Self {
+        AutoScroll { following: true }
+    }
+}
+
+impl AutoScroll {
+    /// Record where a user scroll left the view. `offset` is how far the
+    /// view is scrolled down from the top, `max_offset` how far it can go.
+    ///
+    /// Ending more than `BOTTOM_TOLERANCE` above the bottom stops following;
+    /// scrolling back within the tolerance resumes it.
+    pub fn on_user_scroll(&mut self, offset: f32, max_offset: f32) {
+        self.following = max_offset - offset <= BOTTOM_TOLERANCE;
+    }
+
+    /// Whether new content should scroll the view to the bottom.
+    pub fn is_following(&self) -> bool {
+        self.following
+    }
+
+    /// Follow again regardless of position, e.g. when the user sends a message.
+    pub fn resume(&mut self) {
+        self.following = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_follows_by_default() {
+        assert!(AutoScroll::default().is_following());
+    }
+
+    #[test]
+    fn test_scrolling_up_stops_following() {
+        let mut scroll = AutoScroll::default();
+        scroll.on_user_scroll(400.0, 1000.0);
+        assert!(!scroll.is_following());
+    }
+
+    #[test]
+    fn test_returning_to_bottom_resumes() {
+        let mut scroll = AutoScroll::default();
+        scroll.on_user_scroll(400.0, 1000.0);
+        scroll.on_user_scroll(1000.0 - BOTTOM_TOLERANCE, 1000.0);
+        assert!(scroll.is_following());
+    }
+
+    #[test]
+    fn test_small_scroll_near_bottom_keeps_following() {
+        let mut scroll = AutoScroll::default();
+        scroll.on_user_scroll(990.0, 1000.0);
+        assert!(scroll.is_following());
+    }
+
+    #[test]
+    fn test_resume_after_scrolling_up() {
+        let mut scroll = AutoScroll::default();
+        scroll.on_user_scroll(0.0, 1000.0);
+        scroll.resume();
+        assert!(scroll.is_following());
+    }
+}
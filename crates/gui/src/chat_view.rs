@@ -1,4 +1,5 @@
 use crate::app::DesktopApp;
+use crate::markdown::{self, MarkdownBlock};
 use crate::theme;
 use gpui::prelude::FluentBuilder;
 use gpui::*;
@@ -15,6 +16,12 @@ impl DesktopApp {
             .id("chat-scroll")
             .h(px(500.0))
             .overflow_y_scroll()
+            .track_scroll(&self.scroll_handle)
+            // The handle reflects the wheel delta only once the frame is laid
+            // out, so read the position on the next frame.
+            .on_scroll_wheel(cx.listener(|_this, _event, window, cx| {
+                cx.on_next_frame(window, |this, _window, _cx| this.on_chat_scrolled());
+            }))
             .p(px(16.))
             .flex()
             .flex_col()
@@ -43,7 +50,13 @@ impl DesktopApp {
                                 el.bg(assistant_bubble).text_color(text_color)
                             })
                             .when(is_error, |el| el.bg(error_color).text_color(gpui::white()))
-                            .child(msg.content.clone())
+                            .map(|el| {
+                                if is_user || is_error {
+                                    el.child(msg.content.clone())
+                                } else {
+                                    el.child(self.render_markdown(i, &msg.content, cx))
+                                }
+                            })
                             .child(
                                 div()
                                     .absolute()
@@ -136,4 +149,88 @@ impl DesktopApp {
                 )
             })
     }
+
+    /// Render an assistant reply with fenced code blocks and inline code
+    /// spans styled; everything else stays plain text.
+    fn render_markdown(&self, msg_index: usize, content: &str, cx: &mut Context<Self>) -> Div {
+        let code_bg = theme::code_bg(cx);
+        let text_muted = theme::text_muted(cx);
+        let border = theme::border(cx);
+
+        div().flex().flex_col().gap(px(8.)).children(
+            markdown::parse_blocks(content)
+                .into_iter()
+                .enumerate()
+                .map(|(block_index, block)| match block {
+                    MarkdownBlock::Text(spans) => {
+                        let (text, code_ranges) = markdown::flatten_inline(&spans);
+                        let highlights = code_ranges
+                            .into_iter()
+                            .map(|range| {
+                                (
+                                    range,
+                                    HighlightStyle {
+                                        background_color: Some(code_bg.into()),
+                                        ..Default::default()
+                                    },
+                                )
+                            })
+                            .collect::<Vec<_>>();
+                        div()
+                            .child(StyledText::new(text).with_highlights(highlights))
+                            .into_any_element()
+                    }
+                    MarkdownBlock::Code { lang, code } => {
+                        let code_for_copy = code.clone();
+                        div()
+                            .flex()
+                            .flex_col()
+                            .rounded_md()
+                            .border_1()
+                            .border_color(border)
+                            .bg(code_bg)
+                            .child(
+                                div()
+                                    .flex()
+                                    .flex_row()
+                                    .justify_between()
+                                    .px(px(8.))
+                                    .py(px(2.))
+                                    .text_xs()
+                                    .text_color(text_muted)
+                                    .child(lang)
+                                    // Per-block copy: copies only the code, without fences
+                                    .child(
+                                        div()
+                                            .id(ElementId::Name(
+                                                format!("copy-code-{}-{}", msg_index, block_index)
+                                                    .into(),
+                                            ))
+                                            .cursor_pointer()
+                                            .child("Copy")
+                                            .on_click(cx.listener(
+                                                move |_this, _event, _window, cx| {
+                                                    cx.write_to_clipboard(
+                                                        ClipboardItem::new_string(
+                                                            code_for_copy.clone(),
+                                                        ),
+                                                    );
+                                                },
+                                            )),
+                                    ),
+                            )
+                            .child(
+                                div()
+                                    .px(px(10.))
+                                    .pb(px(8.))
+                                    .font_family("Menlo")
+                                    .whitespace_nowrap()
+                                    .overflow_x_hidden()
+                                    .child(code),
+                            )
+                            .into_any_element()
+                    }
+                }),
+        )
+    }
 }
@@ -1,10 +1,13 @@
 mod app;
+mod autoscroll;
 mod chat_view;
 mod input_bar;
 mod log_panel;
 mod log_store;
+mod markdown;
 mod monitor_view;
 mod multiline_input;
+mod preferences;
 mod settings_view;
 mod sidebar;
 mod status_bar;
@@ -22,22 +25,39 @@ use text_input::{
 };
 use theme::{Theme, ThemeMode};
 use log_store::{LogStore, GuiLogLayer};
+use preferences::{preferences_path, GuiPreferences};
+use gearclaw_core::logging::{self, LogFormat};
 
 fn main() {
     // Initialize logging system
     let log_store = LogStore::new();
     let gui_layer = GuiLogLayer::new(log_store.entries.clone());
+    // GEARCLAW_LOG_FORMAT only changes the terminal output; the log panel
+    // keeps its own line format.
+    let log_format = LogFormat::from_env().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        LogFormat::Text
+    });
+    let fmt_layer = match log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_target(true)
+            .with_ansi(false)
+            .boxed(),
+        LogFormat::Json => logging::fmt_layer(log_format, std::io::stdout),
+    };
     tracing_subscriber::registry()
         .with(gui_layer)
-        .with(tracing_subscriber::fmt::layer().with_target(true).with_ansi(false))
+        .with(fmt_layer)
         .with(tracing_subscriber::EnvFilter::try_from_default_env()
             .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")))
         .init();
 
+    let preferences = GuiPreferences::load_from(&preferences_path());
+
     Application::new().run(move |cx: &mut App| {
-        // Initialize theme from system appearance
+        // Initialize theme from the saved mode (System follows the OS appearance)
         let appearance = cx.window_appearance();
-        cx.set_global(Theme::for_appearance(appearance, ThemeMode::System));
+        cx.set_global(Theme::for_appearance(appearance, preferences.theme));
 
         // Register log store as global
         cx.set_global(log_store.clone());
@@ -76,7 +96,7 @@ fn main() {
                     }),
                     ..Default::default()
                 },
-                |_window, cx| cx.new(|cx| DesktopApp::new(cx)),
+                |_window, cx| cx.new(|cx| DesktopApp::new(preferences.clone(), cx)),
             )
             .unwrap();
 
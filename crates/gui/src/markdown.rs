@@ -0,0 +1,203 @@
+//! Minimal markdown tokenizer for chat messages.
+//!
+//! Only fenced code blocks and inline `code` spans are recognised; all other
+//! markdown is passed through as plain text.
+
+use std::ops::Range;
+
+/// A top-level piece of a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkdownBlock {
+    /// Plain text, possibly containing inline code spans.
+    Text(Vec<InlineSpan>),
+    /// A fenced code block. `lang` is the info string after the opening fence.
+    Code { lang: String, code: String },
+}
+
+/// A run of text inside a `MarkdownBlock::Text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InlineSpan {
+    Text(String),
+    Code(String),
+}
+
+/// Length of the backtick fence opening `line`, if it is one.
+fn fence_len(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start();
+    let ticks = trimmed.chars().take_while(|c| *c == '`').count();
+    (ticks >= 3).then_some(ticks)
+}
+
+/// Split a message into text and fenced code blocks.
+///
+/// A fence that is never closed runs to the end of the message, so a reply
+/// that is still streaming renders its partial code block as code.
+pub fn parse_blocks(text: &str) -> Vec<MarkdownBlock> {
+    let mut blocks = Vec::new();
+    let mut plain: Vec<&str> = Vec::new();
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        let Some(open) = fence_len(line) else {
+            plain.push(line);
+            continue;
+        };
+
+        if !plain.is_empty() {
+            blocks.push(MarkdownBlock::Text(parse_inline(&plain.join("\n"))));
+            plain.clear();
+        }
+
+        let lang = line.trim_start()[open..].trim().to_string();
+        let mut code: Vec<&str> = Vec::new();
+        for line in lines.by_ref() {
+            let trimmed = line.trim();
+            if fence_len(trimmed).is_some_and(|close| close >= open)
+                && trimmed.chars().all(|c| c == '`')
+            {
+                break;
+            }
+            code.push(line);
+        }
+        blocks.push(MarkdownBlock::Code {
+            lang,
+            code: code.join("\n"),
+        });
+    }
+
+    if !plain.is_empty() {
+        blocks.push(MarkdownBlock::Text(parse_inline(&plain.join("\n"))));
+    }
+    blocks
+}
+
+/// Split text on single-backtick `code` spans. An unmatched backtick is
+/// kept as literal text.
+pub fn parse_inline(text: &str) -> Vec<InlineSpan> {
+    let mut spans = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('`') {
+        let Some(len) = rest[start + 1..].find('`') else {
+            break;
+        };
+        let end = start + 1 + len;
+        if len == 0 {
+            // "``" is not a span; keep it as text.
+            push_text(&mut spans, &rest[..end + 1]);
+        } else {
+            push_text(&mut spans, &rest[..start]);
+            spans.push(InlineSpan::Code(rest[start + 1..end].to_string()));
+        }
+        rest = &rest[end + 1..];
+    }
+    push_text(&mut spans, rest);
+    spans
+}
+
+fn push_text(spans: &mut Vec<InlineSpan>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(InlineSpan::Text(last)) = spans.last_mut() {
+        last.push_str(text);
+    } else {
+        spans.push(InlineSpan::Text(text.to_string()));
+    }
+}
+
+/// Join spans into one string, returning the byte ranges of the code spans
+/// so they can be highlighted in a single selectable text run.
+pub fn flatten_inline(spans: &[InlineSpan]) -> (String, Vec<Range<usize>>) {
+    let mut text = String::new();
+    let mut code_ranges = Vec::new();
+    for span in spans {
+        match span {
+            InlineSpan::Text(t) => text.push_str(t),
+            InlineSpan::Code(c) => {
+                let start = text.len();
+                text.push_str(c);
+                code_ranges.push(start..text.len());
+            }
+        }
+    }
+    (text, code_ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> MarkdownBlock {
+        MarkdownBlock::Text(vec![InlineSpan::Text(s.to_string())])
+    }
+
+    #[test]
+    fn test_plain_text_is_one_block() {
+        assert_eq!(parse_blocks("hello\nworld"), vec![text("hello\nworld")]);
+    }
+
+    #[test]
+    fn test_fenced_block_with_language() {
+        let blocks = parse_blocks("Run this:\n```bash\nls -la\necho hi\n```\nDone.");
+        assert_eq!(
+            blocks,
+            vec![
+                text("Run this:"),
+                MarkdownBlock::Code {
+                    lang: "bash".to_string(),
+                    code: "ls -la\necho hi".to_string(),
+                },
+                text("Done."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unclosed_fence_runs_to_end() {
+        let blocks = parse_blocks("```\nfn main() {");
+        assert_eq!(
+            blocks,
+            vec![MarkdownBlock::Code {
+                lang: String::new(),
+                code: "fn main() {".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_longer_fence_contains_shorter_one() {
+        let blocks = parse_blocks("````md\n```rust\nlet x = 1;\n```\n````");
+        assert_eq!(
+            blocks,
+            vec![MarkdownBlock::Code {
+                lang: "md".to_string(),
+                code: "```rust\nlet x = 1;\n```".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_inline_code_spans() {
+        assert_eq!(
+            parse_inline("use `cargo test` or `make`"),
+            vec![
+                InlineSpan::Text("use ".to_string()),
+                InlineSpan::Code("cargo test".to_string()),
+                InlineSpan::Text(" or ".to_string()),
+                InlineSpan::Code("make".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_inline("a ` b"),
+            vec![InlineSpan::Text("a ` b".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_flatten_inline_ranges() {
+        let (text, ranges) = flatten_inline(&parse_inline("run `ls` now"));
+        assert_eq!(text, "run ls now");
+        assert_eq!(ranges, vec![4..6]);
+    }
+}
@@ -0,0 +1,123 @@
+//! GUI preferences persisted across launches.
+//!
+//! Stored as JSON in `~/.gearclaw/gui.json`, separate from `config.toml`:
+//! these are choices made in the window (theme, selected model and endpoint)
+//! rather than agent configuration.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::theme::ThemeMode;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuiPreferences {
+    /// `System` keeps following the OS appearance.
+    pub theme: ThemeMode,
+    /// Model chosen in the settings view; overrides `llm.primary` when set.
+    pub model: Option<String>,
+    /// Endpoint chosen in the settings view; overrides `llm.endpoint` when set.
+    pub endpoint: Option<String>,
+}
+
+/// Default location of the preferences file.
+pub fn preferences_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".gearclaw/gui.json")
+}
+
+impl GuiPreferences {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Read preferences, falling back to defaults if the file is missing or
+    /// unreadable so a bad file never blocks startup.
+    pub fn load_from(path: &Path) -> Self {
+        let Ok(json) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        Self::from_json(&json).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid GUI preferences {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    pub fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_json())
+    }
+
+    /// Save to the default path, logging instead of failing.
+    pub fn save(&self) {
+        let path = preferences_path();
+        if let Err(e) = self.save_to(&path) {
+            tracing::warn!("Failed to save GUI preferences {}: {}", path.display(), e);
+        }
+    }
+
+    /// Apply the model/endpoint overrides to a loaded config.
+    pub fn apply_to(&self, config: &mut gearclaw_core::config::Config) {
+        if let Some(model) = &self.model {
+            config.llm.primary = model.clone();
+        }
+        if let Some(endpoint) = &self.endpoint {
+            config.llm.endpoint = endpoint.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let prefs = GuiPreferences {
+            theme: ThemeMode::Dark,
+            model: Some("gpt-4o".to_string()),
+            endpoint: Some("http://localhost:11434/v1".to_string()),
+        };
+        assert_eq!(GuiPreferences::from_json(&prefs.to_json()).unwrap(), prefs);
+    }
+
+    #[test]
+    fn test_theme_serialized_lowercase() {
+        let prefs = GuiPreferences {
+            theme: ThemeMode::System,
+            ..Default::default()
+        };
+        assert!(prefs.to_json().contains("\"system\""));
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let prefs = GuiPreferences::from_json("{\"theme\": \"light\"}").unwrap();
+        assert_eq!(prefs.theme, ThemeMode::Light);
+        assert_eq!(prefs.model, None);
+    }
+
+    #[test]
+    fn test_save_and_load_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/gui.json");
+        let prefs = GuiPreferences {
+            theme: ThemeMode::Light,
+            model: Some("m".to_string()),
+            endpoint: None,
+        };
+        prefs.save_to(&path).unwrap();
+        assert_eq!(GuiPreferences::load_from(&path), prefs);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(GuiPreferences::load_from(&path), GuiPreferences::default());
+    }
+}
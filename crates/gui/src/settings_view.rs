@@ -1,6 +1,6 @@
 use crate::app::{DesktopApp, ViewMode};
 use crate::theme;
-use gearclaw_core::config::Config;
+use gearclaw_core::config::{Config, ConfigLoader};
 use gpui::*;
 
 impl DesktopApp {
@@ -171,8 +171,11 @@ impl DesktopApp {
             .content()
             .to_string();
 
-        // Load existing config or create sample
-        let mut config = Config::load(&None).unwrap_or_else(|_| Config::sample());
+        // Load existing config or create sample. The loaded copy lets the
+        // save write back only what changed, keeping `${VAR}` references
+        // and other layers out of the user file.
+        let loaded = Config::load(&None).ok();
+        let mut config = loaded.clone().unwrap_or_else(Config::sample);
         let mut errors = Vec::new();
         let parse_bool = |value: &str| match value.trim().to_lowercase().as_str() {
             "true" | "1" | "yes" | "y" => Some(true),
@@ -180,13 +183,13 @@ impl DesktopApp {
             _ => None,
         };
 
-        config.llm.endpoint = endpoint;
+        config.llm.endpoint = endpoint.clone();
         config.llm.api_key = if api_key.is_empty() {
             None
         } else {
             Some(api_key)
         };
-        config.llm.primary = model;
+        config.llm.primary = model.clone();
         config.llm.embedding_model = embedding;
         if !temperature.trim().is_empty() {
             match temperature.trim().parse::<f32>() {
@@ -259,16 +262,26 @@ impl DesktopApp {
             return;
         }
 
-        // Save to default path
-        let config_path = dirs::home_dir().unwrap().join(".gearclaw/config.toml");
+        // Save to the file the config was loaded from, or the default path
+        let config_path = loaded
+            .as_ref()
+            .and_then(|_| ConfigLoader::resolve_config_path(None).ok())
+            .unwrap_or_else(|| dirs::home_dir().unwrap().join(".gearclaw/config.toml"));
 
         // Ensure directory exists
         if let Some(parent) = config_path.parent() {
             let _ = std::fs::create_dir_all(parent);
         }
 
-        match config.save(&config_path) {
+        let saved = match &loaded {
+            Some(loaded) => config.save_edits(loaded, &config_path),
+            None => config.save(&config_path),
+        };
+        match saved {
             Ok(_) => {
+                self.preferences.model = Some(model).filter(|m| !m.trim().is_empty());
+                self.preferences.endpoint = Some(endpoint).filter(|e| !e.trim().is_empty());
+                self.preferences.save();
                 self.messages.push(crate::app::ChatMessage {
                     role: "assistant".to_string(),
                     content: "✅ Settings saved successfully!".to_string(),
@@ -80,11 +80,13 @@ impl DesktopApp {
                     .cursor_pointer()
                     .hover(|s| s.opacity(0.8))
                     .child(format!("🎨 Theme: {}", current_mode.label()))
-                    .on_click(cx.listener(|_this, _event, window, cx| {
+                    .on_click(cx.listener(|this, _event, window, cx| {
                         let current = theme::mode(cx);
                         let new_mode = current.next();
                         let appearance = window.appearance();
                         cx.set_global(Theme::for_appearance(appearance, new_mode));
+                        this.preferences.theme = new_mode;
+                        this.preferences.save();
                         cx.notify();
                     })),
             )
@@ -1,10 +1,13 @@
 use gpui::{App, Global, Rgba};
+use serde::{Deserialize, Serialize};
 
 /// Theme mode selection.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ThemeMode {
     Light,
     Dark,
+    #[default]
     System,
 }
 
@@ -46,6 +49,7 @@ pub struct Theme {
     pub error_color: Rgba,
     pub input_bg: Rgba,
     pub stop_button: Rgba,
+    pub code_bg: Rgba,
 }
 
 impl Global for Theme {}
@@ -78,6 +82,7 @@ impl Theme {
             error_color: rgba(0xf4, 0x43, 0x36),
             input_bg: rgba(0x2a, 0x2a, 0x2a),
             stop_button: rgba(0xd3, 0x2f, 0x2f),
+            code_bg: rgba(0x1a, 0x1a, 0x1a),
         }
     }
 
@@ -99,6 +104,7 @@ impl Theme {
             error_color: rgba(0xd3, 0x2f, 0x2f),
             input_bg: rgba(0xf5, 0xf5, 0xf5),
             stop_button: rgba(0xd3, 0x2f, 0x2f),
+            code_bg: rgba(0xf6, 0xf6, 0xf6),
         }
     }
 
@@ -138,5 +144,6 @@ pub fn assistant_bubble(cx: &App) -> Rgba { current(cx).assistant_bubble }
 pub fn error_color(cx: &App) -> Rgba { current(cx).error_color }
 pub fn input_bg(cx: &App) -> Rgba { current(cx).input_bg }
 pub fn stop_button(cx: &App) -> Rgba { current(cx).stop_button }
+pub fn code_bg(cx: &App) -> Rgba { current(cx).code_bg }
 
 pub fn mode(cx: &App) -> ThemeMode { current(cx).mode }
@@ -1,11 +1,13 @@
-use eventsource_stream::Eventsource;
+use eventsource_stream::{EventStreamError, Eventsource};
 use futures::Stream;
 use futures::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::pin::Pin;
+use std::time::Duration;
 use thiserror::Error;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
 #[derive(Debug, Error)]
 pub enum LlmError {
@@ -13,10 +15,26 @@ pub enum LlmError {
     Request(String),
     #[error("response error: {0}")]
     Response(String),
+    /// No response, or no stream data, within the configured timeout
+    #[error("timeout: {0}")]
+    Timeout(String),
+    /// The API answered with a non-success HTTP status
+    #[error("API error {status}: {message}")]
+    Status { status: u16, message: String },
     #[error("json error: {0}")]
     Json(#[from] serde_json::Error),
 }
 
+impl LlmError {
+    /// HTTP status of a [`LlmError::Status`] failure
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
@@ -36,17 +54,91 @@ pub struct ChatCompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<ToolSpec>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamOptions {
+    pub include_usage: bool,
+}
+
+/// Sampling parameters sent with chat completion requests. `None` leaves the
+/// provider default in place.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SamplingParams {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub presence_penalty: Option<f32>,
+}
+
+impl SamplingParams {
+    /// Fill every unset field in `self` from `base`.
+    pub fn or(self, base: SamplingParams) -> SamplingParams {
+        SamplingParams {
+            temperature: self.temperature.or(base.temperature),
+            top_p: self.top_p.or(base.top_p),
+            frequency_penalty: self.frequency_penalty.or(base.frequency_penalty),
+            presence_penalty: self.presence_penalty.or(base.presence_penalty),
+        }
+    }
+}
+
+/// Token accounting reported by the endpoint for a single completion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionResponse {
+    #[serde(default)]
+    pub id: String,
+    pub choices: Vec<Choice>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Choice {
+    pub message: Message,
+    pub finish_reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionStreamResponse {
     pub id: String,
+    /// Empty on the trailing usage-only chunk.
+    #[serde(default)]
     pub choices: Vec<StreamChoice>,
+    /// Only present on the final chunk when `stream_options.include_usage` is set.
+    #[serde(default)]
+    pub usage: Option<Usage>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -117,13 +209,235 @@ pub struct EmbeddingData {
     pub embedding: Vec<f32>,
 }
 
+/// What an endpoint's models accept. Requests leave out unsupported parts
+/// instead of failing on endpoints that reject them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelCapabilities {
+    /// Accepts `tools` (function calling)
+    pub supports_tools: bool,
+    /// Accepts `stream_options.include_usage` on streamed requests
+    pub supports_streaming_usage: bool,
+    /// Context window in tokens, when known
+    pub max_context: Option<usize>,
+}
+
+impl Default for ModelCapabilities {
+    fn default() -> Self {
+        Self {
+            supports_tools: true,
+            supports_streaming_usage: true,
+            max_context: None,
+        }
+    }
+}
+
+/// Retry behaviour for transient LLM endpoint failures (429, 5xx, network errors).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one; `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on every subsequent attempt.
+    pub base_delay_ms: u64,
+    /// Upper bound for a single backoff delay.
+    pub max_delay_ms: u64,
+    /// Add up to 50% random jitter to each delay.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy that sends every request exactly once.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+        matches!(status.as_u16(), 429 | 500 | 502 | 503 | 504)
+    }
+
+    fn backoff_delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let max = Duration::from_millis(self.max_delay_ms);
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(max);
+        }
+
+        let exp = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.saturating_sub(1).min(16));
+        let mut delay_ms = exp.min(self.max_delay_ms);
+        if self.jitter && delay_ms > 0 {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.subsec_nanos() as u64)
+                .unwrap_or(0);
+            delay_ms += nanos % (delay_ms / 2 + 1);
+        }
+        Duration::from_millis(delay_ms).min(max)
+    }
+}
+
+/// Mask the secret in an `Authorization` header value, keeping the scheme and
+/// a short key prefix (e.g. `Bearer sk-abc123` -> `Bearer sk-***`).
+pub fn redact_authorization(value: &str) -> String {
+    let (scheme, secret) = match value.split_once(' ') {
+        Some((scheme, secret)) => (Some(scheme), secret.trim()),
+        None => (None, value.trim()),
+    };
+    let prefix: String = if secret.chars().count() > 8 {
+        secret.chars().take(3).collect()
+    } else {
+        String::new()
+    };
+    match scheme {
+        Some(scheme) => format!("{} {}***", scheme, prefix),
+        None => format!("{}***", prefix),
+    }
+}
+
+/// Replace every `api_key`-like string field in `value` with `***`.
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if matches!(key.as_str(), "api_key" | "apikey" | "authorization") {
+                    if let serde_json::Value::String(secret) = field {
+                        *secret = redact_authorization(secret);
+                    }
+                } else {
+                    redact_json(field);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+/// Statuses that mean "this model cannot answer right now": unknown model or
+/// capacity errors that survived the retry policy.
+fn is_fallback_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::NOT_FOUND || RetryPolicy::is_retryable_status(status)
+}
+
+/// Read `Retry-After` as either delay-seconds or an HTTP-date (RFC 9110).
+/// A date in the past means retry now; anything unparseable falls back to
+/// the policy's own backoff.
+fn parse_retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    // IMF-fixdate is a subset of RFC 2822
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// How the API key is presented to the endpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>` (OpenAI and most compatible gateways)
+    #[default]
+    Bearer,
+    /// `api-key: <key>` (Azure OpenAI)
+    ApiKeyHeader,
+}
+
+/// Classify a reqwest failure, keeping timeouts distinct from other
+/// transport errors.
+fn transport_error(context: &str, error: reqwest::Error) -> LlmError {
+    if error.is_timeout() {
+        LlmError::Timeout(format!("{}: {}", context, error))
+    } else {
+        LlmError::Request(format!("{}: {}", context, error))
+    }
+}
+
+/// Pass `body` through, failing with [`LlmError::Timeout`] when `idle`
+/// elapses without a new chunk. The wait restarts with every chunk.
+fn idle_limited<S, B>(
+    body: S,
+    idle: Option<Duration>,
+) -> impl Stream<Item = Result<B, LlmError>> + Send
+where
+    S: Stream<Item = Result<B, reqwest::Error>> + Send + Unpin,
+    B: Send,
+{
+    futures::stream::unfold(Some(body), move |body| async move {
+        let mut body = body?;
+        let next = match idle {
+            Some(idle) => match tokio::time::timeout(idle, body.next()).await {
+                Ok(next) => next,
+                Err(_) => {
+                    let error = LlmError::Timeout(format!("stream sent no data for {:?}", idle));
+                    return Some((Err(error), None));
+                }
+            },
+            None => body.next().await,
+        }?;
+        Some((
+            next.map_err(|e| transport_error("stream error", e)),
+            Some(body),
+        ))
+    })
+}
+
+/// Stream of parsed chat completion chunks.
+pub type ChatStream =
+    Pin<Box<dyn Stream<Item = Result<ChatCompletionStreamResponse, LlmError>> + Send>>;
+
+/// Build the HTTP client used for LLM requests. HTTP/2 is negotiated via ALPN
+/// unless `force_http1` is set.
+fn build_http_client(force_http1: bool) -> Client {
+    let builder = Client::builder();
+    let builder = if force_http1 {
+        builder.http1_only()
+    } else {
+        builder
+    };
+    builder.build().unwrap_or_else(|_| Client::new())
+}
+
 pub struct LLMClient {
     client: Client,
     api_key: String,
     endpoint: String,
     model: String,
+    fallback_models: Vec<String>,
     embedding_model: String,
-    temperature: Option<f32>,
+    embedding_endpoint: Option<String>,
+    embedding_api_key: Option<String>,
+    sampling: SamplingParams,
+    retry_policy: RetryPolicy,
+    trace_http: bool,
+    auth_scheme: AuthScheme,
+    extra_headers: HashMap<String, String>,
+    capabilities: ModelCapabilities,
+    request_timeout: Option<Duration>,
+    stream_idle_timeout: Option<Duration>,
 }
 
 impl LLMClient {
@@ -135,15 +449,223 @@ impl LLMClient {
         temperature: Option<f32>,
     ) -> Self {
         Self {
-            client: Client::builder()
-                .http1_only()
-                .build()
-                .unwrap_or_else(|_| Client::new()),
+            client: build_http_client(false),
             api_key,
             endpoint,
             model,
+            fallback_models: Vec::new(),
             embedding_model,
-            temperature,
+            embedding_endpoint: None,
+            embedding_api_key: None,
+            sampling: SamplingParams {
+                temperature,
+                ..SamplingParams::default()
+            },
+            retry_policy: RetryPolicy::default(),
+            trace_http: false,
+            auth_scheme: AuthScheme::default(),
+            extra_headers: HashMap::new(),
+            capabilities: ModelCapabilities::default(),
+            request_timeout: None,
+            stream_idle_timeout: None,
+        }
+    }
+
+    /// Models tried in order when the primary model is missing or over
+    /// capacity.
+    pub fn with_fallbacks(mut self, fallback_models: Vec<String>) -> Self {
+        self.fallback_models = fallback_models;
+        self
+    }
+
+    /// Send embedding requests to a different base URL and/or key than chat
+    /// (e.g. a local embedding server). `None` falls back to the chat
+    /// endpoint and key.
+    pub fn with_embedding_endpoint(
+        mut self,
+        endpoint: Option<String>,
+        api_key: Option<String>,
+    ) -> Self {
+        self.embedding_endpoint = endpoint;
+        self.embedding_api_key = api_key;
+        self
+    }
+
+    /// Primary model followed by the configured fallbacks.
+    pub fn models(&self) -> Vec<String> {
+        std::iter::once(self.model.clone())
+            .chain(self.fallback_models.iter().cloned())
+            .collect()
+    }
+
+    /// Default sampling parameters for every chat request. Overrides the
+    /// `temperature` passed to [`LLMClient::new`].
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Fail a request that takes longer than `request` overall (including
+    /// reading a streamed body, so a stream still producing chunks at the
+    /// deadline ends with [`LlmError::Timeout`]), and a stream that goes
+    /// `stream_idle` without a new chunk. Each attempt of a retried request
+    /// gets the full budget. `None` waits indefinitely.
+    pub fn with_timeouts(
+        mut self,
+        request: Option<Duration>,
+        stream_idle: Option<Duration>,
+    ) -> Self {
+        self.request_timeout = request;
+        self.stream_idle_timeout = stream_idle;
+        self
+    }
+
+    /// Restrict the transport to HTTP/1.1. Some OpenAI-compatible proxies
+    /// mishandle SSE over HTTP/2 (streams stall or are truncated); this is the
+    /// escape hatch for them. HTTP/2 is allowed by default.
+    pub fn with_force_http1(mut self, force_http1: bool) -> Self {
+        self.client = build_http_client(force_http1);
+        self
+    }
+
+    /// Use a caller-supplied HTTP client (custom proxies, TLS roots, tests).
+    pub fn with_http_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Extra headers sent with every request to the chat endpoint (e.g.
+    /// `OpenAI-Organization`, `HTTP-Referer`), but not to a separate
+    /// embedding endpoint.
+    pub fn with_extra_headers(mut self, extra_headers: HashMap<String, String>) -> Self {
+        self.extra_headers = extra_headers;
+        self
+    }
+
+    pub fn with_auth_scheme(mut self, auth_scheme: AuthScheme) -> Self {
+        self.auth_scheme = auth_scheme;
+        self
+    }
+
+    /// Declare what the endpoint supports; requests drop `tools` and
+    /// `stream_options` when it doesn't.
+    pub fn with_capabilities(mut self, capabilities: ModelCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn capabilities(&self) -> ModelCapabilities {
+        self.capabilities
+    }
+
+    /// POST `body` as JSON to `url` with auth and extra headers applied.
+    fn post_json<T: Serialize>(&self, url: &str, body: &T) -> reqwest::RequestBuilder {
+        self.post_json_with_key(url, &self.api_key, true, body)
+    }
+
+    /// Extra headers are meant for the chat endpoint, so callers posting to
+    /// another host pass `extra_headers: false`.
+    fn post_json_with_key<T: Serialize>(
+        &self,
+        url: &str,
+        api_key: &str,
+        extra_headers: bool,
+        body: &T,
+    ) -> reqwest::RequestBuilder {
+        let mut builder = self
+            .client
+            .post(url)
+            .header("Content-Type", "application/json");
+        builder = match self.auth_scheme {
+            AuthScheme::Bearer => builder.header("Authorization", format!("Bearer {}", api_key)),
+            AuthScheme::ApiKeyHeader => builder.header("api-key", api_key),
+        };
+        if extra_headers {
+            for (name, value) in &self.extra_headers {
+                builder = builder.header(name, value);
+            }
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        builder.json(body)
+    }
+
+    /// Log full request bodies and response status/bodies at `debug` level.
+    /// Prompts end up in the logs, so keep this off in production.
+    pub fn with_http_tracing(mut self, enabled: bool) -> Self {
+        self.trace_http = enabled;
+        self
+    }
+
+    fn trace_request<T: Serialize>(&self, url: &str, body: &T) {
+        if !self.trace_http {
+            return;
+        }
+        let mut body = serde_json::to_value(body).unwrap_or_default();
+        redact_json(&mut body);
+        let auth = match self.auth_scheme {
+            AuthScheme::Bearer => format!(
+                "Authorization: {}",
+                redact_authorization(&format!("Bearer {}", self.api_key))
+            ),
+            AuthScheme::ApiKeyHeader => format!("api-key: {}", redact_authorization(&self.api_key)),
+        };
+        debug!("LLM request POST {} ({}): {}", url, auth, body);
+    }
+
+    fn trace_response(&self, url: &str, status: reqwest::StatusCode, body: &str) {
+        if self.trace_http {
+            debug!("LLM response {} from {}: {}", status, url, body);
+        }
+    }
+
+    /// Send a request built by `build`, retrying on network errors and
+    /// retryable status codes. The last response is returned as-is so callers
+    /// can inspect non-retryable failures themselves.
+    async fn send_with_retry<F>(&self, build: F) -> Result<reqwest::Response, reqwest::Error>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let max_attempts = self.retry_policy.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match build().send().await {
+                Ok(response)
+                    if attempt < max_attempts
+                        && RetryPolicy::is_retryable_status(response.status()) =>
+                {
+                    let delay = self
+                        .retry_policy
+                        .backoff_delay(attempt, parse_retry_after(&response));
+                    warn!(
+                        "LLM request returned {}, retrying in {:?} (attempt {}/{})",
+                        response.status(),
+                        delay,
+                        attempt + 1,
+                        max_attempts
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) if attempt < max_attempts && !e.is_builder() => {
+                    let delay = self.retry_policy.backoff_delay(attempt, None);
+                    warn!(
+                        "LLM request failed: {}, retrying in {:?} (attempt {}/{})",
+                        e,
+                        delay,
+                        attempt + 1,
+                        max_attempts
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+            attempt += 1;
         }
     }
 
@@ -152,26 +674,26 @@ impl LLMClient {
             model: self.embedding_model.clone(),
             input: text.to_string(),
         };
-        let url = format!("{}/embeddings", self.endpoint.trim_end_matches('/'));
+        let endpoint = self.embedding_endpoint.as_deref().unwrap_or(&self.endpoint);
+        let api_key = self.embedding_api_key.as_deref().unwrap_or(&self.api_key);
+        let url = format!("{}/embeddings", endpoint.trim_end_matches('/'));
+        let same_endpoint = endpoint.trim_end_matches('/') == self.endpoint.trim_end_matches('/');
 
         info!("Sending embedding request to: {}", url);
+        self.trace_request(&url, &request);
         let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
+            .send_with_retry(|| self.post_json_with_key(&url, api_key, same_endpoint, &request))
             .await
-            .map_err(|e| LlmError::Request(format!("embedding request failed: {}", e)))?;
+            .map_err(|e| transport_error("embedding request failed", e))?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
-            return Err(LlmError::Response(format!(
-                "embedding API error {}: {}",
-                status, error_text
-            )));
+            self.trace_response(&url, status, &error_text);
+            return Err(LlmError::Status {
+                status: status.as_u16(),
+                message: format!("embedding request: {}", error_text),
+            });
         }
 
         let embedding_response: EmbeddingResponse = response
@@ -187,41 +709,210 @@ impl LLMClient {
             .ok_or_else(|| LlmError::Response("no embedding returned".to_string()))
     }
 
-    pub async fn chat_completion_stream(
+    /// Send a chat request to each model in `models` until one answers with a
+    /// status that should not trigger a fallback. Returns the model that was
+    /// used, its request and its (possibly unsuccessful) response.
+    async fn send_chat<F>(
         &self,
+        models: &[String],
+        build: F,
+    ) -> Result<(String, ChatCompletionRequest, reqwest::Response), LlmError>
+    where
+        F: Fn(&str) -> ChatCompletionRequest,
+    {
+        let url = format!("{}/chat/completions", self.endpoint.trim_end_matches('/'));
+        let mut models = models.iter().peekable();
+        while let Some(model) = models.next() {
+            let request = build(model);
+            self.trace_request(&url, &request);
+            let response = self
+                .send_with_retry(|| self.post_json(&url, &request))
+                .await
+                .map_err(|e| transport_error("request failed", e))?;
+
+            let status = response.status();
+            if self.trace_http && status.is_success() {
+                debug!("LLM response {} from {} (model {})", status, url, model);
+            }
+            if let Some(next) = models.peek() {
+                if is_fallback_status(status) {
+                    let error_text = response.text().await.unwrap_or_default();
+                    self.trace_response(&url, status, &error_text);
+                    warn!(
+                        "model {} failed with {}: {}; falling back to {}",
+                        model, status, error_text, next
+                    );
+                    continue;
+                }
+            }
+            return Ok((model.clone(), request, response));
+        }
+        Err(LlmError::Request("no model configured".to_string()))
+    }
+
+    fn build_request(
+        &self,
+        model: &str,
         messages: Vec<Message>,
         tools: Option<Vec<ToolSpec>>,
         max_tokens: Option<usize>,
-    ) -> Result<
-        Pin<Box<dyn Stream<Item = Result<ChatCompletionStreamResponse, LlmError>> + Send>>,
-        LlmError,
-    > {
-        let request = ChatCompletionRequest {
-            model: self.model.clone(),
+        sampling: SamplingParams,
+        stream: bool,
+    ) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: model.to_string(),
             messages,
             max_tokens,
-            temperature: self.temperature,
-            tools: tools.clone(),
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            frequency_penalty: sampling.frequency_penalty,
+            presence_penalty: sampling.presence_penalty,
+            tools: tools.filter(|_| self.capabilities.supports_tools),
             tool_choice: None,
-            stream: Some(true),
-        };
+            stream: Some(stream),
+            stream_options: (stream && self.capabilities.supports_streaming_usage).then_some(
+                StreamOptions {
+                    include_usage: true,
+                },
+            ),
+        }
+    }
+
+    /// Non-streaming chat completion; the returned response carries `usage`
+    /// when the endpoint reports it.
+    pub async fn chat_completion(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+    ) -> Result<ChatCompletionResponse, LlmError> {
+        self.chat_completion_with_sampling(messages, tools, max_tokens, SamplingParams::default())
+            .await
+    }
+
+    /// Like [`LLMClient::chat_completion`], with per-request sampling
+    /// overrides layered over the client defaults.
+    pub async fn chat_completion_with_sampling(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+        sampling: SamplingParams,
+    ) -> Result<ChatCompletionResponse, LlmError> {
+        self.chat_completion_with_models(&self.models(), messages, tools, max_tokens, sampling)
+            .await
+            .map(|(_, response)| response)
+    }
+
+    /// Non-streaming chat completion against `models` in order, returning the
+    /// model that answered alongside its response.
+    pub async fn chat_completion_with_models(
+        &self,
+        models: &[String],
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+        sampling: SamplingParams,
+    ) -> Result<(String, ChatCompletionResponse), LlmError> {
+        let sampling = sampling.or(self.sampling);
+        let (model, _, response) = self
+            .send_chat(models, |model| {
+                self.build_request(
+                    model,
+                    messages.clone(),
+                    tools.clone(),
+                    max_tokens,
+                    sampling,
+                    false,
+                )
+            })
+            .await?;
 
         let url = format!("{}/chat/completions", self.endpoint.trim_end_matches('/'));
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
+        let status = response.status();
+        let body = response
+            .text()
             .await
-            .map_err(|e| LlmError::Request(format!("request failed: {}", e)))?;
+            .map_err(|e| transport_error("failed to read response body", e))?;
+        self.trace_response(&url, status, &body);
+
+        if !status.is_success() {
+            return Err(LlmError::Status {
+                status: status.as_u16(),
+                message: body,
+            });
+        }
+
+        Ok((model, serde_json::from_str(&body)?))
+    }
+
+    pub async fn chat_completion_stream(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+    ) -> Result<ChatStream, LlmError> {
+        self.chat_completion_stream_with_sampling(
+            messages,
+            tools,
+            max_tokens,
+            SamplingParams::default(),
+        )
+        .await
+    }
+
+    /// Like [`LLMClient::chat_completion_stream`], with per-request sampling
+    /// overrides layered over the client defaults.
+    pub async fn chat_completion_stream_with_sampling(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+        sampling: SamplingParams,
+    ) -> Result<ChatStream, LlmError> {
+        self.chat_completion_stream_with_models(
+            &self.models(),
+            messages,
+            tools,
+            max_tokens,
+            sampling,
+        )
+        .await
+        .map(|(_, stream)| stream)
+    }
+
+    /// Streaming chat completion against `models` in order, returning the
+    /// model that answered alongside its chunk stream.
+    pub async fn chat_completion_stream_with_models(
+        &self,
+        models: &[String],
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+        sampling: SamplingParams,
+    ) -> Result<(String, ChatStream), LlmError> {
+        let sampling = sampling.or(self.sampling);
+        let (model, request, response) = self
+            .send_chat(models, |model| {
+                self.build_request(
+                    model,
+                    messages.clone(),
+                    tools.clone(),
+                    max_tokens,
+                    sampling,
+                    true,
+                )
+            })
+            .await?;
+
+        let url = format!("{}/chat/completions", self.endpoint.trim_end_matches('/'));
 
         let response = if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
+            self.trace_response(&url, status, &error_text);
 
-            if status == reqwest::StatusCode::BAD_REQUEST && tools.is_some() {
+            if status == reqwest::StatusCode::BAD_REQUEST && request.tools.is_some() {
                 warn!(
                     "chat completion with tools returned 400, retrying without tools: {}",
                     error_text
@@ -240,76 +931,216 @@ impl LLMClient {
                             .as_ref()
                             .map(|c| c.trim())
                             .filter(|c| !c.is_empty())
-                            .map(|c| c.to_string());
-
-                        if content.is_none() {
-                            return None;
-                        }
+                            .map(|c| c.to_string())?;
 
                         Some(Message {
                             role: m.role.clone(),
-                            content,
+                            content: Some(content),
                             tool_calls: None,
                             tool_call_id: None,
                         })
                     })
                     .collect();
 
-                let fallback_request = ChatCompletionRequest {
-                    model: self.model.clone(),
-                    messages: fallback_messages,
-                    max_tokens,
-                    temperature: self.temperature,
-                    tools: None,
-                    tool_choice: None,
-                    stream: Some(true),
-                };
+                let fallback_request =
+                    self.build_request(&model, fallback_messages, None, max_tokens, sampling, true);
 
+                self.trace_request(&url, &fallback_request);
                 let fallback_response = self
-                    .client
-                    .post(&url)
-                    .header("Authorization", format!("Bearer {}", self.api_key))
-                    .header("Content-Type", "application/json")
-                    .json(&fallback_request)
+                    .post_json(&url, &fallback_request)
                     .send()
                     .await
-                    .map_err(|e| LlmError::Request(format!("fallback request failed: {}", e)))?;
+                    .map_err(|e| transport_error("fallback request failed", e))?;
 
                 if !fallback_response.status().is_success() {
                     let fallback_status = fallback_response.status();
                     let fallback_error_text = fallback_response.text().await.unwrap_or_default();
-                    return Err(LlmError::Response(format!(
-                        "API error {}: {}; fallback without tools failed {}: {}",
-                        status, error_text, fallback_status, fallback_error_text
-                    )));
+                    self.trace_response(&url, fallback_status, &fallback_error_text);
+                    return Err(LlmError::Status {
+                        status: fallback_status.as_u16(),
+                        message: format!(
+                            "{}; the request with tools failed first with {}: {}",
+                            fallback_error_text, status, error_text
+                        ),
+                    });
                 }
 
                 fallback_response
             } else {
-                return Err(LlmError::Response(format!(
-                    "API error {}: {}",
-                    status, error_text
-                )));
+                return Err(LlmError::Status {
+                    status: status.as_u16(),
+                    message: error_text,
+                });
             }
         } else {
             response
         };
 
-        let stream = response
-            .bytes_stream()
+        // `[DONE]` is the normal end-of-stream sentinel: stop yielding there.
+        let stream = idle_limited(Box::pin(response.bytes_stream()), self.stream_idle_timeout)
             .eventsource()
+            .take_while(|event| {
+                futures::future::ready(!matches!(event, Ok(event) if event.data == "[DONE]"))
+            })
             .map(|event| match event {
-                Ok(event) => {
-                    if event.data == "[DONE]" {
-                        Err(LlmError::Response("Stream finished".to_string()))
-                    } else {
-                        serde_json::from_str::<ChatCompletionStreamResponse>(&event.data)
-                            .map_err(LlmError::Json)
-                    }
-                }
+                Ok(event) => serde_json::from_str::<ChatCompletionStreamResponse>(&event.data)
+                    .map_err(LlmError::Json),
+                Err(EventStreamError::Transport(e)) => Err(e),
                 Err(e) => Err(LlmError::Request(format!("stream error: {}", e))),
             });
 
-        Ok(Box::pin(stream))
+        Ok((model, Box::pin(stream)))
+    }
+}
+
+/// The LLM operations the agent and memory rely on. [`LLMClient`] talks to
+/// an OpenAI-compatible endpoint; tests can substitute a scripted backend.
+#[async_trait::async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// Models to try, primary first
+    fn models(&self) -> Vec<String>;
+
+    fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::default()
+    }
+
+    /// Model that [`LlmBackend::get_embedding`] uses; vectors from different
+    /// models are not comparable
+    fn embedding_model(&self) -> String;
+
+    /// Non-streaming completion, used for summaries
+    async fn chat_completion(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+    ) -> Result<ChatCompletionResponse, LlmError>;
+
+    /// Streamed completion against `models` in order; returns the model that
+    /// answered with its chunk stream
+    async fn chat_completion_stream(
+        &self,
+        models: &[String],
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+        sampling: SamplingParams,
+    ) -> Result<(String, ChatStream), LlmError>;
+
+    async fn get_embedding(&self, text: &str) -> Result<Vec<f32>, LlmError>;
+}
+
+#[async_trait::async_trait]
+impl LlmBackend for LLMClient {
+    fn models(&self) -> Vec<String> {
+        LLMClient::models(self)
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.capabilities
+    }
+
+    fn embedding_model(&self) -> String {
+        self.embedding_model.clone()
+    }
+
+    async fn chat_completion(
+        &self,
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+    ) -> Result<ChatCompletionResponse, LlmError> {
+        LLMClient::chat_completion(self, messages, tools, max_tokens).await
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        models: &[String],
+        messages: Vec<Message>,
+        tools: Option<Vec<ToolSpec>>,
+        max_tokens: Option<usize>,
+        sampling: SamplingParams,
+    ) -> Result<(String, ChatStream), LlmError> {
+        self.chat_completion_stream_with_models(models, messages, tools, max_tokens, sampling)
+            .await
+    }
+
+    async fn get_embedding(&self, text: &str) -> Result<Vec<f32>, LlmError> {
+        LLMClient::get_embedding(self, text).await
+    }
+}
+
+/// Model name recorded for [`local_embedding`] vectors
+pub const LOCAL_EMBEDDING_MODEL: &str = "local-hash";
+/// Length of [`local_embedding`] vectors
+pub const LOCAL_EMBEDDING_DIMENSION: usize = 256;
+
+/// Deterministic embedding computed without a network call, for tests and
+/// offline use. Each token (a run of ASCII letters and digits, or a single
+/// other alphanumeric character such as a CJK ideograph) is hashed into a
+/// signed bucket and the vector is L2-normalized, so texts sharing words
+/// score as similar. It carries no meaning beyond word overlap.
+pub fn local_embedding(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0f32; LOCAL_EMBEDDING_DIMENSION];
+    let mut add = |token: &str| {
+        // FNV-1a: stable across builds, unlike `DefaultHasher`
+        let hash = token.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+        let bucket = (hash % LOCAL_EMBEDDING_DIMENSION as u64) as usize;
+        vector[bucket] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+    };
+
+    let lower = text.to_lowercase();
+    let mut word = String::new();
+    for c in lower.chars() {
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            add(&word);
+            word.clear();
+        }
+        if c.is_alphanumeric() {
+            add(c.encode_utf8(&mut [0; 4]));
+        }
+    }
+    if !word.is_empty() {
+        add(&word);
+    }
+
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_bearer_token_keeping_scheme_and_prefix() {
+        assert_eq!(
+            redact_authorization("Bearer sk-abcdef0123456789"),
+            "Bearer sk-***"
+        );
+        assert_eq!(redact_authorization("Bearer short"), "Bearer ***");
+        assert_eq!(redact_authorization("sk-abcdef0123456789"), "sk-***");
+    }
+
+    #[test]
+    fn redacts_nested_api_keys_in_json() {
+        let mut body = serde_json::json!({
+            "model": "gpt-test",
+            "api_key": "sk-abcdef0123456789",
+            "extra": [{ "Authorization": "Bearer sk-abcdef0123456789" }]
+        });
+        redact_json(&mut body);
+        assert_eq!(body["api_key"], "sk-***");
+        assert_eq!(body["extra"][0]["Authorization"], "Bearer sk-***");
+        assert_eq!(body["model"], "gpt-test");
     }
 }
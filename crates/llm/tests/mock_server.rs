@@ -0,0 +1,515 @@
+mod common;
+
+use common::{MockResponse, MockServer};
+use futures::StreamExt;
+use gearclaw_llm::{AuthScheme, LLMClient, LlmError, Message, RetryPolicy, SamplingParams, Usage};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn client_for(server: &MockServer) -> LLMClient {
+    LLMClient::new(
+        "test-key".to_string(),
+        server.url.clone(),
+        "gpt-test".to_string(),
+        "embed-test".to_string(),
+        Some(0.7),
+    )
+}
+
+fn fast_retry(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay_ms: 5,
+        max_delay_ms: 50,
+        jitter: false,
+    }
+}
+
+fn embedding_ok() -> MockResponse {
+    MockResponse::json(200, json!({ "data": [{ "embedding": [0.1, 0.2, 0.3] }] }))
+}
+
+#[tokio::test]
+async fn retries_transient_failures_then_succeeds() {
+    let server = MockServer::start(vec![
+        MockResponse::json(503, json!({ "error": "overloaded" })),
+        MockResponse::json(429, json!({ "error": "slow down" })).with_header("Retry-After", "0"),
+        embedding_ok(),
+    ])
+    .await;
+    let client = client_for(&server).with_retry_policy(fast_retry(3));
+
+    let embedding = client.get_embedding("hello").await.expect("embedding");
+    assert_eq!(embedding, vec![0.1, 0.2, 0.3]);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn honors_retry_after_http_date() {
+    let later = chrono::Utc::now() + chrono::Duration::hours(1);
+    let server = MockServer::start(vec![
+        MockResponse::json(429, json!({ "error": "slow down" })).with_header(
+            "Retry-After",
+            &later.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+        ),
+        embedding_ok(),
+    ])
+    .await;
+    let client = client_for(&server).with_retry_policy(fast_retry(2));
+
+    // The hour-long wait is clamped to max_delay_ms rather than ignored
+    let started = std::time::Instant::now();
+    client.get_embedding("hello").await.expect("embedding");
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn falls_back_to_backoff_on_unparseable_retry_after() {
+    let server = MockServer::start(vec![
+        MockResponse::json(503, json!({})).with_header("Retry-After", "soon"),
+        embedding_ok(),
+    ])
+    .await;
+    let client = client_for(&server).with_retry_policy(fast_retry(2));
+
+    client.get_embedding("hello").await.expect("embedding");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn does_not_retry_auth_errors() {
+    let server = MockServer::start(vec![
+        MockResponse::json(401, json!({ "error": "bad key" })),
+        embedding_ok(),
+    ])
+    .await;
+    let client = client_for(&server).with_retry_policy(fast_retry(3));
+
+    let err = client.get_embedding("hello").await.unwrap_err();
+    assert_eq!(err.status(), Some(401));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_attempts() {
+    let server = MockServer::start(vec![MockResponse::json(502, json!({}))]).await;
+    let client = client_for(&server).with_retry_policy(fast_retry(2));
+
+    assert!(client.get_embedding("hello").await.is_err());
+    assert_eq!(server.requests().len(), 2);
+}
+
+fn user(text: &str) -> Vec<Message> {
+    vec![Message {
+        role: "user".to_string(),
+        content: Some(text.to_string()),
+        tool_calls: None,
+        tool_call_id: None,
+    }]
+}
+
+#[tokio::test]
+async fn stream_reports_usage_from_final_chunk() {
+    let server = MockServer::start(vec![MockResponse::sse(&[
+        r#"{"id":"c1","choices":[{"delta":{"content":"hi"},"finish_reason":null}]}"#,
+        r#"{"id":"c1","choices":[],"usage":{"prompt_tokens":12,"completion_tokens":3,"total_tokens":15}}"#,
+        "[DONE]",
+    ])])
+    .await;
+    let client = client_for(&server);
+
+    let mut stream = client
+        .chat_completion_stream(user("hello"), None, None)
+        .await
+        .expect("stream");
+    let mut usage = None;
+    while let Some(Ok(chunk)) = stream.next().await {
+        if chunk.usage.is_some() {
+            usage = chunk.usage;
+        }
+    }
+
+    assert_eq!(
+        usage,
+        Some(Usage {
+            prompt_tokens: 12,
+            completion_tokens: 3,
+            total_tokens: 15,
+        })
+    );
+    let body = server.requests()[0].json();
+    assert_eq!(body["stream_options"]["include_usage"], json!(true));
+}
+
+#[tokio::test]
+async fn non_streaming_completion_reports_usage() {
+    let server = MockServer::start(vec![MockResponse::json(
+        200,
+        json!({
+            "id": "c2",
+            "choices": [{
+                "message": { "role": "assistant", "content": "pong" },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6 }
+        }),
+    )])
+    .await;
+    let client = client_for(&server);
+
+    let response = client
+        .chat_completion(user("ping"), None, None)
+        .await
+        .expect("completion");
+    assert_eq!(response.choices[0].message.content.as_deref(), Some("pong"));
+    assert_eq!(response.usage.map(|u| u.total_tokens), Some(6));
+    assert!(server.requests()[0].json().get("stream_options").is_none());
+}
+
+fn completion_ok() -> MockResponse {
+    MockResponse::json(
+        200,
+        json!({
+            "id": "c3",
+            "choices": [{ "message": { "role": "assistant", "content": "ok" }, "finish_reason": "stop" }]
+        }),
+    )
+}
+
+#[tokio::test]
+async fn configured_sampling_lands_in_request_body() {
+    let server = MockServer::start(vec![MockResponse::sse(&["[DONE]"])]).await;
+    let client = LLMClient::new(
+        "test-key".to_string(),
+        server.url.clone(),
+        "gpt-test".to_string(),
+        "embed-test".to_string(),
+        Some(0.0),
+    )
+    .with_sampling(SamplingParams {
+        temperature: Some(0.0),
+        top_p: Some(0.9),
+        frequency_penalty: Some(0.5),
+        presence_penalty: None,
+    });
+
+    let mut stream = client
+        .chat_completion_stream(user("hello"), None, None)
+        .await
+        .expect("stream");
+    while stream.next().await.is_some() {}
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["temperature"], json!(0.0));
+    assert_eq!(body["top_p"].as_f64(), Some(0.9));
+    assert_eq!(body["frequency_penalty"], json!(0.5));
+    assert!(body.get("presence_penalty").is_none());
+}
+
+#[tokio::test]
+async fn per_request_sampling_overrides_client_defaults() {
+    let server = MockServer::start(vec![completion_ok()]).await;
+    let client = client_for(&server);
+
+    client
+        .chat_completion_with_sampling(
+            user("hello"),
+            None,
+            None,
+            SamplingParams {
+                temperature: Some(0.2),
+                presence_penalty: Some(1.0),
+                ..SamplingParams::default()
+            },
+        )
+        .await
+        .expect("completion");
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["temperature"].as_f64(), Some(0.2));
+    assert_eq!(body["presence_penalty"], json!(1.0));
+    assert!(body.get("top_p").is_none());
+}
+
+#[tokio::test]
+async fn falls_back_to_next_model_when_primary_is_missing() {
+    let server = MockServer::start(vec![
+        MockResponse::json(404, json!({ "error": { "message": "model not found" } })),
+        completion_ok(),
+    ])
+    .await;
+    let client = client_for(&server)
+        .with_retry_policy(fast_retry(3))
+        .with_fallbacks(vec!["gpt-backup".to_string(), "gpt-last".to_string()]);
+
+    let (model, response) = client
+        .chat_completion_with_models(
+            &client.models(),
+            user("hello"),
+            None,
+            None,
+            SamplingParams::default(),
+        )
+        .await
+        .expect("completion");
+
+    assert_eq!(model, "gpt-backup");
+    assert_eq!(response.choices[0].message.content.as_deref(), Some("ok"));
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].json()["model"], json!("gpt-test"));
+    assert_eq!(requests[1].json()["model"], json!("gpt-backup"));
+}
+
+#[tokio::test]
+async fn done_sentinel_ends_stream_without_error() {
+    let server = MockServer::start(vec![MockResponse::sse(&[
+        r#"{"id":"c4","choices":[{"delta":{"content":"he"},"finish_reason":null}]}"#,
+        r#"{"id":"c4","choices":[{"delta":{"content":"llo"},"finish_reason":"stop"}]}"#,
+        "[DONE]",
+    ])])
+    .await;
+    let client = client_for(&server);
+
+    let mut stream = client
+        .chat_completion_stream(user("hi"), None, None)
+        .await
+        .expect("stream");
+    let mut text = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.expect("no error items before or at [DONE]");
+        for choice in chunk.choices {
+            text.push_str(choice.delta.content.as_deref().unwrap_or_default());
+        }
+    }
+    assert_eq!(text, "hello");
+}
+
+#[tokio::test]
+async fn stalled_stream_fails_after_idle_timeout() {
+    let server = MockServer::start(vec![MockResponse::sse(&[
+        r#"{"id":"c5","choices":[{"delta":{"content":"he"},"finish_reason":null}]}"#,
+    ])
+    .then_stall()])
+    .await;
+    let client = client_for(&server).with_timeouts(None, Some(Duration::from_millis(200)));
+
+    let mut stream = client
+        .chat_completion_stream(user("hi"), None, None)
+        .await
+        .expect("stream");
+    let first = stream.next().await.expect("first chunk");
+    assert_eq!(
+        first.expect("chunk before the stall").choices[0]
+            .delta
+            .content
+            .as_deref(),
+        Some("he")
+    );
+
+    let stalled = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("idle timeout should end the wait");
+    assert!(
+        matches!(stalled, Some(Err(LlmError::Timeout(_)))),
+        "{:?}",
+        stalled
+    );
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn request_timeout_bounds_a_stalled_stream() {
+    let server = MockServer::start(vec![MockResponse::sse(&[
+        r#"{"id":"c6","choices":[{"delta":{"content":"he"},"finish_reason":null}]}"#,
+    ])
+    .then_stall()])
+    .await;
+    let client = client_for(&server).with_timeouts(Some(Duration::from_millis(300)), None);
+
+    let mut stream = client
+        .chat_completion_stream(user("hi"), None, None)
+        .await
+        .expect("stream");
+    let error = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match stream.next().await {
+                Some(Ok(_)) => continue,
+                other => break other,
+            }
+        }
+    })
+    .await
+    .expect("request timeout should end the stream");
+    assert!(
+        matches!(error, Some(Err(LlmError::Timeout(_)))),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn extra_headers_and_api_key_auth_reach_the_server() {
+    let server = MockServer::start(vec![completion_ok(), embedding_ok()]).await;
+    let client = client_for(&server)
+        .with_auth_scheme(AuthScheme::ApiKeyHeader)
+        .with_extra_headers(HashMap::from([
+            ("OpenAI-Organization".to_string(), "org-123".to_string()),
+            (
+                "HTTP-Referer".to_string(),
+                "https://gearclaw.dev".to_string(),
+            ),
+        ]));
+
+    client
+        .chat_completion(user("hello"), None, None)
+        .await
+        .expect("completion");
+    client.get_embedding("hello").await.expect("embedding");
+
+    for request in server.requests() {
+        assert_eq!(request.header("api-key"), Some("test-key"));
+        assert_eq!(request.header("Authorization"), None);
+        assert_eq!(request.header("OpenAI-Organization"), Some("org-123"));
+        assert_eq!(request.header("HTTP-Referer"), Some("https://gearclaw.dev"));
+    }
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn bearer_auth_is_the_default() {
+    let server = MockServer::start(vec![completion_ok()]).await;
+    client_for(&server)
+        .chat_completion(user("hello"), None, None)
+        .await
+        .expect("completion");
+
+    assert_eq!(
+        server.requests()[0].header("Authorization"),
+        Some("Bearer test-key")
+    );
+}
+
+#[tokio::test]
+async fn embeddings_use_their_own_endpoint_and_key() {
+    let chat = MockServer::start(vec![completion_ok()]).await;
+    let embeddings = MockServer::start(vec![embedding_ok()]).await;
+    let client = client_for(&chat)
+        .with_embedding_endpoint(
+            Some(format!("{}/", embeddings.url)),
+            Some("embed-key".to_string()),
+        )
+        .with_extra_headers(HashMap::from([(
+            "OpenAI-Organization".to_string(),
+            "org-123".to_string(),
+        )]));
+
+    client
+        .chat_completion(user("hello"), None, None)
+        .await
+        .expect("completion");
+    client.get_embedding("hello").await.expect("embedding");
+
+    let chat_requests = chat.requests();
+    assert_eq!(chat_requests.len(), 1);
+    assert_eq!(chat_requests[0].path, "/v1/chat/completions");
+    assert_eq!(
+        chat_requests[0].header("Authorization"),
+        Some("Bearer test-key")
+    );
+    assert_eq!(
+        chat_requests[0].header("OpenAI-Organization"),
+        Some("org-123")
+    );
+    let embedding_requests = embeddings.requests();
+    assert_eq!(embedding_requests.len(), 1);
+    assert_eq!(embedding_requests[0].path, "/v1/embeddings");
+    assert_eq!(
+        embedding_requests[0].header("Authorization"),
+        Some("Bearer embed-key")
+    );
+    assert_eq!(embedding_requests[0].header("OpenAI-Organization"), None);
+}
+
+#[tokio::test]
+async fn embeddings_default_to_the_chat_endpoint() {
+    let server = MockServer::start(vec![embedding_ok()]).await;
+    client_for(&server)
+        .get_embedding("hello")
+        .await
+        .expect("embedding");
+
+    assert_eq!(server.requests()[0].path, "/v1/embeddings");
+    assert_eq!(
+        server.requests()[0].header("Authorization"),
+        Some("Bearer test-key")
+    );
+}
+
+/// Serve one SSE body over cleartext HTTP/2 (h2c) to every request.
+async fn start_h2_sse_server(body: &'static str) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let Ok(mut conn) = h2::server::handshake(socket).await else {
+                    return;
+                };
+                while let Some(Ok((_request, mut respond))) = conn.accept().await {
+                    let response = http::Response::builder()
+                        .status(200)
+                        .header("content-type", "text/event-stream")
+                        .body(())
+                        .expect("response");
+                    let Ok(mut send) = respond.send_response(response, false) else {
+                        return;
+                    };
+                    // Split across frames to exercise chunk reassembly.
+                    let (head, tail) = body.split_at(body.len() / 2);
+                    let _ = send.send_data(bytes::Bytes::from_static(head.as_bytes()), false);
+                    let _ = send.send_data(bytes::Bytes::from_static(tail.as_bytes()), true);
+                }
+            });
+        }
+    });
+    format!("http://{}/v1", addr)
+}
+
+#[tokio::test]
+async fn streaming_parses_over_http2() {
+    let url = start_h2_sse_server(concat!(
+        "data: {\"id\":\"h2\",\"choices\":[{\"delta\":{\"content\":\"over \"},\"finish_reason\":null}]}\n\n",
+        "data: {\"id\":\"h2\",\"choices\":[{\"delta\":{\"content\":\"h2\"},\"finish_reason\":\"stop\"}]}\n\n",
+        "data: [DONE]\n\n",
+    ))
+    .await;
+    let http2_client = reqwest::Client::builder()
+        .http2_prior_knowledge()
+        .build()
+        .expect("http2 client");
+    let client = LLMClient::new(
+        "test-key".to_string(),
+        url,
+        "gpt-test".to_string(),
+        "embed-test".to_string(),
+        None,
+    )
+    .with_http_client(http2_client);
+
+    let mut stream = client
+        .chat_completion_stream(user("hi"), None, None)
+        .await
+        .expect("stream");
+    let mut text = String::new();
+    while let Some(chunk) = stream.next().await {
+        for choice in chunk.expect("chunk").choices {
+            text.push_str(choice.delta.content.as_deref().unwrap_or_default());
+        }
+    }
+    assert_eq!(text, "over h2");
+}
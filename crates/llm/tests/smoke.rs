@@ -23,9 +23,13 @@ fn llm_smoke_request_serde_roundtrip() {
         }],
         max_tokens: Some(32),
         temperature: Some(0.7),
+        top_p: None,
+        frequency_penalty: None,
+        presence_penalty: None,
         tools: None,
         tool_choice: None,
         stream: Some(true),
+        stream_options: None,
     };
 
     let json = serde_json::to_string(&req).expect("serialize request");
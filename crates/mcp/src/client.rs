@@ -0,0 +1,550 @@
+//! Minimal MCP client speaking newline-delimited JSON-RPC 2.0 over a child
+//! process's stdin/stdout.
+use crate::{McpError, McpServerConfig, ToolResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Mutex};
+use tracing::{debug, warn};
+
+/// Protocol revision sent in `initialize`.
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type PendingMap = HashMap<u64, oneshot::Sender<Result<Value, McpError>>>;
+
+/// `serverInfo` from the `initialize` result.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+}
+
+/// Result of the `initialize` handshake.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeResult {
+    pub protocol_version: String,
+    #[serde(default)]
+    pub capabilities: Value,
+    #[serde(default)]
+    pub server_info: ServerInfo,
+}
+
+/// A tool as advertised by `tools/list`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub input_schema: Option<Value>,
+}
+
+/// A resource as advertised by `resources/list`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpResource {
+    pub uri: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+}
+
+/// One content item returned by `resources/read`. Text resources carry
+/// `text`; binary ones carry base64 in `blob`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub blob: Option<String>,
+}
+
+/// A prompt template as advertised by `prompts/list`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpPrompt {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Extract the `key` array and `nextCursor` from one page of a `*/list` result.
+fn parse_page<T: DeserializeOwned>(
+    method: &str,
+    key: &str,
+    mut result: Value,
+) -> Result<(Vec<T>, Option<String>), McpError> {
+    let items = match result.get_mut(key) {
+        Some(items) => serde_json::from_value(items.take())
+            .map_err(|e| McpError::Protocol(format!("invalid {} result: {}", method, e)))?,
+        None => Vec::new(),
+    };
+    let cursor = result
+        .get("nextCursor")
+        .and_then(Value::as_str)
+        .filter(|cursor| !cursor.is_empty())
+        .map(str::to_string);
+    Ok((items, cursor))
+}
+
+/// Parse an `initialize` result, rejecting payloads without a protocol version.
+pub fn parse_initialize_result(result: Value) -> Result<InitializeResult, McpError> {
+    serde_json::from_value(result)
+        .map_err(|e| McpError::Protocol(format!("invalid initialize result: {}", e)))
+}
+
+/// Flatten a `tools/call` result into a [`ToolResult`]. Text content blocks
+/// are joined with newlines; other blocks are rendered as JSON.
+pub fn parse_call_tool_result(result: Value) -> ToolResult {
+    let is_error = result
+        .get("isError")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let output = result
+        .get("content")
+        .and_then(Value::as_array)
+        .map(|blocks| {
+            blocks
+                .iter()
+                .map(|block| match block.get("text").and_then(Value::as_str) {
+                    Some(text) if block.get("type").and_then(Value::as_str) == Some("text") => {
+                        text.to_string()
+                    }
+                    _ => block.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+
+    ToolResult {
+        success: !is_error,
+        error: is_error.then(|| output.clone()),
+        output,
+    }
+}
+
+/// Route one line of server output to the request waiting for it. Lines that
+/// are not JSON-RPC responses (notifications, stray logging) are ignored.
+fn dispatch_line(line: &str, pending: &mut PendingMap) {
+    let Ok(message) = serde_json::from_str::<Value>(line) else {
+        debug!("ignoring non-JSON MCP output: {}", line);
+        return;
+    };
+    let Some(id) = message.get("id").and_then(Value::as_u64) else {
+        debug!("ignoring MCP message without numeric id: {}", line);
+        return;
+    };
+    let Some(sender) = pending.remove(&id) else {
+        return;
+    };
+    let outcome = match message.get("error") {
+        Some(error) => Err(McpError::Rpc {
+            code: error.get("code").and_then(Value::as_i64).unwrap_or(0),
+            message: error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+                .to_string(),
+        }),
+        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+    };
+    let _ = sender.send(outcome);
+}
+
+/// Connection to one spawned MCP server.
+pub struct McpClient {
+    name: String,
+    child: Mutex<Child>,
+    stdin: Mutex<ChildStdin>,
+    pending: Arc<Mutex<PendingMap>>,
+    next_id: AtomicU64,
+    /// Cleared by the reader task once the server closes its stdout.
+    alive: Arc<AtomicBool>,
+    server_info: ServerInfo,
+    /// How long any request waits for its response.
+    timeout: Duration,
+}
+
+impl McpClient {
+    /// Spawn the server process and complete the `initialize` handshake.
+    pub async fn connect(name: &str, config: &McpServerConfig) -> Result<Self, McpError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .envs(&config.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| McpError::Other(format!("failed to spawn '{}': {}", config.command, e)))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| McpError::Other("child stdin unavailable".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| McpError::Other("child stdout unavailable".to_string()))?;
+
+        if let Some(stderr) = child.stderr.take() {
+            let server = name.to_string();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    debug!("[mcp:{}] {}", server, line);
+                }
+            });
+        }
+
+        let pending: Arc<Mutex<PendingMap>> = Arc::new(Mutex::new(HashMap::new()));
+        let reader_pending = pending.clone();
+        let alive = Arc::new(AtomicBool::new(true));
+        let reader_alive = alive.clone();
+        let server = name.to_string();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                dispatch_line(&line, &mut *reader_pending.lock().await);
+            }
+            // Server closed stdout: fail everything still waiting.
+            warn!("MCP server '{}' closed its output stream", server);
+            reader_alive.store(false, Ordering::SeqCst);
+            for (_, sender) in reader_pending.lock().await.drain() {
+                let _ = sender.send(Err(McpError::Other(format!(
+                    "MCP server '{}' exited",
+                    server
+                ))));
+            }
+        });
+
+        let mut client = Self {
+            name: name.to_string(),
+            child: Mutex::new(child),
+            stdin: Mutex::new(stdin),
+            pending,
+            next_id: AtomicU64::new(1),
+            alive,
+            server_info: ServerInfo::default(),
+            timeout: config.timeout(),
+        };
+
+        let init = client
+            .request_with_timeout(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": "gearclaw",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }),
+                INITIALIZE_TIMEOUT,
+            )
+            .await
+            .map_err(|e| match e {
+                McpError::Timeout(_) => {
+                    McpError::Timeout(format!("MCP server '{}' initialize timed out", name))
+                }
+                e => e,
+            })?;
+        let init = parse_initialize_result(init)?;
+        client
+            .notify("notifications/initialized", json!({}))
+            .await?;
+
+        debug!(
+            "MCP server '{}' initialized: {} {} (protocol {})",
+            name, init.server_info.name, init.server_info.version, init.protocol_version
+        );
+        client.server_info = init.server_info;
+        Ok(client)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn server_info(&self) -> &ServerInfo {
+        &self.server_info
+    }
+
+    async fn write_message(&self, message: Value) -> Result<(), McpError> {
+        let mut line = message.to_string();
+        line.push('\n');
+        let mut stdin = self.stdin.lock().await;
+        stdin
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| McpError::Other(format!("write to MCP server failed: {}", e)))?;
+        stdin
+            .flush()
+            .await
+            .map_err(|e| McpError::Other(format!("flush to MCP server failed: {}", e)))
+    }
+
+    /// Send a JSON-RPC request and wait for its response, up to the
+    /// server's configured timeout.
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, McpError> {
+        self.request_with_timeout(method, params, self.timeout)
+            .await
+    }
+
+    async fn request_with_timeout(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, McpError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(id, tx);
+
+        let sent = self
+            .write_message(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": method,
+                "params": params,
+            }))
+            .await;
+        if let Err(e) = sent {
+            self.pending.lock().await.remove(&id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(response) => response.map_err(|_| {
+                McpError::Other(format!("MCP server '{}' dropped request {}", self.name, id))
+            })?,
+            Err(_) => {
+                // A late response is then ignored by the reader
+                self.pending.lock().await.remove(&id);
+                Err(McpError::Timeout(format!(
+                    "{} timed out after {:?}",
+                    method, timeout
+                )))
+            }
+        }
+    }
+
+    /// Requests still waiting for a response.
+    pub async fn in_flight(&self) -> usize {
+        self.pending.lock().await.len()
+    }
+
+    /// Send a JSON-RPC notification (no response expected).
+    pub async fn notify(&self, method: &str, params: Value) -> Result<(), McpError> {
+        self.write_message(json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        }))
+        .await
+    }
+
+    /// Every item of a paginated `*/list` method, following cursors.
+    async fn list_all<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        key: &str,
+    ) -> Result<Vec<T>, McpError> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let (page, next) = parse_page(method, key, self.request(method, params).await?)?;
+            items.extend(page);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => return Ok(items),
+            }
+        }
+    }
+
+    /// All tools advertised by the server.
+    pub async fn list_tools(&self) -> Result<Vec<McpTool>, McpError> {
+        self.list_all("tools/list", "tools").await
+    }
+
+    /// All resources advertised by the server.
+    pub async fn list_resources(&self) -> Result<Vec<McpResource>, McpError> {
+        self.list_all("resources/list", "resources").await
+    }
+
+    /// All prompt templates advertised by the server.
+    pub async fn list_prompts(&self) -> Result<Vec<McpPrompt>, McpError> {
+        self.list_all("prompts/list", "prompts").await
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>, McpError> {
+        let result = self
+            .request("resources/read", json!({ "uri": uri }))
+            .await?;
+        parse_page("resources/read", "contents", result).map(|(contents, _)| contents)
+    }
+
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<ToolResult, McpError> {
+        let result = self
+            .request(
+                "tools/call",
+                json!({ "name": name, "arguments": arguments }),
+            )
+            .await?;
+        Ok(parse_call_tool_result(result))
+    }
+
+    /// Whether the server is still reachable. Turns false as soon as the
+    /// server closes its output stream, which is how a crash shows up.
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    /// Whether the server process has exited.
+    pub async fn has_exited(&self) -> bool {
+        matches!(self.child.lock().await.try_wait(), Ok(Some(_)) | Err(_))
+    }
+
+    /// Kill the server process. Pending requests fail once its output closes.
+    pub async fn kill(&self) {
+        if let Err(e) = self.child.lock().await.start_kill() {
+            debug!("failed to kill MCP server '{}': {}", self.name, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_initialize_result() {
+        let init = parse_initialize_result(json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "mock", "version": "1.0" }
+        }))
+        .expect("parse");
+        assert_eq!(init.protocol_version, "2024-11-05");
+        assert_eq!(init.server_info.name, "mock");
+        assert!(init.capabilities.get("tools").is_some());
+    }
+
+    #[test]
+    fn rejects_initialize_result_without_protocol_version() {
+        assert!(matches!(
+            parse_initialize_result(json!({ "serverInfo": { "name": "mock" } })),
+            Err(McpError::Protocol(_))
+        ));
+    }
+
+    #[test]
+    fn parses_list_pages_with_cursor() {
+        let (resources, cursor) = parse_page::<McpResource>(
+            "resources/list",
+            "resources",
+            json!({
+                "resources": [
+                    { "uri": "file:///a.txt", "name": "a.txt", "mimeType": "text/plain" }
+                ],
+                "nextCursor": "page-2"
+            }),
+        )
+        .expect("parse");
+        assert_eq!(resources[0].mime_type.as_deref(), Some("text/plain"));
+        assert_eq!(cursor.as_deref(), Some("page-2"));
+
+        let (prompts, cursor) =
+            parse_page::<McpPrompt>("prompts/list", "prompts", json!({ "prompts": [] }))
+                .expect("parse");
+        assert!(prompts.is_empty());
+        assert!(cursor.is_none());
+
+        assert!(matches!(
+            parse_page::<McpResource>("resources/list", "resources", json!({ "resources": [{}] })),
+            Err(McpError::Protocol(_))
+        ));
+    }
+
+    #[test]
+    fn flattens_tool_call_content() {
+        let result = parse_call_tool_result(json!({
+            "content": [
+                { "type": "text", "text": "line one" },
+                { "type": "text", "text": "line two" }
+            ]
+        }));
+        assert!(result.success);
+        assert_eq!(result.output, "line one\nline two");
+
+        let failed = parse_call_tool_result(json!({
+            "content": [{ "type": "text", "text": "boom" }],
+            "isError": true
+        }));
+        assert!(!failed.success);
+        assert_eq!(failed.error.as_deref(), Some("boom"));
+    }
+
+    #[tokio::test]
+    async fn dispatches_responses_and_errors_by_id() {
+        let mut pending = PendingMap::new();
+        let (ok_tx, ok_rx) = oneshot::channel();
+        let (err_tx, err_rx) = oneshot::channel();
+        pending.insert(1, ok_tx);
+        pending.insert(2, err_tx);
+
+        dispatch_line("not json", &mut pending);
+        dispatch_line(
+            r#"{"jsonrpc":"2.0","method":"notifications/progress"}"#,
+            &mut pending,
+        );
+        dispatch_line(
+            r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32601,"message":"nope"}}"#,
+            &mut pending,
+        );
+        dispatch_line(
+            r#"{"jsonrpc":"2.0","id":1,"result":{"ok":true}}"#,
+            &mut pending,
+        );
+
+        assert_eq!(ok_rx.await.unwrap().unwrap(), json!({ "ok": true }));
+        assert!(matches!(
+            err_rx.await.unwrap(),
+            Err(McpError::Rpc { code: -32601, .. })
+        ));
+        assert!(pending.is_empty());
+    }
+}
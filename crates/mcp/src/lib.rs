@@ -1,15 +1,32 @@
+pub mod client;
+
+use client::{McpClient, McpPrompt, McpResource, McpTool};
+pub use client::{PromptArgument, ResourceContents};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tracing::warn;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum McpCapability {
     Enabled,
     Disabled,
 }
 
+/// MCP servers are only spawned in builds with the `stdio` feature.
+#[cfg(feature = "stdio")]
+pub const BUILD_MCP_CAPABILITY: McpCapability = McpCapability::Enabled;
+#[cfg(not(feature = "stdio"))]
 pub const BUILD_MCP_CAPABILITY: McpCapability = McpCapability::Disabled;
 
+/// Separator between the server key and the tool name in aggregated tool
+/// names, e.g. `filesystem__read`.
+pub const TOOL_NAME_SEPARATOR: &str = "__";
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct McpConfig {
     #[serde(default)]
@@ -23,6 +40,20 @@ pub struct McpServerConfig {
     pub args: Vec<String>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Seconds to wait for the response to any request (`tools/list`,
+    /// `tools/call`, ...) before giving up.
+    #[serde(default = "McpServerConfig::default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl McpServerConfig {
+    fn default_timeout_secs() -> u64 {
+        30
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,61 +76,582 @@ pub struct ToolResult {
 pub enum McpError {
     #[error("tool not found: {0}")]
     ToolNotFound(String),
+    #[error("resource not found: {0}")]
+    ResourceNotFound(String),
+    #[error("protocol error: {0}")]
+    Protocol(String),
+    #[error("rpc error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    /// No response within the server's `timeout_secs`
+    #[error("{0}")]
+    Timeout(String),
     #[error("{0}")]
     Other(String),
 }
 
+/// A read-only resource offered by one of the configured servers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSpec {
+    pub server: String,
+    pub uri: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+}
+
+impl ResourceSpec {
+    fn from_mcp(server: &str, resource: McpResource) -> Self {
+        Self {
+            server: server.to_string(),
+            uri: resource.uri,
+            name: resource.name,
+            description: resource.description,
+            mime_type: resource.mime_type,
+        }
+    }
+}
+
+/// A prompt template offered by one of the configured servers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptSpec {
+    pub server: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgument>,
+}
+
+impl PromptSpec {
+    fn from_mcp(server: &str, prompt: McpPrompt) -> Self {
+        Self {
+            server: server.to_string(),
+            name: prompt.name,
+            description: prompt.description,
+            arguments: prompt.arguments,
+        }
+    }
+}
+
+impl ToolSpec {
+    fn from_mcp(server: &str, tool: McpTool) -> Self {
+        let requires_args = tool
+            .input_schema
+            .as_ref()
+            .and_then(|schema| schema.get("required"))
+            .and_then(|required| required.as_array())
+            .is_some_and(|required| !required.is_empty());
+        Self {
+            name: format!("{}{}{}", server, TOOL_NAME_SEPARATOR, tool.name),
+            description: tool.description.unwrap_or_default(),
+            requires_args,
+            parameters: tool.input_schema,
+        }
+    }
+}
+
+/// Health of one configured server as seen by the supervisor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerHealth {
+    /// Connected and its process is still running.
+    Healthy,
+    /// Not running; will be respawned on next use once its backoff elapses.
+    Unhealthy,
+    /// Exceeded the restart cap and will not be respawned.
+    Failed,
+}
+
+/// Snapshot of a server's supervision state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerStatus {
+    pub health: ServerHealth,
+    /// Respawn attempts made since the initial connection.
+    pub restarts: u32,
+    pub last_error: Option<String>,
+}
+
+/// How crashed servers are respawned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Respawn attempts allowed before a server is marked failed.
+    pub max_restarts: u32,
+    /// Wait after the first respawn attempt; doubles after each attempt.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// A server that stayed up this long before crashing starts over with
+    /// a fresh restart budget.
+    pub stable_after: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            stable_after: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    fn backoff(&self, restarts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(restarts.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+struct ServerSlot {
+    client: Option<Arc<McpClient>>,
+    restarts: u32,
+    last_error: Option<String>,
+    /// Earliest time the next respawn may be attempted.
+    retry_at: Option<Instant>,
+    /// Set after a call timed out; the server is pinged before its next use.
+    needs_check: bool,
+    /// When the current client connected.
+    connected_at: Option<Instant>,
+}
+
+impl ServerSlot {
+    fn new() -> Self {
+        Self {
+            client: None,
+            restarts: 0,
+            last_error: None,
+            retry_at: None,
+            needs_check: false,
+            connected_at: None,
+        }
+    }
+
+    fn connected(&mut self, client: Arc<McpClient>) {
+        self.client = Some(client);
+        self.connected_at = Some(Instant::now());
+    }
+
+    fn live_client(&self) -> Option<Arc<McpClient>> {
+        self.client.as_ref().filter(|c| c.is_alive()).cloned()
+    }
+
+    fn status(&self, policy: &RestartPolicy) -> ServerStatus {
+        let health = match self.live_client() {
+            Some(_) if !self.needs_check => ServerHealth::Healthy,
+            Some(_) => ServerHealth::Unhealthy,
+            None if self.restarts >= policy.max_restarts => ServerHealth::Failed,
+            None => ServerHealth::Unhealthy,
+        };
+        ServerStatus {
+            health,
+            restarts: self.restarts,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
 pub struct McpManager {
-    #[allow(dead_code)]
     config: McpConfig,
+    capability: McpCapability,
+    restart_policy: RestartPolicy,
+    /// One slot per configured server, each locked independently so a slow
+    /// respawn does not block calls to other servers.
+    servers: HashMap<String, Mutex<ServerSlot>>,
+    /// Resource URI -> server name, from the most recent `list_resources`.
+    resource_index: Mutex<HashMap<String, String>>,
 }
 
 impl McpManager {
     pub fn new(config: McpConfig) -> Self {
-        Self { config }
+        Self::with_capability(config, BUILD_MCP_CAPABILITY)
+    }
+
+    /// Construct with an explicit capability, independent of build features.
+    pub fn with_capability(config: McpConfig, capability: McpCapability) -> Self {
+        let servers = config
+            .servers
+            .keys()
+            .map(|name| (name.clone(), Mutex::new(ServerSlot::new())))
+            .collect();
+        Self {
+            config,
+            capability,
+            restart_policy: RestartPolicy::default(),
+            servers,
+            resource_index: Mutex::new(HashMap::new()),
+        }
     }
+
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = policy;
+        self
+    }
+
     pub fn capability(&self) -> McpCapability {
-        BUILD_MCP_CAPABILITY
+        self.capability
     }
 
     pub fn is_enabled(&self) -> bool {
         matches!(self.capability(), McpCapability::Enabled)
     }
 
+    /// Spawn and initialize every configured server. Servers that fail to
+    /// start are logged and retried lazily on first use.
     pub async fn init_clients(&self) -> Result<(), McpError> {
-        if !self.config.servers.is_empty() {
-            warn!(
-                "MCP servers configured but MCP support is disabled. {} server(s) will not be initialized.",
-                self.config.servers.len()
-            );
+        if !self.is_enabled() {
+            if !self.config.servers.is_empty() {
+                warn!(
+                    "MCP servers configured but MCP support is disabled. {} server(s) will not be initialized.",
+                    self.config.servers.len()
+                );
+            }
+            return Ok(());
+        }
+
+        for (name, slot) in &self.servers {
+            let mut slot = slot.lock().await;
+            match McpClient::connect(name, &self.config.servers[name]).await {
+                Ok(client) => {
+                    info!(
+                        "MCP server '{}' connected ({})",
+                        name,
+                        client.server_info().name
+                    );
+                    slot.connected(Arc::new(client));
+                }
+                Err(e) => {
+                    warn!("MCP server '{}' failed to start: {}", name, e);
+                    slot.last_error = Some(e.to_string());
+                }
+            }
         }
         Ok(())
     }
 
+    /// Supervision state of every configured server.
+    pub async fn server_status(&self) -> HashMap<String, ServerStatus> {
+        let mut statuses = HashMap::new();
+        for (name, slot) in &self.servers {
+            statuses.insert(name.clone(), slot.lock().await.status(&self.restart_policy));
+        }
+        statuses
+    }
+
+    /// Live client for `server`, respawning it if it has exited and the
+    /// restart policy allows another attempt.
+    async fn client(&self, server: &str) -> Result<Arc<McpClient>, McpError> {
+        let slot = self
+            .servers
+            .get(server)
+            .ok_or_else(|| McpError::Other(format!("unknown MCP server '{}'", server)))?;
+        let mut slot = slot.lock().await;
+        if let Some(client) = slot.live_client() {
+            if !slot.needs_check {
+                return Ok(client);
+            }
+            slot.needs_check = false;
+            match client.request("ping", json!({})).await {
+                // Any response, even "method not found", shows it is responsive.
+                Ok(_) | Err(McpError::Rpc { .. }) => {
+                    debug!("MCP server '{}' passed its health check", server);
+                    return Ok(client);
+                }
+                Err(McpError::Timeout(_)) => {
+                    slot.last_error = Some("health check timed out".to_string())
+                }
+                Err(e) => slot.last_error = Some(format!("health check failed: {}", e)),
+            }
+        }
+
+        if let Some(dead) = slot.client.take() {
+            warn!("MCP server '{}' is unhealthy", server);
+            dead.kill().await;
+            if slot.last_error.is_none() {
+                slot.last_error = Some("server process exited".to_string());
+            }
+            // Occasional crashes of a long-running server should not add
+            // up to a permanent failure
+            if slot
+                .connected_at
+                .is_some_and(|at| at.elapsed() >= self.restart_policy.stable_after)
+            {
+                slot.restarts = 0;
+                slot.retry_at = None;
+            }
+        }
+
+        let unavailable = |slot: &ServerSlot, reason: &str| {
+            McpError::Other(format!(
+                "MCP server '{}' {} (last error: {})",
+                server,
+                reason,
+                slot.last_error.as_deref().unwrap_or("unknown")
+            ))
+        };
+        if slot.restarts >= self.restart_policy.max_restarts {
+            return Err(unavailable(&slot, "exceeded its restart limit"));
+        }
+        if slot.retry_at.is_some_and(|at| Instant::now() < at) {
+            return Err(unavailable(&slot, "is waiting to restart"));
+        }
+
+        slot.restarts += 1;
+        slot.retry_at = Some(Instant::now() + self.restart_policy.backoff(slot.restarts));
+        info!(
+            "Restarting MCP server '{}' (attempt {}/{})",
+            server, slot.restarts, self.restart_policy.max_restarts
+        );
+        match McpClient::connect(server, &self.config.servers[server]).await {
+            Ok(client) => {
+                let client = Arc::new(client);
+                slot.connected(client.clone());
+                slot.last_error = None;
+                Ok(client)
+            }
+            Err(e) => {
+                warn!("MCP server '{}' failed to restart: {}", server, e);
+                slot.last_error = Some(e.to_string());
+                Err(unavailable(&slot, "failed to restart"))
+            }
+        }
+    }
+
+    /// Live clients for every configured server in name order, skipping
+    /// (and logging) servers that are unavailable.
+    async fn live_clients(&self, purpose: &str) -> Vec<(String, Arc<McpClient>)> {
+        if !self.is_enabled() {
+            return Vec::new();
+        }
+        let mut names: Vec<&String> = self.servers.keys().collect();
+        names.sort();
+
+        let mut clients = Vec::new();
+        for name in names {
+            match self.client(name).await {
+                Ok(client) => clients.push((name.clone(), client)),
+                Err(e) => warn!("Skipping {} from MCP server '{}': {}", purpose, name, e),
+            }
+        }
+        clients
+    }
+
+    /// Tools from every connected server, named `server__tool` so that
+    /// servers exposing the same tool name do not collide.
     pub async fn list_tools(&self) -> Vec<ToolSpec> {
-        vec![]
+        let mut specs = Vec::new();
+        for (name, client) in self.live_clients("tools").await {
+            match client.list_tools().await {
+                Ok(tools) => specs.extend(
+                    tools
+                        .into_iter()
+                        .map(|tool| ToolSpec::from_mcp(&name, tool)),
+                ),
+                Err(e) => {
+                    warn!("MCP server '{}' tools/list failed: {}", name, e);
+                    self.recheck_after_timeout(&name, &client, &e).await;
+                }
+            }
+        }
+        specs
+    }
+
+    /// Resources from every connected server. Also refreshes the URI index
+    /// used by [`McpManager::read_resource`].
+    pub async fn list_resources(&self) -> Vec<ResourceSpec> {
+        let mut specs = Vec::new();
+        for (name, client) in self.live_clients("resources").await {
+            match client.list_resources().await {
+                Ok(resources) => specs.extend(
+                    resources
+                        .into_iter()
+                        .map(|resource| ResourceSpec::from_mcp(&name, resource)),
+                ),
+                Err(e) => {
+                    warn!("MCP server '{}' resources/list failed: {}", name, e);
+                    self.recheck_after_timeout(&name, &client, &e).await;
+                }
+            }
+        }
+        *self.resource_index.lock().await = specs
+            .iter()
+            .map(|spec| (spec.uri.clone(), spec.server.clone()))
+            .collect();
+        specs
+    }
+
+    /// Read a resource by URI from whichever server advertised it.
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>, McpError> {
+        let mut server = self.resource_index.lock().await.get(uri).cloned();
+        if server.is_none() {
+            self.list_resources().await;
+            server = self.resource_index.lock().await.get(uri).cloned();
+        }
+        let server = server.ok_or_else(|| McpError::ResourceNotFound(uri.to_string()))?;
+        self.client(&server).await?.read_resource(uri).await
+    }
+
+    /// Prompt templates from every connected server.
+    pub async fn list_prompts(&self) -> Vec<PromptSpec> {
+        let mut specs = Vec::new();
+        for (name, client) in self.live_clients("prompts").await {
+            match client.list_prompts().await {
+                Ok(prompts) => specs.extend(
+                    prompts
+                        .into_iter()
+                        .map(|prompt| PromptSpec::from_mcp(&name, prompt)),
+                ),
+                Err(e) => {
+                    warn!("MCP server '{}' prompts/list failed: {}", name, e);
+                    self.recheck_after_timeout(&name, &client, &e).await;
+                }
+            }
+        }
+        specs
+    }
+
+    /// Split a namespaced tool name into `(server, tool)`. Server keys are
+    /// matched against the configuration, longest first, so keys that
+    /// themselves contain the separator still resolve.
+    pub fn resolve(&self, name: &str) -> Result<(String, String), McpError> {
+        self.config
+            .servers
+            .keys()
+            .filter_map(|server| {
+                name.strip_prefix(server.as_str())
+                    .and_then(|rest| rest.strip_prefix(TOOL_NAME_SEPARATOR))
+                    .filter(|tool| !tool.is_empty())
+                    .map(|tool| (server.clone(), tool.to_string()))
+            })
+            .max_by_key(|(server, _)| server.len())
+            .ok_or_else(|| McpError::ToolNotFound(name.to_string()))
     }
 
     pub async fn call_tool(
         &self,
         name: &str,
-        _args: serde_json::Value,
+        args: serde_json::Value,
     ) -> Result<ToolResult, McpError> {
-        Err(McpError::ToolNotFound(format!(
-            "MCP tool '{}' not available (MCP support disabled)",
-            name
-        )))
+        if !self.is_enabled() {
+            return Err(McpError::ToolNotFound(format!(
+                "MCP tool '{}' not available (MCP support disabled)",
+                name
+            )));
+        }
+
+        let (server, tool) = self.resolve(name)?;
+        let client = self.client(&server).await?;
+        match client.call_tool(&tool, args).await {
+            Err(e @ McpError::Timeout(_)) => {
+                warn!("MCP tool '{}' {}", name, e);
+                self.recheck_after_timeout(&server, &client, &e).await;
+                Err(McpError::Timeout("tool call timed out".to_string()))
+            }
+            result => result,
+        }
+    }
+
+    /// After a request to `client` timed out, ping the server before its
+    /// next use (unless it has been replaced meanwhile).
+    async fn recheck_after_timeout(&self, server: &str, client: &Arc<McpClient>, error: &McpError) {
+        if !matches!(error, McpError::Timeout(_)) {
+            return;
+        }
+        let mut slot = self.servers[server].lock().await;
+        if slot
+            .client
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, client))
+        {
+            warn!("MCP server '{}' will be re-checked", server);
+            slot.needs_check = true;
+            slot.last_error = Some(error.to_string());
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{McpCapability, McpConfig, McpManager};
+    use super::{McpCapability, McpConfig, McpError, McpManager, McpServerConfig};
+    use std::collections::HashMap;
+
+    fn manager_with_servers(names: &[&str]) -> McpManager {
+        let servers = names
+            .iter()
+            .map(|name| {
+                (
+                    name.to_string(),
+                    McpServerConfig {
+                        command: "unused".to_string(),
+                        args: vec![],
+                        env: HashMap::new(),
+                        timeout_secs: 30,
+                    },
+                )
+            })
+            .collect();
+        McpManager::with_capability(McpConfig { servers }, McpCapability::Enabled)
+    }
 
+    #[test]
+    fn resolve_splits_server_and_tool() {
+        let manager = manager_with_servers(&["filesystem", "git"]);
+        assert_eq!(
+            manager.resolve("filesystem__read").unwrap(),
+            ("filesystem".to_string(), "read".to_string())
+        );
+        assert_eq!(
+            manager.resolve("git__read").unwrap(),
+            ("git".to_string(), "read".to_string())
+        );
+        // Tool names may contain the separator themselves.
+        assert_eq!(
+            manager.resolve("git__log__oneline").unwrap(),
+            ("git".to_string(), "log__oneline".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_prefers_longest_server_key() {
+        let manager = manager_with_servers(&["fs", "fs__remote"]);
+        assert_eq!(
+            manager.resolve("fs__remote__read").unwrap(),
+            ("fs__remote".to_string(), "read".to_string())
+        );
+        assert_eq!(
+            manager.resolve("fs__read").unwrap(),
+            ("fs".to_string(), "read".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_rejects_unknown_or_unprefixed_names() {
+        let manager = manager_with_servers(&["filesystem"]);
+        for name in ["read", "other__read", "filesystem__", "filesystemread"] {
+            assert!(
+                matches!(manager.resolve(name), Err(McpError::ToolNotFound(_))),
+                "{} should not resolve",
+                name
+            );
+        }
+    }
+
+    #[cfg(not(feature = "stdio"))]
     #[test]
     fn build_capability_is_explicitly_disabled() {
         let manager = McpManager::new(McpConfig::default());
         assert_eq!(manager.capability(), McpCapability::Disabled);
         assert!(!manager.is_enabled());
     }
+
+    #[cfg(feature = "stdio")]
+    #[test]
+    fn build_capability_is_enabled_with_stdio() {
+        let manager = McpManager::new(McpConfig::default());
+        assert_eq!(manager.capability(), McpCapability::Enabled);
+        assert!(manager.is_enabled());
+    }
 }
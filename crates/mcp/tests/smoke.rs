@@ -16,9 +16,16 @@ fn mcp_smoke_manager_new_with_server_config() {
             command: "node".to_string(),
             args: vec!["server.js".to_string()],
             env: HashMap::new(),
+            timeout_secs: 30,
         },
     );
 
     let config = McpConfig { servers };
     let _manager = McpManager::new(config);
 }
+
+#[test]
+fn mcp_smoke_server_timeout_defaults_to_30s() {
+    let config: McpServerConfig = serde_json::from_str(r#"{"command": "node"}"#).unwrap();
+    assert_eq!(config.timeout_secs, 30);
+}
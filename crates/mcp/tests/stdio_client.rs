@@ -0,0 +1,313 @@
+use gearclaw_mcp::client::McpClient;
+use gearclaw_mcp::{
+    McpCapability, McpConfig, McpError, McpManager, McpServerConfig, RestartPolicy, ServerHealth,
+};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn mock_server(name: &str) -> McpServerConfig {
+    McpServerConfig {
+        command: env!("CARGO_BIN_EXE_mcp-mock-server").to_string(),
+        args: vec![],
+        env: HashMap::from([("MOCK_MCP_NAME".to_string(), name.to_string())]),
+        timeout_secs: 30,
+    }
+}
+
+#[tokio::test]
+async fn client_handshakes_lists_and_calls_tools() {
+    let client = McpClient::connect("mock", &mock_server("alpha"))
+        .await
+        .expect("connect");
+    assert_eq!(client.server_info().name, "mock-alpha");
+
+    let tools = client.list_tools().await.expect("tools/list");
+    let names: Vec<_> = tools.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["echo", "fail", "pid", "sleep", "crash"]);
+
+    let result = client
+        .call_tool("echo", json!({ "text": "hi" }))
+        .await
+        .expect("tools/call");
+    assert!(result.success);
+    assert_eq!(result.output, "alpha: hi");
+
+    let failed = client
+        .call_tool("fail", json!({}))
+        .await
+        .expect("tools/call");
+    assert!(!failed.success);
+
+    assert!(client.call_tool("missing", json!({})).await.is_err());
+}
+
+#[tokio::test]
+async fn connect_reports_missing_binary() {
+    let config = McpServerConfig {
+        command: "/nonexistent/mcp-server".to_string(),
+        args: vec![],
+        env: HashMap::new(),
+        timeout_secs: 30,
+    };
+    assert!(McpClient::connect("missing", &config).await.is_err());
+}
+
+#[tokio::test]
+async fn enabled_manager_exposes_server_tools() {
+    let config = McpConfig {
+        servers: HashMap::from([("alpha".to_string(), mock_server("alpha"))]),
+    };
+    let manager = McpManager::with_capability(config, McpCapability::Enabled);
+    manager.init_clients().await.expect("init");
+
+    let tools = manager.list_tools().await;
+    let echo = tools
+        .iter()
+        .find(|t| t.name == "alpha__echo")
+        .expect("echo tool");
+    assert!(echo.requires_args);
+    assert!(echo.parameters.is_some());
+
+    let result = manager
+        .call_tool("alpha__echo", json!({ "text": "via manager" }))
+        .await
+        .expect("call");
+    assert_eq!(result.output, "alpha: via manager");
+}
+
+#[tokio::test]
+async fn duplicate_tool_names_are_namespaced_per_server() {
+    let config = McpConfig {
+        servers: HashMap::from([
+            ("alpha".to_string(), mock_server("alpha")),
+            ("beta".to_string(), mock_server("beta")),
+        ]),
+    };
+    let manager = McpManager::with_capability(config, McpCapability::Enabled);
+    manager.init_clients().await.expect("init");
+
+    let names: Vec<String> = manager
+        .list_tools()
+        .await
+        .into_iter()
+        .map(|t| t.name)
+        .collect();
+    assert!(names.contains(&"alpha__echo".to_string()));
+    assert!(names.contains(&"beta__echo".to_string()));
+    let alpha_count = names.iter().filter(|n| n.starts_with("alpha__")).count();
+    assert_eq!(alpha_count, names.len() / 2);
+
+    for server in ["alpha", "beta"] {
+        let result = manager
+            .call_tool(&format!("{}__echo", server), json!({ "text": "hi" }))
+            .await
+            .expect("call");
+        assert_eq!(result.output, format!("{}: hi", server));
+    }
+
+    assert!(manager.call_tool("echo", json!({})).await.is_err());
+}
+
+fn single_server_manager(server: McpServerConfig, policy: RestartPolicy) -> McpManager {
+    let config = McpConfig {
+        servers: HashMap::from([("alpha".to_string(), server)]),
+    };
+    McpManager::with_capability(config, McpCapability::Enabled).with_restart_policy(policy)
+}
+
+async fn server_pid(manager: &McpManager) -> String {
+    manager
+        .call_tool("alpha__pid", json!({}))
+        .await
+        .expect("pid")
+        .output
+}
+
+#[tokio::test]
+async fn crashed_server_is_respawned_on_next_call() {
+    let manager = single_server_manager(mock_server("alpha"), RestartPolicy::default());
+    manager.init_clients().await.expect("init");
+    let first_pid = server_pid(&manager).await;
+
+    assert!(manager.call_tool("alpha__crash", json!({})).await.is_err());
+    let status = &manager.server_status().await["alpha"];
+    assert_eq!(status.health, ServerHealth::Unhealthy);
+
+    let second_pid = server_pid(&manager).await;
+    assert_ne!(first_pid, second_pid);
+    let status = &manager.server_status().await["alpha"];
+    assert_eq!(status.health, ServerHealth::Healthy);
+    assert_eq!(status.restarts, 1);
+}
+
+#[tokio::test]
+async fn restarts_stop_at_the_cap() {
+    let broken = McpServerConfig {
+        command: "/nonexistent/mcp-server".to_string(),
+        args: vec![],
+        env: HashMap::new(),
+        timeout_secs: 30,
+    };
+    let policy = RestartPolicy {
+        max_restarts: 2,
+        initial_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+        ..RestartPolicy::default()
+    };
+    let manager = single_server_manager(broken, policy);
+    manager.init_clients().await.expect("init");
+    assert_eq!(
+        manager.server_status().await["alpha"].health,
+        ServerHealth::Unhealthy
+    );
+
+    for _ in 0..3 {
+        assert!(manager.call_tool("alpha__echo", json!({})).await.is_err());
+    }
+    let status = &manager.server_status().await["alpha"];
+    assert_eq!(status.health, ServerHealth::Failed);
+    assert_eq!(status.restarts, 2);
+    assert!(status.last_error.is_some());
+}
+
+#[tokio::test]
+async fn restart_budget_resets_after_stable_uptime() {
+    let policy = RestartPolicy {
+        max_restarts: 1,
+        initial_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+        stable_after: Duration::from_millis(200),
+    };
+    let manager = single_server_manager(mock_server("alpha"), policy);
+    manager.init_clients().await.expect("init");
+
+    let _ = manager.call_tool("alpha__crash", json!({})).await;
+    server_pid(&manager).await;
+    assert_eq!(manager.server_status().await["alpha"].restarts, 1);
+
+    // Up long enough to count as recovered: the next crash is restarted
+    // instead of hitting the one-restart cap
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let _ = manager.call_tool("alpha__crash", json!({})).await;
+    server_pid(&manager).await;
+    let status = &manager.server_status().await["alpha"];
+    assert_eq!(status.health, ServerHealth::Healthy);
+    assert_eq!(status.restarts, 1);
+}
+
+#[tokio::test]
+async fn respawn_waits_for_backoff() {
+    let policy = RestartPolicy {
+        max_restarts: 5,
+        initial_backoff: Duration::from_secs(60),
+        max_backoff: Duration::from_secs(60),
+        ..RestartPolicy::default()
+    };
+    let manager = single_server_manager(mock_server("alpha"), policy);
+    manager.init_clients().await.expect("init");
+
+    // First crash respawns immediately; a second crash within the backoff
+    // window does not.
+    let _ = manager.call_tool("alpha__crash", json!({})).await;
+    server_pid(&manager).await;
+    let _ = manager.call_tool("alpha__crash", json!({})).await;
+    let err = manager
+        .call_tool("alpha__echo", json!({ "text": "hi" }))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("waiting to restart"), "{}", err);
+}
+
+#[tokio::test]
+async fn slow_tool_call_times_out_and_server_is_rechecked() {
+    let server = McpServerConfig {
+        timeout_secs: 1,
+        ..mock_server("alpha")
+    };
+    let manager = single_server_manager(server, RestartPolicy::default());
+    manager.init_clients().await.expect("init");
+    let first_pid = server_pid(&manager).await;
+
+    let err = manager
+        .call_tool("alpha__sleep", json!({ "ms": 3000 }))
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "tool call timed out");
+    assert_eq!(
+        manager.server_status().await["alpha"].health,
+        ServerHealth::Unhealthy
+    );
+
+    // The server is still stuck, so the health check fails and it is replaced.
+    let second_pid = server_pid(&manager).await;
+    assert_ne!(first_pid, second_pid);
+    assert_eq!(manager.server_status().await["alpha"].restarts, 1);
+}
+
+#[tokio::test]
+async fn hung_tools_list_times_out() {
+    let mut server = McpServerConfig {
+        timeout_secs: 1,
+        ..mock_server("alpha")
+    };
+    server
+        .env
+        .insert("MOCK_MCP_HANG".to_string(), "tools/list".to_string());
+
+    let client = McpClient::connect("mock", &server).await.expect("connect");
+    let err = client.list_tools().await.unwrap_err();
+    assert!(matches!(err, McpError::Timeout(_)), "{}", err);
+    assert_eq!(client.in_flight().await, 0);
+
+    let manager = single_server_manager(server, RestartPolicy::default());
+    manager.init_clients().await.expect("init");
+    let listed = tokio::time::timeout(Duration::from_secs(5), manager.list_tools())
+        .await
+        .expect("a hung server must not stall tools/list");
+    assert!(listed.is_empty());
+    assert_eq!(
+        manager.server_status().await["alpha"].health,
+        ServerHealth::Unhealthy
+    );
+}
+
+#[tokio::test]
+async fn resources_and_prompts_are_listed_and_read() {
+    let manager = single_server_manager(mock_server("alpha"), RestartPolicy::default());
+    manager.init_clients().await.expect("init");
+
+    let resources = manager.list_resources().await;
+    assert_eq!(resources.len(), 1);
+    let readme = &resources[0];
+    assert_eq!(readme.server, "alpha");
+    assert_eq!(readme.uri, "mock://alpha/readme");
+    assert_eq!(readme.mime_type.as_deref(), Some("text/plain"));
+
+    let contents = manager.read_resource(&readme.uri).await.expect("read");
+    assert_eq!(contents.len(), 1);
+    assert_eq!(contents[0].text.as_deref(), Some("readme of alpha"));
+
+    assert!(matches!(
+        manager.read_resource("mock://alpha/missing").await,
+        Err(McpError::ResourceNotFound(_))
+    ));
+
+    let prompts = manager.list_prompts().await;
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0].name, "summarize");
+    assert!(prompts[0].arguments[0].required);
+}
+
+#[tokio::test]
+async fn read_resource_refreshes_unknown_uris() {
+    let manager = single_server_manager(mock_server("alpha"), RestartPolicy::default());
+    manager.init_clients().await.expect("init");
+
+    // No prior list_resources call.
+    let contents = manager
+        .read_resource("mock://alpha/readme")
+        .await
+        .expect("read");
+    assert_eq!(contents[0].uri, "mock://alpha/readme");
+}
@@ -0,0 +1,148 @@
+//! Scripted MCP server for integration tests: newline-delimited JSON-RPC on
+//! stdin/stdout. `MOCK_MCP_NAME` labels tool output so tests can tell
+//! instances apart; `MOCK_MCP_HANG` names a method the server never
+//! answers.
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+fn main() {
+    let name = std::env::var("MOCK_MCP_NAME").unwrap_or_else(|_| "mock".to_string());
+    let hang = std::env::var("MOCK_MCP_HANG").unwrap_or_default();
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+
+    // Real servers sometimes log to stdout; clients must skip it.
+    writeln!(stdout, "mock server starting").ok();
+    stdout.flush().ok();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        let Ok(request) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let Some(id) = request.get("id").cloned() else {
+            // Notification, e.g. notifications/initialized.
+            continue;
+        };
+        let method = request["method"].as_str().unwrap_or_default();
+        let params = &request["params"];
+        if method == hang {
+            continue;
+        }
+
+        let response = match handle(&name, method, params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message }
+            }),
+        };
+        writeln!(stdout, "{}", response).ok();
+        stdout.flush().ok();
+    }
+}
+
+fn text(value: String) -> Value {
+    json!({ "content": [{ "type": "text", "text": value }] })
+}
+
+fn handle(name: &str, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+    match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": params["protocolVersion"],
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": format!("mock-{}", name), "version": "0.1.0" }
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({
+            "tools": [
+                {
+                    "name": "echo",
+                    "description": "Echo the given text",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": { "text": { "type": "string" } },
+                        "required": ["text"]
+                    }
+                },
+                {
+                    "name": "fail",
+                    "description": "Always reports a tool error",
+                    "inputSchema": { "type": "object", "properties": {} }
+                },
+                {
+                    "name": "pid",
+                    "description": "Report the server's process id",
+                    "inputSchema": { "type": "object", "properties": {} }
+                },
+                {
+                    "name": "sleep",
+                    "description": "Sleep before answering",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": { "ms": { "type": "integer" } }
+                    }
+                },
+                {
+                    "name": "crash",
+                    "description": "Exit without responding",
+                    "inputSchema": { "type": "object", "properties": {} }
+                }
+            ]
+        })),
+        "resources/list" => Ok(json!({
+            "resources": [{
+                "uri": format!("mock://{}/readme", name),
+                "name": "README",
+                "description": "Server readme",
+                "mimeType": "text/plain"
+            }]
+        })),
+        "resources/read" => {
+            let uri = params["uri"].as_str().unwrap_or_default();
+            if uri == format!("mock://{}/readme", name) {
+                Ok(json!({
+                    "contents": [{
+                        "uri": uri,
+                        "mimeType": "text/plain",
+                        "text": format!("readme of {}", name)
+                    }]
+                }))
+            } else {
+                Err((-32002, format!("resource not found: {}", uri)))
+            }
+        }
+        "prompts/list" => Ok(json!({
+            "prompts": [{
+                "name": "summarize",
+                "description": "Summarize a topic",
+                "arguments": [{ "name": "topic", "required": true }]
+            }]
+        })),
+        "tools/call" => {
+            let args = &params["arguments"];
+            match params["name"].as_str() {
+                Some("echo") => Ok(text(format!(
+                    "{}: {}",
+                    name,
+                    args["text"].as_str().unwrap_or_default()
+                ))),
+                Some("fail") => Ok(json!({
+                    "content": [{ "type": "text", "text": "tool failed" }],
+                    "isError": true
+                })),
+                Some("pid") => Ok(text(std::process::id().to_string())),
+                Some("sleep") => {
+                    let ms = args["ms"].as_u64().unwrap_or_default();
+                    std::thread::sleep(std::time::Duration::from_millis(ms));
+                    Ok(text(format!("slept {}ms", ms)))
+                }
+                Some("crash") => std::process::exit(1),
+                Some(other) => Err((-32602, format!("unknown tool {}", other))),
+                None => Err((-32602, "missing tool name".to_string())),
+            }
+        }
+        other => Err((-32601, format!("method not found: {}", other))),
+    }
+}
@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use gearclaw_llm::LLMClient;
+use gearclaw_llm::LlmBackend;
 use glob::glob;
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
@@ -11,19 +11,116 @@ use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use tracing::{info, warn};
 
+/// Source of chunks indexed from the workspace's markdown files
+pub const WORKSPACE_SOURCE: &str = "workspace";
+/// Source of notes saved with [`MemoryManager::add_note`]; `sync` only
+/// prunes workspace files, so notes survive it
+pub const NOTES_SOURCE: &str = "notes";
+
+/// `k` in reciprocal rank fusion: a result at rank `r` contributes
+/// `1 / (k + r)`, so lower ranks still count without dominating
+const RRF_K: f32 = 60.0;
+/// Full-text matches considered for fusion in hybrid search
+const KEYWORD_CANDIDATES: usize = 50;
+
+/// `meta` keys recording what produced the stored embeddings
+const META_EMBEDDING_MODEL: &str = "embedding_model";
+const META_EMBEDDING_DIMENSION: &str = "embedding_dimension";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryConfig {
     #[serde(default)]
     pub enabled: bool,
     pub db_path: PathBuf,
+    /// Fuse embedding similarity with an FTS5 keyword ranking, so exact
+    /// terms (error codes, identifiers) that embed poorly are still found
+    #[serde(default)]
+    pub hybrid_search: bool,
+    /// Skip a result whose embedding is more similar than this to one
+    /// already returned; `1.0` or more turns deduplication off
+    #[serde(default = "MemoryConfig::default_dedup_threshold")]
+    pub dedup_threshold: f32,
+    /// Where embeddings come from
+    #[serde(default)]
+    pub embedding: EmbeddingSource,
+}
+
+/// Source of the vectors stored and searched by [`MemoryManager`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingSource {
+    /// The LLM backend's embedding model
+    #[default]
+    Llm,
+    /// [`gearclaw_llm::local_embedding`]: no network, word overlap only
+    Local,
+}
+
+impl MemoryConfig {
+    pub const DEFAULT_DEDUP_THRESHOLD: f32 = 0.97;
+
+    fn default_dedup_threshold() -> f32 {
+        Self::DEFAULT_DEDUP_THRESHOLD
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct SearchResult {
     pub path: String,
     pub text: String,
+    /// Cosine similarity to the query, or the fused reciprocal-rank score
+    /// in hybrid search
     pub score: f32,
+    /// First and last line (1-based) of the chunk in its file; `None` for notes
     pub start_line: Option<usize>,
+    pub end_line: Option<usize>,
+}
+
+/// A search candidate: the chunk's id and embedding alongside its result
+struct Ranked {
+    id: String,
+    embedding: Vec<f32>,
+    result: SearchResult,
+}
+
+/// A paragraph of a markdown file, as indexed by [`MemoryManager::sync`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk<'a> {
+    pub text: &'a str,
+    /// 1-based line range of `text` within the file
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Split `content` into blank-line separated paragraphs with their line
+/// ranges. Whitespace-only paragraphs are dropped.
+pub fn split_chunks(content: &str) -> Vec<Chunk<'_>> {
+    let mut chunks = Vec::new();
+    let mut line = 1;
+    for piece in content.split("\n\n") {
+        let leading = piece.len() - piece.trim_start_matches('\n').len();
+        let start_line = line + leading;
+        let end_line = start_line + piece.trim_matches('\n').matches('\n').count();
+        if !piece.trim().is_empty() {
+            chunks.push(Chunk {
+                text: piece,
+                start_line,
+                end_line,
+            });
+        }
+        // The separator's second newline starts the next piece.
+        line += piece.matches('\n').count() + 2;
+    }
+    chunks
+}
+
+/// Progress snapshot emitted by [`MemoryManager::sync_with_progress`]
+/// after each file has been indexed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncProgress {
+    pub files_total: usize,
+    pub files_done: usize,
+    pub current_path: String,
 }
 
 #[derive(Debug, Error)]
@@ -36,6 +133,15 @@ pub enum MemoryError {
     Json(#[from] serde_json::Error),
     #[error("llm error: {0}")]
     Llm(String),
+    #[error(
+        "reindex required: memory index was built with {stored} but the current \
+         embeddings are {current}; delete {db_path} and sync again to rebuild it"
+    )]
+    EmbeddingMismatch {
+        stored: String,
+        current: String,
+        db_path: String,
+    },
     #[error("{0}")]
     Other(String),
 }
@@ -45,14 +151,14 @@ pub struct MemoryManager {
     config: MemoryConfig,
     conn: Arc<Mutex<Connection>>,
     workspace_path: PathBuf,
-    llm_client: Arc<LLMClient>,
+    llm_client: Arc<dyn LlmBackend>,
 }
 
 impl MemoryManager {
     pub fn new(
         config: MemoryConfig,
         workspace_path: PathBuf,
-        llm_client: Arc<LLMClient>,
+        llm_client: Arc<dyn LlmBackend>,
     ) -> Result<Self, MemoryError> {
         let db_path = &config.db_path;
         if let Some(parent) = db_path.parent() {
@@ -105,15 +211,71 @@ impl MemoryManager {
             [],
         )?;
 
+        // Keyword index over chunk text, kept in step with `chunks` by triggers
+        let has_fts: bool = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'chunks_fts'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count > 0)?;
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS chunks_fts USING fts5(text, chunk_id UNINDEXED);
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_insert AFTER INSERT ON chunks BEGIN
+                INSERT INTO chunks_fts (text, chunk_id) VALUES (new.text, new.id);
+            END;
+            CREATE TRIGGER IF NOT EXISTS chunks_fts_delete AFTER DELETE ON chunks BEGIN
+                DELETE FROM chunks_fts WHERE chunk_id = old.id;
+            END;",
+        )?;
+        if !has_fts {
+            // Databases from before the keyword index: backfill it once.
+            conn.execute(
+                "INSERT INTO chunks_fts (text, chunk_id) SELECT text, id FROM chunks",
+                [],
+            )?;
+        }
+
+        // Indexes written before chunk line ranges were tracked stored the
+        // chunk's position as `start_line`; forget their files so the next
+        // sync reindexes them.
+        let has_line_ranges: Option<String> = conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'chunk_line_ranges'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if has_line_ranges.is_none() {
+            conn.execute(
+                "DELETE FROM files WHERE source = ?",
+                params![WORKSPACE_SOURCE],
+            )?;
+            conn.execute(
+                "INSERT INTO meta (key, value) VALUES ('chunk_line_ranges', '1')",
+                [],
+            )?;
+        }
+
         Ok(())
     }
 
     pub async fn sync(&self) -> Result<(), MemoryError> {
+        self.sync_with_progress(|_| {}).await
+    }
+
+    /// Same as [`MemoryManager::sync`], but invokes `progress` once for every
+    /// file that gets (re)indexed.
+    pub async fn sync_with_progress<F>(&self, progress: F) -> Result<(), MemoryError>
+    where
+        F: Fn(SyncProgress) + Send + Sync,
+    {
         if !self.config.enabled {
             return Ok(());
         }
 
         info!("开始同步记忆...");
+        self.check_embedding_meta(None, false)?;
         let pattern = self.workspace_path.join("**/*.md");
         let pattern_str = pattern
             .to_str()
@@ -166,9 +328,9 @@ impl MemoryManager {
 
         {
             let conn = self.conn.lock().unwrap();
-            let mut stmt = conn.prepare("SELECT path FROM files WHERE source = 'workspace'")?;
+            let mut stmt = conn.prepare("SELECT path FROM files WHERE source = ?")?;
             let stored_paths: Vec<String> = stmt
-                .query_map([], |row| row.get(0))?
+                .query_map(params![WORKSPACE_SOURCE], |row| row.get(0))?
                 .filter_map(Result::ok)
                 .collect();
 
@@ -176,59 +338,208 @@ impl MemoryManager {
                 if !current_paths.contains(&path) {
                     info!("Removing deleted file from memory: {}", path);
                     conn.execute("DELETE FROM files WHERE path = ?", params![path])?;
-                    conn.execute("DELETE FROM chunks WHERE path = ?", params![path])?;
+                    conn.execute(
+                        "DELETE FROM chunks WHERE path = ? AND source = ?",
+                        params![path, WORKSPACE_SOURCE],
+                    )?;
                 }
             }
         }
 
-        for (abs_path, rel_path, mtime, size) in files_to_process {
+        let files_total = files_to_process.len();
+        let mut checked_dimension = false;
+        for (files_done, (abs_path, rel_path, mtime, size)) in
+            files_to_process.into_iter().enumerate()
+        {
             info!("Indexing file: {}", rel_path);
             let content = fs::read_to_string(&abs_path)?;
             let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
-            let chunks: Vec<&str> = content
-                .split("\n\n")
-                .filter(|s| !s.trim().is_empty())
-                .collect();
+            let chunks = split_chunks(&content);
 
             let mut chunk_entries = Vec::new();
-            for (i, chunk_text) in chunks.iter().enumerate() {
-                let embedding = self
-                    .llm_client
-                    .get_embedding(chunk_text)
-                    .await
-                    .map_err(|e| MemoryError::Llm(e.to_string()))?;
+            for (i, chunk) in chunks.iter().enumerate() {
+                let chunk_text = chunk.text;
+                let embedding = self.embed(chunk_text).await?;
+                if !checked_dimension {
+                    self.check_embedding_meta(Some(embedding.len()), true)?;
+                    checked_dimension = true;
+                }
                 let embedding_json = serde_json::to_string(&embedding)?;
                 let chunk_id = format!(
                     "{:x}",
                     Sha256::digest(format!("{}:{}:{}", rel_path, i, chunk_text).as_bytes())
                 );
-                chunk_entries.push((chunk_id, chunk_text.to_string(), embedding_json, i));
+                chunk_entries.push((
+                    chunk_id,
+                    chunk_text.to_string(),
+                    embedding_json,
+                    chunk.start_line,
+                    chunk.end_line,
+                ));
             }
 
             {
                 let mut conn = self.conn.lock().unwrap();
                 let tx = conn.transaction()?;
-                tx.execute("DELETE FROM chunks WHERE path = ?", params![rel_path])?;
+                tx.execute(
+                    "DELETE FROM chunks WHERE path = ? AND source = ?",
+                    params![rel_path, WORKSPACE_SOURCE],
+                )?;
                 {
                     let mut stmt = tx.prepare(
-                        "INSERT INTO chunks (id, path, source, text, embedding, start_line) VALUES (?, ?, ?, ?, ?, ?)",
+                        "INSERT INTO chunks (id, path, source, text, embedding, start_line, end_line) VALUES (?, ?, ?, ?, ?, ?, ?)",
                     )?;
-                    for (id, text, emb, idx) in chunk_entries {
-                        stmt.execute(params![id, rel_path, "workspace", text, emb, idx])?;
+                    for (id, text, emb, start_line, end_line) in chunk_entries {
+                        stmt.execute(params![
+                            id,
+                            rel_path,
+                            WORKSPACE_SOURCE,
+                            text,
+                            emb,
+                            start_line,
+                            end_line
+                        ])?;
                     }
                 }
                 tx.execute(
                     "INSERT OR REPLACE INTO files (path, source, hash, mtime, size) VALUES (?, ?, ?, ?, ?)",
-                    params![rel_path, "workspace", hash, mtime, size],
+                    params![rel_path, WORKSPACE_SOURCE, hash, mtime, size],
                 )?;
                 tx.commit()?;
             }
+
+            progress(SyncProgress {
+                files_total,
+                files_done: files_done + 1,
+                current_path: rel_path,
+            });
         }
 
         info!("Memory sync completed.");
         Ok(())
     }
 
+    /// Save `text` as a note in the [`NOTES_SOURCE`] source, embedded now so
+    /// later searches find it. `tags` are appended to the stored text.
+    /// Returns the note's path, `note:<id>`.
+    pub async fn add_note(&self, text: &str, tags: &[String]) -> Result<String, MemoryError> {
+        if !self.config.enabled {
+            return Err(MemoryError::Other("memory is disabled".to_string()));
+        }
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(MemoryError::Other("note text is empty".to_string()));
+        }
+        let body = if tags.is_empty() {
+            text.to_string()
+        } else {
+            format!("{}\nTags: {}", text, tags.join(", "))
+        };
+
+        let embedding = self.embed(&body).await?;
+        self.check_embedding_meta(Some(embedding.len()), true)?;
+        let embedding_json = serde_json::to_string(&embedding)?;
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| MemoryError::Other(e.to_string()))?
+            .as_nanos();
+        let id = format!(
+            "{:x}",
+            Sha256::digest(format!("{}:{}:{}", NOTES_SOURCE, stamp, body).as_bytes())
+        );
+        let path = format!("note:{}", &id[..12]);
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO chunks (id, path, source, text, embedding) VALUES (?, ?, ?, ?, ?)",
+            params![id, path, NOTES_SOURCE, body, embedding_json],
+        )?;
+        info!("Saved memory note {}", path);
+        Ok(path)
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, MemoryError> {
+        match self.config.embedding {
+            EmbeddingSource::Llm => self
+                .llm_client
+                .get_embedding(text)
+                .await
+                .map_err(|e| MemoryError::Llm(e.to_string())),
+            EmbeddingSource::Local => Ok(gearclaw_llm::local_embedding(text)),
+        }
+    }
+
+    fn embedding_model(&self) -> String {
+        match self.config.embedding {
+            EmbeddingSource::Llm => self.llm_client.embedding_model(),
+            EmbeddingSource::Local => gearclaw_llm::LOCAL_EMBEDDING_MODEL.to_string(),
+        }
+    }
+
+    /// Compare the current embedding model, and `dimension` when an
+    /// embedding is at hand, with those recorded in `meta`. The first
+    /// embedding written (`record`) records both; a mismatch is an error,
+    /// since cosine similarity across models or dimensions is meaningless.
+    fn check_embedding_meta(
+        &self,
+        dimension: Option<usize>,
+        record: bool,
+    ) -> Result<(), MemoryError> {
+        let model = self.embedding_model();
+        let conn = self.conn.lock().unwrap();
+        let read = |key: &str| -> Result<Option<String>, MemoryError> {
+            Ok(conn
+                .query_row(
+                    "SELECT value FROM meta WHERE key = ?",
+                    params![key],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        };
+        let stored_model = read(META_EMBEDDING_MODEL)?;
+        let stored_dimension = read(META_EMBEDDING_DIMENSION)?;
+
+        let describe = |model: &str, dimension: Option<&str>| match dimension {
+            Some(dimension) => format!("`{}` ({} dimensions)", model, dimension),
+            None => format!("`{}`", model),
+        };
+        let dimension_text = dimension.map(|d| d.to_string());
+        let model_differs = stored_model
+            .as_deref()
+            .is_some_and(|stored| stored != model);
+        let dimension_differs = match (&stored_dimension, &dimension_text) {
+            (Some(stored), Some(current)) => stored != current,
+            _ => false,
+        };
+        if model_differs || dimension_differs {
+            return Err(MemoryError::EmbeddingMismatch {
+                stored: describe(
+                    stored_model.as_deref().unwrap_or("an unknown model"),
+                    stored_dimension.as_deref(),
+                ),
+                current: describe(&model, dimension_text.as_deref()),
+                db_path: self.config.db_path.display().to_string(),
+            });
+        }
+
+        if let (true, None, Some(dimension)) = (record, &stored_dimension, &dimension_text) {
+            info!(
+                "Recording embedding model {} ({} dimensions) for the memory index",
+                model, dimension
+            );
+            conn.execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES (?, ?), (?, ?)",
+                params![
+                    META_EMBEDDING_MODEL,
+                    model,
+                    META_EMBEDDING_DIMENSION,
+                    dimension
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
     pub async fn search(
         &self,
         query: &str,
@@ -237,16 +548,13 @@ impl MemoryManager {
         if !self.config.enabled {
             return Ok(Vec::new());
         }
-        let query_embedding = self
-            .llm_client
-            .get_embedding(query)
-            .await
-            .map_err(|e| MemoryError::Llm(e.to_string()))?;
+        let query_embedding = self.embed(query).await?;
+        self.check_embedding_meta(Some(query_embedding.len()), false)?;
 
         let chunks = {
             let conn = self.conn.lock().unwrap();
             let mut stmt =
-                conn.prepare("SELECT id, path, text, embedding, start_line FROM chunks")?;
+                conn.prepare("SELECT id, path, text, embedding, start_line, end_line FROM chunks")?;
             let rows = stmt
                 .query_map([], |row| {
                     let id: String = row.get(0)?;
@@ -254,30 +562,122 @@ impl MemoryManager {
                     let text: String = row.get(2)?;
                     let emb_json: String = row.get(3)?;
                     let start_line: Option<usize> = row.get(4)?;
+                    let end_line: Option<usize> = row.get(5)?;
                     let embedding: Vec<f32> = serde_json::from_str(&emb_json).unwrap_or_default();
-                    Ok((id, path, text, embedding, start_line))
+                    Ok((id, path, text, embedding, start_line, end_line))
                 })?
                 .filter_map(Result::ok)
                 .collect::<Vec<_>>();
             rows
         };
+        // Indexes written before `meta` existed have only the vectors to go by
+        if let Some((_, _, _, stored, _, _)) = chunks
+            .iter()
+            .find(|chunk| chunk.3.len() != query_embedding.len())
+        {
+            return Err(MemoryError::EmbeddingMismatch {
+                stored: format!("{} dimensions", stored.len()),
+                current: format!(
+                    "`{}` ({} dimensions)",
+                    self.embedding_model(),
+                    query_embedding.len()
+                ),
+                db_path: self.config.db_path.display().to_string(),
+            });
+        }
 
-        let mut scored: Vec<SearchResult> = chunks
+        let mut scored: Vec<Ranked> = chunks
             .into_iter()
-            .map(|(_id, path, text, embedding, start_line)| SearchResult {
-                path,
-                text,
-                score: cosine_similarity(&query_embedding, &embedding),
-                start_line,
+            .map(|(id, path, text, embedding, start_line, end_line)| {
+                let result = SearchResult {
+                    path,
+                    text,
+                    score: cosine_similarity(&query_embedding, &embedding),
+                    start_line,
+                    end_line,
+                };
+                Ranked {
+                    id,
+                    embedding,
+                    result,
+                }
             })
             .collect();
-        scored.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        Ok(scored.into_iter().take(limit).collect())
+        sort_by_score(&mut scored);
+
+        if self.config.hybrid_search {
+            let keyword_ranking = self.keyword_search(query, KEYWORD_CANDIDATES)?;
+            fuse_rankings(&mut scored, &keyword_ranking);
+        }
+        Ok(dedup(scored, limit, self.config.dedup_threshold))
+    }
+
+    /// Chunk ids matching any word of `query`, best BM25 match first
+    fn keyword_search(&self, query: &str, limit: usize) -> Result<Vec<String>, MemoryError> {
+        let Some(fts_query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT chunk_id FROM chunks_fts WHERE chunks_fts MATCH ? ORDER BY bm25(chunks_fts) LIMIT ?",
+        )?;
+        let ids = stmt
+            .query_map(params![fts_query, limit as i64], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(ids)
+    }
+}
+
+/// FTS5 query matching any word of `query`; each word is quoted so
+/// punctuation in the query can't be read as FTS syntax
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"", term))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+fn sort_by_score(results: &mut [Ranked]) {
+    results.sort_by(|a, b| {
+        b.result
+            .score
+            .partial_cmp(&a.result.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+/// Re-rank `ranked` (best first) by reciprocal rank fusion with
+/// `keyword_ranking`, replacing each score with its fused score
+fn fuse_rankings(ranked: &mut [Ranked], keyword_ranking: &[String]) {
+    for (rank, candidate) in ranked.iter_mut().enumerate() {
+        let mut fused = 1.0 / (RRF_K + rank as f32 + 1.0);
+        if let Some(keyword_rank) = keyword_ranking.iter().position(|k| *k == candidate.id) {
+            fused += 1.0 / (RRF_K + keyword_rank as f32 + 1.0);
+        }
+        candidate.result.score = fused;
+    }
+    sort_by_score(ranked);
+}
+
+/// Take up to `limit` results from `ranked` (best first), skipping any
+/// whose embedding is more than `threshold` similar to one already taken
+fn dedup(ranked: Vec<Ranked>, limit: usize, threshold: f32) -> Vec<SearchResult> {
+    let mut selected: Vec<Ranked> = Vec::new();
+    for candidate in ranked {
+        if selected.len() == limit {
+            break;
+        }
+        let duplicate = threshold < 1.0
+            && selected
+                .iter()
+                .any(|kept| cosine_similarity(&kept.embedding, &candidate.embedding) > threshold);
+        if !duplicate {
+            selected.push(candidate);
+        }
     }
+    selected.into_iter().map(|ranked| ranked.result).collect()
 }
 
 fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
@@ -0,0 +1,94 @@
+//! Embedding endpoint stub, so search ranking can be tested with chosen vectors
+#![allow(dead_code)]
+
+use gearclaw_llm::LLMClient;
+use gearclaw_memory::{EmbeddingSource, MemoryConfig, MemoryManager};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Serve `/embeddings`, answering each input with `embed(input)`; returns
+/// the endpoint base URL
+pub async fn start_embedder(embed: fn(&str) -> Vec<f32>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}/v1", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let body = read_request_body(&mut stream).await;
+            let request: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+            let input = request["input"].as_str().unwrap_or_default();
+            let payload =
+                serde_json::json!({ "data": [{ "embedding": embed(input) }] }).to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                payload.len(),
+                payload
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        }
+    });
+    endpoint
+}
+
+async fn read_request_body(stream: &mut tokio::net::TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).await.unwrap_or(0);
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(split) = text.find("\r\n\r\n") {
+            let content_length = text[..split]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if request.len() >= split + 4 + content_length {
+                return text[split + 4..].to_string();
+            }
+        }
+    }
+    String::new()
+}
+
+/// Enabled manager indexing `dir/workspace`, with its database in `dir`
+pub fn manager(
+    dir: &Path,
+    endpoint: &str,
+    configure: impl FnOnce(&mut MemoryConfig),
+) -> MemoryManager {
+    manager_with_model(dir, endpoint, "embed-test", configure)
+}
+
+/// [`manager`] requesting embeddings from `embedding_model`
+pub fn manager_with_model(
+    dir: &Path,
+    endpoint: &str,
+    embedding_model: &str,
+    configure: impl FnOnce(&mut MemoryConfig),
+) -> MemoryManager {
+    let llm_client = Arc::new(LLMClient::new(
+        "test-key".to_string(),
+        endpoint.to_string(),
+        "gpt-test".to_string(),
+        embedding_model.to_string(),
+        Some(0.7),
+    ));
+    let mut config = MemoryConfig {
+        enabled: true,
+        db_path: dir.join("memory.db"),
+        hybrid_search: false,
+        dedup_threshold: MemoryConfig::DEFAULT_DEDUP_THRESHOLD,
+        embedding: EmbeddingSource::Llm,
+    };
+    configure(&mut config);
+    MemoryManager::new(config, dir.join("workspace"), llm_client).expect("manager")
+}
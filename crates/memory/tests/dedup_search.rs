@@ -0,0 +1,65 @@
+mod common;
+
+use common::{manager, start_embedder};
+use tempfile::TempDir;
+
+/// Boilerplate footers embed identically; everything else is distinct.
+fn embed(text: &str) -> Vec<f32> {
+    if text.contains("Generated by docs tooling") {
+        vec![1.0, 0.0, 0.0]
+    } else if text.contains("Install") {
+        vec![0.0, 1.0, 0.0]
+    } else if text.contains("Upgrade") {
+        vec![0.0, 0.0, 1.0]
+    } else {
+        // The query: closest to the footers
+        vec![0.9, 0.1, 0.0]
+    }
+}
+
+fn write_workspace(dir: &std::path::Path) {
+    let workspace = dir.join("workspace");
+    std::fs::create_dir_all(&workspace).unwrap();
+    std::fs::write(
+        workspace.join("a.md"),
+        "Install guide.\n\nGenerated by docs tooling.\n",
+    )
+    .unwrap();
+    std::fs::write(
+        workspace.join("b.md"),
+        "Upgrade guide.\n\nGenerated by docs tooling.\n",
+    )
+    .unwrap();
+}
+
+#[tokio::test]
+async fn identical_chunks_are_returned_once() {
+    let dir = TempDir::new().unwrap();
+    write_workspace(dir.path());
+    let endpoint = start_embedder(embed).await;
+    let memory = manager(dir.path(), &endpoint, |_| {});
+    memory.sync().await.unwrap();
+
+    let results = memory.search("docs footer", 3).await.unwrap();
+    let footers = results
+        .iter()
+        .filter(|r| r.text.contains("Generated by docs tooling"))
+        .count();
+    assert_eq!(footers, 1, "{:?}", results);
+    // The freed slot goes to the next distinct chunk.
+    assert_eq!(results.len(), 3);
+}
+
+#[tokio::test]
+async fn dedup_threshold_of_one_keeps_duplicates() {
+    let dir = TempDir::new().unwrap();
+    write_workspace(dir.path());
+    let endpoint = start_embedder(embed).await;
+    let memory = manager(dir.path(), &endpoint, |config| config.dedup_threshold = 1.0);
+    memory.sync().await.unwrap();
+
+    let results = memory.search("docs footer", 2).await.unwrap();
+    assert!(results
+        .iter()
+        .all(|r| r.text.contains("Generated by docs tooling")));
+}
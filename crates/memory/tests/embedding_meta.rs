@@ -0,0 +1,142 @@
+mod common;
+
+use common::{manager, manager_with_model, start_embedder};
+use gearclaw_memory::MemoryError;
+use rusqlite::{params, Connection};
+use tempfile::TempDir;
+
+fn embed3(_text: &str) -> Vec<f32> {
+    vec![1.0, 0.0, 0.0]
+}
+
+fn embed4(_text: &str) -> Vec<f32> {
+    vec![1.0, 0.0, 0.0, 0.0]
+}
+
+fn write_note(dir: &std::path::Path, name: &str) {
+    let workspace = dir.join("workspace");
+    std::fs::create_dir_all(&workspace).unwrap();
+    std::fs::write(workspace.join(name), "Some notes.\n").unwrap();
+}
+
+fn meta(dir: &std::path::Path, key: &str) -> Option<String> {
+    let conn = Connection::open(dir.join("memory.db")).unwrap();
+    conn.query_row(
+        "SELECT value FROM meta WHERE key = ?",
+        params![key],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+#[tokio::test]
+async fn first_sync_records_embedding_model_and_dimension() {
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md");
+    let endpoint = start_embedder(embed3).await;
+    let memory = manager(dir.path(), &endpoint, |_| {});
+
+    assert_eq!(meta(dir.path(), "embedding_model"), None);
+    memory.sync().await.unwrap();
+
+    assert_eq!(
+        meta(dir.path(), "embedding_model").as_deref(),
+        Some("embed-test")
+    );
+    assert_eq!(
+        meta(dir.path(), "embedding_dimension").as_deref(),
+        Some("3")
+    );
+    // Unchanged settings keep syncing.
+    write_note(dir.path(), "b.md");
+    memory.sync().await.unwrap();
+}
+
+#[tokio::test]
+async fn sync_with_another_embedding_model_is_refused() {
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md");
+    let endpoint = start_embedder(embed3).await;
+    manager(dir.path(), &endpoint, |_| {}).sync().await.unwrap();
+
+    let err = manager_with_model(dir.path(), &endpoint, "embed-other", |_| {})
+        .sync()
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, MemoryError::EmbeddingMismatch { .. }),
+        "{}",
+        err
+    );
+    let message = err.to_string();
+    assert!(
+        message.contains("`embed-test` (3 dimensions)"),
+        "{}",
+        message
+    );
+    assert!(message.contains("`embed-other`"), "{}", message);
+    assert!(message.contains("memory.db"), "{}", message);
+}
+
+#[tokio::test]
+async fn embeddings_of_another_dimension_are_refused() {
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md");
+    let endpoint = start_embedder(embed3).await;
+    manager(dir.path(), &endpoint, |_| {}).sync().await.unwrap();
+
+    write_note(dir.path(), "b.md");
+    let resized = start_embedder(embed4).await;
+    let memory = manager(dir.path(), &resized, |_| {});
+    let err = memory.sync().await.unwrap_err();
+    assert!(err.to_string().contains("(4 dimensions)"), "{}", err);
+    let err = memory.add_note("remember this", &[]).await.unwrap_err();
+    assert!(
+        matches!(err, MemoryError::EmbeddingMismatch { .. }),
+        "{}",
+        err
+    );
+    // Nothing from the mismatched model reached the index.
+    let conn = Connection::open(dir.path().join("memory.db")).unwrap();
+    let chunks: i64 = conn
+        .query_row("SELECT COUNT(*) FROM chunks", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(chunks, 1);
+}
+
+#[tokio::test]
+async fn search_with_another_dimension_requires_a_reindex() {
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md");
+    let endpoint = start_embedder(embed3).await;
+    manager(dir.path(), &endpoint, |_| {}).sync().await.unwrap();
+
+    let resized = start_embedder(embed4).await;
+    let err = manager(dir.path(), &resized, |_| {})
+        .search("notes", 5)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, MemoryError::EmbeddingMismatch { .. }),
+        "{}",
+        err
+    );
+    assert!(err.to_string().starts_with("reindex required"), "{}", err);
+}
+
+#[tokio::test]
+async fn search_checks_vectors_of_an_index_without_meta() {
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md");
+    let endpoint = start_embedder(embed3).await;
+    manager(dir.path(), &endpoint, |_| {}).sync().await.unwrap();
+    let conn = Connection::open(dir.path().join("memory.db")).unwrap();
+    conn.execute("DELETE FROM meta", []).unwrap();
+
+    let resized = start_embedder(embed4).await;
+    let err = manager(dir.path(), &resized, |_| {})
+        .search("notes", 5)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("3 dimensions"), "{}", err);
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::{manager, start_embedder};
+use tempfile::TempDir;
+
+/// Every chunk points the same way except the troubleshooting one, so
+/// cosine similarity alone ranks it last for any ordinary query.
+fn embed(text: &str) -> Vec<f32> {
+    if text.starts_with("Troubleshooting") {
+        vec![0.0, 1.0]
+    } else {
+        vec![1.0, 0.0]
+    }
+}
+
+fn write_workspace(dir: &std::path::Path) {
+    let workspace = dir.join("workspace");
+    std::fs::create_dir_all(&workspace).unwrap();
+    std::fs::write(
+        workspace.join("ops.md"),
+        "Deploys run every weekday morning.\n\n\
+         Backups are copied to the second region.\n\n\
+         Alerts page the on-call engineer.\n\n\
+         Dashboards live in the shared folder.\n\n\
+         Troubleshooting: ERR_DISK_FULL means the data volume is out of space.\n",
+    )
+    .unwrap();
+}
+
+#[tokio::test]
+async fn hybrid_search_surfaces_exact_term_cosine_ranks_low() {
+    let dir = TempDir::new().unwrap();
+    write_workspace(dir.path());
+    let endpoint = start_embedder(embed).await;
+
+    // The filler chunks embed identically; keep them all so they crowd the
+    // top of the cosine ranking.
+    let vector_only = manager(dir.path(), &endpoint, |config| {
+        config.dedup_threshold = 1.0;
+    });
+    vector_only.sync().await.unwrap();
+    let results = vector_only.search("ERR_DISK_FULL", 3).await.unwrap();
+    assert!(results.iter().all(|r| !r.text.contains("ERR_DISK_FULL")));
+
+    // Reopening with hybrid search uses the keyword index built during sync.
+    drop(vector_only);
+    let hybrid = manager(dir.path(), &endpoint, |config| {
+        config.hybrid_search = true;
+        config.dedup_threshold = 1.0;
+    });
+    let results = hybrid.search("ERR_DISK_FULL", 3).await.unwrap();
+    assert!(results[0].text.contains("ERR_DISK_FULL"), "{:?}", results);
+    assert_eq!(results[0].start_line, Some(9));
+}
+
+fn keyword_rows(dir: &std::path::Path) -> i64 {
+    let conn = rusqlite::Connection::open(dir.join("memory.db")).unwrap();
+    conn.query_row("SELECT COUNT(*) FROM chunks_fts", [], |row| row.get(0))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn keyword_index_follows_chunk_inserts_and_deletes() {
+    let dir = TempDir::new().unwrap();
+    write_workspace(dir.path());
+    let endpoint = start_embedder(embed).await;
+    let hybrid = manager(dir.path(), &endpoint, |config| config.hybrid_search = true);
+    hybrid.sync().await.unwrap();
+    assert_eq!(keyword_rows(dir.path()), 5);
+
+    // Punctuation in the query is not read as FTS syntax.
+    let results = hybrid.search("ERR_DISK_FULL (disk)?", 1).await.unwrap();
+    assert!(results[0].text.contains("ERR_DISK_FULL"));
+
+    std::fs::remove_file(dir.path().join("workspace/ops.md")).unwrap();
+    hybrid.sync().await.unwrap();
+    assert_eq!(keyword_rows(dir.path()), 0);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::manager;
+use gearclaw_llm::{local_embedding, LOCAL_EMBEDDING_DIMENSION};
+use gearclaw_memory::{EmbeddingSource, MemoryError};
+use tempfile::TempDir;
+
+/// Nothing listens here; any embedding request would fail
+const UNREACHABLE: &str = "http://127.0.0.1:9/v1";
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[test]
+fn local_embedding_is_deterministic_and_normalized() {
+    let a = local_embedding("Backups are copied to the second region.");
+    assert_eq!(a.len(), LOCAL_EMBEDDING_DIMENSION);
+    assert_eq!(
+        a,
+        local_embedding("backups are COPIED to the second region")
+    );
+    assert!((cosine(&a, &a) - 1.0).abs() < 1e-5);
+
+    let related = local_embedding("which region holds the backups");
+    let unrelated = local_embedding("alerts page the on-call engineer");
+    assert!(cosine(&a, &related) > cosine(&a, &unrelated));
+
+    // CJK text is split per character rather than into one long token
+    let zh = local_embedding("数据库备份");
+    assert!(cosine(&zh, &local_embedding("备份")) > 0.0);
+    assert!(local_embedding("").iter().all(|v| *v == 0.0));
+}
+
+#[tokio::test]
+async fn sync_and_search_run_offline_with_local_embeddings() {
+    let dir = TempDir::new().unwrap();
+    let workspace = dir.path().join("workspace");
+    std::fs::create_dir_all(&workspace).unwrap();
+    std::fs::write(
+        workspace.join("ops.md"),
+        "Deploys run every weekday morning.\n\n\
+         Backups are copied to the second region.\n\n\
+         Alerts page the on-call engineer.\n",
+    )
+    .unwrap();
+
+    let memory = manager(dir.path(), UNREACHABLE, |config| {
+        config.embedding = EmbeddingSource::Local;
+    });
+    memory.sync().await.unwrap();
+    memory
+        .add_note("The staging database password rotates monthly", &[])
+        .await
+        .unwrap();
+
+    let results = memory.search("where are backups copied", 2).await.unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].text.contains("Backups"), "{:?}", results[0]);
+
+    let results = memory.search("database password", 1).await.unwrap();
+    assert!(
+        results[0].text.contains("staging database"),
+        "{:?}",
+        results[0]
+    );
+
+    // Local vectors are recorded as their own model, so switching back to
+    // the endpoint's embeddings is caught rather than mixed in
+    drop(memory);
+    let remote = manager(dir.path(), UNREACHABLE, |_| {});
+    assert!(matches!(
+        remote.sync().await,
+        Err(MemoryError::EmbeddingMismatch { .. })
+    ));
+}
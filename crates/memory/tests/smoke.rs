@@ -1,6 +1,6 @@
 use gearclaw_llm::LLMClient;
-use gearclaw_memory::{MemoryConfig, MemoryManager};
-use std::sync::Arc;
+use gearclaw_memory::{split_chunks, EmbeddingSource, MemoryConfig, MemoryManager, SyncProgress};
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 fn unique_db_path() -> std::path::PathBuf {
@@ -17,6 +17,9 @@ fn memory_smoke_manager_new() {
     let config = MemoryConfig {
         enabled: false,
         db_path: db_path.clone(),
+        hybrid_search: false,
+        dedup_threshold: MemoryConfig::DEFAULT_DEDUP_THRESHOLD,
+        embedding: EmbeddingSource::Llm,
     };
     let llm_client = Arc::new(LLMClient::new(
         "test-key".to_string(),
@@ -31,3 +34,72 @@ fn memory_smoke_manager_new() {
 
     let _ = std::fs::remove_file(db_path);
 }
+
+#[tokio::test]
+async fn memory_sync_reports_progress_once_per_file() {
+    let db_path = unique_db_path();
+    let workspace = db_path.with_extension("workspace");
+    std::fs::create_dir_all(workspace.join("notes")).expect("workspace");
+    // Whitespace-only files produce no chunks, so no embedding request is made.
+    std::fs::write(workspace.join("a.md"), "\n").expect("write a.md");
+    std::fs::write(workspace.join("notes/b.md"), "  \n").expect("write b.md");
+
+    let config = MemoryConfig {
+        enabled: true,
+        db_path: db_path.clone(),
+        hybrid_search: false,
+        dedup_threshold: MemoryConfig::DEFAULT_DEDUP_THRESHOLD,
+        embedding: EmbeddingSource::Llm,
+    };
+    let llm_client = Arc::new(LLMClient::new(
+        "test-key".to_string(),
+        "https://example.com/v1".to_string(),
+        "gpt-test".to_string(),
+        "embed-test".to_string(),
+        Some(0.7),
+    ));
+    let manager = MemoryManager::new(config, workspace.clone(), llm_client).expect("manager");
+
+    let events: Mutex<Vec<SyncProgress>> = Mutex::new(Vec::new());
+    manager
+        .sync_with_progress(|p| events.lock().unwrap().push(p))
+        .await
+        .expect("sync");
+
+    let events = events.into_inner().unwrap();
+    assert_eq!(events.len(), 2);
+    assert!(events.iter().all(|p| p.files_total == 2));
+    assert_eq!(
+        events.iter().map(|p| p.files_done).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+
+    // Unchanged files are skipped on the next sync and report no progress.
+    let calls = Mutex::new(0usize);
+    manager
+        .sync_with_progress(|_| *calls.lock().unwrap() += 1)
+        .await
+        .expect("resync");
+    assert_eq!(calls.into_inner().unwrap(), 0);
+
+    let _ = std::fs::remove_dir_all(workspace);
+    let _ = std::fs::remove_file(db_path);
+}
+
+#[test]
+fn memory_chunks_carry_their_line_ranges() {
+    let content = "# Title\n\nfirst line\nsecond line\n\n\n\nlast\n";
+    let chunks = split_chunks(content);
+    let ranges: Vec<(&str, usize, usize)> = chunks
+        .iter()
+        .map(|c| (c.text.trim(), c.start_line, c.end_line))
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            ("# Title", 1, 1),
+            ("first line\nsecond line", 3, 4),
+            ("last", 8, 8),
+        ]
+    );
+}
@@ -1,8 +1,9 @@
 use chrono::{DateTime, Utc};
-use gearclaw_llm::Message;
+use gearclaw_llm::{LlmBackend, Message, ToolCall, Usage};
 use serde::{Deserialize, Serialize};
 use std::fmt;
-use std::path::PathBuf;
+use std::future::Future;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 const MAX_SESSION_ID_LENGTH: usize = 128;
 
@@ -14,6 +15,85 @@ pub enum SessionError {
     Json(#[from] serde_json::Error),
     #[error("invalid session id: {0}")]
     InvalidSessionId(String),
+    #[error("summarization failed: {0}")]
+    Summarize(String),
+    #[error("session not found: {0}")]
+    NotFound(String),
+    #[error("session already exists: {0}")]
+    AlreadyExists(String),
+    #[error("directory not found: {}", .0.display())]
+    DirectoryNotFound(PathBuf),
+}
+
+fn is_session_id_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | ':')
+}
+
+/// Deterministically map an arbitrary identifier (e.g. a platform user or
+/// channel ID) to a valid session ID. Already-valid IDs are returned
+/// unchanged; anything else has unsupported characters replaced with `_`
+/// and gets a hash suffix of the original so distinct inputs stay distinct.
+pub fn sanitize_session_id(raw: &str) -> String {
+    if SessionManager::validate_session_id(raw).is_ok() {
+        return raw.to_string();
+    }
+
+    // FNV-1a: stable across Rust versions, unlike `DefaultHasher`.
+    let hash = raw.bytes().fold(0xcbf2_9ce4_8422_2325u64, |acc, b| {
+        (acc ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    let suffix = format!("-{:016x}", hash);
+
+    let mut base: String = raw
+        .trim()
+        .chars()
+        .map(|ch| if is_session_id_char(ch) { ch } else { '_' })
+        .collect();
+    while base.contains("..") {
+        base = base.replace("..", "_");
+    }
+    let base: String = base
+        .chars()
+        .take(MAX_SESSION_ID_LENGTH - suffix.len())
+        .collect();
+    let base = if base.is_empty() || base == "." {
+        "session".to_string()
+    } else {
+        base
+    };
+    format!("{}{}", base, suffix)
+}
+
+/// Prefix of the system message that replaces compacted history.
+pub const SUMMARY_PREFIX: &str = "[Conversation summary]";
+
+const SUMMARIZE_INSTRUCTION: &str =
+    "Summarize the conversation below for your own future reference. \
+Keep facts, decisions, file paths, commands and open tasks; drop small talk. \
+Answer with the summary only.";
+
+/// Rough token estimate for text: ~4 ASCII chars per token, one token per
+/// non-ASCII char (CJK).
+pub fn estimate_text_tokens(text: &str) -> usize {
+    let (ascii, other) = text.chars().fold((0usize, 0usize), |(a, o), ch| {
+        if ch.is_ascii() {
+            (a + 1, o)
+        } else {
+            (a, o + 1)
+        }
+    });
+    ascii.div_ceil(4) + other
+}
+
+/// Rough token estimate for a message: [`estimate_text_tokens`] of its
+/// content and tool calls plus a small per-message overhead.
+pub fn estimate_tokens(message: &Message) -> usize {
+    let mut tokens = 4 + message.content.as_deref().map_or(0, estimate_text_tokens);
+    for call in message.tool_calls.iter().flatten() {
+        tokens += estimate_text_tokens(&call.function.name)
+            + estimate_text_tokens(&call.function.arguments);
+    }
+    tokens
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,12 +104,49 @@ pub struct Session {
     pub messages: Vec<Message>,
     #[serde(default = "default_cwd")]
     pub cwd: PathBuf,
+    /// Token usage accumulated across every LLM call made for this session.
+    #[serde(default)]
+    pub usage: Usage,
+    /// Estimated prompt size of `messages`, kept in sync on every mutation.
+    #[serde(default)]
+    pub estimated_tokens: usize,
+    /// Settings replacing the agent's global ones for this session. Not
+    /// persisted: callers re-apply them whenever the session is loaded.
+    #[serde(skip)]
+    pub overrides: SessionOverrides,
+    /// Tool calls held back for user confirmation in the last turn (see the
+    /// agent's `tools.confirm_destructive`). Not persisted.
+    #[serde(skip)]
+    pub pending_confirmations: Vec<ToolCall>,
+}
+
+/// Per-session replacements for global agent settings (e.g. from a channel
+/// override); `None` keeps the global value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionOverrides {
+    pub system_prompt: Option<String>,
+    pub security: Option<String>,
+    pub memory_enabled: Option<bool>,
+    /// Model tried before the configured primary and fallbacks
+    pub model: Option<String>,
 }
 
 fn default_cwd() -> PathBuf {
     std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
 }
 
+/// Resolve `target` against `cwd` (absolute targets replace it) and
+/// canonicalize the result, which must be an existing directory.
+pub fn resolve_dir(cwd: &Path, target: &Path) -> Result<PathBuf, SessionError> {
+    let joined = cwd.join(target);
+    let resolved = std::fs::canonicalize(&joined).unwrap_or(joined);
+    if resolved.is_dir() {
+        Ok(resolved)
+    } else {
+        Err(SessionError::DirectoryNotFound(resolved))
+    }
+}
+
 impl Session {
     pub fn new(id: String) -> Self {
         Self {
@@ -38,24 +155,280 @@ impl Session {
             updated_at: Utc::now(),
             messages: Vec::new(),
             cwd: default_cwd(),
+            usage: Usage::default(),
+            estimated_tokens: 0,
+            overrides: SessionOverrides::default(),
+            pending_confirmations: Vec::new(),
         }
     }
 
+    /// Move the working directory used by file and exec tools to `target`,
+    /// resolved with [`resolve_dir`]. On error `cwd` is left unchanged.
+    pub fn change_dir(&mut self, target: &Path) -> Result<&Path, SessionError> {
+        self.cwd = resolve_dir(&self.cwd, target)?;
+        self.updated_at = Utc::now();
+        Ok(&self.cwd)
+    }
+
     pub fn add_message(&mut self, message: Message) {
+        self.estimated_tokens += estimate_tokens(&message);
         self.messages.push(message);
         self.updated_at = Utc::now();
     }
 
+    /// Recompute `estimated_tokens` from scratch (e.g. after loading an older
+    /// session file that did not store it).
+    pub fn refresh_token_estimate(&mut self) {
+        self.estimated_tokens = self.messages.iter().map(estimate_tokens).sum();
+    }
+
+    /// Index of the first message that must be kept verbatim so the last
+    /// `keep_recent` user turns survive compaction. `None` when there is
+    /// nothing older to compact.
+    fn compaction_split(&self, keep_recent: usize) -> Option<usize> {
+        let split = if keep_recent == 0 {
+            self.messages.len()
+        } else {
+            self.messages
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, m)| m.role == "user")
+                .nth(keep_recent - 1)
+                .map(|(idx, _)| idx)?
+        };
+        (split > 0).then_some(split)
+    }
+
+    /// Replace everything before the last `keep_recent` user turns with a
+    /// single summary message produced by `summarize`. Returns how many
+    /// messages were folded into the summary.
+    pub async fn compact_with<F, Fut>(
+        &mut self,
+        keep_recent: usize,
+        summarize: F,
+    ) -> Result<usize, SessionError>
+    where
+        F: FnOnce(Vec<Message>) -> Fut,
+        Fut: Future<Output = Result<String, SessionError>>,
+    {
+        let Some(split) = self.compaction_split(keep_recent) else {
+            return Ok(0);
+        };
+
+        let older: Vec<Message> = self.messages[..split].to_vec();
+        let summary = summarize(older).await?;
+        let recent = self.messages.split_off(split);
+        self.messages = std::iter::once(Message {
+            role: "system".to_string(),
+            content: Some(format!("{}\n{}", SUMMARY_PREFIX, summary.trim())),
+            tool_calls: None,
+            tool_call_id: None,
+        })
+        .chain(recent)
+        .collect();
+        self.refresh_token_estimate();
+        self.updated_at = Utc::now();
+        Ok(split)
+    }
+
+    /// [`Session::compact_with`] using `llm` to write the summary.
+    pub async fn compact(
+        &mut self,
+        keep_recent: usize,
+        llm: &dyn LlmBackend,
+    ) -> Result<usize, SessionError> {
+        self.compact_with(keep_recent, |older| async move {
+            let request = vec![
+                Message {
+                    role: "system".to_string(),
+                    content: Some(SUMMARIZE_INSTRUCTION.to_string()),
+                    tool_calls: None,
+                    tool_call_id: None,
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: Some(render_transcript(&older)),
+                    tool_calls: None,
+                    tool_call_id: None,
+                },
+            ];
+            let response = llm
+                .chat_completion(request, None, None)
+                .await
+                .map_err(|e| SessionError::Summarize(e.to_string()))?;
+            response
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|c| c.message.content)
+                .filter(|c| !c.trim().is_empty())
+                .ok_or_else(|| SessionError::Summarize("empty summary".to_string()))
+        })
+        .await
+    }
+
+    pub fn record_usage(&mut self, usage: Usage) {
+        self.usage += usage;
+        self.updated_at = Utc::now();
+    }
+
     pub fn get_messages(&self) -> Vec<Message> {
         self.messages.clone()
     }
 
+    /// History to send to the LLM, trimmed to `max_tokens`. See [`trim_to_budget`].
+    pub fn messages_within_budget(&self, max_tokens: usize) -> Vec<Message> {
+        trim_to_budget(&self.messages, max_tokens)
+    }
+
     pub fn clear_history(&mut self) {
         self.messages.clear();
+        self.estimated_tokens = 0;
         self.updated_at = Utc::now();
     }
 }
 
+/// Select the newest messages that fit in `max_tokens` without mutating the
+/// input. System messages are always kept. An assistant message with
+/// `tool_calls` and the tool results answering it are kept or dropped together,
+/// and tool results whose call was trimmed away are never returned. The newest
+/// group is kept even if it alone exceeds the budget.
+pub fn trim_to_budget(messages: &[Message], max_tokens: usize) -> Vec<Message> {
+    // Group non-system messages into units that must stay together.
+    let mut units: Vec<Vec<usize>> = Vec::new();
+    let mut open_calls: Vec<String> = Vec::new();
+    for (idx, message) in messages.iter().enumerate() {
+        match message.role.as_str() {
+            "system" => {}
+            "tool" => {
+                let answers_open_call = message
+                    .tool_call_id
+                    .as_ref()
+                    .is_some_and(|id| open_calls.contains(id));
+                match units.last_mut() {
+                    Some(unit) if answers_open_call => unit.push(idx),
+                    // Orphaned tool result: never send it on its own.
+                    _ => {}
+                }
+            }
+            _ => {
+                open_calls = message
+                    .tool_calls
+                    .iter()
+                    .flatten()
+                    .map(|call| call.id.clone())
+                    .collect();
+                units.push(vec![idx]);
+            }
+        }
+    }
+
+    let mut keep = vec![false; messages.len()];
+    let mut used = 0;
+    for (idx, message) in messages.iter().enumerate() {
+        if message.role == "system" {
+            keep[idx] = true;
+            used += estimate_tokens(message);
+        }
+    }
+    for (n, unit) in units.iter().rev().enumerate() {
+        let cost: usize = unit
+            .iter()
+            .map(|&idx| estimate_tokens(&messages[idx]))
+            .sum();
+        if n > 0 && used + cost > max_tokens {
+            break;
+        }
+        used += cost;
+        for &idx in unit {
+            keep[idx] = true;
+        }
+    }
+
+    messages
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(message, _)| message.clone())
+        .collect()
+}
+
+/// Markdown transcript: session metadata, one heading per message, tool
+/// calls and tool output in fenced blocks.
+fn render_markdown(session: &Session) -> String {
+    let mut out = format!("# Session `{}`\n\n", session.id);
+    out.push_str(&format!(
+        "- Created: {}\n- Updated: {}\n- Working directory: `{}`\n- Messages: {}\n",
+        session.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        session.updated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        session.cwd.display(),
+        session.messages.len()
+    ));
+    if session.usage.total_tokens > 0 {
+        out.push_str(&format!("- Tokens used: {}\n", session.usage.total_tokens));
+    }
+
+    for message in &session.messages {
+        out.push_str("\n---\n\n");
+        match message.role.as_str() {
+            "tool" => {
+                match &message.tool_call_id {
+                    Some(id) => out.push_str(&format!("### Tool result (`{}`)\n\n", id)),
+                    None => out.push_str("### Tool result\n\n"),
+                }
+                out.push_str(&fenced("", message.content.as_deref().unwrap_or_default()));
+            }
+            role => {
+                let heading = match role {
+                    "user" => "User",
+                    "assistant" => "Assistant",
+                    "system" => "System",
+                    other => other,
+                };
+                out.push_str(&format!("### {}\n\n", heading));
+                if let Some(content) = message.content.as_deref().filter(|c| !c.is_empty()) {
+                    out.push_str(content.trim_end());
+                    out.push_str("\n\n");
+                }
+                for call in message.tool_calls.iter().flatten() {
+                    out.push_str(&format!("**Tool call** `{}`\n\n", call.function.name));
+                    let arguments =
+                        serde_json::from_str::<serde_json::Value>(&call.function.arguments)
+                            .and_then(|v| serde_json::to_string_pretty(&v))
+                            .unwrap_or_else(|_| call.function.arguments.clone());
+                    out.push_str(&fenced("json", &arguments));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Wrap `body` in a code fence longer than any backtick run it contains.
+fn fenced(lang: &str, body: &str) -> String {
+    let longest_run = body.split(|ch| ch != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}{}\n{}\n{}\n\n", fence, lang, body.trim_end(), fence)
+}
+
+/// Plain-text rendering of `messages` used as summarization input.
+fn render_transcript(messages: &[Message]) -> String {
+    let mut out = String::new();
+    for message in messages {
+        if let Some(content) = message.content.as_deref().filter(|c| !c.is_empty()) {
+            out.push_str(&format!("{}: {}\n", message.role, content));
+        }
+        for call in message.tool_calls.iter().flatten() {
+            out.push_str(&format!(
+                "{} called {}({})\n",
+                message.role, call.function.name, call.function.arguments
+            ));
+        }
+    }
+    out
+}
+
 impl fmt::Display for Session {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -68,6 +441,32 @@ impl fmt::Display for Session {
     }
 }
 
+/// Lightweight listing entry for a stored session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub id: String,
+    pub message_count: usize,
+    pub updated_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+/// Outcome of [`SessionManager::prune`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// IDs of the deleted sessions, sorted
+    pub pruned: Vec<String>,
+    /// IDs of expired sessions that could not be deleted, sorted
+    pub failed: Vec<String>,
+}
+
+/// Subset of a session file needed for listing; messages are counted, not parsed.
+#[derive(Deserialize)]
+struct SessionHeader {
+    updated_at: DateTime<Utc>,
+    #[serde(default)]
+    messages: Vec<serde::de::IgnoredAny>,
+}
+
 pub struct SessionManager {
     session_dir: PathBuf,
 }
@@ -100,17 +499,103 @@ impl SessionManager {
         Ok(sessions)
     }
 
+    /// Delete sessions whose stored `updated_at` is older than `max_age`.
+    /// The timestamp inside each file is used rather than filesystem mtime,
+    /// which copies and restores do not preserve. Files that cannot be
+    /// parsed are left alone; a session that cannot be deleted is logged
+    /// and reported in [`PruneReport::failed`] without stopping the rest.
+    pub fn prune(&self, max_age: std::time::Duration) -> Result<PruneReport, SessionError> {
+        let max_age = chrono::Duration::from_std(max_age).unwrap_or(chrono::Duration::MAX);
+        let cutoff = Utc::now()
+            .checked_sub_signed(max_age)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+        let mut report = PruneReport::default();
+        for summary in self.list_sessions_detailed()? {
+            if summary.updated_at < cutoff {
+                match self.delete_session(&summary.id) {
+                    Ok(()) => report.pruned.push(summary.id),
+                    Err(e) => {
+                        tracing::warn!("Failed to prune session {}: {}", summary.id, e);
+                        report.failed.push(summary.id);
+                    }
+                }
+            }
+        }
+        report.pruned.sort();
+        report.failed.sort();
+        Ok(report)
+    }
+
+    /// Sessions with metadata, most recently updated first. Files that cannot
+    /// be read or parsed are skipped.
+    pub fn list_sessions_detailed(&self) -> Result<Vec<SessionSummary>, SessionError> {
+        let mut summaries = Vec::new();
+        if !self.session_dir.exists() {
+            return Ok(summaries);
+        }
+
+        for entry in std::fs::read_dir(&self.session_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) != Some("json") {
+                continue;
+            }
+            // The file name is the addressable ID used by load/delete.
+            let Some(id) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let header = std::fs::read_to_string(&path)
+                .map_err(SessionError::from)
+                .and_then(|content| Ok(serde_json::from_str::<SessionHeader>(&content)?));
+            let header = match header {
+                Ok(header) => header,
+                Err(e) => {
+                    tracing::warn!("Skipping unreadable session file {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            summaries.push(SessionSummary {
+                id,
+                message_count: header.messages.len(),
+                updated_at: header.updated_at,
+                size_bytes: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+            });
+        }
+        summaries.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        Ok(summaries)
+    }
+
     pub fn get_or_create_session(&self, id: &str) -> Result<Session, SessionError> {
         let path = self.session_file_path(id)?;
         if path.exists() {
-            let content = std::fs::read_to_string(&path)?;
-            let session: Session = serde_json::from_str(&content)?;
-            Ok(session)
+            self.load_session(id)
         } else {
             Ok(Session::new(id.to_string()))
         }
     }
 
+    /// Load an existing session, failing with [`SessionError::NotFound`]
+    /// instead of creating one.
+    pub fn load_session(&self, id: &str) -> Result<Session, SessionError> {
+        let path = self.session_file_path(id)?;
+        if !path.exists() {
+            return Err(SessionError::NotFound(id.to_string()));
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let mut session: Session = serde_json::from_str(&content)?;
+        session.refresh_token_estimate();
+        Ok(session)
+    }
+
+    /// Render a stored session as a readable Markdown transcript.
+    pub fn export_markdown(&self, id: &str) -> Result<String, SessionError> {
+        Ok(render_markdown(&self.load_session(id)?))
+    }
+
     pub async fn save_session(&self, session: &Session) -> Result<(), SessionError> {
         let path = self.session_file_path(&session.id)?;
         let content = serde_json::to_string_pretty(session)?;
@@ -118,6 +603,22 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Copy `source_id`'s messages and cwd into a new session `new_id` and
+    /// save it. Fails if `new_id` already exists.
+    pub async fn fork(&self, source_id: &str, new_id: &str) -> Result<Session, SessionError> {
+        let source = self.load_session(source_id)?;
+        if self.session_file_path(new_id)?.exists() {
+            return Err(SessionError::AlreadyExists(new_id.to_string()));
+        }
+
+        let mut forked = Session::new(new_id.to_string());
+        forked.messages = source.messages;
+        forked.cwd = source.cwd;
+        forked.refresh_token_estimate();
+        self.save_session(&forked).await?;
+        Ok(forked)
+    }
+
     pub fn delete_session(&self, id: &str) -> Result<(), SessionError> {
         let path = self.session_file_path(id)?;
         if path.exists() {
@@ -127,7 +628,13 @@ impl SessionManager {
     }
 
     fn session_file_path(&self, id: &str) -> Result<PathBuf, SessionError> {
-        Self::validate_session_id(id)?;
+        Self::validate_session_id(id).map_err(|e| match e {
+            SessionError::InvalidSessionId(reason) => SessionError::InvalidSessionId(format!(
+                "{:?}: {} (use sanitize_session_id for external identifiers)",
+                id, reason
+            )),
+            other => other,
+        })?;
         let path = self.session_dir.join(format!("{}.json", id));
         if !path.starts_with(&self.session_dir) {
             return Err(SessionError::InvalidSessionId(
@@ -167,10 +674,7 @@ impl SessionManager {
                 "session id cannot contain path separators".to_string(),
             ));
         }
-        if !id
-            .chars()
-            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | ':'))
-        {
+        if !id.chars().all(is_session_id_char) {
             return Err(SessionError::InvalidSessionId(
                 "session id contains unsupported characters".to_string(),
             ));
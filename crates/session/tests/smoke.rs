@@ -1,4 +1,8 @@
-use gearclaw_session::{Session, SessionManager};
+use gearclaw_llm::{FunctionCall, Message, ToolCall, Usage};
+use gearclaw_session::{
+    estimate_tokens, sanitize_session_id, trim_to_budget, Session, SessionError, SessionManager,
+    SUMMARY_PREFIX,
+};
 
 #[test]
 fn session_smoke_construct_and_message_flow() {
@@ -33,3 +37,413 @@ fn session_manager_accepts_safe_channel_style_id() {
     let loaded = manager.get_or_create_session(id).expect("load");
     assert_eq!(loaded.id, id);
 }
+
+#[tokio::test]
+async fn session_usage_accumulates_and_persists() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+    let mut session = manager.get_or_create_session("usage").expect("session");
+    session.record_usage(Usage {
+        prompt_tokens: 10,
+        completion_tokens: 2,
+        total_tokens: 12,
+    });
+    session.record_usage(Usage {
+        prompt_tokens: 20,
+        completion_tokens: 5,
+        total_tokens: 25,
+    });
+    manager.save_session(&session).await.expect("save");
+
+    let loaded = manager.get_or_create_session("usage").expect("load");
+    assert_eq!(loaded.usage.prompt_tokens, 30);
+    assert_eq!(loaded.usage.completion_tokens, 7);
+    assert_eq!(loaded.usage.total_tokens, 37);
+}
+
+fn text(role: &str, content: &str) -> Message {
+    Message {
+        role: role.to_string(),
+        content: Some(content.to_string()),
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+#[tokio::test]
+async fn session_compaction_keeps_recent_turns_and_shrinks() {
+    let mut session = Session::new("long".to_string());
+    for i in 0..20 {
+        session.add_message(text("user", &format!("question {} {}", i, "x".repeat(200))));
+        session.add_message(text(
+            "assistant",
+            &format!("answer {} {}", i, "y".repeat(200)),
+        ));
+    }
+    let before = session.estimated_tokens;
+
+    let folded = session
+        .compact_with(2, |older| async move {
+            assert_eq!(older.len(), 36);
+            Ok("user asked 18 questions".to_string())
+        })
+        .await
+        .expect("compact");
+
+    assert_eq!(folded, 36);
+    assert_eq!(session.messages.len(), 5);
+    assert_eq!(session.messages[0].role, "system");
+    assert!(session.messages[0]
+        .content
+        .as_deref()
+        .unwrap()
+        .starts_with(SUMMARY_PREFIX));
+    assert!(session.messages[1]
+        .content
+        .as_deref()
+        .unwrap()
+        .starts_with("question 18"));
+    assert!(session.estimated_tokens < before / 5);
+}
+
+#[tokio::test]
+async fn session_compaction_is_noop_for_short_sessions() {
+    let mut session = Session::new("short".to_string());
+    session.add_message(text("user", "hi"));
+    session.add_message(text("assistant", "hello"));
+
+    let folded = session
+        .compact_with(2, |_| async { panic!("nothing to summarize") })
+        .await
+        .expect("compact");
+    assert_eq!(folded, 0);
+    assert_eq!(session.messages.len(), 2);
+}
+
+fn tool_call(id: &str) -> Message {
+    Message {
+        role: "assistant".to_string(),
+        content: None,
+        tool_calls: Some(vec![ToolCall {
+            id: id.to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall {
+                name: "exec".to_string(),
+                arguments: r#"{"command":"ls"}"#.to_string(),
+            },
+        }]),
+        tool_call_id: None,
+    }
+}
+
+fn tool_result(id: &str, output: &str) -> Message {
+    Message {
+        role: "tool".to_string(),
+        content: Some(output.to_string()),
+        tool_calls: None,
+        tool_call_id: Some(id.to_string()),
+    }
+}
+
+#[test]
+fn trim_to_budget_keeps_newest_messages_and_system() {
+    let messages = vec![
+        text("system", "summary"),
+        text("user", &"old ".repeat(100)),
+        text("assistant", &"old ".repeat(100)),
+        text("user", "new question"),
+        text("assistant", "new answer"),
+    ];
+    let budget = estimate_tokens(&messages[0])
+        + estimate_tokens(&messages[3])
+        + estimate_tokens(&messages[4]);
+
+    let trimmed = trim_to_budget(&messages, budget);
+    let contents: Vec<_> = trimmed
+        .iter()
+        .map(|m| m.content.as_deref().unwrap())
+        .collect();
+    assert_eq!(contents, vec!["summary", "new question", "new answer"]);
+    assert_eq!(messages.len(), 5, "input must not be mutated");
+}
+
+#[test]
+fn trim_to_budget_never_splits_tool_call_from_its_results() {
+    let messages = vec![
+        text("user", "list files"),
+        tool_call("call_1"),
+        tool_result("call_1", &"file\n".repeat(200)),
+        text("assistant", "done"),
+    ];
+    // Room for the final answer and the tool result, but not the call too.
+    let budget = estimate_tokens(&messages[3]) + estimate_tokens(&messages[2]);
+
+    let trimmed = trim_to_budget(&messages, budget);
+    assert_eq!(trimmed.len(), 1);
+    assert_eq!(trimmed[0].content.as_deref(), Some("done"));
+    assert!(trimmed.iter().all(|m| m.role != "tool"));
+}
+
+#[test]
+fn trim_to_budget_keeps_tool_pair_when_it_fits() {
+    let messages = vec![
+        text("user", &"old ".repeat(100)),
+        text("user", "list files"),
+        tool_call("call_1"),
+        tool_result("call_1", "a.txt"),
+        text("assistant", "done"),
+    ];
+    let budget = messages[1..].iter().map(estimate_tokens).sum();
+
+    let trimmed = trim_to_budget(&messages, budget);
+    assert_eq!(trimmed.len(), 4);
+    assert_eq!(trimmed[1].tool_calls.as_ref().unwrap()[0].id, "call_1");
+    assert_eq!(trimmed[2].tool_call_id.as_deref(), Some("call_1"));
+}
+
+#[test]
+fn trim_to_budget_drops_orphaned_tool_results() {
+    let messages = vec![tool_result("call_gone", "stale output"), text("user", "hi")];
+
+    let trimmed = trim_to_budget(&messages, usize::MAX);
+    assert_eq!(trimmed.len(), 1);
+    assert_eq!(trimmed[0].role, "user");
+}
+
+#[tokio::test]
+async fn export_markdown_renders_roles_and_tool_calls() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+    let mut session = manager
+        .get_or_create_session("transcript")
+        .expect("session");
+    session.add_message(text("user", "list files"));
+    session.add_message(tool_call("call_1"));
+    session.add_message(tool_result("call_1", "a.txt\n```b```"));
+    session.add_message(text("assistant", "Found a.txt"));
+    manager.save_session(&session).await.expect("save");
+
+    let markdown = manager.export_markdown("transcript").expect("export");
+    assert!(markdown.starts_with("# Session `transcript`"));
+    assert!(markdown.contains("### User\n\nlist files"));
+    assert!(markdown.contains("**Tool call** `exec`"));
+    assert!(markdown.contains("\"command\": \"ls\""));
+    assert!(markdown.contains("### Tool result (`call_1`)\n\n````\na.txt\n```b```\n````"));
+    assert!(markdown.contains("### Assistant\n\nFound a.txt"));
+}
+
+#[test]
+fn export_markdown_reports_missing_session() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+    assert!(matches!(
+        manager.export_markdown("missing"),
+        Err(gearclaw_session::SessionError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn list_sessions_detailed_sorts_by_recency_and_skips_corrupt_files() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+
+    let mut older = manager.get_or_create_session("older").expect("session");
+    older.add_message(text("user", "one"));
+    older.add_message(text("assistant", "two"));
+    older.updated_at = chrono::Utc::now() - chrono::Duration::hours(1);
+    manager.save_session(&older).await.expect("save");
+
+    let mut newer = manager.get_or_create_session("newer").expect("session");
+    newer.add_message(text("user", "hi"));
+    manager.save_session(&newer).await.expect("save");
+
+    std::fs::write(temp.path().join("broken.json"), "{ not json").expect("write");
+
+    let summaries = manager.list_sessions_detailed().expect("list");
+    let ids: Vec<_> = summaries.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec!["newer", "older"]);
+    assert_eq!(summaries[0].message_count, 1);
+    assert_eq!(summaries[1].message_count, 2);
+    assert!(summaries.iter().all(|s| s.size_bytes > 0));
+}
+
+#[tokio::test]
+async fn forked_session_is_independent_of_the_original() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+    let mut original = manager.get_or_create_session("main").expect("session");
+    original.add_message(text("user", "shared history"));
+    original.cwd = temp.path().to_path_buf();
+    manager.save_session(&original).await.expect("save");
+
+    let forked = manager.fork("main", "experiment").await.expect("fork");
+    assert_eq!(forked.messages.len(), 1);
+    assert_eq!(forked.cwd, temp.path());
+    assert!(forked.created_at >= original.created_at);
+
+    original.add_message(text("user", "only in main"));
+    manager.save_session(&original).await.expect("save");
+
+    let reloaded = manager.load_session("experiment").expect("load fork");
+    assert_eq!(reloaded.messages.len(), 1);
+    assert_eq!(
+        manager.load_session("main").expect("load").messages.len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn fork_rejects_existing_or_invalid_target() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+    for id in ["a", "b"] {
+        let session = manager.get_or_create_session(id).expect("session");
+        manager.save_session(&session).await.expect("save");
+    }
+
+    assert!(matches!(
+        manager.fork("a", "b").await,
+        Err(gearclaw_session::SessionError::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        manager.fork("a", "../escape").await,
+        Err(gearclaw_session::SessionError::InvalidSessionId(_))
+    ));
+    assert!(matches!(
+        manager.fork("missing", "c").await,
+        Err(gearclaw_session::SessionError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn prune_uses_stored_updated_at_not_file_mtime() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+
+    // Freshly written file, but the stored timestamp is 40 days old.
+    let mut stale = manager
+        .get_or_create_session("discord:user:1")
+        .expect("session");
+    stale.updated_at = chrono::Utc::now() - chrono::Duration::days(40);
+    manager.save_session(&stale).await.expect("save");
+
+    let fresh = manager
+        .get_or_create_session("discord:user:2")
+        .expect("session");
+    manager.save_session(&fresh).await.expect("save");
+
+    std::fs::write(temp.path().join("broken.json"), "{ not json").expect("write");
+
+    let report = manager
+        .prune(std::time::Duration::from_secs(30 * 24 * 60 * 60))
+        .expect("prune");
+    assert_eq!(report.pruned, vec!["discord:user:1".to_string()]);
+    assert!(report.failed.is_empty());
+    assert_eq!(
+        manager.list_sessions().expect("list"),
+        vec!["broken".to_string(), "discord:user:2".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn prune_continues_past_sessions_it_cannot_delete() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+
+    let mut stale = manager.get_or_create_session("old").expect("session");
+    stale.updated_at = chrono::Utc::now() - chrono::Duration::days(40);
+    manager.save_session(&stale).await.expect("save");
+    // Expired, but its file name is not a valid session id, so delete fails
+    std::fs::write(
+        temp.path().join("bad id.json"),
+        serde_json::json!({ "updated_at": stale.updated_at, "messages": [] }).to_string(),
+    )
+    .expect("write");
+
+    let report = manager
+        .prune(std::time::Duration::from_secs(30 * 24 * 60 * 60))
+        .expect("prune");
+    assert_eq!(report.pruned, vec!["old".to_string()]);
+    assert_eq!(report.failed, vec!["bad id".to_string()]);
+}
+
+#[test]
+fn sanitize_session_id_keeps_valid_ids_unchanged() {
+    for id in ["discord:user:12345", "default", "a-b_c.d"] {
+        assert_eq!(sanitize_session_id(id), id);
+    }
+}
+
+#[test]
+fn sanitize_session_id_maps_invalid_ids_deterministically() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+
+    let inputs = [
+        "discord:user:Alice Smith",
+        "discord:user:Alice/Smith",
+        "slack:channel:#général",
+        "../../etc/passwd",
+        "",
+        &"x".repeat(300),
+    ];
+    let mut seen = std::collections::HashSet::new();
+    for raw in inputs {
+        let id = sanitize_session_id(raw);
+        assert_eq!(id, sanitize_session_id(raw), "must be deterministic");
+        assert!(id.len() <= 128);
+        assert!(
+            manager.get_or_create_session(&id).is_ok(),
+            "{:?} -> {:?} must be a valid session id",
+            raw,
+            id
+        );
+        assert!(seen.insert(id), "distinct inputs must not collide");
+    }
+    assert!(
+        sanitize_session_id("discord:user:Alice Smith").starts_with("discord:user:Alice_Smith-")
+    );
+}
+
+#[test]
+fn invalid_and_missing_sessions_are_distinct_errors() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    let manager = SessionManager::new(temp.path().to_path_buf()).expect("manager");
+    assert!(matches!(
+        manager.load_session("bad id"),
+        Err(gearclaw_session::SessionError::InvalidSessionId(_))
+    ));
+    assert!(matches!(
+        manager.load_session("missing"),
+        Err(gearclaw_session::SessionError::NotFound(_))
+    ));
+}
+
+#[test]
+fn change_dir_resolves_relative_paths_against_cwd() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir(temp.path().join("src")).unwrap();
+    let mut session = Session::new("cwd".to_string());
+    session.cwd = temp.path().canonicalize().unwrap();
+
+    let cwd = session.change_dir("src".as_ref()).unwrap().to_path_buf();
+    assert_eq!(cwd, temp.path().canonicalize().unwrap().join("src"));
+    session.change_dir("..".as_ref()).unwrap();
+    assert_eq!(session.cwd, temp.path().canonicalize().unwrap());
+}
+
+#[test]
+fn change_dir_rejects_missing_paths_and_files() {
+    let temp = tempfile::tempdir().expect("tempdir");
+    std::fs::write(temp.path().join("notes.txt"), "x").unwrap();
+    let mut session = Session::new("cwd".to_string());
+    session.cwd = temp.path().to_path_buf();
+
+    for target in ["missing", "notes.txt"] {
+        assert!(matches!(
+            session.change_dir(target.as_ref()),
+            Err(SessionError::DirectoryNotFound(_))
+        ));
+        assert_eq!(session.cwd, temp.path());
+    }
+}
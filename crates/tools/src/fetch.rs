@@ -0,0 +1,462 @@
+//! Fetch a web page and reduce it to readable text.
+//!
+//! Only http(s) URLs are fetched, and every hop (including redirects) must
+//! resolve to public addresses: the agent runs next to local services and
+//! cloud metadata endpoints a page could otherwise point it at. The
+//! connection is pinned to the checked address so a second DNS answer
+//! cannot swap it for a private one.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use reqwest::Url;
+
+use crate::ToolError;
+
+/// Bytes read when the caller gives no `max_bytes`.
+pub const DEFAULT_FETCH_MAX_BYTES: usize = 256 * 1024;
+/// Upper bound on `max_bytes`, whatever the caller asks for.
+pub const MAX_FETCH_BYTES: usize = 2 * 1024 * 1024;
+/// Request timeout per hop.
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedPage {
+    /// Final URL after redirects
+    pub url: String,
+    pub title: Option<String>,
+    pub text: String,
+    /// The body was cut at the byte limit
+    pub truncated: bool,
+}
+
+impl FetchedPage {
+    /// Render for the model: title, URL, then the text.
+    pub fn to_output(&self) -> String {
+        let mut output = String::new();
+        if let Some(title) = &self.title {
+            output.push_str(&format!("{}\n", title));
+        }
+        output.push_str(&format!("{}\n\n{}", self.url, self.text));
+        if self.truncated {
+            output.push_str("\n\n[内容过长，已截断]");
+        }
+        output
+    }
+}
+
+/// Whether `ip` is loopback, private, link-local or otherwise not a public
+/// internet address.
+pub fn is_blocked_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_blocked_ipv4(v4),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_blocked_ipv4(v4);
+            }
+            let segments = v6.segments();
+            let first = segments[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7 unique local
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10 link-local
+                || (first & 0xffc0) == 0xfe80
+                // 64:ff9b::/96 NAT64 and 2002::/16 6to4 can reach any IPv4
+                // address, internal ones included
+                || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+                || first == 0x2002
+        }
+    }
+}
+
+fn is_blocked_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // 0.0.0.0/8 "this network"
+        || a == 0
+        // 100.64.0.0/10 carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // 198.18.0.0/15 benchmarking
+        || (a == 198 && (b & 0xfe) == 18)
+        // 240.0.0.0/4 reserved
+        || a >= 240
+}
+
+/// Parse `url` and check its scheme, without resolving the host.
+pub fn parse_fetch_url(url: &str) -> Result<Url, ToolError> {
+    let parsed =
+        Url::parse(url).map_err(|e| ToolError::Execution(format!("无效的 URL {}: {}", url, e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(ToolError::Execution(format!(
+            "只支持 http/https URL: {}",
+            url
+        )));
+    }
+    if parsed.host_str().is_none() {
+        return Err(ToolError::Execution(format!("URL 缺少主机名: {}", url)));
+    }
+    Ok(parsed)
+}
+
+/// Resolve `url`'s host and return an address to connect to, rejecting the
+/// URL if any resolved address is not public.
+pub async fn resolve_public(url: &Url) -> Result<SocketAddr, ToolError> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| ToolError::Execution(format!("URL 缺少主机名: {}", url)))?;
+    let port = url.port_or_known_default().unwrap_or(80);
+    // IPv6 literals keep their brackets in host_str
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| ToolError::Execution(format!("无法解析主机 {}: {}", host, e)))?
+        .collect();
+    if let Some(blocked) = addrs.iter().find(|addr| is_blocked_ip(addr.ip())) {
+        return Err(ToolError::Execution(format!(
+            "拒绝访问内网或本机地址: {} ({})",
+            host,
+            blocked.ip()
+        )));
+    }
+    addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| ToolError::Execution(format!("无法解析主机 {}", host)))
+}
+
+/// Download `url`, following up to five redirects, and extract its text.
+/// At most `max_bytes` (capped at [`MAX_FETCH_BYTES`]) of the body are read.
+pub async fn fetch_url(url: &str, max_bytes: usize) -> Result<FetchedPage, ToolError> {
+    let max_bytes = max_bytes.clamp(1, MAX_FETCH_BYTES);
+    let mut current = parse_fetch_url(url)?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let addr = resolve_public(&current).await?;
+        let host = current.host_str().unwrap_or_default().to_string();
+        let client = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve(&host, addr)
+            // A proxy would connect on our behalf, bypassing the check above
+            .no_proxy()
+            .user_agent(concat!("gearclaw/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| ToolError::Execution(format!("创建 HTTP 客户端失败: {}", e)))?;
+
+        let mut response = client
+            .get(current.clone())
+            .send()
+            .await
+            .map_err(|e| ToolError::Execution(format!("请求 {} 失败: {}", current, e)))?;
+        let status = response.status();
+
+        if status.is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| {
+                    ToolError::Execution(format!("{} 返回 {} 但没有 Location", current, status))
+                })?;
+            let next = current
+                .join(location)
+                .map_err(|e| ToolError::Execution(format!("无效的重定向地址: {}", e)))?;
+            current = parse_fetch_url(next.as_str())?;
+            continue;
+        }
+        if !status.is_success() {
+            return Err(ToolError::Execution(format!(
+                "{} 返回错误: {}",
+                current, status
+            )));
+        }
+
+        let is_html = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_none_or(|ct| ct.contains("html"));
+
+        let mut body = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ToolError::Execution(format!("读取 {} 失败: {}", current, e)))?
+        {
+            let room = max_bytes - body.len();
+            if chunk.len() >= room {
+                body.extend_from_slice(&chunk[..room]);
+                truncated = chunk.len() > room || response.chunk().await.ok().flatten().is_some();
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        let raw = String::from_utf8_lossy(&body);
+        let (title, text) = if is_html {
+            (extract_title(&raw), extract_text(&raw))
+        } else {
+            (None, raw.trim().to_string())
+        };
+        return Ok(FetchedPage {
+            url: current.to_string(),
+            title,
+            text,
+            truncated,
+        });
+    }
+
+    Err(ToolError::Execution(format!(
+        "重定向次数过多 (>{}): {}",
+        MAX_REDIRECTS, url
+    )))
+}
+
+/// Elements whose content is never readable text.
+const SKIPPED_ELEMENTS: [&str; 6] = ["script", "style", "noscript", "template", "svg", "head"];
+
+/// Elements that start a new line in the extracted text.
+const BLOCK_ELEMENTS: [&str; 23] = [
+    "p",
+    "div",
+    "br",
+    "li",
+    "ul",
+    "ol",
+    "tr",
+    "table",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "nav",
+    "main",
+    "aside",
+    "blockquote",
+    "pre",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+];
+
+/// The page `<title>`, if any.
+pub fn extract_title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = collapse_whitespace(&decode_entities(&html[start..end]));
+    (!title.is_empty()).then_some(title)
+}
+
+/// Strip tags, scripts and styles from `html`, keeping paragraph breaks.
+pub fn extract_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+
+    while let Some(lt) = rest.find('<') {
+        out.push_str(&decode_entities(&rest[..lt]));
+        rest = &rest[lt..];
+
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map_or("", |end| &rest[end + 3..]);
+            continue;
+        }
+        let Some(gt) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        if !closing && SKIPPED_ELEMENTS.contains(&name.as_str()) && !tag.ends_with('/') {
+            let close = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&close) {
+                Some(end) => rest[end..].find('>').map_or("", |gt| &rest[end + gt + 1..]),
+                None => "",
+            };
+            continue;
+        }
+        if BLOCK_ELEMENTS.contains(&name.as_str()) {
+            out.push('\n');
+        }
+    }
+    out.push_str(&decode_entities(rest));
+
+    out.lines()
+        .map(collapse_whitespace)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decode the named entities common in page text plus numeric references.
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" | "#39" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            ch.map(|ch| (ch, end))
+        });
+        match decoded {
+            Some((ch, end)) => {
+                out.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_non_http_schemes() {
+        for url in [
+            "file:///etc/passwd",
+            "ftp://example.com/",
+            "gopher://x/",
+            "not a url",
+        ] {
+            assert!(parse_fetch_url(url).is_err(), "{} should be rejected", url);
+        }
+        assert!(parse_fetch_url("https://example.com/page").is_ok());
+    }
+
+    #[test]
+    fn blocks_private_and_loopback_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "198.18.0.1",
+            "198.19.255.255",
+            "240.0.0.1",
+            "64:ff9b::7f00:1",
+            "2002:7f00:1::1",
+        ] {
+            assert!(
+                is_blocked_ip(ip.parse().unwrap()),
+                "{} should be blocked",
+                ip
+            );
+        }
+        for ip in ["93.184.216.34", "1.1.1.1", "198.20.0.1", "2606:4700::1111"] {
+            assert!(
+                !is_blocked_ip(ip.parse().unwrap()),
+                "{} should be allowed",
+                ip
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_rejects_loopback_hosts_before_connecting() {
+        for url in [
+            "http://127.0.0.1:1/",
+            "http://localhost/",
+            "http://[::1]/",
+            "http://169.254.169.254/latest/meta-data/",
+        ] {
+            let err = fetch_url(url, 1024).await.unwrap_err().to_string();
+            assert!(err.contains("拒绝访问"), "{}: {}", url, err);
+        }
+        assert!(fetch_url("file:///etc/passwd", 1024).await.is_err());
+    }
+
+    #[test]
+    fn extracts_readable_text_from_html() {
+        let html = r#"<!DOCTYPE html>
+<html><head><title>Rust &amp; Tokio</title>
+<style>body { color: red; }</style></head>
+<body>
+  <script>var x = "<p>hidden</p>";</script>
+  <!-- a comment -->
+  <h1>Hello&nbsp;world</h1>
+  <p>Async   runtime for <b>Rust</b>.</p>
+  <ul><li>fast</li><li>reliable &#x2713;</li></ul>
+</body></html>"#;
+
+        assert_eq!(extract_title(html).as_deref(), Some("Rust & Tokio"));
+        assert_eq!(
+            extract_text(html),
+            "Hello world\nAsync runtime for Rust.\nfast\nreliable ✓"
+        );
+    }
+
+    #[test]
+    fn output_notes_truncation() {
+        let page = FetchedPage {
+            url: "https://example.com/".to_string(),
+            title: Some("Example".to_string()),
+            text: "body".to_string(),
+            truncated: true,
+        };
+        assert_eq!(
+            page.to_output(),
+            "Example\nhttps://example.com/\n\nbody\n\n[内容过长，已截断]"
+        );
+    }
+}
@@ -4,6 +4,12 @@ use thiserror::Error;
 use tokio::process::Command;
 use tracing::{debug, error, info};
 
+pub mod fetch;
+pub mod web_search;
+
+pub use fetch::{fetch_url, FetchedPage};
+pub use web_search::{format_results, SearchProvider, SearchResult, WebSearch};
+
 #[derive(Debug, Error)]
 pub enum ToolError {
     #[error("execution error: {0}")]
@@ -144,18 +150,15 @@ impl ToolExecutor {
         }
 
         match cmd {
-            "python" | "python3" => {
-                if args.iter().any(|arg| arg == "-c") {
-                    return Some("allowlist 模式禁止 python -c 动态执行".to_string());
-                }
+            "python" | "python3" if args.iter().any(|arg| arg == "-c") => {
+                return Some("allowlist 模式禁止 python -c 动态执行".to_string());
             }
-            "node" => {
+            "node"
                 if args
                     .iter()
-                    .any(|arg| matches!(arg.as_str(), "-e" | "--eval" | "-p"))
-                {
-                    return Some("allowlist 模式禁止 node eval 参数".to_string());
-                }
+                    .any(|arg| matches!(arg.as_str(), "-e" | "--eval" | "-p")) =>
+            {
+                return Some("allowlist 模式禁止 node eval 参数".to_string());
             }
             "git" => {
                 const ALLOWED_GIT_SUBCOMMANDS: &[&str] = &[
@@ -308,7 +311,7 @@ impl ToolExecutor {
             },
             ToolSpec {
                 name: "web_search".to_string(),
-                description: "使用命令行搜索网页内容，返回文本结果（不打开浏览器）。适合快速获取信息，但用户看不到浏览器界面。".to_string(),
+                description: "搜索网页，返回标题、摘要和链接列表（不打开浏览器）。适合快速获取信息，但用户看不到浏览器界面。".to_string(),
                 requires_args: true,
                 parameters: Some(json!({
                     "type": "object",
@@ -316,6 +319,19 @@ impl ToolExecutor {
                     "required": ["query"]
                 })),
             },
+            ToolSpec {
+                name: "fetch_url".to_string(),
+                description: "下载网页并提取正文文本（仅 http/https，禁止访问内网和本机地址）".to_string(),
+                requires_args: true,
+                parameters: Some(json!({
+                    "type": "object",
+                    "properties": {
+                        "url": { "type": "string", "description": "网页地址" },
+                        "max_bytes": { "type": "integer", "description": "最多读取的字节数 (默认 262144，上限 2097152)" }
+                    },
+                    "required": ["url"]
+                })),
+            },
             ToolSpec {
                 name: "git_status".to_string(),
                 description: "查看 Git 状态".to_string(),
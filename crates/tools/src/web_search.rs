@@ -0,0 +1,321 @@
+//! Web search backed by a JSON search API.
+//!
+//! DuckDuckGo's Instant Answer API needs no key but only answers
+//! encyclopedic queries; a self-hosted SearXNG instance returns ordinary
+//! web results. Either response is reduced to `{title, snippet, url}`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::ToolError;
+
+/// Default number of results kept per query.
+pub const DEFAULT_MAX_RESULTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchProvider {
+    DuckDuckGo,
+    Searxng,
+}
+
+impl SearchProvider {
+    /// Provider names accepted by [`SearchProvider::from_name`].
+    pub const NAMES: [&'static str; 2] = ["duckduckgo", "searxng"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "duckduckgo" => Some(Self::DuckDuckGo),
+            "searxng" => Some(Self::Searxng),
+            _ => None,
+        }
+    }
+
+    /// Endpoint used when none is configured. SearXNG has no public default
+    /// and expects a local instance.
+    pub fn default_endpoint(&self) -> &'static str {
+        match self {
+            Self::DuckDuckGo => "https://api.duckduckgo.com/",
+            Self::Searxng => "http://localhost:8888/search",
+        }
+    }
+
+    /// Query parameters for `query` on this provider.
+    fn params<'a>(&self, query: &'a str) -> Vec<(&'static str, &'a str)> {
+        match self {
+            Self::DuckDuckGo => vec![
+                ("q", query),
+                ("format", "json"),
+                ("no_html", "1"),
+                ("skip_disambig", "1"),
+            ],
+            Self::Searxng => vec![("q", query), ("format", "json")],
+        }
+    }
+
+    /// Turn a provider response into results, in the provider's order.
+    pub fn parse_response(&self, response: &Value) -> Vec<SearchResult> {
+        match self {
+            Self::DuckDuckGo => parse_duckduckgo(response),
+            Self::Searxng => parse_searxng(response),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub snippet: String,
+    pub url: String,
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .trim()
+}
+
+/// DuckDuckGo Instant Answer: the direct answer and abstract first, then
+/// official results, then related topics (which may be nested in groups).
+fn parse_duckduckgo(response: &Value) -> Vec<SearchResult> {
+    let mut results = Vec::new();
+
+    let answer = str_field(response, "Answer");
+    if !answer.is_empty() {
+        results.push(SearchResult {
+            title: "Answer".to_string(),
+            snippet: answer.to_string(),
+            url: String::new(),
+        });
+    }
+
+    let abstract_text = str_field(response, "AbstractText");
+    if !abstract_text.is_empty() {
+        results.push(SearchResult {
+            title: str_field(response, "Heading").to_string(),
+            snippet: abstract_text.to_string(),
+            url: str_field(response, "AbstractURL").to_string(),
+        });
+    }
+
+    let topics = ["Results", "RelatedTopics"]
+        .iter()
+        .filter_map(|key| response.get(*key).and_then(Value::as_array))
+        .flatten()
+        .flat_map(
+            |topic| match topic.get("Topics").and_then(Value::as_array) {
+                Some(group) => group.iter().collect::<Vec<_>>(),
+                None => vec![topic],
+            },
+        );
+    for topic in topics {
+        let text = str_field(topic, "Text");
+        let url = str_field(topic, "FirstURL");
+        if text.is_empty() || url.is_empty() {
+            continue;
+        }
+        // Topic text reads "Title - description"
+        let (title, snippet) = text.split_once(" - ").unwrap_or((text, ""));
+        results.push(SearchResult {
+            title: title.trim().to_string(),
+            snippet: snippet.trim().to_string(),
+            url: url.to_string(),
+        });
+    }
+
+    results
+}
+
+/// SearXNG JSON: `{"results": [{"title", "content", "url"}]}`.
+fn parse_searxng(response: &Value) -> Vec<SearchResult> {
+    response
+        .get("results")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|item| !str_field(item, "url").is_empty())
+        .map(|item| SearchResult {
+            title: str_field(item, "title").to_string(),
+            snippet: str_field(item, "content").to_string(),
+            url: str_field(item, "url").to_string(),
+        })
+        .collect()
+}
+
+/// Render results as a numbered list for the model.
+pub fn format_results(query: &str, results: &[SearchResult]) -> String {
+    if results.is_empty() {
+        return format!("未找到 \"{}\" 的相关结果", query);
+    }
+    let mut output = format!("\"{}\" 的搜索结果:\n", query);
+    for (i, result) in results.iter().enumerate() {
+        output.push_str(&format!("\n{}. {}\n", i + 1, result.title));
+        if !result.snippet.is_empty() {
+            output.push_str(&format!("   {}\n", result.snippet));
+        }
+        if !result.url.is_empty() {
+            output.push_str(&format!("   {}\n", result.url));
+        }
+    }
+    output
+}
+
+/// Search client for one configured provider.
+pub struct WebSearch {
+    provider: SearchProvider,
+    endpoint: String,
+    max_results: usize,
+    client: reqwest::Client,
+}
+
+impl WebSearch {
+    pub fn new(
+        provider: SearchProvider,
+        endpoint: Option<&str>,
+        max_results: usize,
+        timeout: Duration,
+    ) -> Result<Self, ToolError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .user_agent(concat!("gearclaw/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| ToolError::Execution(format!("创建 HTTP 客户端失败: {}", e)))?;
+        Ok(Self {
+            provider,
+            endpoint: endpoint
+                .unwrap_or_else(|| provider.default_endpoint())
+                .to_string(),
+            max_results,
+            client,
+        })
+    }
+
+    pub async fn search(&self, query: &str) -> Result<Vec<SearchResult>, ToolError> {
+        let url = reqwest::Url::parse_with_params(&self.endpoint, self.provider.params(query))
+            .map_err(|e| {
+                ToolError::Execution(format!("无效的搜索地址 {}: {}", self.endpoint, e))
+            })?;
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| ToolError::Execution(format!("搜索请求失败: {}", e)))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ToolError::Execution(format!(
+                "搜索服务返回错误: {}",
+                status
+            )));
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ToolError::Execution(format!("读取搜索结果失败: {}", e)))?;
+        let json: Value = serde_json::from_str(&body)
+            .map_err(|e| ToolError::Execution(format!("解析搜索结果失败: {}", e)))?;
+
+        let mut results = self.provider.parse_response(&json);
+        results.truncate(self.max_results);
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_duckduckgo_instant_answer() {
+        let response = json!({
+            "Heading": "Rust (programming language)",
+            "AbstractText": "Rust is a general-purpose programming language.",
+            "AbstractURL": "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+            "Answer": "",
+            "Results": [
+                { "FirstURL": "https://www.rust-lang.org/", "Text": "Official site" }
+            ],
+            "RelatedTopics": [
+                {
+                    "FirstURL": "https://duckduckgo.com/Cargo",
+                    "Text": "Cargo - The Rust package manager."
+                },
+                {
+                    "Name": "See also",
+                    "Topics": [
+                        {
+                            "FirstURL": "https://duckduckgo.com/Ferris",
+                            "Text": "Ferris - The unofficial Rust mascot."
+                        }
+                    ]
+                },
+                { "FirstURL": "", "Text": "" }
+            ]
+        });
+
+        let results = SearchProvider::DuckDuckGo.parse_response(&response);
+        assert_eq!(
+            results,
+            vec![
+                SearchResult {
+                    title: "Rust (programming language)".to_string(),
+                    snippet: "Rust is a general-purpose programming language.".to_string(),
+                    url: "https://en.wikipedia.org/wiki/Rust_(programming_language)".to_string(),
+                },
+                SearchResult {
+                    title: "Official site".to_string(),
+                    snippet: String::new(),
+                    url: "https://www.rust-lang.org/".to_string(),
+                },
+                SearchResult {
+                    title: "Cargo".to_string(),
+                    snippet: "The Rust package manager.".to_string(),
+                    url: "https://duckduckgo.com/Cargo".to_string(),
+                },
+                SearchResult {
+                    title: "Ferris".to_string(),
+                    snippet: "The unofficial Rust mascot.".to_string(),
+                    url: "https://duckduckgo.com/Ferris".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_searxng_results() {
+        let response = json!({
+            "results": [
+                { "title": "Tokio", "content": "An async runtime.", "url": "https://tokio.rs/" },
+                { "title": "No url", "content": "dropped" }
+            ]
+        });
+        let results = SearchProvider::Searxng.parse_response(&response);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Tokio");
+        assert_eq!(results[0].snippet, "An async runtime.");
+    }
+
+    #[test]
+    fn formats_results_and_empty_result() {
+        let results = vec![SearchResult {
+            title: "Tokio".to_string(),
+            snippet: "An async runtime.".to_string(),
+            url: "https://tokio.rs/".to_string(),
+        }];
+        let text = format_results("tokio", &results);
+        assert!(text.contains("1. Tokio\n   An async runtime.\n   https://tokio.rs/"));
+        assert!(format_results("nothing", &[]).contains("未找到"));
+    }
+
+    #[test]
+    fn provider_names_are_case_insensitive() {
+        assert_eq!(
+            SearchProvider::from_name("SearXNG"),
+            Some(SearchProvider::Searxng)
+        );
+        assert_eq!(SearchProvider::from_name("bing"), None);
+    }
+}